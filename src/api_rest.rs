@@ -12,6 +12,8 @@ use crate::app::AppMessage;
 use crate::app::MainLoopAction;
 use crate::app_state::AppState;
 use crate::config::{self, load_organ_library};
+use crate::organ::CouplerKind;
+use crate::virtual_stops::{VirtualStop, VirtualStopRank, VirtualStopSet};
 
 /// A handle that controls the lifecycle of the API Server.
 /// When this struct is dropped, the server shuts down and the background thread exits.
@@ -106,6 +108,8 @@ pub struct ReverbEntry {
 pub struct AudioSettingsResponse {
     gain: f32,
     polyphony: usize,
+    master_tuning_cents: f32,
+    transpose: i8,
     reverb_mix: f32,
     active_reverb_index: Option<usize>,
     is_recording_midi: bool,
@@ -124,6 +128,37 @@ pub struct TremulantSetRequest {
     active: bool,
 }
 
+#[derive(Serialize, Clone, ToSchema)]
+pub struct CouplerResponse {
+    id: String,
+    name: String,
+    /// One of "unison", "sub_octave", "super_octave", "bass", "melody"
+    kind: String,
+    source_manual: String,
+    destination_manual: String,
+    key_shift: i8,
+    active: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CouplerSetRequest {
+    active: bool,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+pub struct EnclosureResponse {
+    id: String,
+    name: String,
+    /// Pedal position, 0 (closed) to 127 (open)
+    value: u8,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct EnclosureSetRequest {
+    /// Pedal position, 0 (closed) to 127 (open)
+    value: u8,
+}
+
 // --- Shared State ---
 
 struct ApiData {
@@ -150,13 +185,23 @@ struct ApiData {
         get_audio_settings,
         set_gain,
         set_polyphony,
+        set_master_tuning,
+        set_transpose,
         start_stop_midi_recording,
         start_stop_audio_recording,
         get_reverbs,
         set_reverb,
         set_reverb_mix,
         get_tremulants,
-        set_tremulant
+        set_tremulant,
+        get_couplers,
+        set_coupler,
+        get_enclosures,
+        set_enclosure,
+        get_virtual_stops,
+        save_virtual_stop,
+        delete_virtual_stop,
+        apply_virtual_stops
     ),
     components(
         schemas(
@@ -172,7 +217,13 @@ struct ApiData {
             ReverbEntry,
             AudioSettingsResponse,
             TremulantResponse,
-            TremulantSetRequest
+            TremulantSetRequest,
+            CouplerResponse,
+            CouplerSetRequest,
+            EnclosureResponse,
+            EnclosureSetRequest,
+            VirtualStop,
+            VirtualStopRank
         )
     ),
     tags(
@@ -274,8 +325,8 @@ async fn load_organ(body: web::Json<LoadOrganRequest>, data: web::Data<ApiData>)
 async fn panic(data: web::Data<ApiData>) -> impl Responder {
     let mut state = data.app_state.lock().unwrap();
 
-    // Send the signal to the audio engine
-    let _ = data.audio_tx.send(AppMessage::AllNotesOff);
+    // Send the signal to the audio engine and forget the held keys
+    let _ = state.all_notes_off(&data.audio_tx);
 
     state.add_midi_log("API: Executed Panic (All Notes Off)".into());
     HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
@@ -412,6 +463,8 @@ async fn get_audio_settings(data: web::Data<ApiData>) -> impl Responder {
     let resp = AudioSettingsResponse {
         gain: state.gain,
         polyphony: state.polyphony,
+        master_tuning_cents: state.master_tuning_cents,
+        transpose: state.transpose,
         reverb_mix: state.reverb_mix,
         active_reverb_index: state.selected_reverb_index,
         is_recording_midi: state.is_recording_midi,
@@ -450,6 +503,36 @@ async fn set_polyphony(body: web::Json<ValueRequest>, data: web::Data<ApiData>)
     HttpResponse::Ok().json(serde_json::json!({"status": "success", "polyphony": state.polyphony}))
 }
 
+/// Set Master Tuning in cents (-200 - 200).
+#[utoipa::path(
+    post, path = "/audio/tuning", tag = "Audio",
+    request_body = ValueRequest,
+    responses((status = 200))
+)]
+async fn set_master_tuning(
+    body: web::Json<ValueRequest>,
+    data: web::Data<ApiData>,
+) -> impl Responder {
+    let mut state = data.app_state.lock().unwrap();
+    state.set_master_tuning(body.value, &data.audio_tx);
+    HttpResponse::Ok().json(
+        serde_json::json!({"status": "success", "master_tuning_cents": state.master_tuning_cents}),
+    )
+}
+
+/// Set Transposition in semitones (-12 - 12). Held notes follow the change.
+#[utoipa::path(
+    post, path = "/audio/transpose", tag = "Audio",
+    request_body = ValueRequest,
+    responses((status = 200))
+)]
+async fn set_transpose(body: web::Json<ValueRequest>, data: web::Data<ApiData>) -> impl Responder {
+    let mut state = data.app_state.lock().unwrap();
+    let semitones = body.value.round().clamp(i8::MIN as f32, i8::MAX as f32) as i8;
+    state.set_transpose(semitones, &data.audio_tx);
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "transpose": state.transpose}))
+}
+
 /// Start or Stop MIDI Recording.
 #[utoipa::path(
     post, path = "/record/midi", tag = "Recording",
@@ -621,6 +704,204 @@ async fn set_tremulant(
     HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
 }
 
+/// Get list of Couplers and their status.
+#[utoipa::path(
+    get, path = "/couplers", tag = "Couplers",
+    responses((status = 200, body = Vec<CouplerResponse>))
+)]
+async fn get_couplers(data: web::Data<ApiData>) -> impl Responder {
+    let state = data.app_state.lock().unwrap();
+    let mut list = Vec::new();
+
+    let mut coupler_ids: Vec<_> = state.organ.couplers.keys().collect();
+    coupler_ids.sort();
+
+    for id in coupler_ids {
+        let coupler = &state.organ.couplers[id];
+        let kind = match coupler.kind {
+            CouplerKind::Unison => "unison",
+            CouplerKind::SubOctave => "sub_octave",
+            CouplerKind::SuperOctave => "super_octave",
+            CouplerKind::Bass => "bass",
+            CouplerKind::Melody => "melody",
+        };
+        list.push(CouplerResponse {
+            id: id.clone(),
+            name: coupler.name.clone(),
+            kind: kind.to_string(),
+            source_manual: coupler.source_manual_id.clone(),
+            destination_manual: coupler.destination_manual_id.clone(),
+            key_shift: coupler.key_shift,
+            active: state.active_couplers.contains(id),
+        });
+    }
+    HttpResponse::Ok().json(list)
+}
+
+/// Engage/Disengage a Coupler by ID.
+#[utoipa::path(
+    post, path = "/couplers/{coupler_id}", tag = "Couplers",
+    request_body = CouplerSetRequest,
+    params(
+        ("coupler_id" = String, Path, description = "Coupler ID")
+    ),
+    responses((status = 200), (status = 404))
+)]
+async fn set_coupler(
+    path: web::Path<String>,
+    body: web::Json<CouplerSetRequest>,
+    data: web::Data<ApiData>,
+) -> impl Responder {
+    let coupler_id = path.into_inner();
+    let mut state = data.app_state.lock().unwrap();
+
+    if !state.organ.couplers.contains_key(&coupler_id) {
+        return HttpResponse::NotFound().body("Coupler ID not found");
+    }
+
+    if let Err(e) = state.set_coupler_active(coupler_id.clone(), body.active, &data.audio_tx) {
+        return HttpResponse::InternalServerError().body(e.to_string());
+    }
+
+    let action = if body.active { "Engaged" } else { "Disengaged" };
+    state.add_midi_log(format!("API: {} Coupler '{}'", action, coupler_id));
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success"}))
+}
+
+/// Get list of Enclosures (swell boxes) and their pedal positions.
+#[utoipa::path(
+    get, path = "/enclosures", tag = "Enclosures",
+    responses((status = 200, body = Vec<EnclosureResponse>))
+)]
+async fn get_enclosures(data: web::Data<ApiData>) -> impl Responder {
+    let state = data.app_state.lock().unwrap();
+    let mut list = Vec::new();
+
+    let mut enclosure_ids: Vec<_> = state.organ.enclosures.keys().collect();
+    enclosure_ids.sort();
+
+    for id in enclosure_ids {
+        let enclosure = &state.organ.enclosures[id];
+        list.push(EnclosureResponse {
+            id: id.clone(),
+            name: enclosure.name.clone(),
+            value: state
+                .enclosure_values
+                .get(id)
+                .copied()
+                .unwrap_or(enclosure.default_value),
+        });
+    }
+    HttpResponse::Ok().json(list)
+}
+
+/// Set the pedal position of an Enclosure by ID.
+#[utoipa::path(
+    post, path = "/enclosures/{enclosure_id}", tag = "Enclosures",
+    request_body = EnclosureSetRequest,
+    params(
+        ("enclosure_id" = String, Path, description = "Enclosure ID")
+    ),
+    responses((status = 200), (status = 400), (status = 404))
+)]
+async fn set_enclosure(
+    path: web::Path<String>,
+    body: web::Json<EnclosureSetRequest>,
+    data: web::Data<ApiData>,
+) -> impl Responder {
+    let enclosure_id = path.into_inner();
+    if body.value > 127 {
+        return HttpResponse::BadRequest().body("Value must be between 0 and 127");
+    }
+
+    let mut state = data.app_state.lock().unwrap();
+    if !state.organ.enclosures.contains_key(&enclosure_id) {
+        return HttpResponse::NotFound().body("Enclosure ID not found");
+    }
+
+    state.set_enclosure_value(enclosure_id, body.value, &data.audio_tx);
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "value": body.value}))
+}
+
+/// Get the virtual stops defined for the loaded organ.
+/// Changes take effect after the organ is reloaded.
+#[utoipa::path(
+    get, path = "/virtual_stops", tag = "Virtual Stops",
+    responses((status = 200, body = Vec<VirtualStop>))
+)]
+async fn get_virtual_stops(data: web::Data<ApiData>) -> impl Responder {
+    let state = data.app_state.lock().unwrap();
+    HttpResponse::Ok().json(VirtualStopSet::load(&state.organ.name).stops)
+}
+
+/// Create a virtual stop, or replace the one with the same name.
+#[utoipa::path(
+    post, path = "/virtual_stops", tag = "Virtual Stops",
+    request_body = VirtualStop,
+    responses((status = 200), (status = 400), (status = 500))
+)]
+async fn save_virtual_stop(
+    body: web::Json<VirtualStop>,
+    data: web::Data<ApiData>,
+) -> impl Responder {
+    let mut state = data.app_state.lock().unwrap();
+    let mut set = VirtualStopSet::load(&state.organ.name);
+    let stop = body.into_inner();
+    let existing = set.stops.iter().position(|s| s.name == stop.name.trim());
+
+    if let Err(e) = set.validate(&state.organ, &stop, existing) {
+        return HttpResponse::BadRequest().body(e.to_string());
+    }
+    let name = stop.name.trim().to_string();
+    set.upsert(stop, existing);
+    if let Err(e) = set.save(&state.organ.name) {
+        return HttpResponse::InternalServerError().body(e.to_string());
+    }
+
+    state.add_midi_log(format!("API: Saved virtual stop '{}'", name));
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "reload_required": true}))
+}
+
+/// Delete a virtual stop by name.
+#[utoipa::path(
+    delete, path = "/virtual_stops/{name}", tag = "Virtual Stops",
+    params(
+        ("name" = String, Path, description = "Virtual stop name")
+    ),
+    responses((status = 200), (status = 404), (status = 500))
+)]
+async fn delete_virtual_stop(path: web::Path<String>, data: web::Data<ApiData>) -> impl Responder {
+    let name = path.into_inner();
+    let mut state = data.app_state.lock().unwrap();
+    let mut set = VirtualStopSet::load(&state.organ.name);
+
+    let Some(index) = set.stops.iter().position(|s| s.name == name) else {
+        return HttpResponse::NotFound().body("Virtual stop not found");
+    };
+    set.stops.remove(index);
+    if let Err(e) = set.save(&state.organ.name) {
+        return HttpResponse::InternalServerError().body(e.to_string());
+    }
+
+    state.add_midi_log(format!("API: Deleted virtual stop '{}'", name));
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "reload_required": true}))
+}
+
+/// Reloads the organ so changes to the virtual stops take effect.
+/// Note: This will cause the API server to restart shortly after the response is sent.
+#[utoipa::path(
+    post, path = "/virtual_stops/apply", tag = "Virtual Stops",
+    responses((status = 200, description = "Reload initiated"))
+)]
+async fn apply_virtual_stops(data: web::Data<ApiData>) -> impl Responder {
+    log::info!("API: Reloading organ to apply virtual stops");
+    *data.exit_action.lock().unwrap() = MainLoopAction::Continue;
+    let _ = data.audio_tx.send(AppMessage::Quit);
+    HttpResponse::Ok().json(serde_json::json!({"status": "reloading"}))
+}
+
 // --- Server Launcher ---
 
 pub fn start_api_server(
@@ -672,6 +953,8 @@ pub fn start_api_server(
                 .route("/audio/settings", web::get().to(get_audio_settings))
                 .route("/audio/gain", web::post().to(set_gain))
                 .route("/audio/polyphony", web::post().to(set_polyphony))
+                .route("/audio/tuning", web::post().to(set_master_tuning))
+                .route("/audio/transpose", web::post().to(set_transpose))
                 .route("/audio/reverbs", web::get().to(get_reverbs))
                 .route("/audio/reverbs/select", web::post().to(set_reverb))
                 .route("/audio/reverbs/mix", web::post().to(set_reverb_mix))
@@ -681,6 +964,20 @@ pub fn start_api_server(
                 // Tremulants
                 .route("/tremulants", web::get().to(get_tremulants))
                 .route("/tremulants/{trem_id}", web::post().to(set_tremulant))
+                // Couplers
+                .route("/couplers", web::get().to(get_couplers))
+                .route("/couplers/{coupler_id}", web::post().to(set_coupler))
+                // Enclosures
+                .route("/enclosures", web::get().to(get_enclosures))
+                .route("/enclosures/{enclosure_id}", web::post().to(set_enclosure))
+                // Virtual Stops
+                .route("/virtual_stops", web::get().to(get_virtual_stops))
+                .route("/virtual_stops", web::post().to(save_virtual_stop))
+                .route("/virtual_stops/apply", web::post().to(apply_virtual_stops))
+                .route(
+                    "/virtual_stops/{name}",
+                    web::delete().to(delete_virtual_stop),
+                )
         })
         .bind(("0.0.0.0", port));
 
@@ -2,6 +2,13 @@ use std::path::PathBuf;
 use std::sync::mpsc::Sender;
 use std::time::Instant;
 
+use crate::audio_loader::LoopSelection;
+use crate::output_routing::OutputRouting;
+use crate::resample::ResamplingQuality;
+use crate::stereo::StereoLayout;
+use crate::voicing::PipeVoicing;
+use crate::wind::WindModel;
+
 /// Messages sent from the TUI and MIDI threads to the Audio thread.
 #[derive(Debug)]
 pub enum AppMessage {
@@ -19,6 +26,24 @@ pub enum AppMessage {
     SetPolyphony(usize),
     /// Activate or Deactivate a specific Tremulant (ID, Active)
     SetTremulantActive(String, bool),
+    /// Move an enclosure's expression pedal (ID, Value 0-127)
+    SetEnclosureValue(String, u8),
+    /// Change the voicing of a pipe while playing (rank ID, MIDI note, voicing)
+    SetPipeVoicing(String, u8, PipeVoicing),
+    /// Cent offset of each pitch class (C = 0) from equal temperament
+    SetTemperament([f32; 12]),
+    /// Reference pitch offset in cents
+    SetMasterTuning(f32),
+    /// Semitones every note is shifted by (-12 to 12)
+    SetTranspose(i8),
+    /// Replace the organ's wind model
+    SetWindModel(WindModel),
+    SetStereoLayout(StereoLayout),
+    /// How sustained notes choose among their sample's loops, and the seed of random choices
+    SetLoopSelection(LoopSelection, u64),
+    /// How voices are interpolated when they play at another pitch than recorded
+    SetResamplingQuality(ResamplingQuality),
+    SetOutputRouting(OutputRouting),
     StartAudioRecording,
     StopAudioRecording,
     StartMidiRecording,
@@ -44,6 +69,8 @@ pub enum TuiMessage {
     Error(String),
     /// Triggered whenever a buffer underrun occurs.
     AudioUnderrun,
+    /// Voices that could not start because all preallocated voice buffers were in use.
+    VoicePoolExhausted(usize),
     ActiveVoicesUpdate(usize),
     CpuLoadUpdate(f32),
     /// Messages for Piano Roll
@@ -57,6 +84,8 @@ pub enum TuiMessage {
     MidiNoteOff(u8, u8),
     /// (channel)
     MidiChannelNotesOff(u8),
+    /// (controller, value, channel)
+    MidiControlChange(u8, u8, u8),
     MidiPlaybackFinished,
     MidiProgress(f32, u32, u32),
     MidiSeekChannel(Sender<i32>),
@@ -76,6 +105,10 @@ pub struct ActiveNote {
     pub stop_index: usize,
     /// The rank this note is playing on.
     pub rank_id: String,
+    /// The pipe sounding in that rank (differs from `note` for transposed ranks).
+    pub pipe_note: u8,
+    /// Extra gain the stop applies to the rank.
+    pub gain_db: f32,
     pub voice_id: u64,
 }
 
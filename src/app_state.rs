@@ -5,14 +5,15 @@ use crate::{
     midi,
     midi_control::{ControlAction, MidiControlMap},
     midi_recorder::MidiRecorder,
-    organ::Organ,
+    organ::{CouplerKind, Organ},
+    virtual_stops::{key_virtual_stops_by_id, resolve_virtual_stop_ids},
 };
 
 use anyhow::Result;
 use midir::{MidiInput, MidiInputConnection, MidiInputPort, MidiOutputConnection};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{BTreeSet, HashMap, VecDeque},
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
     fs::File,
     io::{BufReader, BufWriter},
     path::PathBuf,
@@ -28,11 +29,20 @@ pub const PRESET_FILE_NAME: &str = "rusty-pipes.presets.json";
 pub struct Preset {
     pub name: String,
     pub stop_channels: HashMap<usize, BTreeSet<u8>>,
+    /// Channels of virtual stops, saved by stop ID. Once loaded, this only holds
+    /// virtual stops the organ doesn't have.
+    #[serde(default)]
+    pub virtual_stop_channels: HashMap<String, BTreeSet<u8>>,
 }
 pub type PresetBank = [Option<Preset>; 12];
 pub type PresetConfig = HashMap<String, PresetBank>;
 
 pub const MIDI_LOG_CAPACITY: usize = 10; // Max log lines
+/// Range of the master tuning in cents (A=415 is about -101 cents from A=440)
+pub const MAX_MASTER_TUNING_CENTS: f32 = 200.0;
+pub const MAX_TRANSPOSE: i8 = 12;
+/// How long a setting has to stay unchanged before it is written to disk
+const SETTINGS_SAVE_DELAY: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PlayedNote {
@@ -42,6 +52,14 @@ pub struct PlayedNote {
     pub end_time: Option<Instant>, // None if still playing
 }
 
+/// What holds a pipe (stop index, note) down: the keys played on a channel,
+/// or the couplers those keys drive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipeHolder {
+    Keys(u8),
+    Couplers(u8),
+}
+
 // --- Shared MIDI Connection Logic ---
 
 /// Creates a MIDI connection using the specified input and port.
@@ -89,7 +107,17 @@ pub struct AppState {
     pub presets: PresetBank,
     pub gain: f32,
     pub polyphony: usize,
+    /// Reference pitch offset in cents
+    pub master_tuning_cents: f32,
+    /// Semitones every note is shifted by
+    pub transpose: i8,
+    /// When a setting changed that has not been written to disk yet
+    pub settings_dirty_since: Option<Instant>,
     pub last_underrun: Option<Instant>, // Store when the last buffer underrun occurred
+    /// When voices last failed to start for lack of voice buffers
+    pub last_voice_pool_exhausted: Option<Instant>,
+    /// Voices that failed to start for lack of voice buffers since the organ was loaded
+    pub skipped_voice_count: usize,
     pub active_voice_count: usize,
     pub cpu_load: f32,
     pub keyboard_layout: KeyboardLayout,
@@ -98,6 +126,15 @@ pub struct AppState {
     pub selected_reverb_index: Option<usize>,
     /// Set of currently active tremulant IDs
     pub active_tremulants: BTreeSet<String>,
+    pub active_couplers: BTreeSet<String>,
+    /// Expression pedal position per enclosure ID (0 = closed, 127 = open)
+    pub enclosure_values: HashMap<String, u8>,
+    /// Pipes held down, as (stop index, note), per holder
+    pub held_pipes: HashMap<PipeHolder, BTreeSet<(usize, u8)>>,
+    /// Number of holders per pipe. A pipe sounds while at least one holds it.
+    pub pipe_hold_counts: HashMap<(usize, u8), usize>,
+    /// Velocity each held key was pressed with, by (channel, note)
+    pub key_velocities: HashMap<(u8, u8), u8>,
     pub is_recording_midi: bool,
     pub is_recording_audio: bool,
     pub midi_control_map: MidiControlMap,
@@ -137,6 +174,11 @@ fn format_mm_ss(total_seconds: u32) -> String {
     format!("{:02}:{:02}", minutes, seconds)
 }
 
+/// Maps a controller value to the master tuning range, with 64 as concert pitch.
+fn controller_to_cents(value: u8) -> f32 {
+    (value.min(127) as f32 - 64.0) / 63.0 * MAX_MASTER_TUNING_CENTS
+}
+
 impl AppState {
     pub fn new(
         organ: Arc<Organ>,
@@ -144,8 +186,19 @@ impl AppState {
         polyphony: usize,
         keyboard_layout: KeyboardLayout,
     ) -> Result<Self> {
-        let presets = Self::load_presets(&organ.name);
-        let midi_control_map = MidiControlMap::load(&organ.name);
+        let presets = Self::load_presets(&organ);
+        let midi_control_map = MidiControlMap::load(&organ);
+        let active_couplers = organ
+            .couplers
+            .values()
+            .filter(|c| c.default_active)
+            .map(|c| c.id_str.clone())
+            .collect();
+        let enclosure_values = organ
+            .enclosures
+            .values()
+            .map(|e| (e.id_str.clone(), e.default_value))
+            .collect();
         let mut midi_log = VecDeque::with_capacity(MIDI_LOG_CAPACITY);
         // Initialize with empty lines
         for _ in 0..MIDI_LOG_CAPACITY - 1 {
@@ -165,7 +218,12 @@ impl AppState {
             presets,
             gain,
             polyphony,
+            master_tuning_cents: 0.0,
+            transpose: 0,
+            settings_dirty_since: None,
             last_underrun: None,
+            last_voice_pool_exhausted: None,
+            skipped_voice_count: 0,
             active_voice_count: 0,
             cpu_load: 0.0,
             keyboard_layout,
@@ -173,6 +231,11 @@ impl AppState {
             reverb_mix: 0.0,
             selected_reverb_index: None,
             active_tremulants: BTreeSet::new(),
+            active_couplers,
+            enclosure_values,
+            held_pipes: HashMap::new(),
+            pipe_hold_counts: HashMap::new(),
+            key_velocities: HashMap::new(),
             is_recording_midi: false,
             is_recording_audio: false,
             midi_control_map,
@@ -205,6 +268,7 @@ impl AppState {
         // Update values
         settings.gain = self.gain;
         settings.polyphony = self.polyphony;
+        settings.master_tuning_cents = self.master_tuning_cents;
         settings.lcd_displays = self.lcd_displays.clone();
 
         // Save back to disk
@@ -220,6 +284,30 @@ impl AppState {
         self.refresh_lcds();
     }
 
+    pub fn set_master_tuning(&mut self, cents: f32, audio_tx: &Sender<AppMessage>) {
+        self.master_tuning_cents = cents.clamp(-MAX_MASTER_TUNING_CENTS, MAX_MASTER_TUNING_CENTS);
+        let _ = audio_tx.send(AppMessage::SetMasterTuning(self.master_tuning_cents));
+        // Knobs send a stream of values; save once the last one has settled
+        self.settings_dirty_since = Some(Instant::now());
+    }
+
+    pub fn set_transpose(&mut self, semitones: i8, audio_tx: &Sender<AppMessage>) {
+        self.transpose = semitones.clamp(-MAX_TRANSPOSE, MAX_TRANSPOSE);
+        let _ = audio_tx.send(AppMessage::SetTranspose(self.transpose));
+    }
+
+    /// Writes settings changed by `set_master_tuning` to disk once they have
+    /// settled, or right away when `force` is set (on exit).
+    pub fn flush_settings(&mut self, force: bool) {
+        let due = self
+            .settings_dirty_since
+            .is_some_and(|since| force || since.elapsed() >= SETTINGS_SAVE_DELAY);
+        if due {
+            self.settings_dirty_since = None;
+            self.persist_settings();
+        }
+    }
+
     pub fn refresh_lcds(&mut self) {
         log::info!(
             "Refreshing LCDs. Out connections: {}, Configured Displays: {}",
@@ -317,8 +405,277 @@ impl AppState {
         let _ = audio_tx.send(AppMessage::SetTremulantActive(trem_id, active));
     }
 
+    /// Moves the expression pedal of an enclosure.
+    pub fn set_enclosure_value(
+        &mut self,
+        enclosure_id: String,
+        value: u8,
+        audio_tx: &Sender<AppMessage>,
+    ) {
+        let value = value.min(127);
+        self.enclosure_values.insert(enclosure_id.clone(), value);
+        let _ = audio_tx.send(AppMessage::SetEnclosureValue(enclosure_id, value));
+    }
+
+    /// Engages or disengages a coupler and updates the coupled notes of any held keys.
+    pub fn set_coupler_active(
+        &mut self,
+        coupler_id: String,
+        active: bool,
+        audio_tx: &Sender<AppMessage>,
+    ) -> Result<()> {
+        if active {
+            self.active_couplers.insert(coupler_id);
+        } else {
+            self.active_couplers.remove(&coupler_id);
+        }
+        self.refresh_all_coupled_notes(audio_tx)
+    }
+
+    /// Computes the pipes the active couplers add to the keys held on `channel`, with the
+    /// velocity of the key driving each. A channel plays a manual when at least one of the
+    /// manual's stops is routed to it. Destination stops sound when they are drawn on any channel.
+    fn compute_coupled_notes(&self, channel: u8) -> BTreeMap<(usize, u8), u8> {
+        let mut coupled = BTreeMap::new();
+        let Some(held_notes) = self.channel_active_notes.get(&channel) else {
+            return coupled;
+        };
+        if held_notes.is_empty() || self.active_couplers.is_empty() {
+            return coupled;
+        }
+
+        let source_manuals: BTreeSet<&str> = self
+            .stop_channels
+            .iter()
+            .filter(|(_, channels)| channels.contains(&channel))
+            .filter_map(|(stop_index, _)| self.organ.manual_id_for_stop(*stop_index))
+            .collect();
+
+        for coupler_id in &self.active_couplers {
+            let Some(coupler) = self.organ.couplers.get(coupler_id) else {
+                continue;
+            };
+            if !source_manuals.contains(coupler.source_manual_id.as_str()) {
+                continue;
+            }
+
+            let keys: Vec<u8> = match coupler.kind {
+                CouplerKind::Bass => held_notes.first().copied().into_iter().collect(),
+                CouplerKind::Melody => held_notes.last().copied().into_iter().collect(),
+                _ => held_notes.iter().copied().collect(),
+            };
+            let destination_stops: Vec<usize> = self
+                .organ
+                .stop_indices_for_manual(&coupler.destination_manual_id)
+                .into_iter()
+                .filter(|idx| {
+                    self.stop_channels
+                        .get(idx)
+                        .is_some_and(|channels| !channels.is_empty())
+                })
+                .collect();
+
+            for key in keys {
+                let Ok(target_note) = u8::try_from(key as i16 + coupler.key_shift as i16) else {
+                    continue;
+                };
+                if target_note > 127 {
+                    continue;
+                }
+                let Some(&velocity) = self.key_velocities.get(&(channel, key)) else {
+                    continue;
+                };
+                for &stop_index in &destination_stops {
+                    coupled.insert((stop_index, target_note), velocity);
+                }
+            }
+        }
+        coupled
+    }
+
+    /// Brings the coupled notes of `channel` in line with its held keys, the stop routing
+    /// and the engaged couplers.
+    fn refresh_coupled_notes(&mut self, channel: u8, audio_tx: &Sender<AppMessage>) -> Result<()> {
+        let target = self.compute_coupled_notes(channel);
+        self.set_held_pipes(PipeHolder::Couplers(channel), target, audio_tx)
+    }
+
+    /// Pipe notes of `rank_id` that the key `note` on `channel` holds down, directly or
+    /// through couplers, with the stops' rank offsets (e.g. virtual stop transposition) applied.
+    pub fn pipe_notes_for_key(&self, rank_id: &str, channel: u8, note: u8) -> Vec<u8> {
+        let direct = self
+            .held_pipes
+            .get(&PipeHolder::Keys(channel))
+            .into_iter()
+            .flatten()
+            .filter(|(_, stop_note)| *stop_note == note);
+        let coupled_notes: BTreeSet<u8> = self
+            .active_couplers
+            .iter()
+            .filter_map(|id| self.organ.couplers.get(id))
+            .filter_map(|coupler| u8::try_from(note as i16 + coupler.key_shift as i16).ok())
+            .collect();
+        let coupled = self
+            .held_pipes
+            .get(&PipeHolder::Couplers(channel))
+            .into_iter()
+            .flatten()
+            .filter(|(_, stop_note)| coupled_notes.contains(stop_note));
+
+        let mut pipe_notes = Vec::new();
+        for &(stop_index, stop_note) in direct.chain(coupled) {
+            let Some(stop) = self.organ.stops.get(stop_index) else {
+                continue;
+            };
+            for (rank_index, _) in stop
+                .rank_ids
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| *id == rank_id)
+            {
+                let transpose = stop.rank_offset(rank_index).transpose;
+                if let Ok(pipe_note) = u8::try_from(stop_note as i16 + transpose as i16)
+                    && !pipe_notes.contains(&pipe_note)
+                {
+                    pipe_notes.push(pipe_note);
+                }
+            }
+        }
+        pipe_notes
+    }
+
+    /// Refreshes the coupled notes of every channel with held keys or sounding couplers.
+    fn refresh_all_coupled_notes(&mut self, audio_tx: &Sender<AppMessage>) -> Result<()> {
+        let channels: BTreeSet<u8> = self
+            .channel_active_notes
+            .keys()
+            .copied()
+            .chain(self.held_pipes.keys().filter_map(|holder| match holder {
+                PipeHolder::Couplers(channel) => Some(*channel),
+                PipeHolder::Keys(_) => None,
+            }))
+            .collect();
+        for channel in channels {
+            self.refresh_coupled_notes(channel, audio_tx)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the pipes `holder` holds down with `target` (pipe -> velocity).
+    /// Sends NoteOn for pipes nothing held before and NoteOff for pipes nothing holds anymore,
+    /// so that a pipe held by a key and a coupler keeps sounding until both let go.
+    fn set_held_pipes(
+        &mut self,
+        holder: PipeHolder,
+        target: BTreeMap<(usize, u8), u8>,
+        audio_tx: &Sender<AppMessage>,
+    ) -> Result<()> {
+        let current = self.held_pipes.remove(&holder).unwrap_or_default();
+
+        for &pipe in current.iter().filter(|pipe| !target.contains_key(pipe)) {
+            let Some(count) = self.pipe_hold_counts.get_mut(&pipe) else {
+                continue;
+            };
+            *count -= 1;
+            if *count == 0 {
+                self.pipe_hold_counts.remove(&pipe);
+                if let Some(stop) = self.organ.stops.get(pipe.0) {
+                    audio_tx.send(AppMessage::NoteOff(pipe.1, stop.name.clone()))?;
+                }
+            }
+        }
+        for (&pipe, &velocity) in target.iter().filter(|(pipe, _)| !current.contains(pipe)) {
+            let count = self.pipe_hold_counts.entry(pipe).or_default();
+            *count += 1;
+            if *count == 1
+                && let Some(stop) = self.organ.stops.get(pipe.0)
+            {
+                audio_tx.send(AppMessage::NoteOn(pipe.1, velocity, stop.name.clone()))?;
+            }
+        }
+
+        if !target.is_empty() {
+            self.held_pipes.insert(holder, target.into_keys().collect());
+        }
+        Ok(())
+    }
+
+    /// Lets go of the pipes `holder` holds that match `release`.
+    fn release_held_pipes(
+        &mut self,
+        holder: PipeHolder,
+        release: impl Fn(&(usize, u8)) -> bool,
+        audio_tx: &Sender<AppMessage>,
+    ) -> Result<()> {
+        let Some(current) = self.held_pipes.get(&holder) else {
+            return Ok(());
+        };
+        // Pipes that stay held keep their count, their velocity is not used
+        let target = current
+            .iter()
+            .filter(|pipe| !release(pipe))
+            .map(|&pipe| (pipe, 0))
+            .collect();
+        self.set_held_pipes(holder, target, audio_tx)
+    }
+
+    /// Plays a key on `channel`: the stops routed to the channel and the couplers it drives.
+    fn press_key(
+        &mut self,
+        channel: u8,
+        note: u8,
+        velocity: u8,
+        audio_tx: &Sender<AppMessage>,
+    ) -> Result<()> {
+        self.channel_active_notes
+            .entry(channel)
+            .or_default()
+            .insert(note);
+        self.key_velocities.insert((channel, note), velocity);
+
+        let mut target: BTreeMap<(usize, u8), u8> = self
+            .held_pipes
+            .get(&PipeHolder::Keys(channel))
+            .into_iter()
+            .flatten()
+            .map(|&pipe| (pipe, velocity))
+            .collect();
+        for (stop_index, active_channels) in &self.stop_channels {
+            if active_channels.contains(&channel) {
+                target.insert((*stop_index, note), velocity);
+            }
+        }
+        self.set_held_pipes(PipeHolder::Keys(channel), target, audio_tx)?;
+        self.refresh_coupled_notes(channel, audio_tx)
+    }
+
+    /// Releases a key on `channel`, and whatever it played directly or through couplers.
+    fn release_key(&mut self, channel: u8, note: u8, audio_tx: &Sender<AppMessage>) -> Result<()> {
+        if let Some(notes) = self.channel_active_notes.get_mut(&channel) {
+            notes.remove(&note);
+        }
+        self.key_velocities.remove(&(channel, note));
+        self.release_held_pipes(
+            PipeHolder::Keys(channel),
+            |&(_, pipe_note)| pipe_note == note,
+            audio_tx,
+        )?;
+        self.refresh_coupled_notes(channel, audio_tx)
+    }
+
+    /// Silences every pipe and forgets all held keys, e.g. for the panic button
+    /// or when MIDI file playback jumps.
+    pub fn all_notes_off(&mut self, audio_tx: &Sender<AppMessage>) -> Result<()> {
+        self.channel_active_notes.clear();
+        self.key_velocities.clear();
+        self.held_pipes.clear();
+        self.pipe_hold_counts.clear();
+        audio_tx.send(AppMessage::AllNotesOff)?;
+        Ok(())
+    }
+
     /// Loads the MIDI channel mapping preset bank for the specified organ from the JSON file.
-    fn load_presets(organ_name: &str) -> PresetBank {
+    fn load_presets(organ: &Organ) -> PresetBank {
         let preset_path = get_preset_file_path();
         File::open(preset_path)
             .map_err(anyhow::Error::from) // Convert std::io::Error
@@ -329,7 +686,17 @@ impl AppState {
             .ok() // Convert Result to Option
             .and_then(|config: PresetConfig| {
                 // Find the presets for this organ
-                config.get(organ_name).cloned()
+                config.get(&organ.name).cloned()
+            })
+            .map(|mut bank: PresetBank| {
+                for preset in bank.iter_mut().flatten() {
+                    resolve_virtual_stop_ids(
+                        organ,
+                        &mut preset.virtual_stop_channels,
+                        &mut preset.stop_channels,
+                    );
+                }
+                bank
             })
             .unwrap_or_else(Default::default) // Return an empty bank [None; 12] if not found
     }
@@ -346,7 +713,15 @@ impl AppState {
             .unwrap_or_default(); // Create a new map if it doesn't exist
 
         // Update or insert the preset bank for the current organ
-        config.insert(self.organ.name.clone(), self.presets.clone());
+        let mut bank = self.presets.clone();
+        for preset in bank.iter_mut().flatten() {
+            key_virtual_stops_by_id(
+                &self.organ,
+                &mut preset.stop_channels,
+                &mut preset.virtual_stop_channels,
+            );
+        }
+        config.insert(self.organ.name.clone(), bank);
 
         // Write the entire config file back to disk
         let file = File::create(preset_path)?;
@@ -390,26 +765,26 @@ impl AppState {
                         ControlAction::SetTremulant { id, active } => {
                             self.set_tremulant_active(id, active, audio_tx);
                         }
+                        ControlAction::SetCoupler { id, active } => {
+                            self.set_coupler_active(id, active, audio_tx)?;
+                        }
                         ControlAction::LoadPreset { slot_index } => {
                             let _ = self.recall_preset(slot_index, audio_tx);
                         }
-                    }
-                }
-
-                // Track the active note (for visuals/logic)
-                self.channel_active_notes
-                    .entry(channel)
-                    .or_default()
-                    .insert(note);
-                // Find all stops mapped to this channel and send AppMessage
-                for (stop_index, active_channels) in &self.stop_channels {
-                    if active_channels.contains(&channel) {
-                        if let Some(stop) = self.organ.stops.get(*stop_index) {
-                            let stop_name = stop.name.clone();
-                            audio_tx.send(AppMessage::NoteOn(note, vel, stop_name))?;
+                        ControlAction::SetEnclosure { id, value } => {
+                            self.set_enclosure_value(id, value, audio_tx);
+                        }
+                        ControlAction::TransposeStep { delta } => {
+                            self.set_transpose(self.transpose.saturating_add(delta), audio_tx);
+                        }
+                        ControlAction::SetMasterTuning { value } => {
+                            self.set_master_tuning(controller_to_cents(value), audio_tx);
                         }
                     }
                 }
+
+                // Play the stops mapped to this channel and the couplers they drive
+                self.press_key(channel, note, vel, audio_tx)?;
             }
             TuiMessage::MidiNoteOff(note, channel) => {
                 // Create Spec
@@ -436,22 +811,64 @@ impl AppState {
                         ControlAction::SetTremulant { id, active } => {
                             self.set_tremulant_active(id, active, audio_tx);
                         }
+                        ControlAction::SetCoupler { id, active } => {
+                            self.set_coupler_active(id, active, audio_tx)?;
+                        }
                         ControlAction::LoadPreset { slot_index } => {
                             let _ = self.recall_preset(slot_index, audio_tx);
                         }
+                        ControlAction::SetEnclosure { id, value } => {
+                            self.set_enclosure_value(id, value, audio_tx);
+                        }
+                        ControlAction::TransposeStep { delta } => {
+                            self.set_transpose(self.transpose.saturating_add(delta), audio_tx);
+                        }
+                        ControlAction::SetMasterTuning { value } => {
+                            self.set_master_tuning(controller_to_cents(value), audio_tx);
+                        }
                     }
                 }
 
-                // Stop tracking the active note
-                if let Some(notes) = self.channel_active_notes.get_mut(&channel) {
-                    notes.remove(&note);
-                }
-                // Find all stops mapped to this channel and send AppMessage
-                for (stop_index, active_channels) in &self.stop_channels {
-                    if active_channels.contains(&channel) {
-                        if let Some(stop) = self.organ.stops.get(*stop_index) {
-                            let stop_name = stop.name.clone();
-                            audio_tx.send(AppMessage::NoteOff(note, stop_name))?;
+                self.release_key(channel, note, audio_tx)?;
+            }
+            TuiMessage::MidiControlChange(controller, value, channel) => {
+                // Create Spec
+                let spec = MidiEventSpec::ControlChange {
+                    channel,
+                    controller,
+                };
+
+                // MIDI control learning
+                self.last_midi_event_received = Some((spec.clone(), Instant::now()));
+
+                let mut actions = self.midi_control_map.check_event(&spec);
+                actions.extend(self.midi_control_map.check_control_change(&spec, value));
+                for action in actions {
+                    match action {
+                        ControlAction::SetStop {
+                            index,
+                            internal_channel,
+                            active,
+                        } => {
+                            self.set_stop_channel_state(index, internal_channel, active, audio_tx)?;
+                        }
+                        ControlAction::SetTremulant { id, active } => {
+                            self.set_tremulant_active(id, active, audio_tx);
+                        }
+                        ControlAction::SetCoupler { id, active } => {
+                            self.set_coupler_active(id, active, audio_tx)?;
+                        }
+                        ControlAction::LoadPreset { slot_index } => {
+                            let _ = self.recall_preset(slot_index, audio_tx);
+                        }
+                        ControlAction::SetEnclosure { id, value } => {
+                            self.set_enclosure_value(id, value, audio_tx);
+                        }
+                        ControlAction::TransposeStep { delta } => {
+                            self.set_transpose(self.transpose.saturating_add(delta), audio_tx);
+                        }
+                        ControlAction::SetMasterTuning { value } => {
+                            self.set_master_tuning(controller_to_cents(value), audio_tx);
                         }
                     }
                 }
@@ -478,34 +895,41 @@ impl AppState {
                         ControlAction::SetTremulant { id, active } => {
                             self.set_tremulant_active(id, active, audio_tx);
                         }
+                        ControlAction::SetCoupler { id, active } => {
+                            self.set_coupler_active(id, active, audio_tx)?;
+                        }
                         ControlAction::LoadPreset { slot_index } => {
                             let _ = self.recall_preset(slot_index, audio_tx);
                         }
+                        ControlAction::SetEnclosure { id, value } => {
+                            self.set_enclosure_value(id, value, audio_tx);
+                        }
+                        ControlAction::TransposeStep { delta } => {
+                            self.set_transpose(self.transpose.saturating_add(delta), audio_tx);
+                        }
+                        ControlAction::SetMasterTuning { value } => {
+                            self.set_master_tuning(controller_to_cents(value), audio_tx);
+                        }
                     }
                 }
             }
             TuiMessage::MidiChannelNotesOff(channel) => {
                 // Handle channel-specific all notes off
-                if let Some(notes_to_stop) = self.channel_active_notes.remove(&channel) {
-                    // Find all stops mapped to this channel
-                    for (stop_index, active_channels) in &self.stop_channels {
-                        if active_channels.contains(&channel) {
-                            if let Some(stop) = self.organ.stops.get(*stop_index) {
-                                let stop_name = stop.name.clone();
-                                // Send NoteOff for each note that was active on this channel
-                                for &note in &notes_to_stop {
-                                    audio_tx.send(AppMessage::NoteOff(note, stop_name.clone()))?;
-                                }
-                            }
-                        }
-                    }
-                }
+                self.channel_active_notes.remove(&channel);
+                self.key_velocities
+                    .retain(|&(key_channel, _), _| key_channel != channel);
+                self.set_held_pipes(PipeHolder::Keys(channel), BTreeMap::new(), audio_tx)?;
+                self.refresh_coupled_notes(channel, audio_tx)?;
             }
 
             // --- Other TUI messages ---
             TuiMessage::CpuLoadUpdate(cpu_load) => self.cpu_load = cpu_load,
             TuiMessage::ActiveVoicesUpdate(count) => self.active_voice_count = count,
             TuiMessage::AudioUnderrun => self.last_underrun = Some(Instant::now()),
+            TuiMessage::VoicePoolExhausted(count) => {
+                self.last_voice_pool_exhausted = Some(Instant::now());
+                self.skipped_voice_count += count;
+            }
             TuiMessage::MidiLog(log) => self.add_midi_log(log),
             TuiMessage::Error(err) => self.error_msg = Some(err),
             TuiMessage::TuiNoteOn(note, channel, start_time) => {
@@ -559,16 +983,15 @@ impl AppState {
         } else if !active && was_active {
             stop_set.remove(&channel);
             // Cut notes if disabling
-            if let Some(notes_to_stop) = self.channel_active_notes.get(&channel) {
-                if let Some(stop) = self.organ.stops.get(stop_index) {
-                    let stop_name = stop.name.clone();
-                    for &note in notes_to_stop {
-                        audio_tx.send(AppMessage::NoteOff(note, stop_name.clone()))?;
-                    }
-                }
-            }
+            self.release_held_pipes(
+                PipeHolder::Keys(channel),
+                |&(pipe_stop, _)| pipe_stop == stop_index,
+                audio_tx,
+            )?;
         }
 
+        self.refresh_all_coupled_notes(audio_tx)?;
+
         // Update LCD info
         if let Some(stop) = self.organ.stops.get(stop_index) {
             self.last_stop_change_name = self.get_stop_activity_label(active) + &stop.name.clone();
@@ -602,17 +1025,8 @@ impl AppState {
             // Update Log
             self.add_midi_log(format!("Key On: {} (Ch 1, Vel {})", note_name, velocity));
 
-            // Dispatch Audio for mapped stops
-            // We iterate all loaded stops to see which ones are listening to Channel 0
-            for (stop_idx, stop) in self.organ.stops.iter().enumerate() {
-                if let Some(channels) = self.stop_channels.get(&stop_idx) {
-                    if channels.contains(&channel) {
-                        // This stop is mapped to Ch 1, play it!
-                        let _ =
-                            audio_tx.send(AppMessage::NoteOn(note, velocity, stop.name.clone()));
-                    }
-                }
-            }
+            // Dispatch Audio for the stops listening to Channel 0
+            let _ = self.press_key(channel, note, velocity, audio_tx);
         } else {
             // --- NOTE OFF ---
 
@@ -630,13 +1044,7 @@ impl AppState {
             self.add_midi_log(format!("Key Off: {} (Ch 1)", note_name));
 
             // Dispatch Audio
-            for (stop_idx, stop) in self.organ.stops.iter().enumerate() {
-                if let Some(channels) = self.stop_channels.get(&stop_idx) {
-                    if channels.contains(&channel) {
-                        let _ = audio_tx.send(AppMessage::NoteOff(note, stop.name.clone()));
-                    }
-                }
-            }
+            let _ = self.release_key(channel, note, audio_tx);
         }
     }
 
@@ -653,15 +1061,12 @@ impl AppState {
             if stop_set.contains(&channel) {
                 stop_set.remove(&channel);
 
-                // --- Send NoteOff for all active notes on this channel for this stop ---
-                if let Some(notes_to_stop) = self.channel_active_notes.get(&channel) {
-                    if let Some(stop) = self.organ.stops.get(stop_index) {
-                        let stop_name = stop.name.clone();
-                        for &note in notes_to_stop {
-                            audio_tx.send(AppMessage::NoteOff(note, stop_name.clone()))?;
-                        }
-                    }
-                }
+                // --- Release the notes held on this channel for this stop ---
+                self.release_held_pipes(
+                    PipeHolder::Keys(channel),
+                    |&(pipe_stop, _)| pipe_stop == stop_index,
+                    audio_tx,
+                )?;
                 false
             } else {
                 stop_set.insert(channel);
@@ -669,6 +1074,8 @@ impl AppState {
             }
         };
 
+        self.refresh_all_coupled_notes(audio_tx)?;
+
         // Update LCD info
         if let Some(stop) = self.organ.stops.get(stop_index) {
             self.last_stop_change_name =
@@ -679,6 +1086,34 @@ impl AppState {
         Ok(())
     }
 
+    /// Draws or retires a stop as a console drawknob would.
+    /// A drawn stop is routed to the channels already playing its manual, or channel 0.
+    pub fn toggle_stop(&mut self, stop_index: usize, audio_tx: &Sender<AppMessage>) -> Result<()> {
+        let is_active = self
+            .stop_channels
+            .get(&stop_index)
+            .is_some_and(|channels| !channels.is_empty());
+        if is_active {
+            return self.select_none_channels_for_stop(stop_index, audio_tx);
+        }
+
+        let mut channels: BTreeSet<u8> = BTreeSet::new();
+        if let Some(manual_id) = self.organ.manual_id_for_stop(stop_index) {
+            for other in self.organ.stop_indices_for_manual(manual_id) {
+                if let Some(other_channels) = self.stop_channels.get(&other) {
+                    channels.extend(other_channels.iter().copied().filter(|&c| c < 16));
+                }
+            }
+        }
+        if channels.is_empty() {
+            channels.insert(0);
+        }
+        for channel in channels {
+            self.set_stop_channel_state(stop_index, channel, true, audio_tx)?;
+        }
+        Ok(())
+    }
+
     /// Activates all channels for the specified stop.
     pub fn select_all_channels_for_stop(&mut self, stop_index: usize) {
         let stop_set = self.stop_channels.entry(stop_index).or_default();
@@ -704,28 +1139,21 @@ impl AppState {
             let channels_to_deactivate: Vec<u8> =
                 stop_set.iter().copied().filter(|&c| c < 16).collect();
 
-            if !channels_to_deactivate.is_empty() {
-                if let Some(stop) = self.organ.stops.get(stop_index) {
-                    let stop_name = stop.name.clone();
-                    for channel in channels_to_deactivate {
-                        // --- Send NoteOff for all active notes on this channel for this stop ---
-                        if let Some(notes_to_stop) = self.channel_active_notes.get(&channel) {
-                            for &note in notes_to_stop {
-                                audio_tx.send(AppMessage::NoteOff(note, stop_name.clone()))?;
-                            }
-                        }
-                        // Now remove it from the state
-                        stop_set.remove(&channel);
-                    }
-                } else {
-                    // Fallback (shouldn't happen)
-                    for channel in channels_to_deactivate {
-                        stop_set.remove(&channel);
-                    }
-                }
+            for &channel in &channels_to_deactivate {
+                stop_set.remove(&channel);
+            }
+            // --- Release the notes held on these channels for this stop ---
+            for channel in channels_to_deactivate {
+                self.release_held_pipes(
+                    PipeHolder::Keys(channel),
+                    |&(pipe_stop, _)| pipe_stop == stop_index,
+                    audio_tx,
+                )?;
             }
         }
 
+        self.refresh_all_coupled_notes(audio_tx)?;
+
         // Update LCD info
         if let Some(stop) = self.organ.stops.get(stop_index) {
             self.last_stop_change_name = self.get_stop_activity_label(false) + &stop.name.clone();
@@ -798,6 +1226,7 @@ impl AppState {
         let new_preset = Preset {
             name: name.clone(),
             stop_channels: self.stop_channels.clone(),
+            virtual_stop_channels: HashMap::new(),
         };
         self.presets[slot] = Some(new_preset);
 
@@ -816,7 +1245,7 @@ impl AppState {
         if slot >= 12 {
             return Ok(());
         }
-        if let Some(preset_data) = &self.presets[slot] {
+        if let Some(preset_data) = self.presets[slot].clone() {
             let new_preset_map = &preset_data.stop_channels;
             let _preset_name = &preset_data.name;
 
@@ -835,7 +1264,7 @@ impl AppState {
                 // We iterate over the OLD map to find Stop -> Channel mappings that have been removed.
                 for (stop_index, old_active_channels) in &old_map {
                     // Get the set of channels enabled for this stop in the new preset
-                    let new_active_channels_opt = self.stop_channels.get(stop_index);
+                    let new_active_channels_opt = new_preset_map.get(stop_index);
 
                     for &channel in old_active_channels {
                         // Check if this specific channel is still mapped to this stop in the new preset
@@ -847,25 +1276,18 @@ impl AppState {
                         // If the channel is no longer mapped to this stop, we must cut the audio
                         // for any notes currently being held on this MIDI channel.
                         if !is_still_mapped {
-                            if let Some(active_notes_on_channel) =
-                                self.channel_active_notes.get(&channel)
-                            {
-                                if let Some(stop) = self.organ.stops.get(*stop_index) {
-                                    let stop_name = stop.name.clone();
-
-                                    // Send NoteOff for currently active notes on this specific channel/stop combo
-                                    for &note in active_notes_on_channel {
-                                        audio_tx
-                                            .send(AppMessage::NoteOff(note, stop_name.clone()))?;
-                                    }
-                                }
-                            }
+                            self.release_held_pipes(
+                                PipeHolder::Keys(channel),
+                                |&(pipe_stop, _)| pipe_stop == *stop_index,
+                                audio_tx,
+                            )?;
                         }
                     }
                 }
                 log::info!("Recalled preset from slot F{}", slot + 1);
                 self.last_recalled_preset_name = format!("F{}: {}", slot + 1, _preset_name);
                 self.add_midi_log(format!("Recalled preset F{}", slot + 1));
+                self.refresh_all_coupled_notes(audio_tx)?;
             } else {
                 // This can happen if the organ definition file changed
                 let err_msg = format!(
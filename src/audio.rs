@@ -8,6 +8,7 @@ use ringbuf::HeapRb;
 use ringbuf::traits::{Consumer, Observer, Producer, Split};
 use std::cmp::Ordering as CmpOrdering;
 use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
@@ -17,14 +18,20 @@ use crate::TuiMessage;
 use crate::app::{ActiveNote, AppMessage};
 use crate::midi_recorder::MidiRecorder;
 use crate::organ::Organ;
+use crate::output_routing::{MAX_OUTPUT_BUSES, OutputRouting};
+use crate::resample::{RESAMPLE_HISTORY_FRAMES, Resampler};
 
-use crate::audio_convolver::StereoConvolver;
-use crate::audio_event::{enforce_voice_limit, process_message, process_note_on};
-use crate::audio_loader::run_loader_job;
+use crate::audio_event::{
+    PipeHistoryMap, PipeTuning, ReverbConvolvers, enforce_voice_limit, process_message,
+    process_note_on,
+};
+use crate::audio_loader::start_loader_pool;
 use crate::audio_recorder::AudioRecorder;
 use crate::voice::{
-    CHANNEL_COUNT, MAX_NEW_VOICES_PER_BLOCK, SpawnJob, TREMULANT_AM_BOOST, TremulantLfo, Voice,
+    CHANNEL_COUNT, ENCLOSURE_CLOSED_CUTOFF_HZ, ENCLOSURE_SLEW_PER_SEC, MAX_NEW_VOICES_PER_BLOCK,
+    SpawnJob, TREMULANT_AM_BOOST, TremulantLfo, VOICE_LEVEL_TIME, Voice, VoicePool,
 };
+use crate::wind::{WindDroop, WindSupply};
 
 // Handle struct that manages the lifecycle for the audio thread
 #[allow(dead_code)]
@@ -234,26 +241,20 @@ fn spawn_audio_processing_thread<P>(
     buffer_size_frames: usize,
     mut system_gain: f32,
     mut polyphony: usize,
+    tracker_delay_scale: f32,
     tui_tx: mpsc::Sender<TuiMessage>,
     shared_midi_recorder: Arc<Mutex<Option<MidiRecorder>>>,
     stop_signal: Arc<AtomicBool>,
+    device_channels: usize,
+    output_routing: OutputRouting,
 ) where
     P: Producer<Item = f32> + Send + 'static,
 {
-    let (ir_loader_tx, ir_loader_rx) = mpsc::channel::<Result<StereoConvolver>>();
+    let (ir_loader_tx, ir_loader_rx) = mpsc::channel::<Result<ReverbConvolvers>>();
     let (spawner_tx, spawner_rx) = mpsc::channel::<SpawnJob>();
 
-    // Background Thread: Spawner / Loader
-    thread::spawn(move || {
-        log::info!("[SpawnerThread] Started.");
-        for job in spawner_rx {
-            // Detached thread for each voice load to allow concurrency
-            thread::spawn(move || {
-                run_loader_job(job);
-            });
-        }
-        log::info!("[SpawnerThread] Shutting down.");
-    });
+    // Background Threads: a fixed set of loaders streams the samples of all voices
+    start_loader_pool(spawner_rx);
 
     // Real-time Audio Processing Thread
     thread::spawn(move || {
@@ -266,16 +267,26 @@ fn spawn_audio_processing_thread<P>(
 
         let mut active_notes: HashMap<u8, Vec<ActiveNote>> = HashMap::new();
         let mut voices: HashMap<u64, Voice> = HashMap::with_capacity(128);
+        let mut voice_pool = VoicePool::new(polyphony, spawner_tx);
         let mut voice_counter: u64 = 0;
 
+        // Each output bus is mixed in stereo, then the buses are laid out on the device channels
+        let mut output_buses = output_routing.effective_buses();
+        let mut bus_buffers: Vec<Vec<f32>> =
+            vec![vec![0.0; buffer_size_frames * CHANNEL_COUNT]; MAX_OUTPUT_BUSES];
+        // Stereo sum of all buses, for recording
         let mut mix_buffer: Vec<f32> = vec![0.0; buffer_size_frames * CHANNEL_COUNT];
+        let mut output_buffer: Vec<f32> = vec![0.0; buffer_size_frames * device_channels];
         // Scratch buffers for Reverb
         let mut reverb_dry_l: Vec<f32> = vec![0.0; buffer_size_frames];
         let mut reverb_dry_r: Vec<f32> = vec![0.0; buffer_size_frames];
         let mut wet_buffer_l: Vec<f32> = vec![0.0; buffer_size_frames];
         let mut wet_buffer_r: Vec<f32> = vec![0.0; buffer_size_frames];
 
-        let mut convolver = StereoConvolver::new(buffer_size_frames);
+        // One reverb per output the buses play on, so every bus is reverberated on its
+        // own speakers. Built with the IR for the outputs in use only.
+        let mut convolvers: ReverbConvolvers = Vec::new();
+        let mut reverb_ir_path: Option<PathBuf> = None;
         let mut wet_dry_ratio: f32 = 0.0;
 
         let mut voices_to_remove: Vec<u64> = Vec::with_capacity(32);
@@ -290,6 +301,27 @@ fn spawn_audio_processing_thread<P>(
         let mut active_tremulants_ids: HashMap<String, bool> = HashMap::new();
         let mut tremulant_lfos: HashMap<String, TremulantLfo> = HashMap::new();
         let mut prev_windchest_mods: HashMap<String, f32> = HashMap::new();
+        // Enclosure pedal positions (0.0 closed - 1.0 open): requested and smoothed
+        let mut enclosure_targets: HashMap<String, f32> = organ
+            .enclosures
+            .values()
+            .map(|enc| (enc.id_str.clone(), enc.default_value as f32 / 127.0))
+            .collect();
+        let mut enclosure_levels: HashMap<String, f32> = enclosure_targets.clone();
+        let mut pipe_history: PipeHistoryMap = HashMap::new();
+        let mut pipe_tuning = PipeTuning {
+            rank_buses: output_routing.rank_buses(&organ),
+            ..Default::default()
+        };
+        let mut wind_supply = WindSupply::new(&organ);
+        // Per windchest group: (gain, brightness)
+        let mut prev_enclosure_mods: HashMap<String, (f32, f32)> = HashMap::new();
+        let enclosure_lp_coeff =
+            1.0 - (-std::f32::consts::TAU * ENCLOSURE_CLOSED_CUTOFF_HZ / sample_rate as f32).exp();
+        // Per block smoothing of the voice level meters
+        let level_coeff =
+            1.0 - (-(buffer_size_frames as f32) / (sample_rate as f32 * VOICE_LEVEL_TIME)).exp();
+        let history_samples = RESAMPLE_HISTORY_FRAMES * CHANNEL_COUNT;
         let mut scratch_read_buffer: Vec<f32> = vec![0.0; buffer_size_frames * CHANNEL_COUNT * 2];
         let mut audio_recorder: Option<AudioRecorder> = None;
 
@@ -316,17 +348,25 @@ fn spawn_audio_processing_thread<P>(
                         &mut wet_dry_ratio,
                         &mut system_gain,
                         &mut polyphony,
+                        tracker_delay_scale,
                         &ir_loader_tx,
                         sample_rate,
                         buffer_size_frames,
+                        device_channels,
                         &mut active_notes,
                         &organ,
                         &mut voices,
                         &mut voice_counter,
                         &stop_name_to_index_map,
-                        &spawner_tx,
+                        &mut voice_pool,
                         &mut pending_note_queue,
                         &mut active_tremulants_ids,
+                        &mut enclosure_targets,
+                        &mut pipe_history,
+                        &mut pipe_tuning,
+                        &mut wind_supply,
+                        &mut output_buses,
+                        &mut reverb_ir_path,
                         &mut audio_recorder,
                         &tui_tx,
                         &shared_midi_recorder,
@@ -346,7 +386,11 @@ fn spawn_audio_processing_thread<P>(
                         &mut voice_counter,
                         &stop_name_to_index_map,
                         sample_rate,
-                        &spawner_tx,
+                        tracker_delay_scale,
+                        &mut voice_pool,
+                        &active_tremulants_ids,
+                        &mut pipe_history,
+                        &pipe_tuning,
                     );
                     new_voice_count += 1;
                 } else {
@@ -355,14 +399,17 @@ fn spawn_audio_processing_thread<P>(
             }
 
             // Receive Reverb IR
-            if let Ok(Ok(conv)) = ir_loader_rx.try_recv() {
-                convolver = conv;
+            voice_pool.receive_buffers();
+            if let Ok(Ok(convs)) = ir_loader_rx.try_recv() {
+                convolvers = convs;
                 if wet_dry_ratio == 0.0 {
                     wet_dry_ratio = 0.3;
                 }
             }
 
-            mix_buffer.fill(0.0);
+            for buffer in bus_buffers.iter_mut().take(output_buses.len()) {
+                buffer.fill(0.0);
+            }
             enforce_voice_limit(&mut voices, sample_rate, polyphony);
 
             // Update Tremulants
@@ -370,6 +417,10 @@ fn spawn_audio_processing_thread<P>(
             let mut current_windchest_mods: HashMap<String, f32> = HashMap::new();
 
             for (trem_id, trem_def) in &organ.tremulants {
+                // Wave tremulants are handled by switching samples, not by the LFO
+                if trem_def.is_wave {
+                    continue;
+                }
                 let is_active = *active_tremulants_ids.get(trem_id).unwrap_or(&false);
                 let target_level = if is_active { 1.0 } else { 0.0 };
                 let lfo = tremulant_lfos
@@ -434,6 +485,47 @@ fn spawn_audio_processing_thread<P>(
                 }
             }
 
+            // Update Enclosures
+            // Gain follows the pedal down to the enclosure's minimum level, and the
+            // closing shutters blend in a low-passed copy of the signal.
+            let mut current_enclosure_mods: HashMap<String, (f32, f32)> = HashMap::new();
+            if !organ.enclosures.is_empty() {
+                let max_step = ENCLOSURE_SLEW_PER_SEC * dt;
+                for (enc_id, target) in &enclosure_targets {
+                    let level = enclosure_levels.entry(enc_id.clone()).or_insert(*target);
+                    *level += (*target - *level).clamp(-max_step, max_step);
+                }
+
+                for wc_group in organ.windchest_groups.values() {
+                    if wc_group.enclosure_ids.is_empty() {
+                        continue;
+                    }
+                    let mut gain = 1.0;
+                    let mut brightness: f32 = 1.0;
+                    for enc_id in &wc_group.enclosure_ids {
+                        let (Some(enc), Some(level)) =
+                            (organ.enclosures.get(enc_id), enclosure_levels.get(enc_id))
+                        else {
+                            continue;
+                        };
+                        let min_level = enc.min_amplitude_level * 0.01;
+                        gain *= min_level + (1.0 - min_level) * level;
+                        brightness = brightness.min(*level);
+                    }
+                    current_enclosure_mods.insert(wc_group.id_str.clone(), (gain, brightness));
+                    // Handle unpadded ID (e.g. "1" vs "01")
+                    let unpadded = wc_group.id_str.trim_start_matches('0');
+                    let key_unpadded = if unpadded.is_empty() { "0" } else { unpadded };
+                    if key_unpadded != wc_group.id_str {
+                        current_enclosure_mods.insert(key_unpadded.to_string(), (gain, brightness));
+                    }
+                }
+            }
+
+            // Update Wind
+            // Pipes speaking on a windchest group make its pitch and volume sag slightly.
+            wind_supply.update(&voices, dt);
+
             // Crossfade Logic
             // Checks if any attack voices are waiting for their release samples to be ready
             let mut crossfades_to_start: Vec<(u64, u64)> = Vec::with_capacity(16);
@@ -443,16 +535,21 @@ fn spawn_audio_processing_thread<P>(
                         if let Some(rv) = voices.get(&release_id) {
                             // Check if the release voice has buffered enough data to start playing
                             // We need at least one buffer worth of data to be safe
-                            let frames_buffered = rv.input_buffer.len() / CHANNEL_COUNT;
+                            let frames_buffered =
+                                (rv.input_buffer.len() - rv.buffer_start_idx) / CHANNEL_COUNT;
                             let rb_available = rv.consumer.occupied_len() / CHANNEL_COUNT;
 
                             // Condition: Either we have data in the input buffer,
                             // OR the ringbuffer has enough to fill it.
-                            if frames_buffered > 0 || rb_available > buffer_size_frames {
+                            // A delayed release only takes over in the block it starts sounding.
+                            let is_due = rv.start_delay_frames < buffer_size_frames;
+                            if is_due && (frames_buffered > 0 || rb_available > buffer_size_frames)
+                            {
                                 crossfades_to_start.push((*attack_id, release_id));
                             } else if rv.is_finished.load(Ordering::Relaxed) {
                                 // If the loader finished but gave us no data, abort the wait
                                 crossfades_to_start.push((*attack_id, u64::MAX));
+                                voices_to_remove.push(release_id);
                             }
                         } else {
                             // Release voice died?
@@ -464,18 +561,27 @@ fn spawn_audio_processing_thread<P>(
 
             // Apply the crossfade state changes
             for (aid, rid) in crossfades_to_start {
+                let mut attack_state = None;
                 if let Some(av) = voices.get_mut(&aid) {
                     av.is_fading_out = true;
                     av.is_awaiting_release_sample = false;
                     av.release_voice_id = None;
+                    attack_state = av.current_phase().map(|phase| (phase, av.level));
                 }
                 if rid != u64::MAX {
                     if let Some(rv) = voices.get_mut(&rid) {
                         rv.is_fading_in = true;
+                        // The release picks up where the attack's waveform is right now
+                        if let Some((phase, level)) = attack_state {
+                            rv.align_release(phase, level, buffer_size_frames * 2);
+                        }
                     }
                 }
             }
 
+            // Master tuning and transposition apply to every voice, including held notes
+            let master_pitch = pipe_tuning.master_pitch_ratio();
+
             // Voice Processing Loop
             for (voice_id, voice) in voices.iter_mut() {
                 if voice.is_fading_out && voice.fade_level <= 0.0001 {
@@ -483,6 +589,22 @@ fn spawn_audio_processing_thread<P>(
                     continue;
                 }
 
+                // Tracker Delay
+                // Voices waiting longer than this block stay silent; otherwise they
+                // start mid-block at the exact delayed frame.
+                if voice.start_delay_frames >= buffer_size_frames {
+                    voice.start_delay_frames -= buffer_size_frames;
+                    continue;
+                }
+                let delay_frames = voice.start_delay_frames;
+                let render_frames = buffer_size_frames - delay_frames;
+
+                // A release waiting to take over from its attack stays at its start,
+                // so it can be aligned to the attack when the crossfade begins
+                if voice.fade_level == 0.0 && !voice.is_fading_in && !voice.is_fading_out {
+                    continue;
+                }
+
                 // Calculate Tremulant Impact
                 let (trem_start_am, trem_end_am) = if let Some(wc_id) = &voice.windchest_group_id {
                     let start = *prev_windchest_mods.get(wc_id).unwrap_or(&1.0);
@@ -492,22 +614,47 @@ fn spawn_audio_processing_thread<P>(
                     (1.0, 1.0)
                 };
 
-                let pitch_start = 1.0 + (trem_start_am - 1.0) * 0.1;
-                let pitch_end = 1.0 + (trem_end_am - 1.0) * 0.1;
+                // Calculate Enclosure Impact
+                let (enc_start_gain, enc_end_gain, enc_brightness) = match voice
+                    .windchest_group_id
+                    .as_ref()
+                    .and_then(|wc_id| Some((wc_id, *current_enclosure_mods.get(wc_id)?)))
+                {
+                    Some((wc_id, (gain, brightness))) => {
+                        let start = prev_enclosure_mods.get(wc_id).map_or(gain, |m| m.0);
+                        (start, gain, Some(brightness))
+                    }
+                    None => (1.0, 1.0, None),
+                };
+
+                // Calculate Wind Impact
+                let (wind_start, wind_end) = match &voice.windchest_group_id {
+                    Some(wc_id) => wind_supply.droop(wc_id),
+                    None => (WindDroop::default(), WindDroop::default()),
+                };
+
+                let pitch_ratio = voice.pitch_ratio * master_pitch;
+                let pitch_start =
+                    (1.0 + (trem_start_am - 1.0) * 0.1) * wind_start.pitch * pitch_ratio;
+                let pitch_end = (1.0 + (trem_end_am - 1.0) * 0.1) * wind_end.pitch * pitch_ratio;
                 let avg_pitch = (pitch_start + pitch_end) * 0.5;
 
                 // Buffer Management (Lazy Compaction)
-                let needed_frames_float = buffer_size_frames as f32 * avg_pitch;
-                let needed_frames = needed_frames_float.ceil() as usize + 2; // +2 for interpolation safety
+                let needed_frames_float = render_frames as f32 * avg_pitch;
+                // +1 for the cursor's fraction, plus the frames the interpolator reads ahead
+                let needed_frames = needed_frames_float.ceil() as usize
+                    + 1
+                    + pipe_tuning.resampler.quality.lookahead_frames();
                 let needed_samples = needed_frames * CHANNEL_COUNT;
 
                 // If the buffer is getting too full/fragmented, compact it now.
-                // We keep valid data from buffer_start_idx onwards.
+                // We keep valid data from buffer_start_idx onwards, and the history before it.
                 if voice.buffer_start_idx + needed_samples > voice.input_buffer.capacity() {
-                    let remaining = voice.input_buffer.len() - voice.buffer_start_idx;
-                    voice.input_buffer.copy_within(voice.buffer_start_idx.., 0);
+                    let history_start = voice.buffer_start_idx - history_samples;
+                    let remaining = voice.input_buffer.len() - history_start;
+                    voice.input_buffer.copy_within(history_start.., 0);
                     voice.input_buffer.truncate(remaining);
-                    voice.buffer_start_idx = 0;
+                    voice.buffer_start_idx = history_samples;
                 }
 
                 // Fill Buffer
@@ -538,88 +685,156 @@ fn spawn_audio_processing_thread<P>(
 
                 // Create a SAFE slice of the valid data we are about to read.
                 let input_slice = &voice.input_buffer[voice.buffer_start_idx..];
+                voice.start_delay_frames = 0;
 
                 // Envelope
                 let env_start = voice.fade_level;
                 let mut env_end = env_start;
+                // Frame within the block at which a delayed fade-out begins
+                let mut fade_start_frame = 0;
                 if voice.is_fading_in {
-                    env_end =
-                        (env_start + voice.fade_increment * buffer_size_frames as f32).min(1.0);
+                    env_end = (env_start + voice.fade_increment * render_frames as f32).min(1.0);
                     if env_end >= 1.0 {
                         voice.is_fading_in = false;
                     }
                 } else if voice.is_fading_out {
-                    env_end =
-                        (env_start - voice.fade_increment * buffer_size_frames as f32).max(0.0);
+                    // Without a release sample the fade-out waits for the tracker delay
+                    fade_start_frame = voice.fade_out_delay_frames.min(render_frames);
+                    voice.fade_out_delay_frames -= fade_start_frame;
+                    env_end = (env_start
+                        - voice.fade_increment * (render_frames - fade_start_frame) as f32)
+                        .max(0.0);
                 }
                 voice.fade_level = env_end;
 
-                let gain_delta =
-                    (trem_end_am * env_end - trem_start_am * env_start) / buffer_size_frames as f32;
-                let mut current_gain_scalar = trem_start_am * env_start * voice.gain;
+                // A capped release fades out once its time is up
+                if let Some(frames_left) = voice.max_length_frames {
+                    if frames_left <= render_frames {
+                        voice.max_length_frames = None;
+                        voice.is_fading_in = false;
+                        voice.is_fading_out = true;
+                    } else {
+                        voice.max_length_frames = Some(frames_left - render_frames);
+                    }
+                }
 
-                let mix_chunks = mix_buffer.chunks_exact_mut(CHANNEL_COUNT);
+                let start_gain =
+                    trem_start_am * enc_start_gain * wind_start.gain * env_start * voice.gain;
+                let end_gain = trem_end_am * enc_end_gain * wind_end.gain * env_end * voice.gain;
+                let mut gain_delta = (end_gain - start_gain) / render_frames as f32;
+                // A fade starting mid-block holds the envelope until its frame, then ramps
+                let (gain_knee_frame, knee_gain, fade_gain_delta) =
+                    if fade_start_frame > 0 && fade_start_frame < render_frames {
+                        let t = fade_start_frame as f32 / render_frames as f32;
+                        let mod_start = trem_start_am * enc_start_gain * wind_start.gain;
+                        let mod_end = trem_end_am * enc_end_gain * wind_end.gain;
+                        let knee_gain =
+                            (mod_start + (mod_end - mod_start) * t) * env_start * voice.gain;
+                        gain_delta = (knee_gain - start_gain) / fade_start_frame as f32;
+                        let fade_gain_delta =
+                            (end_gain - knee_gain) / (render_frames - fade_start_frame) as f32;
+                        (fade_start_frame, knee_gain, fade_gain_delta)
+                    } else {
+                        (usize::MAX, end_gain, gain_delta)
+                    };
+                let mut current_gain_scalar = start_gain;
+                let [mut lp_l, mut lp_r] = voice.enclosure_lp_state;
+                let [[ll, lr], [rl, rr]] = voice.pan;
+                let mut block_energy = 0.0f32;
+
+                let bus = voice.output_bus.min(output_buses.len() - 1);
+                let mix_chunks = bus_buffers[bus][delay_frames * CHANNEL_COUNT..]
+                    .chunks_exact_mut(CHANNEL_COUNT);
                 let is_fast_path = (avg_pitch - 1.0).abs() < 0.00001;
 
                 if is_fast_path {
                     // Safe Fast Path
                     // No resampling. We map input samples 1:1 to output samples.
                     let start_offset = voice.cursor_pos.round() as usize * CHANNEL_COUNT;
-                    let end_offset = start_offset + buffer_size_frames * CHANNEL_COUNT;
+                    let end_offset = start_offset + render_frames * CHANNEL_COUNT;
 
                     // Ensure we don't read past the end (should be covered by needed_samples check, but strict safety requires this)
                     if let Some(valid_chunk) = input_slice.get(start_offset..end_offset) {
                         // ZIP allows the compiler to remove bounds checks and use SIMD
-                        for (mix, input_frame) in
-                            mix_chunks.zip(valid_chunk.chunks_exact(CHANNEL_COUNT))
+                        for (frame, (mix, input_frame)) in mix_chunks
+                            .zip(valid_chunk.chunks_exact(CHANNEL_COUNT))
+                            .enumerate()
                         {
                             // input_frame is guaranteed to have 2 elements [L, R]
-                            let l = input_frame[0];
-                            let r = input_frame[1];
-                            mix[0] += l * current_gain_scalar;
-                            mix[1] += r * current_gain_scalar;
+                            let mut l = input_frame[0];
+                            let mut r = input_frame[1];
+                            block_energy += l * l + r * r;
+                            if let Some(brightness) = enc_brightness {
+                                lp_l += enclosure_lp_coeff * (l - lp_l);
+                                lp_r += enclosure_lp_coeff * (r - lp_r);
+                                l = lp_l + (l - lp_l) * brightness;
+                                r = lp_r + (r - lp_r) * brightness;
+                            }
+                            mix[0] += (l * ll + r * lr) * current_gain_scalar;
+                            mix[1] += (l * rl + r * rr) * current_gain_scalar;
 
-                            current_gain_scalar += gain_delta;
+                            if frame + 1 == gain_knee_frame {
+                                current_gain_scalar = knee_gain;
+                                gain_delta = fade_gain_delta;
+                            } else {
+                                current_gain_scalar += gain_delta;
+                            }
                         }
                     }
 
                     // Advance cursor
-                    voice.cursor_pos =
-                        (voice.cursor_pos.round() as usize + buffer_size_frames) as f32;
+                    voice.cursor_pos = (voice.cursor_pos.round() as usize + render_frames) as f32;
                 } else {
                     // Safe Slow Path
-                    // Linear Interpolation.
-                    let pitch_delta = (pitch_end - pitch_start) / buffer_size_frames as f32;
+                    // Interpolation at the selected resampling quality.
+                    let pitch_delta = (pitch_end - pitch_start) / render_frames as f32;
                     let mut current_pitch_rate = pitch_start;
+                    let resampler = &pipe_tuning.resampler;
+                    let band = Resampler::band(pitch_start.max(pitch_end));
+                    // Include the played frames before the cursor the interpolator looks back at
+                    let history_slice =
+                        &voice.input_buffer[voice.buffer_start_idx - history_samples..];
 
                     // We hinted to the compiler earlier that we have 'needed_samples'.
                     // This assert helps the optimizer hoist bounds checks out of the loop.
-                    assert!(input_slice.len() >= needed_samples);
+                    assert!(history_slice.len() >= history_samples + needed_samples);
 
-                    for mix in mix_chunks {
+                    for (frame, mix) in mix_chunks.enumerate() {
                         let idx = voice.cursor_pos.floor() as usize;
                         let frac = voice.cursor_pos - idx as f32;
-                        let idx_stereo = idx * CHANNEL_COUNT;
 
-                        // Standard indexing is safe here.
-                        // Because of the assert above, LLVM knows these indices are valid.
-                        let s0_l = input_slice[idx_stereo];
-                        let s0_r = input_slice[idx_stereo + 1];
-                        let s1_l = input_slice[idx_stereo + 2];
-                        let s1_r = input_slice[idx_stereo + 3];
-
-                        let out_l = s0_l + (s1_l - s0_l) * frac;
-                        let out_r = s0_r + (s1_r - s0_r) * frac;
+                        let [mut out_l, mut out_r] = resampler.interpolate(
+                            history_slice,
+                            idx + RESAMPLE_HISTORY_FRAMES,
+                            frac,
+                            band,
+                        );
+                        block_energy += out_l * out_l + out_r * out_r;
+                        if let Some(brightness) = enc_brightness {
+                            lp_l += enclosure_lp_coeff * (out_l - lp_l);
+                            lp_r += enclosure_lp_coeff * (out_r - lp_r);
+                            out_l = lp_l + (out_l - lp_l) * brightness;
+                            out_r = lp_r + (out_r - lp_r) * brightness;
+                        }
 
-                        mix[0] += out_l * current_gain_scalar;
-                        mix[1] += out_r * current_gain_scalar;
+                        mix[0] += (out_l * ll + out_r * lr) * current_gain_scalar;
+                        mix[1] += (out_l * rl + out_r * rr) * current_gain_scalar;
 
                         voice.cursor_pos += current_pitch_rate;
-                        current_gain_scalar += gain_delta;
+                        if frame + 1 == gain_knee_frame {
+                            current_gain_scalar = knee_gain;
+                            gain_delta = fade_gain_delta;
+                        } else {
+                            current_gain_scalar += gain_delta;
+                        }
                         current_pitch_rate += pitch_delta;
                     }
                 }
 
+                voice.enclosure_lp_state = [lp_l, lp_r];
+                let block_level = block_energy / (render_frames * CHANNEL_COUNT) as f32;
+                voice.level += (block_level - voice.level) * level_coeff;
+
                 // Lazy Cleanup
                 // Instead of draining, just advance the integer start index
                 let samples_consumed_int = voice.cursor_pos.floor() as usize;
@@ -636,37 +851,88 @@ fn spawn_audio_processing_thread<P>(
             }
 
             prev_windchest_mods = current_windchest_mods;
+            prev_enclosure_mods = current_enclosure_mods;
 
             // Remove voices
             if !voices_to_remove.is_empty() {
                 for vid in voices_to_remove.iter() {
-                    voices.remove(vid);
+                    // An attack that ran out before its release was ready hands over right away
+                    let Some(mut removed) = voices.remove(vid) else {
+                        continue;
+                    };
+                    if let Some(release_id) = removed.release_voice_id
+                        && let Some(rv) = voices.get_mut(&release_id)
+                    {
+                        rv.is_fading_in = true;
+                    }
+                    voice_pool.recycle(&mut removed);
                 }
                 voices_to_remove.clear();
             }
 
-            // Apply Reverb & Global Gain
-            let apply_reverb = wet_dry_ratio > 0.0 && convolver.is_loaded;
-            if apply_reverb {
-                for i in 0..buffer_size_frames {
-                    reverb_dry_l[i] = mix_buffer[i * 2];
-                    reverb_dry_r[i] = mix_buffer[i * 2 + 1];
-                }
-                convolver.process(
-                    &reverb_dry_l,
-                    &reverb_dry_r,
-                    &mut wet_buffer_l,
-                    &mut wet_buffer_r,
-                );
-                let dl = (1.0 - wet_dry_ratio) * system_gain;
-                let wl = wet_dry_ratio * system_gain;
+            // Apply Global Gain & Reverb
+            // Every bus plays dry on its outputs, and feeds the reverb of those outputs by its send level.
+            mix_buffer.fill(0.0);
+            output_buffer.fill(0.0);
+            let apply_reverb =
+                wet_dry_ratio > 0.0 && convolvers.first().is_some_and(|(_, c)| c.is_loaded);
+            for (bus, buffer) in output_buses.iter().zip(&bus_buffers) {
+                let dry_share = if apply_reverb {
+                    1.0 - wet_dry_ratio * bus.reverb_send.clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                let dl = bus.gain * dry_share * system_gain;
+                let channel = bus.output_channel(device_channels);
                 for i in 0..buffer_size_frames {
-                    mix_buffer[i * 2] = (mix_buffer[i * 2] * dl) + (wet_buffer_l[i] * wl);
-                    mix_buffer[i * 2 + 1] = (mix_buffer[i * 2 + 1] * dl) + (wet_buffer_r[i] * wl);
+                    let l = buffer[i * 2] * dl;
+                    let r = buffer[i * 2 + 1] * dl;
+                    output_buffer[i * device_channels + channel] += l;
+                    output_buffer[i * device_channels + channel + 1] += r;
+                    mix_buffer[i * 2] += l;
+                    mix_buffer[i * 2 + 1] += r;
                 }
-            } else {
-                for s in mix_buffer.iter_mut() {
-                    *s *= system_gain;
+            }
+
+            if apply_reverb {
+                let wl = wet_dry_ratio * system_gain;
+                for (channel, convolver) in convolvers.iter_mut() {
+                    let channel = *channel;
+                    let mut has_buses = false;
+                    reverb_dry_l.fill(0.0);
+                    reverb_dry_r.fill(0.0);
+                    for (bus, buffer) in output_buses
+                        .iter()
+                        .zip(&bus_buffers)
+                        .filter(|(bus, _)| bus.output_channel(device_channels) == channel)
+                    {
+                        has_buses = true;
+                        let send = bus.gain * bus.reverb_send;
+                        if send == 0.0 {
+                            continue;
+                        }
+                        for i in 0..buffer_size_frames {
+                            reverb_dry_l[i] += buffer[i * 2] * send;
+                            reverb_dry_r[i] += buffer[i * 2 + 1] * send;
+                        }
+                    }
+                    if !has_buses {
+                        continue;
+                    }
+                    convolver.process(
+                        &reverb_dry_l,
+                        &reverb_dry_r,
+                        &mut wet_buffer_l,
+                        &mut wet_buffer_r,
+                    );
+                    for i in 0..buffer_size_frames {
+                        let l = wet_buffer_l[i] * wl;
+                        let r = wet_buffer_r[i] * wl;
+                        output_buffer[i * device_channels + channel] += l;
+                        output_buffer[i * device_channels + channel + 1] += r;
+                        mix_buffer[i * 2] += l;
+                        mix_buffer[i * 2 + 1] += r;
+                    }
                 }
             }
 
@@ -689,18 +955,27 @@ fn spawn_audio_processing_thread<P>(
                 }
                 let _ = tui_tx.send(TuiMessage::CpuLoadUpdate(max_load_accumulator));
                 max_load_accumulator = 0.0;
+                let skipped_voices = voice_pool.take_exhausted_count();
+                if skipped_voices > 0 {
+                    log::warn!(
+                        "[AudioThread] Voice pool of {} buffers exhausted, {} voices not started",
+                        voice_pool.size(),
+                        skipped_voices
+                    );
+                    let _ = tui_tx.send(TuiMessage::VoicePoolExhausted(skipped_voices));
+                }
                 last_ui_update = Instant::now();
             }
 
             // Push to Audio Driver
             let mut offset = 0;
-            let needed = mix_buffer.len();
+            let needed = output_buffer.len();
             while offset < needed {
                 if stop_signal.load(Ordering::Relaxed) {
                     log::info!("[AudioThread] Stop signal received. Exiting.");
                     break;
                 }
-                let pushed = producer.push_slice(&mix_buffer[offset..needed]);
+                let pushed = producer.push_slice(&output_buffer[offset..needed]);
                 offset += pushed;
                 if offset < needed {
                     thread::sleep(Duration::from_millis(1));
@@ -716,10 +991,12 @@ pub fn start_audio_playback(
     requested_buffer_size: usize,
     gain: f32,
     polyphony: usize,
+    tracker_delay_scale: f32,
     audio_device_name: Option<String>,
     sample_rate: u32,
     tui_tx: mpsc::Sender<TuiMessage>,
     shared_midi_recorder: Arc<Mutex<Option<MidiRecorder>>>,
+    output_routing: OutputRouting,
 ) -> Result<AudioHandle> {
     let (device, mut stream_config) = get_device_by_name(audio_device_name)?;
 
@@ -745,6 +1022,35 @@ pub fn start_audio_playback(
         ));
     }
 
+    // Open enough channels for every output bus
+    let needed_channels = output_routing.channels_needed();
+    if (stream_config.channels as usize) < needed_channels {
+        let channels = match valid_configs
+            .iter()
+            .map(|c| c.channels())
+            .filter(|channels| *channels as usize >= needed_channels)
+            .min()
+        {
+            Some(channels) => channels,
+            None => {
+                log::warn!(
+                    "[Cpal] Output buses need {} channels, but the device offers fewer. Buses past its channels play on the first two.",
+                    needed_channels
+                );
+                // The widest config keeps as many buses on their own outputs as possible
+                valid_configs
+                    .iter()
+                    .map(|c| c.channels())
+                    .max()
+                    .unwrap_or(stream_config.channels)
+            }
+        };
+        if channels > stream_config.channels {
+            stream_config.channels = channels;
+            valid_configs.retain(|c| c.channels() == channels);
+        }
+    }
+
     valid_configs.sort_by(|a, b| {
         if a.sample_format() == SampleFormat::F32 {
             CmpOrdering::Less
@@ -787,13 +1093,13 @@ pub fn start_audio_playback(
     );
 
     // Setup Ring Buffer
-    let mix_channels = 2;
+    let device_channels = stream_config.channels as usize;
     let actual_buffer_frames = match stream_config.buffer_size {
         BufferSize::Fixed(v) => v as usize,
         _ => requested_buffer_size,
     };
 
-    let ring_buf_capacity = actual_buffer_frames * mix_channels * 3;
+    let ring_buf_capacity = actual_buffer_frames * device_channels * 3;
     let ring_buf = HeapRb::<f32>::new(ring_buf_capacity);
     let (producer, consumer) = ring_buf.split();
 
@@ -807,13 +1113,15 @@ pub fn start_audio_playback(
         actual_buffer_frames,
         gain,
         polyphony,
+        tracker_delay_scale,
         tui_tx.clone(),
         shared_midi_recorder,
         stop_signal.clone(),
+        device_channels,
+        output_routing,
     );
 
     let err_callback = |err| log::error!("[Stream Error] {}", err);
-    let device_channels = stream_config.channels as usize;
 
     let stream = match sample_format {
         SampleFormat::F32 => build_stream::<f32>(
@@ -874,44 +1182,32 @@ fn build_stream<T>(
 where
     T: SizedSample + FromSample<f32> + Send + 'static,
 {
-    let mut stereo_read_buffer: Vec<f32> = Vec::with_capacity(1024);
+    let mut read_buffer: Vec<f32> = Vec::with_capacity(1024);
 
     let stream = device.build_output_stream(
         config,
         move |output: &mut [T], _: &cpal::OutputCallbackInfo| {
-            let out_channels = device_channels;
-            let in_channels = 2;
-            let frames_to_write = output.len() / out_channels;
-            let samples_to_read = frames_to_write * in_channels;
+            // The audio thread already lays out every device channel
+            let frames_to_write = output.len() / device_channels;
+            let samples_to_read = frames_to_write * device_channels;
 
-            if stereo_read_buffer.len() < samples_to_read {
-                stereo_read_buffer.resize(samples_to_read, 0.0);
+            if read_buffer.len() < samples_to_read {
+                read_buffer.resize(samples_to_read, 0.0);
             }
 
-            let read_count = consumer.pop_slice(&mut stereo_read_buffer[..samples_to_read]);
-            let frames_processed = read_count / in_channels;
-
-            let mut in_idx = 0;
-            let mut out_idx = 0;
-
-            for _ in 0..frames_processed {
-                let l_f32 = stereo_read_buffer[in_idx + 0];
-                let r_f32 = stereo_read_buffer[in_idx + 1];
-
-                output[out_idx + 0] = T::from_sample(l_f32);
-                output[out_idx + 1] = T::from_sample(r_f32);
-
-                for ch in 2..out_channels {
-                    output[out_idx + ch] = T::from_sample(0.0f32);
-                }
+            let read_count = consumer.pop_slice(&mut read_buffer[..samples_to_read]);
+            let frames_processed = read_count / device_channels;
+            let samples_processed = frames_processed * device_channels;
 
-                in_idx += in_channels;
-                out_idx += out_channels;
+            for (out, sample) in output[..samples_processed]
+                .iter_mut()
+                .zip(&read_buffer[..samples_processed])
+            {
+                *out = T::from_sample(*sample);
             }
 
             if frames_processed < frames_to_write {
-                let silence_start = frames_processed * out_channels;
-                for sample in &mut output[silence_start..] {
+                for sample in &mut output[samples_processed..] {
                     *sample = T::from_sample(0.0f32);
                 }
                 if frames_processed == 0 {
@@ -56,16 +56,23 @@ pub struct StereoConvolver {
 }
 
 impl StereoConvolver {
-    pub fn new(block_size: usize) -> Self {
-        Self {
-            convolver_l: FFTConvolver::<f32>::default(),
-            convolver_r: FFTConvolver::<f32>::default(),
-            is_loaded: false,
-            block_size,
-        }
+    /// `outputs` convolvers, all with the IR of `path`.
+    pub fn from_file_per_output(
+        path: &Path,
+        sample_rate: u32,
+        block_size: usize,
+        outputs: usize,
+    ) -> Result<Vec<Self>> {
+        let (ir_l, ir_r) = Self::load_ir(path, sample_rate)?;
+        let convolvers = (0..outputs)
+            .map(|_| Self::with_ir(&ir_l, &ir_r, block_size))
+            .collect();
+        log::info!("[Convolver] Successfully prepared IR.");
+        Ok(convolvers)
     }
 
-    pub fn from_file(path: &Path, sample_rate: u32, block_size: usize) -> Result<Self> {
+    /// Reads an IR as normalized left and right channels at `sample_rate`.
+    fn load_ir(path: &Path, sample_rate: u32) -> Result<(Vec<f32>, Vec<f32>)> {
         log::info!("[Convolver] Loading IR from {:?}", path);
 
         let file = File::open(path)
@@ -153,20 +160,22 @@ impl StereoConvolver {
             log::warn!("[Convolver] IR appears to be silent.");
         }
 
+        Ok((ir_l, ir_r))
+    }
+
+    fn with_ir(ir_l: &[f32], ir_r: &[f32], block_size: usize) -> Self {
         let mut convolver_l = FFTConvolver::<f32>::default();
         let mut convolver_r = FFTConvolver::<f32>::default();
 
-        let _ = convolver_l.init(block_size, &ir_l);
-        let _ = convolver_r.init(block_size, &ir_r);
-
-        log::info!("[Convolver] Successfully prepared IR.");
+        let _ = convolver_l.init(block_size, ir_l);
+        let _ = convolver_r.init(block_size, ir_r);
 
-        Ok(Self {
+        Self {
             convolver_l,
             convolver_r,
             is_loaded: true,
             block_size,
-        })
+        }
     }
 
     pub fn process(&mut self, dry_l: &[f32], dry_r: &[f32], wet_l: &mut [f32], wet_r: &mut [f32]) {
@@ -1,4 +1,5 @@
 use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
 use std::sync::atomic::Ordering;
 use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
@@ -7,13 +8,19 @@ use std::time::{Duration, Instant};
 use crate::TuiMessage;
 use crate::app::{ActiveNote, AppMessage};
 use crate::audio_convolver::StereoConvolver;
+use crate::audio_loader::LoopSelection;
 use crate::audio_recorder::AudioRecorder;
 use crate::midi_recorder::MidiRecorder;
-use crate::organ::Organ;
-use crate::voice::{SpawnJob, VOICE_STEALING_FADE_TIME, Voice};
+use crate::organ::{AttackSample, Organ, Pipe, Rank};
+use crate::output_routing::{OutputBus, reverb_channels};
+use crate::resample::Resampler;
+use crate::stereo::PanTable;
+use crate::voice::{VOICE_STEALING_FADE_TIME, Voice, VoicePool, db_to_amplitude};
+use crate::voicing::PipeVoicing;
+use crate::wind::WindSupply;
 
 /// If voice limit is exceeded, this finds the oldest *release* samples
-/// and forces them to fade out quickly.
+/// (or decaying percussive attacks) and forces them to fade out quickly.
 pub fn enforce_voice_limit(voices: &mut HashMap<u64, Voice>, sample_rate: u32, polyphony: usize) {
     let active_musical_voices = voices.values().filter(|v| !v.is_fading_out).count();
 
@@ -27,7 +34,9 @@ pub fn enforce_voice_limit(voices: &mut HashMap<u64, Voice>, sample_rate: u32, p
     let mut candidates: Vec<(u64, Instant)> = voices
         .iter()
         .filter(|(_, v)| {
-            !v.is_attack_sample && !v.is_fading_out && v.note_on_time.elapsed() > min_age
+            (!v.is_attack_sample || v.is_percussive)
+                && !v.is_fading_out
+                && v.note_on_time.elapsed() > min_age
         })
         .map(|(id, v)| (*id, v.note_on_time))
         .collect();
@@ -50,18 +59,291 @@ pub fn enforce_voice_limit(voices: &mut HashMap<u64, Voice>, sample_rate: u32, p
     }
 }
 
+/// Articulation history of a single pipe, used to choose between alternate attacks.
+#[derive(Debug, Default)]
+pub struct PipeHistory {
+    pub last_release: Option<Instant>,
+    pub next_round_robin: usize,
+    /// Sustained notes played so far, for choosing their loops
+    pub loops_played: u64,
+}
+
+/// Keyed by (rank ID, MIDI note).
+pub type PipeHistoryMap = HashMap<(String, u8), PipeHistory>;
+
+/// Changes to the pitch, gain, stereo position, output, loops and resampling of pipes made while playing.
+#[derive(Debug, Default)]
+pub struct PipeTuning {
+    /// Voicing edited live, keyed by (rank ID, MIDI note)
+    pub voicing_overrides: HashMap<(String, u8), PipeVoicing>,
+    /// Temperament offset in cents of each pitch class (C = 0)
+    pub temperament_cents: [f32; 12],
+    /// Reference pitch offset in cents
+    pub master_tuning_cents: f32,
+    /// Semitones every note is shifted by
+    pub transpose: i8,
+    /// Pan of the pipes placed by the stereo layout or the organ definition
+    pub pans: PanTable,
+    /// Output bus of each rank not playing on the main bus
+    pub rank_buses: HashMap<String, usize>,
+    /// How sustained notes choose among their sample's loops
+    pub loop_selection: LoopSelection,
+    pub loop_seed: u64,
+    /// Interpolation of voices playing at another pitch than recorded
+    pub resampler: Resampler,
+}
+
+impl PipeTuning {
+    /// The pipe's voicing. Voicing edited live replaces the one loaded with the organ.
+    fn voicing(&self, pipe: &Pipe, rank_id: &str, note: u8) -> PipeVoicing {
+        self.voicing_overrides
+            .get(&(rank_id.to_string(), note))
+            .copied()
+            .unwrap_or(pipe.voicing)
+    }
+
+    /// Playback speed of a pipe's samples with its voicing and the temperament.
+    /// The temperament applies to the note the pipe sounds after transposition.
+    fn pitch_ratio(&self, voicing: &PipeVoicing, note: u8) -> f32 {
+        let pitch_class = (note as i32 + self.transpose as i32).rem_euclid(12) as usize;
+        let cents = voicing.tuning_cents + self.temperament_cents[pitch_class];
+        2.0f32.powf(cents / 1200.0)
+    }
+
+    /// Updates the pitch of sounding pipes after the temperament or transposition changed.
+    fn retune_sounding(
+        &self,
+        organ: &Organ,
+        active_notes: &HashMap<u8, Vec<ActiveNote>>,
+        voices: &mut HashMap<u64, Voice>,
+    ) {
+        for active_note in active_notes.values().flatten() {
+            let Some(pipe) = organ
+                .ranks
+                .get(&active_note.rank_id)
+                .and_then(|rank| rank.pipes.get(&active_note.pipe_note))
+            else {
+                continue;
+            };
+            if let Some(voice) = voices.get_mut(&active_note.voice_id) {
+                let voicing = self.voicing(pipe, &active_note.rank_id, active_note.pipe_note);
+                voice.pitch_ratio = self.pitch_ratio(&voicing, active_note.pipe_note);
+            }
+        }
+    }
+
+    /// Puts a new voice at its pipe's stereo position and on its rank's output bus.
+    fn place(&self, voice: &mut Voice, rank_id: &str, note: u8) {
+        voice.pan = self.pans.get(rank_id, note);
+        voice.output_bus = self.output_bus(rank_id);
+    }
+
+    fn output_bus(&self, rank_id: &str) -> usize {
+        self.rank_buses.get(rank_id).copied().unwrap_or(0)
+    }
+
+    /// Loop choice for the next sustained note of a pipe.
+    fn loop_choice(&self, rank_id: &str, note: u8, history: &mut PipeHistory) -> u64 {
+        let choice =
+            self.loop_selection
+                .choice(self.loop_seed, rank_id, note, history.loops_played);
+        history.loops_played = history.loops_played.wrapping_add(1);
+        choice
+    }
+
+    /// Playback speed shared by all voices for the master tuning and transposition.
+    pub fn master_pitch_ratio(&self) -> f32 {
+        let cents = self.master_tuning_cents + self.transpose as f32 * 100.0;
+        2.0f32.powf(cents / 1200.0)
+    }
+}
+
+/// The pipe's gain with `voicing` in place of the voicing it was loaded with.
+fn voiced_gain_db(pipe: &Pipe, voicing: &PipeVoicing) -> f32 {
+    pipe.gain_db - pipe.voicing.gain_db + voicing.gain_db
+}
+
+/// Picks the attack for a new note. Attacks whose criteria (velocity range, tremulant
+/// state, time since the last release) match are narrowed down to the most specific
+/// ones, and repeated notes rotate through those round-robin.
+fn select_attack<'a>(
+    pipe: &'a Pipe,
+    velocity: u8,
+    tremulant_on: bool,
+    history: &mut PipeHistory,
+) -> Option<&'a AttackSample> {
+    if pipe.attacks.len() <= 1 {
+        return pipe.attacks.first();
+    }
+
+    let since_release_ms = history.last_release.map(|t| t.elapsed().as_millis() as i64);
+    let specificity = |a: &AttackSample| {
+        let time_window = if a.max_time_since_release_ms == -1 {
+            i64::MAX
+        } else {
+            a.max_time_since_release_ms
+        };
+        (a.max_velocity.saturating_sub(a.min_velocity), time_window)
+    };
+
+    let matching: Vec<&AttackSample> = pipe
+        .attacks
+        .iter()
+        .filter(|a| {
+            (a.min_velocity..=a.max_velocity).contains(&velocity)
+                && a.is_tremulant.is_none_or(|t| t == tremulant_on)
+                && (a.max_time_since_release_ms == -1
+                    || since_release_ms.is_some_and(|ms| ms <= a.max_time_since_release_ms))
+        })
+        .collect();
+    let Some(best) = matching.iter().map(|a| specificity(a)).min() else {
+        return pipe.attacks.first();
+    };
+    let candidates: Vec<&AttackSample> = matching
+        .into_iter()
+        .filter(|a| specificity(a) == best)
+        .collect();
+
+    let pick = candidates[history.next_round_robin % candidates.len()];
+    history.next_round_robin = history.next_round_robin.wrapping_add(1);
+    Some(pick)
+}
+
+/// Whether any tremulant acting on the rank's windchest group is switched on.
+fn is_rank_tremulant_on(
+    rank: &Rank,
+    organ: &Organ,
+    active_tremulants: &HashMap<String, bool>,
+) -> bool {
+    let Some(wc_id) = &rank.windchest_group_id else {
+        return false;
+    };
+    // Handle unpadded ID (e.g. "1" vs "01")
+    let wc_key = wc_id.trim_start_matches('0');
+    organ
+        .windchest_groups
+        .values()
+        .filter(|g| g.id_str.trim_start_matches('0') == wc_key)
+        .flat_map(|g| &g.tremulant_ids)
+        .any(|t| active_tremulants.get(t).copied().unwrap_or(false))
+}
+
+/// Crossfades held pipes whose tremulant state changed between their non-tremulant
+/// and tremulant samples. The new sample starts at its loop so no attack is heard.
+#[allow(clippy::too_many_arguments)]
+fn crossfade_wave_tremulant(
+    previous_tremulants: &HashMap<String, bool>,
+    active_tremulants: &HashMap<String, bool>,
+    organ: &Arc<Organ>,
+    voices: &mut HashMap<u64, Voice>,
+    active_notes: &mut HashMap<u8, Vec<ActiveNote>>,
+    sample_rate: u32,
+    voice_counter: &mut u64,
+    voice_pool: &mut VoicePool,
+    pipe_history: &mut PipeHistoryMap,
+    pipe_tuning: &PipeTuning,
+) {
+    for notes in active_notes.values_mut() {
+        for active_note in notes.iter_mut() {
+            let Some(rank) = organ.ranks.get(&active_note.rank_id) else {
+                continue;
+            };
+            let tremulant_on = is_rank_tremulant_on(rank, organ, active_tremulants);
+            if tremulant_on == is_rank_tremulant_on(rank, organ, previous_tremulants) {
+                continue;
+            }
+            // Prefer a sustained sample over a re-articulation one
+            let Some((pipe, attack)) = rank.pipes.get(&active_note.pipe_note).and_then(|pipe| {
+                pipe.attacks
+                    .iter()
+                    .filter(|a| a.is_tremulant == Some(tremulant_on))
+                    .min_by_key(|a| a.max_time_since_release_ms != -1)
+                    .map(|attack| (pipe, attack))
+            }) else {
+                continue;
+            };
+
+            let voicing = pipe_tuning.voicing(pipe, &active_note.rank_id, active_note.pipe_note);
+            let total_gain = rank.gain_db + voiced_gain_db(pipe, &voicing) + active_note.gain_db;
+            let history = pipe_history
+                .entry((active_note.rank_id.clone(), active_note.pipe_note))
+                .or_default();
+            let loop_choice =
+                pipe_tuning.loop_choice(&active_note.rank_id, active_note.pipe_note, history);
+            match Voice::new(
+                &attack.path,
+                Arc::clone(organ),
+                sample_rate,
+                total_gain,
+                true,
+                true,
+                rank.is_percussive,
+                true,
+                loop_choice,
+                active_note.start_time,
+                None,
+                voice_pool,
+                rank.windchest_group_id.clone(),
+            ) {
+                Ok(mut voice) => {
+                    voice.pitch_ratio = pipe_tuning.pitch_ratio(&voicing, active_note.pipe_note);
+                    pipe_tuning.place(&mut voice, &active_note.rank_id, active_note.pipe_note);
+                    let voice_id = *voice_counter;
+                    *voice_counter += 1;
+                    // Hand over like a release: the old sample keeps sounding until
+                    // the new one has buffered data, then they crossfade.
+                    let mut superseded_voice_id = None;
+                    if let Some(old_voice) = voices.get_mut(&active_note.voice_id) {
+                        old_voice.is_cancelled.store(true, Ordering::SeqCst);
+                        old_voice.is_awaiting_release_sample = true;
+                        superseded_voice_id = old_voice.release_voice_id.replace(voice_id);
+                        voice.is_fading_in = false;
+                    }
+                    // A switch that was still loading is no longer needed
+                    if let Some(superseded) = superseded_voice_id.and_then(|id| voices.get_mut(&id))
+                    {
+                        superseded.is_cancelled.store(true, Ordering::SeqCst);
+                        superseded.is_fading_out = true;
+                    }
+                    voices.insert(voice_id, voice);
+                    active_note.voice_id = voice_id;
+                }
+                Err(e) => log::error!("Error creating tremulant voice: {}", e),
+            }
+        }
+    }
+}
+
+/// Converts a rank's tracker delay into frames, scaled by the global key-action multiplier.
+fn tracker_delay_frames(rank: &Rank, sample_rate: u32, tracker_delay_scale: f32) -> usize {
+    let delay_secs = rank.tracker_delay_ms as f32 * 0.001 * tracker_delay_scale.max(0.0);
+    (delay_secs * sample_rate as f32).round() as usize
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn trigger_note_release(
     stopped_note: ActiveNote,
     organ: &Arc<Organ>,
     voices: &mut HashMap<u64, Voice>,
     sample_rate: u32,
+    tracker_delay_scale: f32,
     voice_counter: &mut u64,
-    spawner_tx: &mpsc::Sender<SpawnJob>,
+    voice_pool: &mut VoicePool,
+    pipe_history: &mut PipeHistoryMap,
+    pipe_tuning: &PipeTuning,
 ) {
     let press_duration = stopped_note.start_time.elapsed().as_millis() as i64;
-    let note = stopped_note.note;
+    let note = stopped_note.pipe_note;
+    pipe_history
+        .entry((stopped_note.rank_id.clone(), note))
+        .or_default()
+        .last_release = Some(Instant::now());
 
     if let Some(rank) = organ.ranks.get(&stopped_note.rank_id) {
+        // Percussive attacks keep ringing after the key is lifted and have no release
+        if rank.is_percussive {
+            return;
+        }
         if let Some(pipe) = rank.pipes.get(&note) {
             let release_sample = pipe
                 .releases
@@ -72,9 +354,12 @@ pub fn trigger_note_release(
                 .or_else(|| pipe.releases.last());
 
             let mut release_created = false;
+            let delay_frames = tracker_delay_frames(rank, sample_rate, tracker_delay_scale);
 
             if let Some(release) = release_sample {
-                let total_gain = rank.gain_db + pipe.gain_db;
+                let voicing = pipe_tuning.voicing(pipe, &stopped_note.rank_id, note);
+                let total_gain =
+                    rank.gain_db + voiced_gain_db(pipe, &voicing) + stopped_note.gain_db;
                 match Voice::new(
                     &release.path,
                     Arc::clone(&organ),
@@ -82,13 +367,23 @@ pub fn trigger_note_release(
                     total_gain,
                     false,
                     false,
+                    false,
+                    false,
+                    0,
                     Instant::now(),
                     release.preloaded_bytes.clone(),
-                    spawner_tx,
+                    voice_pool,
                     rank.windchest_group_id.clone(),
                 ) {
                     Ok(mut voice) => {
                         voice.fade_level = 0.0;
+                        voice.start_delay_frames = delay_frames;
+                        voice.pitch_ratio = pipe_tuning.pitch_ratio(&voicing, note);
+                        pipe_tuning.place(&mut voice, &stopped_note.rank_id, note);
+                        voice.max_length_frames = voicing
+                            .max_release_ms
+                            .map(|ms| (ms as u64 * sample_rate as u64 / 1000) as usize);
+                        voice.release_alignment = release.alignment.clone();
                         let release_voice_id = *voice_counter;
                         *voice_counter += 1;
                         voices.insert(release_voice_id, voice);
@@ -112,20 +407,25 @@ pub fn trigger_note_release(
                 if let Some(voice) = voices.get_mut(&stopped_note.voice_id) {
                     voice.is_cancelled.store(true, Ordering::SeqCst);
                     voice.is_fading_out = true;
+                    voice.fade_out_delay_frames = delay_frames;
                 }
             }
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_note_off(
     note: u8,
     organ: &Arc<Organ>,
     voices: &mut HashMap<u64, Voice>,
     active_notes: &mut HashMap<u8, Vec<ActiveNote>>,
     sample_rate: u32,
+    tracker_delay_scale: f32,
     voice_counter: &mut u64,
-    spawner_tx: &mpsc::Sender<SpawnJob>,
+    voice_pool: &mut VoicePool,
+    pipe_history: &mut PipeHistoryMap,
+    pipe_tuning: &PipeTuning,
 ) {
     if let Some(notes_to_stop) = active_notes.remove(&note) {
         for stopped_note in notes_to_stop {
@@ -134,8 +434,11 @@ pub fn handle_note_off(
                 organ,
                 voices,
                 sample_rate,
+                tracker_delay_scale,
                 voice_counter,
-                spawner_tx,
+                voice_pool,
+                pipe_history,
+                pipe_tuning,
             );
         }
     }
@@ -149,31 +452,57 @@ pub fn process_note_on(
     voice_counter: &mut u64,
     stop_map: &HashMap<String, usize>,
     sample_rate: u32,
-    spawner_tx: &mpsc::Sender<SpawnJob>,
+    tracker_delay_scale: f32,
+    voice_pool: &mut VoicePool,
+    active_tremulants: &HashMap<String, bool>,
+    pipe_history: &mut PipeHistoryMap,
+    pipe_tuning: &PipeTuning,
 ) {
-    if let AppMessage::NoteOn(note, _vel, stop_name) = msg {
+    if let AppMessage::NoteOn(note, velocity, stop_name) = msg {
         let note_on_time = Instant::now();
         if let Some(stop_index) = stop_map.get(&stop_name) {
             let stop = &organ.stops[*stop_index];
             let mut new_notes = Vec::new();
 
-            for rank_id in &stop.rank_ids {
+            for (rank_index, rank_id) in stop.rank_ids.iter().enumerate() {
+                let offset = stop.rank_offset(rank_index);
+                let Ok(pipe_note) = u8::try_from(note as i16 + offset.transpose as i16) else {
+                    continue;
+                };
                 if let Some(rank) = organ.ranks.get(rank_id) {
-                    if let Some(pipe) = rank.pipes.get(&note) {
-                        let total_gain = rank.gain_db + pipe.gain_db;
+                    if let Some(pipe) = rank.pipes.get(&pipe_note) {
+                        let tremulant_on = is_rank_tremulant_on(rank, organ, active_tremulants);
+                        let history = pipe_history
+                            .entry((rank_id.clone(), pipe_note))
+                            .or_default();
+                        let Some(attack) = select_attack(pipe, velocity, tremulant_on, history)
+                        else {
+                            continue;
+                        };
+                        let loop_choice = pipe_tuning.loop_choice(rank_id, pipe_note, history);
+                        let voicing = pipe_tuning.voicing(pipe, rank_id, pipe_note);
+                        let total_gain =
+                            rank.gain_db + voiced_gain_db(pipe, &voicing) + offset.gain_db;
                         match Voice::new(
-                            &pipe.attack_sample_path,
+                            &attack.path,
                             Arc::clone(&organ),
                             sample_rate,
                             total_gain,
                             false,
                             true,
+                            rank.is_percussive,
+                            false,
+                            loop_choice,
                             note_on_time,
-                            pipe.preloaded_bytes.clone(),
-                            spawner_tx,
+                            attack.preloaded_bytes.clone(),
+                            voice_pool,
                             rank.windchest_group_id.clone(),
                         ) {
-                            Ok(voice) => {
+                            Ok(mut voice) => {
+                                voice.start_delay_frames =
+                                    tracker_delay_frames(rank, sample_rate, tracker_delay_scale);
+                                voice.pitch_ratio = pipe_tuning.pitch_ratio(&voicing, pipe_note);
+                                pipe_tuning.place(&mut voice, rank_id, pipe_note);
                                 let voice_id = *voice_counter;
                                 *voice_counter += 1;
                                 voices.insert(voice_id, voice);
@@ -182,6 +511,8 @@ pub fn process_note_on(
                                     start_time: note_on_time,
                                     stop_index: *stop_index,
                                     rank_id: rank_id.clone(),
+                                    pipe_note,
+                                    gain_db: offset.gain_db,
                                     voice_id,
                                 });
                             }
@@ -197,22 +528,54 @@ pub fn process_note_on(
     }
 }
 
+/// Reverbs of the outputs the buses play on: (first device channel, convolver)
+pub type ReverbConvolvers = Vec<(usize, StereoConvolver)>;
+
+/// Loads an IR into one reverb per output channel on a worker thread.
+fn load_reverb(
+    ir_loader_tx: &mpsc::Sender<Result<ReverbConvolvers, anyhow::Error>>,
+    path: PathBuf,
+    sample_rate: u32,
+    buffer_size_frames: usize,
+    channels: Vec<usize>,
+) {
+    let tx = ir_loader_tx.clone();
+    thread::spawn(move || {
+        let convolvers = StereoConvolver::from_file_per_output(
+            &path,
+            sample_rate,
+            buffer_size_frames,
+            channels.len(),
+        )
+        .map(|convolvers| channels.into_iter().zip(convolvers).collect());
+        let _ = tx.send(convolvers);
+    });
+}
+
 pub fn process_message(
     msg: AppMessage,
     wet_dry_ratio: &mut f32,
     system_gain: &mut f32,
     polyphony: &mut usize,
-    ir_loader_tx: &mpsc::Sender<Result<StereoConvolver, anyhow::Error>>,
+    tracker_delay_scale: f32,
+    ir_loader_tx: &mpsc::Sender<Result<ReverbConvolvers, anyhow::Error>>,
     sample_rate: u32,
     buffer_size_frames: usize,
+    device_channels: usize,
     active_notes: &mut HashMap<u8, Vec<ActiveNote>>,
     organ: &Arc<Organ>,
     voices: &mut HashMap<u64, Voice>,
     voice_counter: &mut u64,
     stop_map: &HashMap<String, usize>,
-    spawner_tx: &mpsc::Sender<SpawnJob>,
+    voice_pool: &mut VoicePool,
     pending_queue: &mut VecDeque<AppMessage>,
     active_tremulants: &mut HashMap<String, bool>,
+    enclosure_targets: &mut HashMap<String, f32>,
+    pipe_history: &mut PipeHistoryMap,
+    pipe_tuning: &mut PipeTuning,
+    wind_supply: &mut WindSupply,
+    output_buses: &mut Vec<OutputBus>,
+    reverb_ir_path: &mut Option<PathBuf>,
     audio_recorder: &mut Option<AudioRecorder>,
     tui_tx: &mpsc::Sender<TuiMessage>,
     shared_midi_recorder: &Arc<Mutex<Option<MidiRecorder>>>,
@@ -246,8 +609,11 @@ pub fn process_message(
                             organ,
                             voices,
                             sample_rate,
+                            tracker_delay_scale,
                             voice_counter,
-                            spawner_tx,
+                            voice_pool,
+                            pipe_history,
+                            pipe_tuning,
                         );
                     }
 
@@ -267,13 +633,107 @@ pub fn process_message(
                     voices,
                     active_notes,
                     sample_rate,
+                    tracker_delay_scale,
                     voice_counter,
-                    spawner_tx,
+                    voice_pool,
+                    pipe_history,
+                    pipe_tuning,
                 );
             }
         }
         AppMessage::SetTremulantActive(id, active) => {
+            let is_wave = organ.tremulants.get(&id).is_some_and(|t| t.is_wave);
+            let previous_tremulants = is_wave.then(|| active_tremulants.clone());
             active_tremulants.insert(id, active);
+            if let Some(previous) = previous_tremulants {
+                crossfade_wave_tremulant(
+                    &previous,
+                    active_tremulants,
+                    organ,
+                    voices,
+                    active_notes,
+                    sample_rate,
+                    voice_counter,
+                    voice_pool,
+                    pipe_history,
+                    pipe_tuning,
+                );
+            }
+        }
+        AppMessage::SetEnclosureValue(id, value) => {
+            enclosure_targets.insert(id, value.min(127) as f32 / 127.0);
+        }
+        AppMessage::SetPipeVoicing(rank_id, note, voicing) => {
+            // Pipes that are sounding pick up the new voicing right away
+            if let Some((rank, pipe)) = organ
+                .ranks
+                .get(&rank_id)
+                .and_then(|rank| Some((rank, rank.pipes.get(&note)?)))
+            {
+                let pipe_gain = voiced_gain_db(pipe, &voicing);
+                let pitch_ratio = pipe_tuning.pitch_ratio(&voicing, note);
+                let sounding = active_notes
+                    .values()
+                    .flatten()
+                    .filter(|n| n.rank_id == rank_id && n.pipe_note == note);
+                for active_note in sounding {
+                    if let Some(voice) = voices.get_mut(&active_note.voice_id) {
+                        voice.gain =
+                            db_to_amplitude(rank.gain_db + pipe_gain + active_note.gain_db);
+                        voice.pitch_ratio = pitch_ratio;
+                    }
+                }
+            }
+            pipe_tuning
+                .voicing_overrides
+                .insert((rank_id, note), voicing);
+        }
+        AppMessage::SetTemperament(cents) => {
+            pipe_tuning.temperament_cents = cents;
+            pipe_tuning.retune_sounding(organ, active_notes, voices);
+        }
+        AppMessage::SetMasterTuning(cents) => pipe_tuning.master_tuning_cents = cents,
+        AppMessage::SetTranspose(semitones) => {
+            pipe_tuning.transpose = semitones;
+            pipe_tuning.retune_sounding(organ, active_notes, voices);
+        }
+        AppMessage::SetWindModel(model) => wind_supply.set_model(model),
+        AppMessage::SetLoopSelection(selection, seed) => {
+            pipe_tuning.loop_selection = selection;
+            pipe_tuning.loop_seed = seed;
+        }
+        AppMessage::SetResamplingQuality(quality) => {
+            pipe_tuning.resampler.quality = quality;
+        }
+        AppMessage::SetStereoLayout(layout) => {
+            pipe_tuning.pans = PanTable::new(&layout, organ);
+            // Sounding pipes move to their new position right away
+            for active_note in active_notes.values().flatten() {
+                if let Some(voice) = voices.get_mut(&active_note.voice_id) {
+                    voice.pan = pipe_tuning
+                        .pans
+                        .get(&active_note.rank_id, active_note.pipe_note);
+                }
+            }
+        }
+        AppMessage::SetOutputRouting(routing) => {
+            pipe_tuning.rank_buses = routing.rank_buses(organ);
+            for active_note in active_notes.values().flatten() {
+                if let Some(voice) = voices.get_mut(&active_note.voice_id) {
+                    voice.output_bus = pipe_tuning.output_bus(&active_note.rank_id);
+                }
+            }
+            *output_buses = routing.effective_buses();
+            // The reverbs follow the outputs the buses play on
+            if let Some(path) = reverb_ir_path {
+                load_reverb(
+                    ir_loader_tx,
+                    path.clone(),
+                    sample_rate,
+                    buffer_size_frames,
+                    reverb_channels(output_buses, device_channels),
+                );
+            }
         }
         AppMessage::StartAudioRecording => {
             match AudioRecorder::start(organ.name.clone(), sample_rate) {
@@ -314,17 +774,20 @@ pub fn process_message(
         }
         AppMessage::SetReverbWetDry(r) => *wet_dry_ratio = r.clamp(0.0, 1.0),
         AppMessage::SetReverbIr(p) => {
-            let tx = ir_loader_tx.clone();
-            thread::spawn(move || {
-                let _ = tx.send(StereoConvolver::from_file(
-                    &p,
-                    sample_rate,
-                    buffer_size_frames,
-                ));
-            });
+            *reverb_ir_path = Some(p.clone());
+            load_reverb(
+                ir_loader_tx,
+                p,
+                sample_rate,
+                buffer_size_frames,
+                reverb_channels(output_buses, device_channels),
+            );
         }
         AppMessage::SetGain(g) => *system_gain = g,
-        AppMessage::SetPolyphony(p) => *polyphony = p,
+        AppMessage::SetPolyphony(p) => {
+            *polyphony = p;
+            voice_pool.reserve_for(p);
+        }
         AppMessage::Quit => {
             // tell the Logic Thread to close the Window.
             // This allows main.rs to finish the loop and handle the respawn.
@@ -1,246 +1,616 @@
 use anyhow::{Result, anyhow};
-use ringbuf::traits::Producer;
+use clap::ValueEnum;
+use ringbuf::traits::{Observer, Producer};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::BufReader;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, mpsc};
 use std::thread;
 use std::time::Duration;
 
-use crate::voice::{CHANNEL_COUNT, SpawnJob};
+use crate::organ::Organ;
+use crate::voice::{CHANNEL_COUNT, LOOP_CROSSFADE_TIME, SpawnJob};
 use crate::wav::{WavSampleReader, parse_smpl_chunk, parse_wav_metadata};
+use crate::wav_converter::SampleMetadata;
 
-/// Worker function that loads samples from disk or cache and fills the ring buffer.
-pub fn run_loader_job(mut job: SpawnJob) {
-    // Check cancellation before doing heavy lifting
-    if job.is_cancelled.load(Ordering::Relaxed) {
-        job.is_finished.store(true, Ordering::SeqCst);
-        return;
+/// How a sustained pipe chooses among the loops of its sample.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "lower")]
+pub enum LoopSelection {
+    /// A random loop for every note
+    #[default]
+    Random,
+    /// Every note of a pipe plays the loop after the one of its previous note
+    Cycle,
+}
+
+impl LoopSelection {
+    /// Number the loader maps onto one of the sample's loops, for the `count`-th note of a pipe.
+    /// Random choices only depend on `seed` and the pipe, so a seed always gives the same loops.
+    pub fn choice(&self, seed: u64, rank_id: &str, note: u8, count: u64) -> u64 {
+        match self {
+            LoopSelection::Cycle => count,
+            LoopSelection::Random => {
+                // FNV-1a of the pipe, mixed with splitmix64
+                let pipe_hash = rank_id
+                    .bytes()
+                    .chain(std::iter::once(note))
+                    .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+                        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+                    });
+                let mut z = (seed ^ pipe_hash)
+                    .wrapping_add(count.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            }
+        }
     }
+}
 
-    let path_str_clone = job
-        .path
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .to_string();
-
-    // Wrap in catch_unwind to prevent a loader panic from crashing the whole engine
-    let panic_result = std::panic::catch_unwind(move || {
-        let result: Result<()> = (|| {
-            // Check Caches
-            let maybe_cached_data = job
-                .organ
-                .sample_cache
-                .as_ref()
-                .and_then(|c| c.get(&job.path).cloned());
-            let maybe_cached_meta = job
-                .organ
-                .metadata_cache
-                .as_ref()
-                .and_then(|c| c.get(&job.path).cloned());
-
-            let loop_info;
-            let input_channels;
-            let mut source: Option<Box<dyn Iterator<Item = f32>>> = None;
-            let mut source_is_finished;
-            let use_memory_reader;
-            let mut samples_in_memory: Vec<f32> = Vec::new();
-
-            let mut interleaved_buffer = vec![0.0f32; 1024 * CHANNEL_COUNT];
-            let frames_to_skip = job.frames_to_skip;
-
-            if let (Some(cached_samples), Some(cached_metadata)) =
-                (maybe_cached_data, maybe_cached_meta)
-            {
-                // Fast Path: Memory Cache
-                samples_in_memory = (*cached_samples).clone();
-                loop_info = if job.is_attack_sample {
-                    cached_metadata.loop_info
-                } else {
-                    None
-                };
-                input_channels = cached_metadata.channel_count as usize;
-                use_memory_reader = true;
-                source_is_finished = false;
+/// Frames decoded and pushed at a time.
+const LOADER_CHUNK_FRAMES: usize = 1024;
+/// Chunks a loader pushes into one voice before moving on to the next, so that
+/// a voice with an empty buffer can't hold up the others for long.
+const LOADER_CHUNKS_PER_TURN: usize = 8;
+/// Frames of a looped sample decoded from disk per turn, ahead of what its voice plays.
+const LOADER_DECODE_FRAMES_PER_TURN: usize = LOADER_CHUNK_FRAMES * LOADER_CHUNKS_PER_TURN;
+/// Memory that looped samples decoded from disk may keep occupying after their notes end.
+const LOOPED_SAMPLE_CACHE_BYTES: usize = 512 * 1024 * 1024;
+
+/// Number of loader threads: one per core, leaving one for the audio thread.
+fn loader_thread_count() -> usize {
+    thread::available_parallelism()
+        .map_or(4, |n| n.get())
+        .saturating_sub(1)
+        .clamp(2, 8)
+}
+
+/// Starts a fixed set of loader threads that stream the samples of all voices.
+/// Each thread takes new jobs as they come in and then keeps topping up the ring
+/// buffers of its voices until they finish or are cancelled.
+pub fn start_loader_pool(jobs: mpsc::Receiver<SpawnJob>) {
+    let jobs = Arc::new(Mutex::new(jobs));
+    let looped_samples = Arc::new(Mutex::new(LoopedSampleCache::default()));
+    let thread_count = loader_thread_count();
+    log::info!("[LoaderPool] Starting {} loader threads.", thread_count);
+    for index in 0..thread_count {
+        let jobs = Arc::clone(&jobs);
+        let looped_samples = Arc::clone(&looped_samples);
+        let spawned = thread::Builder::new()
+            .name(format!("loader-{}", index))
+            .spawn(move || run_loader_thread(&jobs, &looped_samples));
+        if let Err(e) = spawned {
+            log::error!("[LoaderPool] Failed to start loader thread: {}", e);
+        }
+    }
+}
+
+fn run_loader_thread(
+    jobs: &Mutex<mpsc::Receiver<SpawnJob>>,
+    looped_samples: &Mutex<LoopedSampleCache>,
+) {
+    let mut streams: Vec<LoaderStream> = Vec::new();
+    loop {
+        // New notes first, so that they start playing without delay. An idle thread
+        // waits for a job; busy ones only look for one if no other thread is waiting.
+        let new_job = if streams.is_empty() {
+            match jobs.lock().unwrap().recv() {
+                Ok(job) => Some(job),
+                Err(_) => break,
+            }
+        } else {
+            jobs.try_lock().ok().and_then(|rx| rx.try_recv().ok())
+        };
+        let mut progressed = new_job.is_some();
+        if let Some(job) = new_job
+            && let Some(stream) = LoaderStream::open(job, looped_samples)
+        {
+            streams.push(stream);
+        }
+
+        streams.retain_mut(|stream| match stream.turn(looped_samples) {
+            StreamTurn::Progressed => {
+                progressed = true;
+                true
+            }
+            StreamTurn::Waiting => true,
+            StreamTurn::Done => false,
+        });
+
+        // Backpressure: every ring buffer is full, wait for the voices to play some of it
+        if !progressed {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    log::info!("[LoaderPool] Loader thread shutting down.");
+}
+
+/// Outcome of one turn of a loader stream.
+enum StreamTurn {
+    /// Pushed audio into the voice's ring buffer, or decoded some of its sample
+    Progressed,
+    /// The ring buffer is full
+    Waiting,
+    /// The sample ended, the voice was cancelled, or loading failed
+    Done,
+}
+
+/// Decoded samples with their loops and channel count.
+type SharedSample = (Arc<Vec<f32>>, Arc<SampleMetadata>);
+
+/// Looped samples decoded from disk, shared by all notes playing them. Once no
+/// note plays a sample it stays around, least recently used out first, so that
+/// repeated notes don't decode it again.
+#[derive(Default)]
+struct LoopedSampleCache {
+    entries: HashMap<PathBuf, CachedLoopedSample>,
+    /// Counts lookups, to order the entries by last use
+    clock: u64,
+    bytes: usize,
+}
+
+struct CachedLoopedSample {
+    sample: SharedSample,
+    /// Value of the cache's clock when the sample was last looked up
+    last_used: u64,
+}
+
+impl LoopedSampleCache {
+    fn get(&mut self, path: &Path) -> Option<SharedSample> {
+        let entry = self.entries.get_mut(path)?;
+        self.clock += 1;
+        entry.last_used = self.clock;
+        Some(entry.sample.clone())
+    }
+
+    /// Adds a decoded sample, or returns the same one if another thread decoded it meanwhile.
+    fn insert(&mut self, path: PathBuf, sample: SharedSample) -> SharedSample {
+        if let Some(existing) = self.get(&path) {
+            return existing;
+        }
+        self.clock += 1;
+        self.bytes += sample.0.len() * size_of::<f32>();
+        self.entries.insert(
+            path,
+            CachedLoopedSample {
+                sample: sample.clone(),
+                last_used: self.clock,
+            },
+        );
+        self.evict();
+        sample
+    }
+
+    /// Drops the least recently used samples no note is playing until the cache fits its budget.
+    fn evict(&mut self) {
+        if self.bytes <= LOOPED_SAMPLE_CACHE_BYTES {
+            return;
+        }
+        // Samples still playing stay shared, dropping them would free nothing
+        let mut idle: Vec<(u64, PathBuf)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| Arc::strong_count(&entry.sample.0) == 1)
+            .map(|(path, entry)| (entry.last_used, path.clone()))
+            .collect();
+        idle.sort_unstable_by_key(|(last_used, _)| *last_used);
+        for (_, path) in idle {
+            if self.bytes <= LOOPED_SAMPLE_CACHE_BYTES {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&path) {
+                self.bytes -= entry.sample.0.len() * size_of::<f32>();
+            }
+        }
+    }
+}
+
+/// A looped sample that is decoded from disk a little every turn, so that a long
+/// sample doesn't hold up the other streams of its loader thread.
+struct LoopDecode {
+    decoder: WavSampleReader<BufReader<File>>,
+    samples: Vec<f32>,
+    metadata: SampleMetadata,
+}
+
+/// The state of loading one voice's sample from disk or cache into its ring buffer.
+struct LoaderStream {
+    job: SpawnJob,
+    file_name: String,
+    source: Option<Box<dyn Iterator<Item = f32>>>,
+    source_is_finished: bool,
+    use_memory_reader: bool,
+    /// The whole sample when it is played from memory, shared with the cache it came from
+    samples_in_memory: Arc<Vec<f32>>,
+    /// Set while the in-memory sample is still being decoded
+    decoding: Option<LoopDecode>,
+    /// Length of the in-memory sample, including frames not decoded yet
+    total_frames: usize,
+    input_channels: usize,
+    current_frame_index: usize,
+    loop_start_frame: usize,
+    loop_end_frame: usize,
+    loop_crossfade_frames: usize,
+    is_looping_sample: bool,
+    interleaved_buffer: Vec<f32>,
+}
+
+impl LoaderStream {
+    /// Prepares the sample of a job, or finishes the job if that fails.
+    fn open(job: SpawnJob, looped_samples: &Mutex<LoopedSampleCache>) -> Option<Self> {
+        // Check cancellation before doing heavy lifting
+        if job.is_cancelled.load(Ordering::Relaxed) {
+            job.is_finished.store(true, Ordering::SeqCst);
+            return None;
+        }
+
+        let file_name = job
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        // Catch panics to prevent a bad sample from taking down a loader thread
+        let is_finished = Arc::clone(&job.is_finished);
+        match std::panic::catch_unwind(AssertUnwindSafe(|| {
+            Self::prepare(job, file_name.clone(), looped_samples)
+        })) {
+            Ok(Ok(stream)) => return Some(stream),
+            Ok(Err(e)) => log::error!("Loader error: {}", e),
+            Err(e) => log::error!("[LoaderThread] PANICKED for file {:?}: {:?}", file_name, e),
+        }
+        is_finished.store(true, Ordering::SeqCst);
+        None
+    }
+
+    fn prepare(
+        job: SpawnJob,
+        file_name: String,
+        looped_samples: &Mutex<LoopedSampleCache>,
+    ) -> Result<Self> {
+        let loops: Vec<(u32, u32)>;
+        let input_channels;
+        let mut source: Option<Box<dyn Iterator<Item = f32>>> = None;
+        let source_is_finished;
+        let use_memory_reader;
+        let mut samples_in_memory: Arc<Vec<f32>> = Arc::default();
+        let mut decoding = None;
+        let total_frames;
+
+        let frames_to_skip = job.frames_to_skip;
+        // Percussive pipes (chimes, harps) decay naturally, so their loops are ignored
+        let honor_loops = job.is_attack_sample && !job.is_percussive;
+
+        // Check Caches: the precache, then looped samples other notes decoded
+        let precached = job
+            .organ
+            .sample_cache
+            .as_ref()
+            .and_then(|c| c.get(&job.path).cloned())
+            .zip(
+                job.organ
+                    .metadata_cache
+                    .as_ref()
+                    .and_then(|c| c.get(&job.path).cloned()),
+            );
+        let maybe_cached = if precached.is_some() {
+            precached
+        } else if honor_loops {
+            looped_samples.lock().unwrap().get(&job.path)
+        } else {
+            None
+        };
+
+        if let Some((cached_samples, cached_metadata)) = maybe_cached {
+            // Fast Path: Memory Cache, read in place without copying
+            samples_in_memory = cached_samples;
+            loops = if honor_loops {
+                cached_metadata.loops.clone()
             } else {
-                // Slow Path: Disk I/O
-                let file = File::open(&job.path)?;
-                let mut reader = BufReader::new(file);
-                let (fmt, other_chunks, data_start, data_size) =
-                    parse_wav_metadata(&mut reader, &job.path)?;
-
-                if fmt.sample_rate != job.sample_rate {
-                    return Err(anyhow!("Rate mismatch"));
-                }
+                Vec::new()
+            };
+            input_channels = cached_metadata.channel_count as usize;
+            total_frames = samples_in_memory.len() / input_channels;
+            use_memory_reader = true;
+            source_is_finished = false;
+        } else {
+            // Slow Path: Disk I/O
+            let file = File::open(&job.path)
+                .inspect_err(|_| Organ::invalidate_snapshot_for_sample(&job.path))?;
+            let mut reader = BufReader::new(file);
+            let (fmt, other_chunks, data_start, data_size) =
+                parse_wav_metadata(&mut reader, &job.path)?;
 
-                let mut loop_info_from_file = None;
-                for chunk in other_chunks {
-                    if &chunk.id == b"smpl" {
-                        loop_info_from_file = parse_smpl_chunk(&chunk.data);
-                        break;
-                    }
+            if fmt.sample_rate != job.sample_rate {
+                return Err(anyhow!("Rate mismatch"));
+            }
+
+            let mut loops_from_file = Vec::new();
+            for chunk in other_chunks {
+                if &chunk.id == b"smpl" {
+                    loops_from_file = parse_smpl_chunk(&chunk.data).unwrap_or_default();
+                    break;
                 }
-                loop_info = if job.is_attack_sample {
-                    loop_info_from_file
-                } else {
-                    None
-                };
-                input_channels = fmt.num_channels as usize;
+            }
+            loops = if honor_loops {
+                loops_from_file
+            } else {
+                Vec::new()
+            };
+            input_channels = fmt.num_channels as usize;
+            let bytes_per_frame = input_channels * (fmt.bits_per_sample as usize / 8);
+            total_frames = data_size as usize / bytes_per_frame.max(1);
 
-                let decoder = WavSampleReader::new(reader, fmt, data_start, data_size)?;
+            let decoder = WavSampleReader::new(reader, fmt, data_start, data_size)?;
 
-                if job.is_attack_sample && loop_info.is_some() {
-                    // Small looping samples must be fully loaded into memory
-                    samples_in_memory = decoder.collect();
-                    use_memory_reader = true;
-                    source_is_finished = false;
-                } else {
-                    // Long one-shot samples are streamed
-                    let mut iterator = Box::new(decoder);
-
-                    // Skip frames (e.g. if we had preloaded bytes)
-                    let mut skip_successful = true;
-                    if frames_to_skip > 0 {
-                        let samples_to_skip = frames_to_skip * input_channels;
-                        for _ in 0..samples_to_skip {
-                            if iterator.next().is_none() {
-                                skip_successful = false;
-                                break;
-                            }
+            if honor_loops && !loops.is_empty() {
+                // Looping samples must be fully loaded into memory. They are decoded
+                // over the next turns while they play, then shared with every later
+                // note of the pipe.
+                decoding = Some(LoopDecode {
+                    decoder,
+                    samples: Vec::with_capacity(total_frames * input_channels),
+                    metadata: SampleMetadata {
+                        loops: loops.clone(),
+                        channel_count: fmt.num_channels,
+                    },
+                });
+                use_memory_reader = true;
+                source_is_finished = false;
+            } else {
+                // Long one-shot samples are streamed
+                let mut iterator = Box::new(decoder);
+
+                // Skip frames (e.g. if we had preloaded bytes)
+                let mut skip_successful = true;
+                if frames_to_skip > 0 {
+                    let samples_to_skip = frames_to_skip * input_channels;
+                    for _ in 0..samples_to_skip {
+                        if iterator.next().is_none() {
+                            skip_successful = false;
+                            break;
                         }
                     }
-
-                    source_is_finished = !skip_successful;
-                    source = Some(iterator);
-                    use_memory_reader = false;
                 }
+
+                source_is_finished = !skip_successful;
+                source = Some(iterator);
+                use_memory_reader = false;
             }
+        }
 
-            let is_mono = input_channels == 1;
-            let mut current_frame_index: usize = frames_to_skip;
-            let mut loop_start_frame: usize = 0;
-            let mut loop_end_frame: usize = 0;
-            let mut is_looping_sample = job.is_attack_sample && loop_info.is_some();
-
-            // Validate loop points against loaded data size
-            if use_memory_reader && is_looping_sample {
-                let (start, end) = loop_info.unwrap();
-                loop_start_frame = start as usize;
-                let total_frames = samples_in_memory.len() / input_channels;
-                loop_end_frame = if end == 0 { total_frames } else { end as usize };
-                if loop_start_frame >= loop_end_frame || loop_end_frame > total_frames {
-                    is_looping_sample = false;
-                    current_frame_index = 0;
+        let mut current_frame_index: usize = frames_to_skip;
+        let mut loop_start_frame: usize = 0;
+        let mut loop_end_frame: usize = 0;
+        let mut loop_crossfade_frames: usize = 0;
+        let mut is_looping_sample = honor_loops && !loops.is_empty();
+
+        // Validate loop points against the sample's size, then pick this note's loop
+        if use_memory_reader && is_looping_sample {
+            let valid_loops: Vec<(usize, usize)> = loops
+                .iter()
+                .map(|&(start, end)| {
+                    let end = if end == 0 { total_frames } else { end as usize };
+                    (start as usize, end)
+                })
+                .filter(|&(start, end)| start < end && end <= total_frames)
+                .collect();
+            if valid_loops.is_empty() {
+                is_looping_sample = false;
+                current_frame_index = 0;
+            } else {
+                (loop_start_frame, loop_end_frame) =
+                    valid_loops[(job.loop_choice % valid_loops.len() as u64) as usize];
+                // The crossfade reads the audio before the loop start
+                loop_crossfade_frames = ((job.sample_rate as f32 * LOOP_CROSSFADE_TIME) as usize)
+                    .min(loop_start_frame)
+                    .min((loop_end_frame - loop_start_frame) / 2);
+                if job.start_in_loop {
+                    current_frame_index = loop_start_frame;
                 }
             }
+        }
+
+        Ok(Self {
+            job,
+            file_name,
+            source,
+            source_is_finished,
+            use_memory_reader,
+            samples_in_memory,
+            decoding,
+            total_frames,
+            input_channels,
+            current_frame_index,
+            loop_start_frame,
+            loop_end_frame,
+            loop_crossfade_frames,
+            is_looping_sample,
+            interleaved_buffer: vec![0.0f32; LOADER_CHUNK_FRAMES * CHANNEL_COUNT],
+        })
+    }
 
-            // Streaming Loop
-            'loader_loop: loop {
-                if job.is_cancelled.load(Ordering::Relaxed) {
-                    break 'loader_loop;
+    /// Tops up the voice's ring buffer by as much as fits, up to a few chunks.
+    /// A finished stream marks its job finished; dropping it frees the ring buffer.
+    fn turn(&mut self, looped_samples: &Mutex<LoopedSampleCache>) -> StreamTurn {
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut progressed = self.decode_step(looped_samples);
+            for _ in 0..LOADER_CHUNKS_PER_TURN {
+                if self.job.is_cancelled.load(Ordering::Relaxed) {
+                    return None;
                 }
+                let space = self.job.producer.vacant_len() / CHANNEL_COUNT;
+                let frames_read = self.read_chunk(space.min(LOADER_CHUNK_FRAMES));
 
-                let frames_to_read = 1024;
-                let mut frames_read = 0;
-
-                if use_memory_reader {
-                    for i in 0..frames_to_read {
-                        if is_looping_sample {
-                            if current_frame_index >= loop_end_frame {
-                                current_frame_index = loop_start_frame;
-                            }
-                        } else {
-                            if current_frame_index >= (samples_in_memory.len() / input_channels) {
-                                source_is_finished = true;
-                                break;
-                            }
-                        }
+                // Only as much was read as the ring buffer has room for
+                if frames_read > 0 {
+                    self.job
+                        .producer
+                        .push_slice(&self.interleaved_buffer[..frames_read * CHANNEL_COUNT]);
+                    progressed = true;
+                }
 
-                        let sample_l_idx = current_frame_index * input_channels;
-                        // Manual safety checks removed for brevity, but indices are bounded above
-                        let sample_l = samples_in_memory.get(sample_l_idx).cloned().unwrap_or(0.0);
-                        let sample_r = if is_mono {
-                            sample_l
-                        } else {
-                            samples_in_memory
-                                .get(sample_l_idx + 1)
-                                .cloned()
-                                .unwrap_or(0.0)
-                        };
-
-                        interleaved_buffer[i * CHANNEL_COUNT] = sample_l;
-                        interleaved_buffer[i * CHANNEL_COUNT + 1] = sample_r;
-
-                        current_frame_index += 1;
-                        frames_read += 1;
-                    }
-                } else {
-                    if !source_is_finished {
-                        if let Some(ref mut s_iter) = source {
-                            for i in 0..frames_to_read {
-                                if let Some(sample_l) = s_iter.next() {
-                                    let sample_r = if is_mono {
-                                        sample_l
-                                    } else {
-                                        s_iter.next().unwrap_or(0.0)
-                                    };
-                                    interleaved_buffer[i * CHANNEL_COUNT] = sample_l;
-                                    interleaved_buffer[i * CHANNEL_COUNT + 1] = sample_r;
-                                    frames_read += 1;
-                                } else {
-                                    source_is_finished = true;
-                                    break;
-                                }
-                            }
-                        }
-                    }
+                if self.source_is_finished && !self.is_looping_sample {
+                    return None;
                 }
+                if frames_read < LOADER_CHUNK_FRAMES {
+                    break;
+                }
+            }
+            Some(progressed)
+        }));
 
-                // Push data to the ring buffer
-                if frames_read > 0 {
-                    let samples_to_push = frames_read * CHANNEL_COUNT;
-                    let mut offset = 0;
-                    while offset < samples_to_push {
-                        if job.is_cancelled.load(Ordering::Relaxed) {
-                            break 'loader_loop;
-                        }
+        match result {
+            Ok(Some(true)) => StreamTurn::Progressed,
+            Ok(Some(false)) => StreamTurn::Waiting,
+            Ok(None) => {
+                self.job.is_finished.store(true, Ordering::SeqCst);
+                StreamTurn::Done
+            }
+            Err(e) => {
+                log::error!(
+                    "[LoaderThread] PANICKED for file {:?}: {:?}",
+                    self.file_name,
+                    e
+                );
+                self.job.is_finished.store(true, Ordering::SeqCst);
+                StreamTurn::Done
+            }
+        }
+    }
 
-                        let pushed = job
-                            .producer
-                            .push_slice(&interleaved_buffer[offset..samples_to_push]);
-                        offset += pushed;
+    /// Reads up to `frames_to_read` stereo frames into the interleaved buffer.
+    fn read_chunk(&mut self, frames_to_read: usize) -> usize {
+        let mut frames_read = 0;
 
-                        // Backpressure: If ring buffer is full, sleep briefly
-                        if offset < samples_to_push {
-                            thread::sleep(Duration::from_millis(1));
-                        }
+        if self.use_memory_reader {
+            let decoded_frames = self.samples().len() / self.input_channels;
+            for i in 0..frames_to_read {
+                if self.is_looping_sample {
+                    if self.current_frame_index >= self.loop_end_frame {
+                        self.current_frame_index = self.loop_start_frame;
                     }
+                } else if self.current_frame_index >= self.total_frames {
+                    self.source_is_finished = true;
+                    break;
                 }
+                // The rest is read once it is decoded
+                if self.current_frame_index >= decoded_frames {
+                    break;
+                }
+
+                let (mut sample_l, mut sample_r) = self.read_frame(self.current_frame_index);
 
-                if source_is_finished && !is_looping_sample {
-                    break 'loader_loop;
+                // Towards the loop end, fade into the audio leading up to the loop
+                // start, so the jump back lands on a continuous waveform
+                if self.is_looping_sample
+                    && self.loop_crossfade_frames > 0
+                    && self.current_frame_index + self.loop_crossfade_frames >= self.loop_end_frame
+                {
+                    let into_fade =
+                        self.current_frame_index + self.loop_crossfade_frames - self.loop_end_frame;
+                    let t = (into_fade + 1) as f32 / (self.loop_crossfade_frames + 1) as f32;
+                    let (before_l, before_r) = self.read_frame(
+                        self.current_frame_index - (self.loop_end_frame - self.loop_start_frame),
+                    );
+                    sample_l += (before_l - sample_l) * t;
+                    sample_r += (before_r - sample_r) * t;
                 }
 
-                // If looping but we read 0 frames (maybe waiting for something?), prevent cpu spin
-                if is_looping_sample && frames_read == 0 {
-                    thread::sleep(Duration::from_millis(1));
+                self.interleaved_buffer[i * CHANNEL_COUNT] = sample_l;
+                self.interleaved_buffer[i * CHANNEL_COUNT + 1] = sample_r;
+
+                self.current_frame_index += 1;
+                frames_read += 1;
+            }
+        } else if !self.source_is_finished
+            && let Some(ref mut s_iter) = self.source
+        {
+            let is_mono = self.input_channels == 1;
+            for i in 0..frames_to_read {
+                if let Some(sample_l) = s_iter.next() {
+                    let sample_r = if is_mono {
+                        sample_l
+                    } else {
+                        s_iter.next().unwrap_or(0.0)
+                    };
+                    self.interleaved_buffer[i * CHANNEL_COUNT] = sample_l;
+                    self.interleaved_buffer[i * CHANNEL_COUNT + 1] = sample_r;
+                    frames_read += 1;
+                } else {
+                    self.source_is_finished = true;
+                    break;
                 }
             }
-            Ok(())
-        })();
+        }
+
+        frames_read
+    }
 
-        if let Err(e) = result {
-            log::error!("Loader error: {}", e);
+    /// Decodes the next part of a looped sample still being decoded, and shares the
+    /// sample once it is complete. Returns whether anything was decoded.
+    fn decode_step(&mut self, looped_samples: &Mutex<LoopedSampleCache>) -> bool {
+        let Some(decode) = &mut self.decoding else {
+            return false;
+        };
+        let target_len = (decode.samples.len()
+            + LOADER_DECODE_FRAMES_PER_TURN * self.input_channels)
+            .min(self.total_frames * self.input_channels);
+        let mut is_complete = false;
+        while decode.samples.len() < target_len {
+            match decode.decoder.next() {
+                Some(sample) => decode.samples.push(sample),
+                None => {
+                    is_complete = true;
+                    break;
+                }
+            }
+        }
+        if !is_complete && decode.samples.len() < self.total_frames * self.input_channels {
+            return true;
         }
-    });
 
-    if let Err(e) = panic_result {
-        log::error!(
-            "[LoaderThread] PANICKED for file {:?}: {:?}",
-            path_str_clone,
-            e
-        );
+        if let Some(decode) = self.decoding.take() {
+            let decoded = (Arc::new(decode.samples), Arc::new(decode.metadata));
+            // Another note may have decoded the same sample meanwhile
+            (self.samples_in_memory, _) = looped_samples
+                .lock()
+                .unwrap()
+                .insert(self.job.path.clone(), decoded);
+        }
+        // A truncated file is shorter than its header claims
+        self.total_frames = self.samples_in_memory.len() / self.input_channels;
+        if self.loop_end_frame > self.total_frames {
+            self.is_looping_sample = false;
+        }
+        true
+    }
+
+    /// The in-memory sample, as far as it is decoded.
+    fn samples(&self) -> &[f32] {
+        match &self.decoding {
+            Some(decode) => &decode.samples,
+            None => &self.samples_in_memory,
+        }
     }
 
-    job.is_finished.store(true, Ordering::SeqCst);
+    /// One frame of the in-memory sample as (left, right). Mono samples play on both sides.
+    fn read_frame(&self, frame: usize) -> (f32, f32) {
+        let samples = self.samples();
+        let sample_l_idx = frame * self.input_channels;
+        let sample_l = samples.get(sample_l_idx).cloned().unwrap_or(0.0);
+        let sample_r = if self.input_channels == 1 {
+            sample_l
+        } else {
+            samples.get(sample_l_idx + 1).cloned().unwrap_or(0.0)
+        };
+        (sample_l, sample_r)
+    }
 }
@@ -1,15 +1,20 @@
 use anyhow::Result;
 use midir::{MidiInput, MidiInputPort};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use crate::audio::{
     get_audio_device_names, get_default_audio_device_name, get_supported_sample_rates,
 };
+use crate::audio_loader::LoopSelection;
 use crate::input::KeyboardLayout;
+use crate::output_routing::{OutputBus, OutputRoute};
+use crate::resample::ResamplingQuality;
+use crate::temperament::{EQUAL_TEMPERAMENT, Temperament, get_available_temperaments};
 
 /// Represents a specific MIDI trigger (Note or SysEx)
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
@@ -20,6 +25,11 @@ pub enum MidiEventSpec {
         is_note_off: bool,
     },
     SysEx(Vec<u8>),
+    /// A controller, independent of its value (e.g. an expression pedal)
+    ControlChange {
+        channel: u8, // 0-15
+        controller: u8,
+    },
 }
 
 impl fmt::Display for MidiEventSpec {
@@ -33,6 +43,10 @@ impl fmt::Display for MidiEventSpec {
                 let status = if *is_note_off { "Off" } else { "On" };
                 write!(f, "Ch{} Note {} ({})", channel + 1, note, status)
             }
+            MidiEventSpec::ControlChange {
+                channel,
+                controller,
+            } => write!(f, "Ch{} CC {}", channel + 1, controller),
             MidiEventSpec::SysEx(bytes) => {
                 let hex: Vec<String> = bytes.iter().map(|b| format!("{:02X}", b)).collect();
                 // Truncate if too long for display
@@ -78,6 +92,28 @@ impl Default for MidiDeviceConfig {
     }
 }
 
+/// Mix level of one pipe layer (recording perspective) of an organ.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PipeLayerMix {
+    /// Layer number as found in the sample set (1 = first layer of each pipe)
+    pub layer: u32,
+    /// Linear gain applied to this layer (1.0 = as recorded)
+    pub level: f32,
+}
+
+/// A pipe layer (recording perspective) found in a Hauptwerk organ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipeLayerInfo {
+    pub number: u32,
+    /// Number of pipes recorded in this layer
+    pub pipe_count: usize,
+}
+
+/// Key under which an organ's pipe layer selection is stored.
+pub fn pipe_layers_key(organ_file: &Path) -> String {
+    organ_file.to_string_lossy().into_owned()
+}
+
 /// Settings that are saved to the configuration file.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppSettings {
@@ -92,6 +128,27 @@ pub struct AppSettings {
     pub tui_mode: bool,
     pub gain: f32,
     pub polyphony: usize,
+    /// Multiplier applied to every rank's tracker delay (0.0 disables key action delay)
+    #[serde(default = "default_tracker_delay_scale")]
+    pub tracker_delay_scale: f32,
+    /// Temperament name, or the path of a temperament file
+    #[serde(default = "default_temperament")]
+    pub temperament: String,
+    /// Pitch class (0 = C) the temperament is built on
+    #[serde(default)]
+    pub temperament_base_note: u8,
+    /// Reference pitch offset in cents
+    #[serde(default)]
+    pub master_tuning_cents: f32,
+    /// Semitones every note is shifted by. Not saved: every launch starts untransposed.
+    #[serde(skip)]
+    pub transpose: i8,
+    /// How sustained notes choose among the loops of samples that have several
+    #[serde(default)]
+    pub loop_selection: LoopSelection,
+    /// Interpolation of voices playing at another pitch than recorded (tremulants, transposition)
+    #[serde(default)]
+    pub resampling_quality: ResamplingQuality,
     pub audio_device_name: Option<String>,
     pub sample_rate: u32,
     pub keyboard_layout: KeyboardLayout,
@@ -99,6 +156,16 @@ pub struct AppSettings {
     pub midi_devices: Vec<MidiDeviceConfig>,
     #[serde(default)]
     pub lcd_displays: Vec<LcdDisplayConfig>,
+    /// Selected pipe layers per organ file. Organs without an entry load
+    /// the first layer of each pipe.
+    #[serde(default)]
+    pub pipe_layers: HashMap<String, Vec<PipeLayerMix>>,
+    /// Stereo outputs of the audio device. Empty plays everything on the first two channels.
+    #[serde(default)]
+    pub output_buses: Vec<OutputBus>,
+    /// Routes of divisions, windchest groups and ranks to the output buses, per organ name
+    #[serde(default)]
+    pub output_routes: HashMap<String, Vec<OutputRoute>>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
@@ -188,6 +255,14 @@ impl Default for LcdDisplayConfig {
     }
 }
 
+fn default_tracker_delay_scale() -> f32 {
+    1.0
+}
+
+fn default_temperament() -> String {
+    EQUAL_TEMPERAMENT.to_string()
+}
+
 /// Default settings for a new installation.
 impl Default for AppSettings {
     fn default() -> Self {
@@ -203,11 +278,21 @@ impl Default for AppSettings {
             tui_mode: false, // Default to GUI
             gain: 0.4,       // Conservative default gain
             polyphony: 128,
+            tracker_delay_scale: default_tracker_delay_scale(),
+            temperament: default_temperament(),
+            temperament_base_note: 0,
+            master_tuning_cents: 0.0,
+            transpose: 0,
+            loop_selection: LoopSelection::default(),
+            resampling_quality: ResamplingQuality::default(),
             audio_device_name: None,
             sample_rate: 48000,
             keyboard_layout: KeyboardLayout::Qwerty,
             midi_devices: Vec::new(),
             lcd_displays: Vec::new(),
+            pipe_layers: HashMap::new(),
+            output_buses: Vec::new(),
+            output_routes: HashMap::new(),
         }
     }
 }
@@ -227,6 +312,16 @@ pub struct RuntimeConfig {
     pub original_tuning: bool,
     pub gain: f32,
     pub polyphony: usize,
+    pub tracker_delay_scale: f32,
+    pub temperament: String,
+    pub temperament_base_note: u8,
+    pub master_tuning_cents: f32,
+    pub transpose: i8,
+    pub loop_selection: LoopSelection,
+    pub resampling_quality: ResamplingQuality,
+    pub pipe_layers: HashMap<String, Vec<PipeLayerMix>>,
+    pub output_buses: Vec<OutputBus>,
+    pub output_routes: HashMap<String, Vec<OutputRoute>>,
 
     // --- Runtime-Only Settings ---
     pub midi_file: Option<PathBuf>,
@@ -236,6 +331,15 @@ pub struct RuntimeConfig {
     pub lcd_displays: Vec<LcdDisplayConfig>,
 }
 
+impl RuntimeConfig {
+    /// Returns the pipe layer selection of the organ to load (empty = default layer).
+    pub fn selected_pipe_layers(&self) -> &[PipeLayerMix] {
+        self.pipe_layers
+            .get(&pipe_layers_key(&self.organ_file))
+            .map_or(&[], |layers| layers.as_slice())
+    }
+}
+
 /// Loads settings from disk.
 pub fn load_settings() -> Result<AppSettings> {
     let settings: AppSettings = confy::load("rusty-pipes", "settings")?;
@@ -262,6 +366,20 @@ pub fn get_reverb_directory() -> Result<PathBuf> {
     Ok(reverb_dir)
 }
 
+/// Helper to get the directory of user-defined temperaments, creating it if it doesn't exist.
+pub fn get_temperament_directory() -> Result<PathBuf> {
+    let config_path = confy::get_configuration_file_path("rusty-pipes", "settings")?;
+    let parent = config_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("No config parent dir"))?;
+    let temperament_dir = parent.join("temperaments");
+
+    if !temperament_dir.exists() {
+        fs::create_dir_all(&temperament_dir)?;
+    }
+    Ok(temperament_dir)
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct OrganProfile {
     pub name: String,
@@ -337,6 +455,10 @@ pub struct ConfigState {
     pub selected_audio_device_name: Option<String>,
     pub available_sample_rates: Vec<u32>,
     pub available_ir_files: Vec<(String, PathBuf)>,
+    pub available_temperaments: Vec<Temperament>,
+
+    // Pipe layers (perspectives) of the selected organ, once they have been scanned
+    pub available_pipe_layers: Vec<PipeLayerInfo>,
 }
 
 impl ConfigState {
@@ -418,6 +540,7 @@ impl ConfigState {
         }
 
         let available_ir_files = get_available_ir_files();
+        let available_temperaments = get_available_temperaments();
 
         Ok(Self {
             settings,
@@ -428,6 +551,52 @@ impl ConfigState {
             selected_audio_device_name,
             available_sample_rates,
             available_ir_files,
+            available_temperaments,
+            available_pipe_layers: Vec::new(),
         })
     }
+
+    /// Returns the pipe layers that will be loaded for the selected organ.
+    /// Without a saved selection this is the first layer at full level.
+    pub fn effective_pipe_layers(&self) -> Vec<PipeLayerMix> {
+        self.settings
+            .organ_file
+            .as_deref()
+            .and_then(|path| self.settings.pipe_layers.get(&pipe_layers_key(path)))
+            .filter(|layers| !layers.is_empty())
+            .cloned()
+            .unwrap_or_else(|| {
+                self.available_pipe_layers
+                    .first()
+                    .map(|info| PipeLayerMix {
+                        layer: info.number,
+                        level: 1.0,
+                    })
+                    .into_iter()
+                    .collect()
+            })
+    }
+
+    /// Enables a pipe layer at the given level, or disables it with `None`.
+    /// Disabling the last layer returns the organ to its default layer.
+    pub fn set_pipe_layer(&mut self, layer: u32, level: Option<f32>) {
+        let Some(path) = self.settings.organ_file.as_deref() else {
+            return;
+        };
+        let key = pipe_layers_key(path);
+        let mut layers = self.effective_pipe_layers();
+        layers.retain(|mix| mix.layer != layer);
+        if let Some(level) = level {
+            layers.push(PipeLayerMix {
+                layer,
+                level: level.clamp(0.0, 1.0),
+            });
+            layers.sort_by_key(|mix| mix.layer);
+        }
+        if layers.is_empty() {
+            self.settings.pipe_layers.remove(&key);
+        } else {
+            self.settings.pipe_layers.insert(key, layers);
+        }
+    }
 }
@@ -14,10 +14,16 @@ use std::{
 use crate::{
     app::MainLoopAction,
     app::{AppMessage, TuiMessage},
-    app_state::{AppState, Preset},
+    app_state::{AppState, MAX_MASTER_TUNING_CENTS, MAX_TRANSPOSE, Preset},
     config::MidiEventSpec, // Import the new Enum
+    gui_console::ConsoleView,
     gui_midi_learn::{LearnTarget, MidiLearnState, draw_midi_learn_modal},
     gui_organ_manager::OrganManagerUi,
+    gui_output_routing::OutputRoutingUi,
+    gui_stereo::StereoUi,
+    gui_virtual_stops::VirtualStopsUi,
+    gui_voicing::VoicingUi,
+    gui_wind::WindUi,
     input::MusicCommand,
     organ::Organ,
 };
@@ -45,8 +51,17 @@ pub struct EguiApp {
     selected_reverb_index: Option<usize>,
     midi_learn_state: MidiLearnState,
 
+    // Console view (drawn instead of the stop list)
+    show_console: bool,
+    console_view: ConsoleView,
+
     // Organ Manager
     organ_manager: OrganManagerUi,
+    virtual_stops: VirtualStopsUi,
+    voicing: VoicingUi,
+    wind: WindUi,
+    stereo: StereoUi,
+    output_routing: OutputRoutingUi,
     exit_action: Arc<Mutex<MainLoopAction>>,
     gui_is_running: Arc<AtomicBool>,
 }
@@ -97,7 +112,14 @@ pub fn run_gui_loop(
         reverb_files,
         selected_reverb_index,
         midi_learn_state: MidiLearnState::default(),
+        show_console: !organ.panels.is_empty(),
+        console_view: ConsoleView::new(),
         organ_manager: OrganManagerUi::new(),
+        virtual_stops: VirtualStopsUi::new(&organ.name),
+        voicing: VoicingUi::new(&organ.name),
+        wind: WindUi::new(&organ.name),
+        stereo: StereoUi::new(&organ.name),
+        output_routing: OutputRoutingUi::new(&organ.name),
         exit_action: exit_action.clone(),
         gui_is_running,
     };
@@ -285,8 +307,10 @@ impl App for EguiApp {
             }
             // Panic key: P
             if input.key_pressed(egui::Key::P) {
-                self.audio_tx
-                    .send(AppMessage::AllNotesOff)
+                self.app_state
+                    .lock()
+                    .unwrap()
+                    .all_notes_off(&self.audio_tx)
                     .unwrap_or_else(|e| {
                         log::error!("ERROR sending AllNotesOff: {}", e);
                     });
@@ -385,6 +409,15 @@ impl App for EguiApp {
 
         self.organ_manager
             .show(ctx, &self.exit_action, self.app_state.clone());
+        self.virtual_stops
+            .show(ctx, &self.exit_action, self.app_state.clone());
+        self.voicing
+            .show(ctx, self.app_state.clone(), &self.audio_tx);
+        self.wind.show(ctx, self.app_state.clone(), &self.audio_tx);
+        self.stereo
+            .show(ctx, self.app_state.clone(), &self.audio_tx);
+        self.output_routing
+            .show(ctx, self.app_state.clone(), &self.audio_tx);
 
         // Organ Switching via Trigger
         if !self.organ_manager.visible && !self.midi_learn_state.is_open {
@@ -471,13 +504,46 @@ impl EguiApp {
         egui::CentralPanel::default()
             .frame(panel_frame)
             .show(ctx, |ui| {
-                ui.heading(egui::RichText::new(organ.name.clone()).heading().strong());
+                ui.horizontal(|ui| {
+                    ui.heading(egui::RichText::new(organ.name.clone()).heading().strong());
+                    if !organ.panels.is_empty() {
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.selectable_value(
+                                &mut self.show_console,
+                                true,
+                                t!("gui.view_console"),
+                            );
+                            ui.selectable_value(
+                                &mut self.show_console,
+                                false,
+                                t!("gui.view_stops"),
+                            );
+                        });
+                    }
+                });
                 ui.separator();
 
                 self.draw_stop_controls(ui, organ.clone());
 
                 ui.separator();
 
+                if self.show_console && !organ.panels.is_empty() {
+                    let learn_target = self.console_view.draw(
+                        ui,
+                        &organ,
+                        &stop_channels,
+                        &self.app_state,
+                        &self.audio_tx,
+                    );
+                    if let Some((target, name)) = learn_target {
+                        self.midi_learn_state.is_open = true;
+                        self.midi_learn_state.target = target;
+                        self.midi_learn_state.target_name = name;
+                        self.midi_learn_state.learning_slot = None;
+                    }
+                    return;
+                }
+
                 let scroll = egui::ScrollArea::vertical()
                     .auto_shrink([false, false])
                     .vertical_scroll_offset(self.stop_list_scroll_offset);
@@ -513,6 +579,18 @@ impl EguiApp {
                 if ui.button(t!("organ_manager.button")).clicked() {
                     self.organ_manager.visible = true;
                 }
+                if ui.button(t!("virtual_stops.button")).clicked() {
+                    self.virtual_stops.visible = true;
+                }
+                if ui.button(t!("voicing.button")).clicked() {
+                    self.voicing.visible = true;
+                }
+                if ui.button(t!("wind.button")).clicked() {
+                    self.wind.visible = true;
+                }
+                if ui.button(t!("stereo.button")).clicked() {
+                    self.stereo.visible = true;
+                }
 
                 // Right-aligned controls
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -729,6 +807,107 @@ impl EguiApp {
                                     }
                                 });
                         }
+
+                        ui.separator();
+                        ui.heading(t!("gui.couplers_heading"));
+                        ui.add_space(5.0);
+
+                        let mut coupler_ids: Vec<_> = organ.couplers.keys().collect();
+                        coupler_ids.sort();
+
+                        if coupler_ids.is_empty() {
+                            ui.label(egui::RichText::new(t!("gui.no_couplers")).weak());
+                        } else {
+                            egui::Grid::new("coupler_grid")
+                                .num_columns(2)
+                                .spacing([spacing, 5.0])
+                                .show(ui, |ui| {
+                                    for (i, coupler_id) in coupler_ids.iter().enumerate() {
+                                        let coupler = &organ.couplers[*coupler_id];
+                                        let is_active = self
+                                            .app_state
+                                            .lock()
+                                            .unwrap()
+                                            .active_couplers
+                                            .contains(*coupler_id);
+
+                                        let button_text = if is_active {
+                                            egui::RichText::new(&coupler.name).color(egui::Color32::GREEN)
+                                        } else {
+                                            egui::RichText::new(&coupler.name)
+                                        };
+
+                                        let btn = ui.add_sized(btn_size, egui::Button::new(button_text));
+
+                                        // Left Click: Toggle
+                                        if btn.clicked() {
+                                            let mut state = self.app_state.lock().unwrap();
+                                            if let Err(e) = state.set_coupler_active(
+                                                coupler_id.to_string(),
+                                                !is_active,
+                                                &self.audio_tx,
+                                            ) {
+                                                log::error!("Failed to set coupler: {}", e);
+                                            }
+                                        }
+
+                                        // Right Click: Learn
+                                        if btn.secondary_clicked() {
+                                            self.midi_learn_state.is_open = true;
+                                            self.midi_learn_state.target = LearnTarget::Coupler(coupler_id.to_string());
+                                            self.midi_learn_state.target_name = coupler.name.clone();
+                                            self.midi_learn_state.learning_slot = None;
+                                        }
+
+                                        if (i + 1) % 2 == 0 {
+                                            ui.end_row();
+                                        }
+                                    }
+                                });
+                        }
+
+                        ui.separator();
+                        ui.heading(t!("gui.enclosures_heading"));
+                        ui.add_space(5.0);
+
+                        let mut enclosure_ids: Vec<_> = organ.enclosures.keys().collect();
+                        enclosure_ids.sort();
+
+                        if enclosure_ids.is_empty() {
+                            ui.label(egui::RichText::new(t!("gui.no_enclosures")).weak());
+                        } else {
+                            for enclosure_id in enclosure_ids {
+                                let enclosure = &organ.enclosures[enclosure_id];
+                                let mut value = self
+                                    .app_state
+                                    .lock()
+                                    .unwrap()
+                                    .enclosure_values
+                                    .get(enclosure_id)
+                                    .copied()
+                                    .unwrap_or(enclosure.default_value);
+
+                                ui.label(&enclosure.name);
+                                let slider = ui.add(egui::Slider::new(&mut value, 0..=127));
+
+                                if slider.changed() {
+                                    let mut state = self.app_state.lock().unwrap();
+                                    state.set_enclosure_value(
+                                        enclosure_id.to_string(),
+                                        value,
+                                        &self.audio_tx,
+                                    );
+                                }
+
+                                // Right Click: Learn
+                                if slider.secondary_clicked() {
+                                    self.midi_learn_state.is_open = true;
+                                    self.midi_learn_state.target = LearnTarget::Enclosure(enclosure_id.to_string());
+                                    self.midi_learn_state.target_name = enclosure.name.clone();
+                                    self.midi_learn_state.learning_slot = None;
+                                }
+                            }
+                        }
                     
                         ui.separator();
                         ui.heading(t!("gui.audio_settings_heading"));
@@ -815,6 +994,55 @@ impl EguiApp {
                             });
                         });
 
+                        ui.add_space(10.0);
+
+                        // --- Transposition and Master Tuning ---
+                        let (mut transpose, mut master_tuning) = {
+                            let state = self.app_state.lock().unwrap();
+                            (state.transpose, state.master_tuning_cents)
+                        };
+                        egui::Grid::new("tuning_grid").num_columns(2).show(ui, |ui| {
+                            ui.label(t!("gui.transpose_label"));
+                            let transpose_drag = ui.add(
+                                egui::DragValue::new(&mut transpose)
+                                    .range(-MAX_TRANSPOSE..=MAX_TRANSPOSE)
+                                    .speed(0.1),
+                            ).on_hover_text(t!("gui.tuning_learn_hint"));
+                            if transpose_drag.changed() {
+                                self.app_state.lock().unwrap().set_transpose(transpose, &self.audio_tx);
+                            }
+                            if transpose_drag.secondary_clicked() {
+                                self.midi_learn_state.is_open = true;
+                                self.midi_learn_state.target = LearnTarget::Transpose;
+                                self.midi_learn_state.target_name = t!("gui.transpose_label").to_string();
+                                self.midi_learn_state.learning_slot = None;
+                            }
+                            ui.end_row();
+
+                            ui.label(t!("gui.master_tuning_label"));
+                            let tuning_drag = ui.add(
+                                egui::DragValue::new(&mut master_tuning)
+                                    .range(-MAX_MASTER_TUNING_CENTS..=MAX_MASTER_TUNING_CENTS)
+                                    .speed(0.1)
+                                    .suffix(" ct"),
+                            ).on_hover_text(t!("gui.tuning_learn_hint"));
+                            if tuning_drag.changed() {
+                                self.app_state.lock().unwrap().set_master_tuning(master_tuning, &self.audio_tx);
+                            }
+                            if tuning_drag.secondary_clicked() {
+                                self.midi_learn_state.is_open = true;
+                                self.midi_learn_state.target = LearnTarget::MasterTuning;
+                                self.midi_learn_state.target_name = t!("gui.master_tuning_label").to_string();
+                                self.midi_learn_state.learning_slot = None;
+                            }
+                            ui.end_row();
+                        });
+
+                        ui.add_space(10.0);
+                        if ui.button(t!("output_routing.button")).clicked() {
+                            self.output_routing.visible = true;
+                        }
+
                         ui.separator();
                 
                         // --- MIDI File Player Section ---
@@ -863,8 +1091,8 @@ impl EguiApp {
                             if ui.add_enabled_ui(is_playing, |ui| {
                                 ui.add_sized(ctrl_btn_size, egui::Button::new("⏪"))
                             }).inner.on_hover_text(t!("gui.midi_player_rewind")).clicked() {
-                                let state = self.app_state.lock().unwrap();
-                                let _ = self.audio_tx.send(AppMessage::AllNotesOff);
+                                let mut state = self.app_state.lock().unwrap();
+                                let _ = state.all_notes_off(&self.audio_tx);
                                 if let Some(tx) = &state.midi_seek_tx {
                                     let _ = tx.send(-15);
                                 }
@@ -878,8 +1106,7 @@ impl EguiApp {
                                     state.midi_file_stop_signal.store(true, std::sync::atomic::Ordering::Relaxed);
                                     state.is_midi_file_playing = false;
                                     state.handle_tui_all_notes_off();
-                                    state.channel_active_notes.clear();
-                                    let _ = self.audio_tx.send(AppMessage::AllNotesOff);
+                                    let _ = state.all_notes_off(&self.audio_tx);
                                 }
                             } else {
                                 // We use add_enabled so the button looks disabled if no file is selected
@@ -904,8 +1131,8 @@ impl EguiApp {
                             if ui.add_enabled_ui(is_playing, |ui| {
                                 ui.add_sized(ctrl_btn_size, egui::Button::new("⏩"))
                             }).inner.on_hover_text(t!("gui.midi_player_fastforward")).clicked() {
-                                let state = self.app_state.lock().unwrap();
-                                let _ = self.audio_tx.send(AppMessage::AllNotesOff);
+                                let mut state = self.app_state.lock().unwrap();
+                                let _ = state.all_notes_off(&self.audio_tx);
                                 if let Some(tx) = &state.midi_seek_tx {
                                     let _ = tx.send(15);
                                 }
@@ -945,9 +1172,9 @@ impl EguiApp {
                                 let delta = (target_time - current_time) as i32;
 
                                 // Send command
-                                let state = self.app_state.lock().unwrap();
+                                let mut state = self.app_state.lock().unwrap();
                                 // Silence notes before jumping
-                                let _ = self.audio_tx.send(AppMessage::AllNotesOff);
+                                let _ = state.all_notes_off(&self.audio_tx);
                                 
                                 if let Some(tx) = &state.midi_seek_tx {
                                     // midi.rs handles the math: new_time = current + delta
@@ -981,6 +1208,14 @@ impl EguiApp {
                             }
                         };
 
+                        // --- Voice Pool Indicator ---
+                        // Stays lit for a while, as a skipped note is easy to miss
+                        let (is_pool_exhausted, skipped_voice_count) = {
+                            let state = self.app_state.lock().unwrap();
+                            let recent = state.last_voice_pool_exhausted.is_some_and(|last| last.elapsed() < Duration::from_secs(2));
+                            (recent, state.skipped_voice_count)
+                        };
+
                         let (active_voice_count, polyphony, cpu_load) = {
                             let state = self.app_state.lock().unwrap();
                             (state.active_voice_count, state.polyphony, state.cpu_load)
@@ -992,6 +1227,11 @@ impl EguiApp {
                             ui.add_sized(status_btn_size, egui::Button::new(
                             egui::RichText::new(t!("gui.underrun_alert")).color(egui::Color32::WHITE).strong(),
                             ).fill(egui::Color32::RED));
+                        } else if is_pool_exhausted {
+                            ui.add_sized(status_btn_size, egui::Button::new(
+                            egui::RichText::new(t!("gui.voice_pool_alert")).color(egui::Color32::BLACK).strong(),
+                            ).fill(egui::Color32::ORANGE))
+                            .on_hover_text(t!("gui.voice_pool_tooltip", count = skipped_voice_count));
                         } else {
                             // Use the theme's faint background or noninteractive fill
                             let bg = ui.visuals().widgets.noninteractive.bg_fill;
@@ -1050,7 +1290,7 @@ impl EguiApp {
                 .clicked()
             {
                 let mut app_state = self.app_state.lock().unwrap();
-                if let Err(e) = self.audio_tx.send(AppMessage::AllNotesOff) {
+                if let Err(e) = app_state.all_notes_off(&self.audio_tx) {
                     app_state.add_midi_log(format!("ERROR: {}", e));
                 }
             }
@@ -1,13 +1,19 @@
 use crate::app::{LOGO, PIPES};
 use crate::audio::get_supported_sample_rates;
-use crate::config::{AppSettings, ConfigState, RuntimeConfig};
+use crate::config::{AppSettings, ConfigState, PipeLayerInfo, RuntimeConfig};
 use crate::gui_filepicker;
 use crate::gui_midi::MidiMappingWindow;
+use crate::organ_hauptwerk;
+use crate::resample::ResamplingQuality;
+use crate::temperament::NOTE_NAMES;
 use anyhow::Result;
 use eframe::{App, Frame, egui};
 use midir::MidiInput;
 use rust_i18n::t;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread;
 
 #[allow(dead_code)]
 struct ConfigApp {
@@ -19,6 +25,10 @@ struct ConfigApp {
     selected_ir_index: Option<usize>,
     midi_mapping_window: MidiMappingWindow,
     show_lcd_config: bool,
+    /// Organ file whose pipe layers were last scanned for
+    pipe_layers_scanned_for: Option<PathBuf>,
+    pipe_layers_tx: Sender<(PathBuf, Vec<PipeLayerInfo>)>,
+    pipe_layers_rx: Receiver<(PathBuf, Vec<PipeLayerInfo>)>,
 }
 
 impl ConfigApp {
@@ -50,6 +60,7 @@ impl ConfigApp {
             .as_ref()
             .and_then(|path| state.available_ir_files.iter().position(|(_, p)| p == path));
 
+        let (pipe_layers_tx, pipe_layers_rx) = mpsc::channel();
         Self {
             state,
             midi_input_arc,
@@ -59,9 +70,48 @@ impl ConfigApp {
             selected_ir_index,
             midi_mapping_window: MidiMappingWindow::new(),
             show_lcd_config: false,
+            pipe_layers_scanned_for: None,
+            pipe_layers_tx,
+            pipe_layers_rx,
         }
     }
 
+    /// Scans the selected organ for pipe layers on a worker thread whenever the
+    /// organ file changes. Only Hauptwerk organs have layers.
+    fn refresh_pipe_layers(&mut self, ctx: &egui::Context) {
+        while let Ok((path, layers)) = self.pipe_layers_rx.try_recv() {
+            if self.state.settings.organ_file.as_ref() == Some(&path) {
+                self.state.available_pipe_layers = layers;
+            }
+        }
+
+        if self.pipe_layers_scanned_for == self.state.settings.organ_file {
+            return;
+        }
+        self.pipe_layers_scanned_for = self.state.settings.organ_file.clone();
+        self.state.available_pipe_layers.clear();
+        let Some(path) = self.state.settings.organ_file.clone() else {
+            return;
+        };
+        if !matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("Organ_Hauptwerk_xml" | "xml")
+        ) {
+            return;
+        }
+        let tx = self.pipe_layers_tx.clone();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let layers = organ_hauptwerk::scan_pipe_layers(&path).unwrap_or_else(|e| {
+                log::warn!("Failed to scan pipe layers of {:?}: {}", path, e);
+                Vec::new()
+            });
+            if tx.send((path, layers)).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+    }
+
     // Helper to refresh rates when device changes
     fn refresh_sample_rates(&mut self) {
         let device_name = self
@@ -238,6 +288,8 @@ impl ConfigApp {
 
 impl App for ConfigApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut Frame) {
+        self.refresh_pipe_layers(ctx);
+
         // Modal MIDI Mapping Window
         self.midi_mapping_window
             .show(ctx, &mut self.state.settings.midi_devices);
@@ -311,6 +363,51 @@ impl App for ConfigApp {
                             });
                             ui.end_row();
 
+                            // --- Pipe Layers (Perspectives) ---
+                            if self.state.available_pipe_layers.len() > 1 {
+                                ui.label(t!("config.group_pipe_layers"))
+                                    .on_hover_text(t!("config.tooltip_pipe_layers"));
+                                ui.vertical(|ui| {
+                                    let selection = self.state.effective_pipe_layers();
+                                    let mut change = None;
+                                    for info in &self.state.available_pipe_layers {
+                                        let current =
+                                            selection.iter().find(|mix| mix.layer == info.number);
+                                        let mut enabled = current.is_some();
+                                        let mut level = current.map_or(1.0, |mix| mix.level);
+                                        ui.horizontal(|ui| {
+                                            let toggled = ui
+                                                .checkbox(
+                                                    &mut enabled,
+                                                    t!(
+                                                        "config.fmt_pipe_layer",
+                                                        layer = info.number,
+                                                        count = info.pipe_count
+                                                    ),
+                                                )
+                                                .changed();
+                                            let moved = ui
+                                                .add_enabled(
+                                                    enabled,
+                                                    egui::Slider::new(&mut level, 0.0..=1.0)
+                                                        .show_value(true)
+                                                        .min_decimals(2)
+                                                        .text(""),
+                                                )
+                                                .changed();
+                                            if toggled || moved {
+                                                change =
+                                                    Some((info.number, enabled.then_some(level)));
+                                            }
+                                        });
+                                    }
+                                    if let Some((layer, level)) = change {
+                                        self.state.set_pipe_layer(layer, level);
+                                    }
+                                });
+                                ui.end_row();
+                            }
+
                             // --- Audio Device ---
                             ui.label(t!("config.group_audio_device"))
                                 .on_hover_text(t!("config.tooltip_audio_device"));
@@ -515,6 +612,32 @@ impl App for ConfigApp {
                             );
                             ui.end_row();
 
+                            // --- Resampling ---
+                            ui.label(t!("config.group_resampling"))
+                                .on_hover_text(t!("config.tooltip_resampling"));
+                            ui.horizontal(|ui| {
+                                let quality = &mut self.state.settings.resampling_quality;
+                                egui::ComboBox::from_id_salt("resampling_combo")
+                                    .selected_text(quality.display_name())
+                                    .show_ui(ui, |ui| {
+                                        for option in ResamplingQuality::ALL {
+                                            ui.selectable_value(
+                                                quality,
+                                                option,
+                                                option.display_name(),
+                                            );
+                                        }
+                                    });
+                                ui.label(
+                                    egui::RichText::new(t!(
+                                        "config.resampling_cost",
+                                        cost = quality.relative_cost()
+                                    ))
+                                    .weak(),
+                                );
+                            });
+                            ui.end_row();
+
                             // --- Audio Buffer ---
                             ui.label(t!("config.group_buffer"))
                                 .on_hover_text(t!("config.tooltip_buffer"));
@@ -559,6 +682,48 @@ impl App for ConfigApp {
                             });
                             ui.end_row();
 
+                            // --- Temperament ---
+                            ui.label(t!("config.group_temperament"))
+                                .on_hover_text(t!("config.tooltip_temperament"));
+                            ui.horizontal(|ui| {
+                                egui::ComboBox::from_id_salt("temperament_combo")
+                                    .selected_text(self.state.settings.temperament.as_str())
+                                    .show_ui(ui, |ui| {
+                                        for temperament in &self.state.available_temperaments {
+                                            ui.selectable_value(
+                                                &mut self.state.settings.temperament,
+                                                temperament.name.clone(),
+                                                &temperament.name,
+                                            );
+                                        }
+                                    });
+                                egui::ComboBox::from_id_salt("temperament_base_combo")
+                                    .selected_text(t!(
+                                        "config.temperament_base",
+                                        note = NOTE_NAMES[self.state.settings.temperament_base_note
+                                            as usize
+                                            % 12]
+                                    ))
+                                    .show_ui(ui, |ui| {
+                                        for (i, name) in NOTE_NAMES.iter().enumerate() {
+                                            ui.selectable_value(
+                                                &mut self.state.settings.temperament_base_note,
+                                                i as u8,
+                                                *name,
+                                            );
+                                        }
+                                    });
+                                if ui
+                                    .button("📂")
+                                    .on_hover_text(t!("config.tooltip_temperament_folder"))
+                                    .clicked()
+                                    && let Ok(dir) = crate::config::get_temperament_directory()
+                                {
+                                    let _ = open::that(dir);
+                                }
+                            });
+                            ui.end_row();
+
                             // --- LCD Configuration ---
                             ui.label(t!("config.lcd_title"));
                             if ui.button(t!("config.lcd_button")).clicked() {
@@ -634,6 +799,16 @@ impl App for ConfigApp {
                                 active_midi_devices: active_devices,
                                 gain: self.state.settings.gain,
                                 polyphony: self.state.settings.polyphony,
+                                tracker_delay_scale: self.state.settings.tracker_delay_scale,
+                                temperament: self.state.settings.temperament.clone(),
+                                temperament_base_note: self.state.settings.temperament_base_note,
+                                master_tuning_cents: self.state.settings.master_tuning_cents,
+                                transpose: self.state.settings.transpose,
+                                loop_selection: self.state.settings.loop_selection,
+                                resampling_quality: self.state.settings.resampling_quality,
+                                pipe_layers: self.state.settings.pipe_layers.clone(),
+                                output_buses: self.state.settings.output_buses.clone(),
+                                output_routes: self.state.settings.output_routes.clone(),
                                 audio_device_name,
                                 sample_rate: self.state.settings.sample_rate,
                                 lcd_displays: self.state.settings.lcd_displays.clone(),
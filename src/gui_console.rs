@@ -0,0 +1,272 @@
+use eframe::egui;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc::Sender};
+
+use crate::app::AppMessage;
+use crate::app_state::AppState;
+use crate::gui_midi_learn::LearnTarget;
+use crate::organ::{Organ, Panel, PanelControl};
+
+// Size of an element drawn without an image, in panel pixels
+const FALLBACK_ELEMENT_SIZE: f32 = 64.0;
+
+/// Draws the sample set's graphical console (GrandOrgue panels / Hauptwerk display pages)
+/// and lets its drawknobs be operated with the mouse.
+pub struct ConsoleView {
+    // None = the image could not be loaded, don't retry every frame
+    textures: HashMap<PathBuf, Option<egui::TextureHandle>>,
+    selected_panel: usize,
+}
+
+impl ConsoleView {
+    pub fn new() -> Self {
+        Self {
+            textures: HashMap::new(),
+            selected_panel: 0,
+        }
+    }
+
+    fn texture(&mut self, ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+        self.textures
+            .entry(path.to_path_buf())
+            .or_insert_with(|| load_texture(ctx, path))
+            .clone()
+    }
+
+    /// Returns the panel size in pixels, measured from its images when it isn't declared.
+    fn panel_size(&mut self, ctx: &egui::Context, panel: &Panel) -> egui::Vec2 {
+        if panel.width > 0.0 && panel.height > 0.0 {
+            return egui::vec2(panel.width, panel.height);
+        }
+        let mut size = egui::Vec2::ZERO;
+        for image in &panel.images {
+            if let Some(texture) = self.texture(ctx, &image.path) {
+                size = size.max(egui::vec2(image.x, image.y) + texture.size_vec2());
+            }
+        }
+        for element in &panel.elements {
+            let element_size =
+                self.element_size(ctx, element.width, element.height, &element.image_off);
+            size = size.max(egui::vec2(element.x, element.y) + element_size);
+        }
+        if size.x <= 0.0 || size.y <= 0.0 {
+            egui::vec2(800.0, 600.0)
+        } else {
+            size
+        }
+    }
+
+    fn element_size(
+        &mut self,
+        ctx: &egui::Context,
+        width: f32,
+        height: f32,
+        image: &Option<PathBuf>,
+    ) -> egui::Vec2 {
+        let image_size = image
+            .as_ref()
+            .and_then(|path| self.texture(ctx, path))
+            .map(|texture| texture.size_vec2())
+            .unwrap_or(egui::vec2(FALLBACK_ELEMENT_SIZE, FALLBACK_ELEMENT_SIZE));
+        egui::vec2(
+            if width > 0.0 { width } else { image_size.x },
+            if height > 0.0 { height } else { image_size.y },
+        )
+    }
+
+    /// Draws the selected panel scaled to the available space.
+    /// Left click toggles a control; right click returns it as a MIDI learn target.
+    pub fn draw(
+        &mut self,
+        ui: &mut egui::Ui,
+        organ: &Organ,
+        stop_channels: &HashMap<usize, BTreeSet<u8>>,
+        app_state: &Arc<Mutex<AppState>>,
+        audio_tx: &Sender<AppMessage>,
+    ) -> Option<(LearnTarget, String)> {
+        if organ.panels.is_empty() {
+            return None;
+        }
+        self.selected_panel = self.selected_panel.min(organ.panels.len() - 1);
+
+        if organ.panels.len() > 1 {
+            ui.horizontal_wrapped(|ui| {
+                for (i, panel) in organ.panels.iter().enumerate() {
+                    let name = if panel.name.is_empty() {
+                        panel.id_str.clone()
+                    } else {
+                        panel.name.clone()
+                    };
+                    ui.selectable_value(&mut self.selected_panel, i, name);
+                }
+            });
+            ui.separator();
+        }
+
+        let (active_couplers, active_tremulants) = {
+            let state = app_state.lock().unwrap();
+            (
+                state.active_couplers.clone(),
+                state.active_tremulants.clone(),
+            )
+        };
+
+        let ctx = ui.ctx().clone();
+        let panel = &organ.panels[self.selected_panel];
+        let panel_size = self.panel_size(&ctx, panel);
+        let available = ui.available_size();
+        let scale = (available.x / panel_size.x)
+            .min(available.y / panel_size.y)
+            .max(0.1);
+
+        let (response, painter) = ui.allocate_painter(panel_size * scale, egui::Sense::hover());
+        let origin = response.rect.min;
+        let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        painter.rect_filled(response.rect, 0.0, egui::Color32::from_rgb(40, 30, 20));
+
+        for image in &panel.images {
+            if let Some(texture) = self.texture(&ctx, &image.path) {
+                let rect = egui::Rect::from_min_size(
+                    origin + egui::vec2(image.x, image.y) * scale,
+                    texture.size_vec2() * scale,
+                );
+                painter.image(texture.id(), rect, uv, egui::Color32::WHITE);
+            }
+        }
+
+        let mut learn_target = None;
+        for (i, element) in panel.elements.iter().enumerate() {
+            let (name, is_active) = match &element.control {
+                PanelControl::Stop(id) => {
+                    let Some(index) = organ.stops.iter().position(|s| &s.id_str == id) else {
+                        continue;
+                    };
+                    let is_active = stop_channels
+                        .get(&index)
+                        .is_some_and(|channels| !channels.is_empty());
+                    (organ.stops[index].name.clone(), is_active)
+                }
+                PanelControl::Coupler(id) => {
+                    let Some(coupler) = organ.couplers.get(id) else {
+                        continue;
+                    };
+                    (coupler.name.clone(), active_couplers.contains(id))
+                }
+                PanelControl::Tremulant(id) => {
+                    let Some(tremulant) = organ.tremulants.get(id) else {
+                        continue;
+                    };
+                    (tremulant.name.clone(), active_tremulants.contains(id))
+                }
+            };
+
+            let image = if is_active {
+                &element.image_on
+            } else {
+                &element.image_off
+            };
+            let size = self.element_size(&ctx, element.width, element.height, image);
+            let rect = egui::Rect::from_min_size(
+                origin + egui::vec2(element.x, element.y) * scale,
+                size * scale,
+            );
+
+            match image.as_ref().and_then(|path| self.texture(&ctx, path)) {
+                Some(texture) => {
+                    let image_rect =
+                        egui::Rect::from_min_size(rect.min, texture.size_vec2() * scale);
+                    painter.image(texture.id(), image_rect, uv, egui::Color32::WHITE);
+                }
+                None => draw_fallback_knob(&painter, rect, &name, is_active),
+            }
+
+            let element_response = ui
+                .interact(
+                    rect,
+                    ui.id().with(("console_element", self.selected_panel, i)),
+                    egui::Sense::click(),
+                )
+                .on_hover_text(&name);
+
+            if element_response.clicked() {
+                let mut state = app_state.lock().unwrap();
+                let result = match &element.control {
+                    PanelControl::Stop(id) => {
+                        match organ.stops.iter().position(|s| &s.id_str == id) {
+                            Some(index) => state.toggle_stop(index, audio_tx),
+                            None => Ok(()),
+                        }
+                    }
+                    PanelControl::Coupler(id) => {
+                        state.set_coupler_active(id.clone(), !is_active, audio_tx)
+                    }
+                    PanelControl::Tremulant(id) => {
+                        state.set_tremulant_active(id.clone(), !is_active, audio_tx);
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    state.add_midi_log(format!("ERROR: {}", e));
+                }
+            }
+
+            if element_response.secondary_clicked() {
+                let target = match &element.control {
+                    PanelControl::Stop(id) => organ
+                        .stops
+                        .iter()
+                        .position(|s| &s.id_str == id)
+                        .map(LearnTarget::Stop),
+                    PanelControl::Coupler(id) => Some(LearnTarget::Coupler(id.clone())),
+                    PanelControl::Tremulant(id) => Some(LearnTarget::Tremulant(id.clone())),
+                };
+                learn_target = target.map(|target| (target, name));
+            }
+        }
+
+        learn_target
+    }
+}
+
+fn load_texture(ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+    match image::open(path) {
+        Ok(image) => {
+            let rgba = image.to_rgba8();
+            let size = [rgba.width() as usize, rgba.height() as usize];
+            let color_image = egui::ColorImage::from_rgba_unmultiplied(size, rgba.as_raw());
+            Some(ctx.load_texture(
+                path.to_string_lossy(),
+                color_image,
+                egui::TextureOptions::LINEAR,
+            ))
+        }
+        Err(e) => {
+            log::warn!("Could not load console image {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Draws a plain drawknob for elements whose images are missing.
+fn draw_fallback_knob(painter: &egui::Painter, rect: egui::Rect, name: &str, is_active: bool) {
+    let radius = rect.width().min(rect.height()) / 2.0 - 1.0;
+    let fill = if is_active {
+        egui::Color32::from_rgb(230, 220, 190)
+    } else {
+        egui::Color32::from_rgb(120, 110, 90)
+    };
+    painter.circle(
+        rect.center(),
+        radius,
+        fill,
+        egui::Stroke::new(1.0, egui::Color32::BLACK),
+    );
+    painter.text(
+        rect.center(),
+        egui::Align2::CENTER_CENTER,
+        name,
+        egui::FontId::proportional((radius / 3.0).clamp(6.0, 14.0)),
+        egui::Color32::BLACK,
+    );
+}
@@ -1,4 +1,5 @@
 use crate::app_state::AppState;
+use crate::config::MidiEventSpec;
 use eframe::egui;
 use rust_i18n::t;
 use std::sync::{Arc, Mutex};
@@ -8,7 +9,11 @@ use std::time::Instant;
 pub enum LearnTarget {
     Stop(usize),
     Tremulant(String),
+    Coupler(String),
+    Enclosure(String),
     Preset(usize),
+    Transpose,
+    MasterTuning,
 }
 
 impl Default for LearnTarget {
@@ -23,7 +28,7 @@ pub struct MidiLearnState {
     pub target_name: String,
 
     // If Some, we are waiting for a MIDI event to assign to (internal_channel, is_enable_slot)
-    // For Tremulants, internal_channel is ignored (passed as 0)
+    // For Tremulants and Couplers, internal_channel is ignored (passed as 0)
     pub learning_slot: Option<(u8, bool)>,
     pub last_interaction: Instant,
 }
@@ -55,7 +60,12 @@ pub fn draw_midi_learn_modal(
     if let Some((target_internal, is_enable)) = learn_state.learning_slot {
         let mut state = app_state.lock().unwrap();
         if let Some((event, time)) = &state.last_midi_event_received {
-            if *time > learn_state.last_interaction {
+            // Enclosures and the master tuning can only follow a continuous controller
+            let is_ignored = matches!(
+                learn_state.target,
+                LearnTarget::Enclosure(_) | LearnTarget::MasterTuning
+            ) && !matches!(event, MidiEventSpec::ControlChange { .. });
+            if *time > learn_state.last_interaction && !is_ignored {
                 // We caught a midi event!
                 let event_clone = event.clone();
 
@@ -75,6 +85,18 @@ pub fn draw_midi_learn_modal(
                             is_enable,
                         );
                     }
+                    LearnTarget::Coupler(id) => {
+                        state.midi_control_map.learn_coupler(
+                            id.clone(),
+                            event_clone.clone(),
+                            is_enable,
+                        );
+                    }
+                    LearnTarget::Enclosure(id) => {
+                        state
+                            .midi_control_map
+                            .learn_enclosure(id.clone(), event_clone.clone());
+                    }
                     LearnTarget::Preset(slot) => {
                         // Presets only activate
                         if is_enable {
@@ -83,9 +105,19 @@ pub fn draw_midi_learn_modal(
                                 .learn_preset(*slot, event_clone.clone());
                         }
                     }
+                    LearnTarget::Transpose => {
+                        state
+                            .midi_control_map
+                            .learn_transpose(event_clone.clone(), is_enable);
+                    }
+                    LearnTarget::MasterTuning => {
+                        state
+                            .midi_control_map
+                            .learn_master_tuning(event_clone.clone());
+                    }
                 }
                 // Save immediately
-                let _ = state.midi_control_map.save(&state.organ.name);
+                let _ = state.midi_control_map.save(&state.organ);
 
                 // Reset learning state
                 learn_state.learning_slot = None;
@@ -123,8 +155,8 @@ pub fn draw_midi_learn_modal(
             // Clone to avoid borrow conflict
             let target = learn_state.target.clone();
 
-            match target {
-                LearnTarget::Stop(idx) => {
+            match &target {
+                &LearnTarget::Stop(idx) => {
                     ui.label(t!("midi_learn.description_2"));
                     ui.add_space(10.0);
 
@@ -147,19 +179,58 @@ pub fn draw_midi_learn_modal(
                         state
                             .midi_control_map
                             .tremulants
-                            .get(&id)
+                            .get(id)
+                            .cloned()
+                            .unwrap_or_default()
+                    };
+                    draw_toggle_row(ui, learn_state, &target, &control, app_state.clone());
+                }
+                LearnTarget::Coupler(id) => {
+                    ui.add_space(10.0);
+                    let control = {
+                        let state = app_state.lock().unwrap();
+                        state
+                            .midi_control_map
+                            .couplers
+                            .get(id)
                             .cloned()
                             .unwrap_or_default()
                     };
-                    draw_tremulant_row(ui, learn_state, &id, &control, app_state.clone());
+                    draw_toggle_row(ui, learn_state, &target, &control, app_state.clone());
+                }
+                LearnTarget::Enclosure(id) => {
+                    ui.label(t!("midi_learn.description_enclosure"));
+                    ui.add_space(10.0);
+                    let controller = {
+                        let state = app_state.lock().unwrap();
+                        state.midi_control_map.enclosures.get(id).cloned()
+                    };
+                    draw_trigger_row(ui, learn_state, &target, controller, app_state.clone());
                 }
-                LearnTarget::Preset(slot) => {
+                &LearnTarget::Preset(slot) => {
                     ui.add_space(10.0);
                     let trigger = {
                         let state = app_state.lock().unwrap();
                         state.midi_control_map.presets.get(&slot).cloned().flatten()
                     };
-                    draw_preset_row(ui, learn_state, slot, trigger, app_state.clone());
+                    draw_trigger_row(ui, learn_state, &target, trigger, app_state.clone());
+                }
+                LearnTarget::Transpose => {
+                    ui.label(t!("midi_learn.description_transpose"));
+                    ui.add_space(10.0);
+                    let control = app_state.lock().unwrap().midi_control_map.transpose.clone();
+                    draw_toggle_row(ui, learn_state, &target, &control, app_state.clone());
+                }
+                LearnTarget::MasterTuning => {
+                    ui.label(t!("midi_learn.description_master_tuning"));
+                    ui.add_space(10.0);
+                    let controller = app_state
+                        .lock()
+                        .unwrap()
+                        .midi_control_map
+                        .master_tuning
+                        .clone();
+                    draw_trigger_row(ui, learn_state, &target, controller, app_state.clone());
                 }
             }
         });
@@ -167,10 +238,11 @@ pub fn draw_midi_learn_modal(
     learn_state.is_open = is_open;
 }
 
-fn draw_tremulant_row(
+/// Draws the enable/disable row shared by Tremulants, Couplers and Transpose.
+fn draw_toggle_row(
     ui: &mut egui::Ui,
     learn_state: &mut MidiLearnState,
-    trem_id: &str,
+    target: &LearnTarget,
     control: &crate::midi_control::StopChannelControl,
     app_state: Arc<Mutex<AppState>>,
 ) {
@@ -179,8 +251,20 @@ fn draw_tremulant_row(
         .striped(true)
         .spacing([20.0, 8.0])
         .show(ui, |ui| {
-            ui.label(egui::RichText::new(t!("midi_learn.col_enable_event")).strong());
-            ui.label(egui::RichText::new(t!("midi_learn.col_disable_event")).strong());
+            // Transpose steps up and down rather than switching on and off
+            let (enable_header, disable_header) = if *target == LearnTarget::Transpose {
+                (
+                    t!("midi_learn.col_transpose_up"),
+                    t!("midi_learn.col_transpose_down"),
+                )
+            } else {
+                (
+                    t!("midi_learn.col_enable_event"),
+                    t!("midi_learn.col_disable_event"),
+                )
+            };
+            ui.label(egui::RichText::new(enable_header).strong());
+            ui.label(egui::RichText::new(disable_header).strong());
             ui.label(egui::RichText::new(t!("midi_learn.col_actions")).strong());
             ui.end_row();
 
@@ -225,18 +309,24 @@ fn draw_tremulant_row(
             // Clear
             if ui.button(t!("midi_learn.btn_clear")).clicked() {
                 let mut state = app_state.lock().unwrap();
-                state.midi_control_map.clear_tremulant(trem_id);
-                let _ = state.midi_control_map.save(&state.organ.name);
+                match target {
+                    LearnTarget::Tremulant(id) => state.midi_control_map.clear_tremulant(id),
+                    LearnTarget::Coupler(id) => state.midi_control_map.clear_coupler(id),
+                    LearnTarget::Transpose => state.midi_control_map.clear_transpose(),
+                    _ => {}
+                }
+                let _ = state.midi_control_map.save(&state.organ);
             }
             ui.end_row();
         });
 }
 
-fn draw_preset_row(
+/// Draws the single-event row shared by Presets, Enclosures and the master tuning.
+fn draw_trigger_row(
     ui: &mut egui::Ui,
     learn_state: &mut MidiLearnState,
-    slot: usize,
-    trigger: Option<crate::config::MidiEventSpec>,
+    target: &LearnTarget,
+    trigger: Option<MidiEventSpec>,
     app_state: Arc<Mutex<AppState>>,
 ) {
     egui::Grid::new("preset_learn_grid")
@@ -268,8 +358,13 @@ fn draw_preset_row(
             // Clear Button
             if ui.button(t!("midi_learn.btn_clear")).clicked() {
                 let mut state = app_state.lock().unwrap();
-                state.midi_control_map.clear_preset(slot);
-                let _ = state.midi_control_map.save(&state.organ.name);
+                match target {
+                    LearnTarget::Preset(slot) => state.midi_control_map.clear_preset(*slot),
+                    LearnTarget::Enclosure(id) => state.midi_control_map.clear_enclosure(id),
+                    LearnTarget::MasterTuning => state.midi_control_map.clear_master_tuning(),
+                    _ => {}
+                }
+                let _ = state.midi_control_map.save(&state.organ);
             }
             ui.end_row();
         });
@@ -345,7 +440,7 @@ fn draw_stop_grid(
                     if ui.button(t!("midi_learn.btn_clear")).clicked() {
                         let mut state = app_state.lock().unwrap();
                         state.midi_control_map.clear_stop(stop_idx, channel);
-                        let _ = state.midi_control_map.save(&state.organ.name);
+                        let _ = state.midi_control_map.save(&state.organ);
                     }
                     ui.end_row();
                 }
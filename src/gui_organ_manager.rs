@@ -3,6 +3,7 @@ use crate::app_state::AppState;
 use crate::config::{
     MidiEventSpec, OrganLibrary, OrganProfile, load_organ_library, save_organ_library,
 };
+use crate::organ_export::export_in_background;
 use eframe::egui;
 use rust_i18n::t;
 use std::path::PathBuf;
@@ -182,6 +183,15 @@ impl OrganManagerUi {
                         }
                     }
                 }
+
+                // --- Export Loaded Organ ---
+                if ui.button(t!("organ_manager.export_organ")).clicked()
+                    && let Some(output_dir) = rfd::FileDialog::new()
+                        .set_title(t!("organ_manager.export_organ"))
+                        .pick_folder()
+                {
+                    export_in_background(&app_state, output_dir);
+                }
             });
 
         self.visible = open;
@@ -0,0 +1,258 @@
+use crate::app::AppMessage;
+use crate::app_state::AppState;
+use crate::output_routing::{MAX_OUTPUT_BUSES, OutputBus, OutputRouting, RouteTarget, divisions};
+use eframe::egui;
+use rust_i18n::t;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Highest device channel pair offered for a bus.
+const MAX_CHANNEL_PAIRS: usize = 32;
+
+/// Editor for the output buses and for which bus each part of the organ plays on.
+pub struct OutputRoutingUi {
+    pub visible: bool,
+    routing: OutputRouting,
+    organ_name: String,
+    status: Option<String>,
+}
+
+impl OutputRoutingUi {
+    pub fn new(organ_name: &str) -> Self {
+        let mut routing = OutputRouting::load(organ_name);
+        routing.buses = routing.effective_buses();
+        Self {
+            visible: false,
+            routing,
+            organ_name: organ_name.to_string(),
+            status: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        app_state: Arc<Mutex<AppState>>,
+        audio_tx: &Sender<AppMessage>,
+    ) {
+        let mut open = self.visible;
+        let organ = app_state.lock().unwrap().organ.clone();
+
+        let division_list = divisions(&organ);
+        let mut groups: Vec<(String, String)> = organ
+            .windchest_groups
+            .values()
+            .map(|group| (group.id_str.clone(), group.name.clone()))
+            .collect();
+        groups.sort();
+        let mut ranks: Vec<(String, String)> = organ
+            .ranks
+            .values()
+            .map(|rank| (rank.id_str.clone(), rank.name.clone()))
+            .collect();
+        ranks.sort();
+
+        egui::Window::new(t!("output_routing.title"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(t!("output_routing.description"));
+                ui.add_space(10.0);
+
+                let mut changed = false;
+                let mut finished = false;
+                let mut track = |response: egui::Response| {
+                    changed |= response.changed();
+                    // Write the settings once a drag ends rather than on every frame of it
+                    finished |=
+                        (response.changed() && !response.dragged()) || response.drag_stopped();
+                };
+
+                ui.heading(t!("output_routing.buses_heading"));
+                let mut bus_to_remove = None;
+                egui::Grid::new("output_bus_grid")
+                    .num_columns(5)
+                    .striped(true)
+                    .spacing([15.0, 6.0])
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new(t!("output_routing.col_name")).strong());
+                        ui.label(egui::RichText::new(t!("output_routing.col_outputs")).strong());
+                        ui.label(egui::RichText::new(t!("output_routing.col_gain")).strong());
+                        ui.label(
+                            egui::RichText::new(t!("output_routing.col_reverb_send")).strong(),
+                        );
+                        ui.label("");
+                        ui.end_row();
+
+                        for (index, bus) in self.routing.buses.iter_mut().enumerate() {
+                            track(ui.add(
+                                egui::TextEdit::singleline(&mut bus.name).desired_width(120.0),
+                            ));
+
+                            egui::ComboBox::from_id_salt(format!("output_bus_channels_{}", index))
+                                .selected_text(channel_pair_name(bus.first_channel))
+                                .show_ui(ui, |ui| {
+                                    for pair in 0..MAX_CHANNEL_PAIRS {
+                                        track(ui.selectable_value(
+                                            &mut bus.first_channel,
+                                            pair * 2,
+                                            channel_pair_name(pair * 2),
+                                        ));
+                                    }
+                                });
+                            track(
+                                ui.add(
+                                    egui::DragValue::new(&mut bus.gain)
+                                        .range(0.0..=2.0)
+                                        .speed(0.01),
+                                ),
+                            );
+                            track(ui.add(egui::Slider::new(&mut bus.reverb_send, 0.0..=1.0)));
+                            if ui
+                                .add_enabled(index > 0, egui::Button::new("🗑"))
+                                .on_hover_text(t!("output_routing.remove_bus"))
+                                .clicked()
+                            {
+                                bus_to_remove = Some(index);
+                            }
+                            ui.end_row();
+                        }
+                    });
+
+                if let Some(index) = bus_to_remove {
+                    self.routing.remove_bus(index);
+                    changed = true;
+                    finished = true;
+                }
+                if ui
+                    .add_enabled(
+                        self.routing.buses.len() < MAX_OUTPUT_BUSES,
+                        egui::Button::new(t!("output_routing.add_bus")),
+                    )
+                    .clicked()
+                {
+                    let first_channel = self
+                        .routing
+                        .buses
+                        .iter()
+                        .map(|bus| bus.first_channel + 2)
+                        .max()
+                        .unwrap_or(0);
+                    self.routing.buses.push(OutputBus {
+                        name: t!(
+                            "output_routing.new_bus_name",
+                            num = self.routing.buses.len() + 1
+                        )
+                        .to_string(),
+                        first_channel,
+                        ..OutputBus::default()
+                    });
+                    changed = true;
+                    finished = true;
+                }
+                ui.label(
+                    egui::RichText::new(t!("output_routing.channels_hint"))
+                        .small()
+                        .weak(),
+                );
+
+                ui.separator();
+                ui.heading(t!("output_routing.routes_heading"));
+                egui::ScrollArea::vertical()
+                    .max_height(350.0)
+                    .show(ui, |ui| {
+                        for (target, heading, entries) in [
+                            (
+                                RouteTarget::Division,
+                                t!("output_routing.divisions"),
+                                &division_list,
+                            ),
+                            (
+                                RouteTarget::WindchestGroup,
+                                t!("output_routing.windchest_groups"),
+                                &groups,
+                            ),
+                            (RouteTarget::Rank, t!("output_routing.ranks"), &ranks),
+                        ] {
+                            if entries.is_empty() {
+                                continue;
+                            }
+                            egui::CollapsingHeader::new(heading)
+                                .id_salt(format!("output_routes_{:?}", target))
+                                .default_open(target == RouteTarget::Division)
+                                .show(ui, |ui| {
+                                    egui::Grid::new(format!("output_route_grid_{:?}", target))
+                                        .num_columns(2)
+                                        .striped(true)
+                                        .show(ui, |ui| {
+                                            for (id, name) in entries {
+                                                ui.label(format!("{} ({})", name, id));
+                                                let mut route = self.routing.route(target, id);
+                                                let selected = route
+                                                    .and_then(|bus| self.routing.buses.get(bus))
+                                                    .map_or_else(
+                                                        || t!("output_routing.follow").to_string(),
+                                                        |bus| bus.name.clone(),
+                                                    );
+                                                let before = route;
+                                                egui::ComboBox::from_id_salt(format!(
+                                                    "output_route_{:?}_{}",
+                                                    target, id
+                                                ))
+                                                .selected_text(selected)
+                                                .show_ui(ui, |ui| {
+                                                    ui.selectable_value(
+                                                        &mut route,
+                                                        None,
+                                                        t!("output_routing.follow"),
+                                                    );
+                                                    for (index, bus) in
+                                                        self.routing.buses.iter().enumerate()
+                                                    {
+                                                        ui.selectable_value(
+                                                            &mut route,
+                                                            Some(index),
+                                                            &bus.name,
+                                                        );
+                                                    }
+                                                });
+                                                if route != before {
+                                                    self.routing.set_route(target, id, route);
+                                                    changed = true;
+                                                    finished = true;
+                                                }
+                                                ui.end_row();
+                                            }
+                                        });
+                                });
+                        }
+                    });
+
+                if changed
+                    && let Err(e) =
+                        audio_tx.send(AppMessage::SetOutputRouting(self.routing.clone()))
+                {
+                    log::error!("Failed to send output routing: {}", e);
+                }
+                if finished {
+                    self.status = Some(match self.routing.save(&self.organ_name) {
+                        Ok(()) => t!("output_routing.saved").to_string(),
+                        Err(e) => {
+                            log::error!("Failed to save output routing: {}", e);
+                            t!("output_routing.save_failed", err = e).to_string()
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+
+        self.visible = open;
+    }
+}
+
+fn channel_pair_name(first_channel: usize) -> String {
+    format!("{} / {}", first_channel + 1, first_channel + 2)
+}
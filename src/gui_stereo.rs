@@ -0,0 +1,212 @@
+use crate::app::AppMessage;
+use crate::app_state::AppState;
+use crate::stereo::{PanLayout, PanPosition, StereoLayout};
+use eframe::egui;
+use rust_i18n::t;
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Editor for the stereo placement of the organ's windchest groups and ranks.
+pub struct StereoUi {
+    pub visible: bool,
+    layout: StereoLayout,
+    organ_name: String,
+    status: Option<String>,
+}
+
+impl StereoUi {
+    pub fn new(organ_name: &str) -> Self {
+        Self {
+            visible: false,
+            layout: StereoLayout::load(organ_name),
+            organ_name: organ_name.to_string(),
+            status: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        app_state: Arc<Mutex<AppState>>,
+        audio_tx: &Sender<AppMessage>,
+    ) {
+        let mut open = self.visible;
+        let organ = app_state.lock().unwrap().organ.clone();
+
+        let mut groups: Vec<(&String, &String, PanPosition)> = organ
+            .windchest_groups
+            .values()
+            .map(|group| {
+                let position = PanPosition {
+                    pan: group.pan.unwrap_or(0.0),
+                    ..PanPosition::default()
+                };
+                (&group.id_str, &group.name, position)
+            })
+            .collect();
+        groups.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+        let mut ranks: Vec<(&String, &String)> = organ
+            .ranks
+            .values()
+            .map(|rank| (&rank.id_str, &rank.name))
+            .collect();
+        ranks.sort();
+
+        egui::Window::new(t!("stereo.title"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(t!("stereo.description"));
+                ui.add_space(10.0);
+
+                let mut changed = false;
+                let mut finished = false;
+                let mut track = |response: egui::Response| {
+                    changed |= response.changed();
+                    // Write the file once a drag ends rather than on every frame of it
+                    finished |=
+                        (response.changed() && !response.dragged()) || response.drag_stopped();
+                };
+
+                egui::ScrollArea::vertical()
+                    .max_height(450.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("stereo_grid")
+                            .num_columns(5)
+                            .striped(true)
+                            .spacing([15.0, 6.0])
+                            .show(ui, |ui| {
+                                header_row(ui, t!("stereo.col_group"));
+                                for (id, name, definition) in &groups {
+                                    position_row(
+                                        ui,
+                                        &mut self.layout.windchest_groups,
+                                        id,
+                                        name,
+                                        *definition,
+                                        "group",
+                                        &mut track,
+                                    );
+                                }
+
+                                header_row(ui, t!("stereo.col_rank"));
+                                for (id, name) in &ranks {
+                                    position_row(
+                                        ui,
+                                        &mut self.layout.ranks,
+                                        id,
+                                        name,
+                                        PanPosition::default(),
+                                        "rank",
+                                        &mut track,
+                                    );
+                                }
+                            });
+                    });
+
+                if !self.layout.pipes.is_empty() {
+                    ui.add_space(5.0);
+                    ui.label(t!("stereo.pipe_count", count = self.layout.pipes.len()));
+                }
+
+                ui.separator();
+                if ui.button(t!("stereo.reset")).clicked() {
+                    self.layout = StereoLayout::default();
+                    changed = true;
+                    finished = true;
+                }
+
+                if changed
+                    && let Err(e) = audio_tx.send(AppMessage::SetStereoLayout(self.layout.clone()))
+                {
+                    log::error!("Failed to send stereo layout: {}", e);
+                }
+                if finished {
+                    self.status = Some(match self.layout.save(&self.organ_name) {
+                        Ok(()) => t!("stereo.saved").to_string(),
+                        Err(e) => {
+                            log::error!("Failed to save stereo layout: {}", e);
+                            t!("stereo.save_failed", err = e).to_string()
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+
+        self.visible = open;
+    }
+}
+
+fn header_row(ui: &mut egui::Ui, first_column: impl Into<String>) {
+    ui.label(egui::RichText::new(first_column).strong());
+    ui.label(egui::RichText::new(t!("stereo.col_own")).strong());
+    ui.label(egui::RichText::new(t!("stereo.col_pan")).strong());
+    ui.label(egui::RichText::new(t!("stereo.col_layout")).strong());
+    ui.label(egui::RichText::new(t!("stereo.col_width")).strong())
+        .on_hover_text(t!("stereo.tooltip_width"));
+    ui.end_row();
+}
+
+/// Draws one windchest group or rank. Without its own position it follows the one above it,
+/// or the `definition` position of the organ, which is also where an own position starts.
+fn position_row(
+    ui: &mut egui::Ui,
+    positions: &mut HashMap<String, PanPosition>,
+    id: &str,
+    name: &str,
+    definition: PanPosition,
+    kind: &str,
+    track: &mut impl FnMut(egui::Response),
+) {
+    ui.label(format!("{} ({})", name, id));
+    let mut own = positions.contains_key(id);
+    let own_response = ui.checkbox(&mut own, "");
+    if own_response.changed() {
+        if own {
+            positions.insert(id.to_string(), definition);
+        } else {
+            positions.remove(id);
+        }
+    }
+    track(own_response);
+
+    let mut shown = definition;
+    let position = positions.get_mut(id).unwrap_or(&mut shown);
+    track(ui.add_enabled(
+        own,
+        egui::Slider::new(&mut position.pan, -1.0..=1.0).fixed_decimals(2),
+    ));
+    ui.add_enabled_ui(own, |ui| {
+        egui::ComboBox::from_id_salt(format!("stereo_layout_{}_{}", kind, id))
+            .selected_text(layout_name(position.layout))
+            .show_ui(ui, |ui| {
+                for layout in PanLayout::ALL {
+                    track(ui.selectable_value(&mut position.layout, layout, layout_name(layout)));
+                }
+            });
+    });
+    track(
+        ui.add_enabled(
+            own && position.layout != PanLayout::Center,
+            egui::DragValue::new(&mut position.width)
+                .range(0.0..=1.0)
+                .speed(0.01),
+        ),
+    );
+    ui.end_row();
+}
+
+fn layout_name(layout: PanLayout) -> String {
+    match layout {
+        PanLayout::Center => t!("stereo.layout_center"),
+        PanLayout::Alternate => t!("stereo.layout_alternate"),
+        PanLayout::Pyramid => t!("stereo.layout_pyramid"),
+        PanLayout::InvertedPyramid => t!("stereo.layout_inverted_pyramid"),
+        PanLayout::Chromatic => t!("stereo.layout_chromatic"),
+    }
+    .to_string()
+}
@@ -0,0 +1,249 @@
+use crate::app::MainLoopAction;
+use crate::app_state::AppState;
+use crate::organ::Organ;
+use crate::virtual_stops::{
+    MAX_VIRTUAL_STOP_TRANSPOSE, VirtualStop, VirtualStopRank, VirtualStopSet,
+};
+use eframe::egui;
+use rust_i18n::t;
+use std::sync::{Arc, Mutex};
+
+/// Editor window for the virtual stops of the loaded organ.
+pub struct VirtualStopsUi {
+    pub visible: bool,
+    set: VirtualStopSet,
+    organ_name: String,
+    // Index in `set` of the stop being edited, None for a new stop
+    selected: Option<usize>,
+    draft: VirtualStop,
+    rank_to_add: Option<String>,
+    status: Option<String>,
+}
+
+impl VirtualStopsUi {
+    pub fn new(organ_name: &str) -> Self {
+        Self {
+            visible: false,
+            set: VirtualStopSet::load(organ_name),
+            organ_name: organ_name.to_string(),
+            selected: None,
+            draft: VirtualStop::default(),
+            rank_to_add: None,
+            status: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        exit_action: &Arc<Mutex<MainLoopAction>>,
+        app_state: Arc<Mutex<AppState>>,
+    ) {
+        let mut open = self.visible;
+        let organ = app_state.lock().unwrap().organ.clone();
+
+        let mut rank_ids: Vec<&String> = organ.ranks.keys().collect();
+        rank_ids.sort();
+        let mut manual_ids: Vec<&String> = organ.manuals.keys().collect();
+        manual_ids.sort();
+        let rank_label = |id: &str| match organ.ranks.get(id) {
+            Some(rank) => format!("{} ({})", rank.name, id),
+            None => id.to_string(),
+        };
+
+        egui::Window::new(t!("virtual_stops.title"))
+            .open(&mut open)
+            .resize(|r| r.fixed_size([700.0, 450.0]))
+            .show(ctx, |ui| {
+                ui.label(t!("virtual_stops.description"));
+                ui.add_space(10.0);
+
+                ui.horizontal_top(|ui| {
+                    // --- List of Virtual Stops ---
+                    ui.vertical(|ui| {
+                        ui.set_width(200.0);
+                        if ui.button(t!("virtual_stops.new_stop")).clicked() {
+                            self.selected = None;
+                            self.draft = VirtualStop::default();
+                            self.status = None;
+                        }
+                        ui.separator();
+                        if self.set.stops.is_empty() {
+                            ui.label(t!("virtual_stops.empty"));
+                        }
+                        egui::ScrollArea::vertical()
+                            .id_salt("virtual_stop_list")
+                            .max_height(320.0)
+                            .show(ui, |ui| {
+                                for (i, stop) in self.set.stops.iter().enumerate() {
+                                    if ui
+                                        .selectable_label(self.selected == Some(i), &stop.name)
+                                        .clicked()
+                                    {
+                                        self.selected = Some(i);
+                                        self.draft = stop.clone();
+                                        self.status = None;
+                                    }
+                                }
+                            });
+                    });
+
+                    ui.separator();
+
+                    // --- Stop Editor ---
+                    ui.vertical(|ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(t!("virtual_stops.name"));
+                            ui.text_edit_singleline(&mut self.draft.name);
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label(t!("virtual_stops.manual"));
+                            let selected_text = match &self.draft.manual_id {
+                                Some(id) => {
+                                    organ.manuals.get(id).map_or(id.clone(), |m| m.name.clone())
+                                }
+                                None => t!("virtual_stops.no_manual").to_string(),
+                            };
+                            egui::ComboBox::from_id_salt("virtual_stop_manual")
+                                .selected_text(selected_text)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(
+                                        &mut self.draft.manual_id,
+                                        None,
+                                        t!("virtual_stops.no_manual"),
+                                    );
+                                    for id in &manual_ids {
+                                        ui.selectable_value(
+                                            &mut self.draft.manual_id,
+                                            Some((*id).clone()),
+                                            &organ.manuals[*id].name,
+                                        );
+                                    }
+                                });
+                        });
+
+                        ui.add_space(5.0);
+                        ui.label(t!("virtual_stops.ranks"));
+
+                        let mut remove_index = None;
+                        egui::ScrollArea::vertical()
+                            .id_salt("virtual_stop_ranks")
+                            .max_height(220.0)
+                            .show(ui, |ui| {
+                                egui::Grid::new("virtual_stop_rank_grid")
+                                    .striped(true)
+                                    .num_columns(4)
+                                    .show(ui, |ui| {
+                                        for (i, rank) in self.draft.ranks.iter_mut().enumerate() {
+                                            ui.label(rank_label(&rank.rank_id));
+                                            ui.add(
+                                                egui::DragValue::new(&mut rank.transpose)
+                                                    .range(
+                                                        -MAX_VIRTUAL_STOP_TRANSPOSE
+                                                            ..=MAX_VIRTUAL_STOP_TRANSPOSE,
+                                                    )
+                                                    .prefix(t!("virtual_stops.transpose")),
+                                            );
+                                            ui.add(
+                                                egui::DragValue::new(&mut rank.gain_db)
+                                                    .range(-24.0..=12.0)
+                                                    .speed(0.1)
+                                                    .suffix(" dB"),
+                                            );
+                                            if ui.button("❌").clicked() {
+                                                remove_index = Some(i);
+                                            }
+                                            ui.end_row();
+                                        }
+                                    });
+                            });
+                        if let Some(i) = remove_index {
+                            self.draft.ranks.remove(i);
+                        }
+
+                        ui.horizontal(|ui| {
+                            let selected_text = self
+                                .rank_to_add
+                                .as_deref()
+                                .map(rank_label)
+                                .unwrap_or_else(|| "-".to_string());
+                            egui::ComboBox::from_id_salt("virtual_stop_add_rank")
+                                .selected_text(selected_text)
+                                .width(250.0)
+                                .show_ui(ui, |ui| {
+                                    for id in &rank_ids {
+                                        ui.selectable_value(
+                                            &mut self.rank_to_add,
+                                            Some((*id).clone()),
+                                            rank_label(id),
+                                        );
+                                    }
+                                });
+                            if ui
+                                .add_enabled(
+                                    self.rank_to_add.is_some(),
+                                    egui::Button::new(t!("virtual_stops.add_rank")),
+                                )
+                                .clicked()
+                                && let Some(rank_id) = self.rank_to_add.clone()
+                            {
+                                self.draft.ranks.push(VirtualStopRank {
+                                    rank_id,
+                                    ..Default::default()
+                                });
+                            }
+                        });
+
+                        ui.separator();
+
+                        ui.horizontal(|ui| {
+                            if ui.button(t!("virtual_stops.save")).clicked() {
+                                self.save_draft(&organ);
+                            }
+                            if let Some(i) = self.selected
+                                && ui.button(t!("virtual_stops.delete")).clicked()
+                            {
+                                self.set.stops.remove(i);
+                                self.selected = None;
+                                self.draft = VirtualStop::default();
+                                self.write_set();
+                            }
+                            if ui.button(t!("virtual_stops.apply")).clicked() {
+                                // Reload the same organ so the stops are merged in again
+                                *exit_action.lock().unwrap() = MainLoopAction::Continue;
+                                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                            }
+                        });
+
+                        if let Some(status) = &self.status {
+                            ui.label(status);
+                        }
+                    });
+                });
+            });
+
+        self.visible = open;
+    }
+
+    fn save_draft(&mut self, organ: &Organ) {
+        if let Err(e) = self.set.validate(organ, &self.draft, self.selected) {
+            self.status = Some(t!("virtual_stops.save_failed", err = e).to_string());
+            return;
+        }
+        let index = self.set.upsert(self.draft.clone(), self.selected);
+        self.draft = self.set.stops[index].clone();
+        self.selected = Some(index);
+        self.write_set();
+    }
+
+    fn write_set(&mut self) {
+        self.status = Some(match self.set.save(&self.organ_name) {
+            Ok(()) => t!("virtual_stops.saved").to_string(),
+            Err(e) => {
+                log::error!("Failed to save virtual stops: {}", e);
+                t!("virtual_stops.save_failed", err = e).to_string()
+            }
+        });
+    }
+}
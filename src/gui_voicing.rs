@@ -0,0 +1,223 @@
+use crate::app::AppMessage;
+use crate::app_state::AppState;
+use crate::voicing::{PipeVoicing, VoicingOverlay};
+use eframe::egui;
+use rust_i18n::t;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Voicing mode: adjusts the pipe of one rank and note while it is being played.
+pub struct VoicingUi {
+    pub visible: bool,
+    overlay: VoicingOverlay,
+    organ_name: String,
+    rank_id: Option<String>,
+    note: u8,
+    // Select the most recently played key automatically
+    follow_played: bool,
+    status: Option<String>,
+}
+
+impl VoicingUi {
+    pub fn new(organ_name: &str) -> Self {
+        Self {
+            visible: false,
+            overlay: VoicingOverlay::load(organ_name),
+            organ_name: organ_name.to_string(),
+            rank_id: None,
+            note: 60,
+            follow_played: true,
+            status: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        app_state: Arc<Mutex<AppState>>,
+        audio_tx: &Sender<AppMessage>,
+    ) {
+        let mut open = self.visible;
+        let organ = {
+            let state = app_state.lock().unwrap();
+            if self.visible
+                && self.follow_played
+                && let Some(played) = state
+                    .active_midi_notes
+                    .values()
+                    .max_by_key(|n| n.start_time)
+            {
+                // The pipe the selected rank sounds for the key, or the key itself
+                // when the rank isn't drawn
+                self.note = self
+                    .rank_id
+                    .as_deref()
+                    .and_then(|rank_id| {
+                        state
+                            .pipe_notes_for_key(rank_id, played.channel, played.note)
+                            .first()
+                            .copied()
+                    })
+                    .unwrap_or(played.note);
+            }
+            state.organ.clone()
+        };
+
+        let mut rank_ids: Vec<&String> = organ.ranks.keys().collect();
+        rank_ids.sort();
+        if self.rank_id.is_none() {
+            self.rank_id = rank_ids.first().map(|id| (*id).clone());
+        }
+        let rank_label = |id: &str| match organ.ranks.get(id) {
+            Some(rank) => format!("{} ({})", rank.name, id),
+            None => id.to_string(),
+        };
+
+        egui::Window::new(t!("voicing.title"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(t!("voicing.description"));
+                ui.add_space(10.0);
+
+                egui::Grid::new("voicing_pipe_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label(t!("voicing.rank"));
+                        let selected_text = self
+                            .rank_id
+                            .as_deref()
+                            .map(rank_label)
+                            .unwrap_or_else(|| "-".to_string());
+                        egui::ComboBox::from_id_salt("voicing_rank")
+                            .selected_text(selected_text)
+                            .width(250.0)
+                            .show_ui(ui, |ui| {
+                                for id in &rank_ids {
+                                    ui.selectable_value(
+                                        &mut self.rank_id,
+                                        Some((*id).clone()),
+                                        rank_label(id),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label(t!("voicing.note"));
+                        ui.horizontal(|ui| {
+                            ui.add(egui::DragValue::new(&mut self.note).range(0..=127));
+                            ui.checkbox(&mut self.follow_played, t!("voicing.follow_played"));
+                        });
+                        ui.end_row();
+                    });
+
+                let Some(rank_id) = self.rank_id.clone() else {
+                    return;
+                };
+                let Some(pipe) = organ
+                    .ranks
+                    .get(&rank_id)
+                    .and_then(|rank| rank.pipes.get(&self.note))
+                else {
+                    ui.add_space(5.0);
+                    ui.label(t!("voicing.no_pipe"));
+                    return;
+                };
+                if let Some(attack) = pipe.attacks.first() {
+                    ui.label(
+                        egui::RichText::new(attack.path.display().to_string())
+                            .small()
+                            .weak(),
+                    );
+                }
+                ui.separator();
+
+                let mut voicing = self.overlay.get(&rank_id, self.note);
+                let mut changed = false;
+                let mut finished = false;
+                let mut track = |response: egui::Response| {
+                    changed |= response.changed();
+                    // Write the file once a drag ends rather than on every frame of it
+                    finished |=
+                        (response.changed() && !response.dragged()) || response.drag_stopped();
+                };
+
+                egui::Grid::new("voicing_values_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label(t!("voicing.gain"));
+                        track(
+                            ui.add(
+                                egui::DragValue::new(&mut voicing.gain_db)
+                                    .range(-24.0..=12.0)
+                                    .speed(0.1)
+                                    .suffix(" dB"),
+                            ),
+                        );
+                        ui.end_row();
+
+                        ui.label(t!("voicing.tuning"));
+                        track(
+                            ui.add(
+                                egui::DragValue::new(&mut voicing.tuning_cents)
+                                    .range(-100.0..=100.0)
+                                    .speed(0.1)
+                                    .suffix(" ct"),
+                            ),
+                        );
+                        ui.end_row();
+
+                        ui.label(t!("voicing.release"));
+                        ui.horizontal(|ui| {
+                            let mut limited = voicing.max_release_ms.is_some();
+                            track(ui.checkbox(&mut limited, t!("voicing.limit_release")));
+                            let mut ms = voicing.max_release_ms.unwrap_or(1000);
+                            track(
+                                ui.add_enabled(
+                                    limited,
+                                    egui::DragValue::new(&mut ms)
+                                        .range(10..=10000)
+                                        .speed(5.0)
+                                        .suffix(" ms"),
+                                ),
+                            );
+                            voicing.max_release_ms = limited.then_some(ms);
+                        });
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                if ui.button(t!("voicing.reset")).clicked() {
+                    voicing = PipeVoicing::default();
+                    changed = true;
+                    finished = true;
+                }
+
+                if changed {
+                    self.overlay.set(&rank_id, self.note, voicing);
+                    if let Err(e) = audio_tx.send(AppMessage::SetPipeVoicing(
+                        rank_id.clone(),
+                        self.note,
+                        voicing,
+                    )) {
+                        log::error!("Failed to send voicing change: {}", e);
+                    }
+                }
+                if finished {
+                    self.status = Some(match self.overlay.save(&self.organ_name) {
+                        Ok(()) => t!("voicing.saved").to_string(),
+                        Err(e) => {
+                            log::error!("Failed to save voicing: {}", e);
+                            t!("voicing.save_failed", err = e).to_string()
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+
+        self.visible = open;
+    }
+}
@@ -0,0 +1,186 @@
+use crate::app::AppMessage;
+use crate::app_state::AppState;
+use crate::wind::{WindModel, WindParams};
+use eframe::egui;
+use rust_i18n::t;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+/// Editor for the organ's wind model: how much each windchest group sags under load.
+pub struct WindUi {
+    pub visible: bool,
+    model: WindModel,
+    organ_name: String,
+    status: Option<String>,
+}
+
+impl WindUi {
+    pub fn new(organ_name: &str) -> Self {
+        Self {
+            visible: false,
+            model: WindModel::load(organ_name),
+            organ_name: organ_name.to_string(),
+            status: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        app_state: Arc<Mutex<AppState>>,
+        audio_tx: &Sender<AppMessage>,
+    ) {
+        let mut open = self.visible;
+        let organ = app_state.lock().unwrap().organ.clone();
+
+        let mut groups: Vec<(&String, &String)> = organ
+            .windchest_groups
+            .values()
+            .map(|group| (&group.id_str, &group.name))
+            .collect();
+        groups.sort();
+
+        egui::Window::new(t!("wind.title"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(t!("wind.description"));
+                ui.add_space(10.0);
+
+                let mut changed = false;
+                let mut finished = false;
+                let mut track = |response: egui::Response| {
+                    changed |= response.changed();
+                    // Write the file once a drag ends rather than on every frame of it
+                    finished |=
+                        (response.changed() && !response.dragged()) || response.drag_stopped();
+                };
+
+                track(ui.checkbox(&mut self.model.enabled, t!("wind.enabled")));
+                ui.add_space(5.0);
+
+                ui.add_enabled_ui(self.model.enabled, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            egui::Grid::new("wind_grid")
+                                .num_columns(6)
+                                .striped(true)
+                                .spacing([15.0, 6.0])
+                                .show(ui, |ui| {
+                                    ui.label(egui::RichText::new(t!("wind.col_group")).strong());
+                                    ui.label(egui::RichText::new(t!("wind.col_own")).strong());
+                                    ui.label(egui::RichText::new(t!("wind.col_capacity")).strong())
+                                        .on_hover_text(t!("wind.tooltip_capacity"));
+                                    ui.label(egui::RichText::new(t!("wind.col_pitch")).strong());
+                                    ui.label(egui::RichText::new(t!("wind.col_volume")).strong());
+                                    ui.label(egui::RichText::new(t!("wind.col_response")).strong())
+                                        .on_hover_text(t!("wind.tooltip_response"));
+                                    ui.end_row();
+
+                                    ui.label(t!("wind.default_group"));
+                                    ui.label("");
+                                    params_row(ui, &mut self.model.default, true, &mut track);
+                                    ui.end_row();
+
+                                    for (id, name) in &groups {
+                                        ui.label(format!("{} ({})", name, id));
+                                        let mut own = self.model.groups.contains_key(*id);
+                                        let own_response = ui.checkbox(&mut own, "");
+                                        if own_response.changed() {
+                                            if own {
+                                                self.model
+                                                    .groups
+                                                    .insert((*id).clone(), self.model.default);
+                                            } else {
+                                                self.model.groups.remove(*id);
+                                            }
+                                        }
+                                        track(own_response);
+                                        let mut shown = self.model.default;
+                                        let params =
+                                            self.model.groups.get_mut(*id).unwrap_or(&mut shown);
+                                        params_row(ui, params, own, &mut track);
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                });
+
+                ui.separator();
+                if ui.button(t!("wind.reset")).clicked() {
+                    self.model = WindModel {
+                        enabled: self.model.enabled,
+                        ..WindModel::default()
+                    };
+                    changed = true;
+                    finished = true;
+                }
+
+                if changed
+                    && let Err(e) = audio_tx.send(AppMessage::SetWindModel(self.model.clone()))
+                {
+                    log::error!("Failed to send wind model: {}", e);
+                }
+                if finished {
+                    self.status = Some(match self.model.save(&self.organ_name) {
+                        Ok(()) => t!("wind.saved").to_string(),
+                        Err(e) => {
+                            log::error!("Failed to save wind model: {}", e);
+                            t!("wind.save_failed", err = e).to_string()
+                        }
+                    });
+                }
+
+                if let Some(status) = &self.status {
+                    ui.label(status);
+                }
+            });
+
+        self.visible = open;
+    }
+}
+
+/// Draws the four parameter cells of one grid row.
+fn params_row(
+    ui: &mut egui::Ui,
+    params: &mut WindParams,
+    enabled: bool,
+    track: &mut impl FnMut(egui::Response),
+) {
+    track(
+        ui.add_enabled(
+            enabled,
+            egui::DragValue::new(&mut params.capacity_pipes)
+                .range(1.0..=512.0)
+                .speed(0.5),
+        ),
+    );
+    track(
+        ui.add_enabled(
+            enabled,
+            egui::DragValue::new(&mut params.pitch_droop_cents)
+                .range(0.0..=50.0)
+                .speed(0.1)
+                .suffix(" ct"),
+        ),
+    );
+    track(
+        ui.add_enabled(
+            enabled,
+            egui::DragValue::new(&mut params.amplitude_droop_db)
+                .range(0.0..=12.0)
+                .speed(0.05)
+                .suffix(" dB"),
+        ),
+    );
+    track(
+        ui.add_enabled(
+            enabled,
+            egui::DragValue::new(&mut params.response_ms)
+                .range(0.0..=2000.0)
+                .speed(1.0)
+                .suffix(" ms"),
+        ),
+    );
+}
@@ -10,7 +10,7 @@ use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::thread::{self, JoinHandle};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 rust_i18n::i18n!("locales");
 
@@ -25,18 +25,31 @@ mod audio_recorder;
 mod config;
 mod gui;
 mod gui_config;
+mod gui_console;
 mod gui_filepicker;
 mod gui_midi;
 mod gui_midi_learn;
 mod gui_organ_manager;
+mod gui_output_routing;
+mod gui_stereo;
+mod gui_virtual_stops;
+mod gui_voicing;
+mod gui_wind;
 mod input;
 mod loading_ui;
 mod midi;
 mod midi_control;
 mod midi_recorder;
 mod organ;
+mod organ_check;
+mod organ_export;
 mod organ_grandorgue;
 mod organ_hauptwerk;
+mod output_routing;
+mod release_align;
+mod resample;
+mod stereo;
+mod temperament;
 mod tui;
 mod tui_config;
 mod tui_filepicker;
@@ -45,15 +58,25 @@ mod tui_midi;
 mod tui_midi_learn;
 mod tui_organ_manager;
 mod tui_progress;
+mod tui_virtual_stops;
+mod virtual_stops;
 mod voice;
+mod voicing;
 mod wav;
 mod wav_converter;
+mod wind;
 
 use app::{AppMessage, TuiMessage};
-use app_state::{AppState, connect_to_midi};
+use app_state::{AppState, MAX_MASTER_TUNING_CENTS, MAX_TRANSPOSE, connect_to_midi};
+use audio_loader::LoopSelection;
 use config::{AppSettings, MidiDeviceConfig, RuntimeConfig};
 use input::KeyboardLayout;
 use organ::Organ;
+use output_routing::OutputRouting;
+use resample::ResamplingQuality;
+use stereo::StereoLayout;
+use temperament::Temperament;
+use wind::WindModel;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -67,6 +90,13 @@ enum LogLevel {
     Trace,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, ValueEnum, Debug)]
+#[value(rename_all = "lower")]
+enum ReportFormat {
+    Text,
+    Json,
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
@@ -106,6 +136,50 @@ struct Args {
     #[arg(long)]
     list_midi_devices: bool,
 
+    /// Export the organ as a GrandOrgue sample set into this folder and exit
+    #[arg(long, value_name = "OUTPUT_DIR")]
+    export_grandorgue: Option<PathBuf>,
+
+    /// Check an organ definition and its samples for problems and exit (non-zero on errors)
+    #[arg(long, value_name = "ORGAN")]
+    check: Option<PathBuf>,
+
+    /// Output format of the --check report
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    check_format: ReportFormat,
+
+    /// Scale the organ's tracker (key action) delays (0.0 = off, 1.0 = as defined)
+    #[arg(long, value_name = "FACTOR")]
+    tracker_delay_scale: Option<f32>,
+
+    /// Temperament to play in: a built-in or user temperament name, or the path of a temperament file
+    #[arg(long, value_name = "TEMPERAMENT")]
+    temperament: Option<String>,
+
+    /// Note the temperament is built on (e.g. "D" or "Eb")
+    #[arg(long, value_name = "NOTE", value_parser = temperament::parse_base_note)]
+    temperament_base_note: Option<u8>,
+
+    /// Reference pitch offset in cents (e.g. -101.4 to play at A=415 with an A=440 organ)
+    #[arg(long, value_name = "CENTS", allow_hyphen_values = true)]
+    master_tuning: Option<f32>,
+
+    /// Transpose all notes by this many semitones (-12 to 12)
+    #[arg(long, value_name = "SEMITONES", allow_hyphen_values = true)]
+    transpose: Option<i8>,
+
+    /// How sustained notes choose among the loops of samples that have several
+    #[arg(long, value_name = "MODE")]
+    loop_selection: Option<LoopSelection>,
+
+    /// Interpolation of voices playing at another pitch than recorded (tremulants, transposition)
+    #[arg(long, value_name = "QUALITY")]
+    resampling_quality: Option<ResamplingQuality>,
+
+    /// Seed for random loop selection, to play the same loops on every run (e.g. for testing)
+    #[arg(long, value_name = "SEED")]
+    loop_seed: Option<u64>,
+
     /// Select a MIDI device by name (Enables this device with default 1:1 channel mapping)
     #[arg(long, value_name = "MIDI_DEVICE")]
     midi_device: Option<String>,
@@ -214,6 +288,19 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
+    // --- Check an organ definition and exit ---
+    if let Some(organ_path) = &args.check {
+        let report = organ_check::check_organ(organ_path);
+        match args.check_format {
+            ReportFormat::Text => print!("{}", report.to_text()),
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        }
+        if report.has_errors() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let midi_input_arc = Arc::new(Mutex::new(match MidiInput::new("Rusty Pipes MIDI Input") {
         Ok(mi) => Some(mi),
         Err(e) => {
@@ -257,6 +344,27 @@ fn main() -> Result<()> {
     if let Some(d) = args.audio_device {
         settings.audio_device_name = Some(d);
     }
+    if let Some(s) = args.tracker_delay_scale {
+        settings.tracker_delay_scale = s.max(0.0);
+    }
+    if let Some(t) = args.temperament {
+        settings.temperament = t;
+    }
+    if let Some(n) = args.temperament_base_note {
+        settings.temperament_base_note = n;
+    }
+    if let Some(c) = args.master_tuning {
+        settings.master_tuning_cents = c.clamp(-MAX_MASTER_TUNING_CENTS, MAX_MASTER_TUNING_CENTS);
+    }
+    if let Some(t) = args.transpose {
+        settings.transpose = t.clamp(-MAX_TRANSPOSE, MAX_TRANSPOSE);
+    }
+    if let Some(l) = args.loop_selection {
+        settings.loop_selection = l;
+    }
+    if let Some(q) = args.resampling_quality {
+        settings.resampling_quality = q;
+    }
 
     // --- CLI: MIDI Device Selection ---
     // If a device is specified via CLI, we ensure it exists in settings and is enabled.
@@ -278,6 +386,41 @@ fn main() -> Result<()> {
         }
     }
 
+    // --- Export to GrandOrgue and exit ---
+    if let Some(output_dir) = args.export_grandorgue {
+        let Some(organ_file) = settings.organ_file.clone() else {
+            eprintln!("{}", t!("errors.export_no_organ"));
+            return Ok(());
+        };
+        let pipe_layers = settings
+            .pipe_layers
+            .get(&config::pipe_layers_key(&organ_file))
+            .cloned()
+            .unwrap_or_default();
+        let result = Organ::load(
+            &organ_file,
+            settings.convert_to_16bit,
+            false,
+            settings.original_tuning,
+            settings.sample_rate,
+            None,
+            0,
+            &pipe_layers,
+        )
+        .and_then(|organ| organ_export::export_grandorgue(&organ, &output_dir));
+        match result {
+            Ok(odf_path) => println!(
+                "{}",
+                t!("organ_manager.export_done", path = odf_path.display())
+            ),
+            Err(e) => {
+                eprintln!("{}", t!("organ_manager.export_failed", err = e));
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+
     // --- Run Configuration UI ---
     let config_result = if tui_mode {
         tui_config::run_config_ui(settings.clone(), Arc::clone(&midi_input_arc))
@@ -324,11 +467,21 @@ fn main() -> Result<()> {
         midi_devices: devices_to_save,
         gain: config.gain,
         polyphony: config.polyphony,
+        tracker_delay_scale: config.tracker_delay_scale,
+        temperament: config.temperament.clone(),
+        temperament_base_note: config.temperament_base_note,
+        master_tuning_cents: config.master_tuning_cents,
+        transpose: config.transpose,
+        loop_selection: config.loop_selection,
+        resampling_quality: config.resampling_quality,
         audio_device_name: config.audio_device_name.clone(),
         sample_rate: config.sample_rate,
         tui_mode,
         keyboard_layout: active_layout,
         lcd_displays: config.lcd_displays.clone(),
+        pipe_layers: config.pipe_layers.clone(),
+        output_buses: config.output_buses.clone(),
+        output_routes: config.output_routes.clone(),
     };
     if let Err(e) = config::save_settings(&settings_to_save) {
         log::warn!("Failed to save settings: {}", e);
@@ -381,6 +534,7 @@ fn main() -> Result<()> {
                     load_config.sample_rate,
                     Some(progress_tx),
                     (load_config.max_ram_gb * 1024.0) as usize,
+                    load_config.selected_pipe_layers(),
                 );
 
                 log::info!("[LoadingThread] Finished.");
@@ -430,6 +584,7 @@ fn main() -> Result<()> {
                     load_config.sample_rate,
                     Some(tui_progress_tx),
                     (load_config.max_ram_gb * 1024.0) as usize,
+                    load_config.selected_pipe_layers(),
                 );
                 *organ_result_clone.lock().unwrap() = Some(load_result);
             });
@@ -481,21 +636,83 @@ fn main() -> Result<()> {
         if tui_mode {
             println!("{}", t!("main.starting_audio"));
         }
+        let output_routing = OutputRouting::load(&organ.name);
+        if !output_routing.buses.is_empty() {
+            log::info!(
+                "Output routing: {} buses, {} routes",
+                output_routing.buses.len(),
+                output_routing.routes.len()
+            );
+        }
         let _audio_handle = audio::start_audio_playback(
             audio_rx,
             Arc::clone(&organ),
             config.audio_buffer_frames,
             config.gain,
             config.polyphony,
+            config.tracker_delay_scale,
             config.audio_device_name.clone(),
             config.sample_rate,
             tui_tx.clone(),
             shared_midi_recorder.clone(),
+            output_routing,
         )?;
         if tui_mode {
             println!("{}", t!("main.audio_running"));
         }
 
+        // --- Temperament ---
+        match Temperament::resolve(&config.temperament) {
+            Ok(temperament) => {
+                log::info!(
+                    "Temperament: {} on {}",
+                    temperament.name,
+                    temperament::NOTE_NAMES[config.temperament_base_note as usize % 12]
+                );
+                audio_tx.send(AppMessage::SetTemperament(
+                    temperament.cents_for_base(config.temperament_base_note),
+                ))?;
+            }
+            Err(e) => log::warn!("{}, playing in equal temperament", e),
+        }
+
+        // --- Wind model ---
+        let wind_model = WindModel::load(&organ.name);
+        if wind_model.enabled {
+            log::info!(
+                "Wind model enabled ({} windchest groups with their own parameters)",
+                wind_model.groups.len()
+            );
+        }
+        audio_tx.send(AppMessage::SetWindModel(wind_model))?;
+
+        // --- Stereo layout ---
+        let stereo_layout = StereoLayout::load(&organ.name);
+        if !stereo_layout.is_empty() {
+            log::info!(
+                "Stereo layout: {} windchest groups, {} ranks and {} pipes placed",
+                stereo_layout.windchest_groups.len(),
+                stereo_layout.ranks.len(),
+                stereo_layout.pipes.len()
+            );
+        }
+        audio_tx.send(AppMessage::SetStereoLayout(stereo_layout))?;
+
+        // --- Loop selection ---
+        let loop_seed = args.loop_seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_nanos() as u64)
+        });
+        if let Some(seed) = args.loop_seed {
+            log::info!("Loop selection: {:?}, seed {}", config.loop_selection, seed);
+        }
+        audio_tx.send(AppMessage::SetLoopSelection(
+            config.loop_selection,
+            loop_seed,
+        ))?;
+        audio_tx.send(AppMessage::SetResamplingQuality(config.resampling_quality))?;
+
         // --- Load IR file ---
         if let Some(path) = &config.ir_file {
             if path.exists() {
@@ -535,6 +752,11 @@ fn main() -> Result<()> {
 
             state.lcd_displays = config.lcd_displays.clone();
             state.refresh_lcds();
+
+            state.master_tuning_cents = config.master_tuning_cents;
+            state.transpose = config.transpose;
+            audio_tx.send(AppMessage::SetMasterTuning(state.master_tuning_cents))?;
+            audio_tx.send(AppMessage::SetTranspose(state.transpose))?;
         }
 
         let exit_action = Arc::new(Mutex::new(app::MainLoopAction::Exit));
@@ -594,6 +816,7 @@ fn main() -> Result<()> {
                             log::error!("{}", err_msg);
                             app_state_locked.add_midi_log(err_msg);
                         }
+                        app_state_locked.flush_settings(false);
 
                         if let Some(ctx) = &egui_ctx {
                             if gui_running_clone.load(Ordering::Relaxed) {
@@ -602,7 +825,8 @@ fn main() -> Result<()> {
                         }
                     }
                     Err(mpsc::RecvTimeoutError::Timeout) => {
-                        // No message arrived, just loop back and check logic_stop_clone again
+                        // No message arrived, save settled settings and check logic_stop_clone again
+                        logic_app_state.lock().unwrap().flush_settings(false);
                         continue;
                     }
                     Err(mpsc::RecvTimeoutError::Disconnected) => {
@@ -719,6 +943,7 @@ fn main() -> Result<()> {
         };
 
         gui_is_running.store(false, Ordering::SeqCst);
+        app_state.lock().unwrap().flush_settings(true);
 
         match loop_action {
             app::MainLoopAction::ReloadOrgan { file } => {
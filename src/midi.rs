@@ -170,6 +170,9 @@ fn parse_and_send(message: &[u8], tui_tx: &Sender<TuiMessage>, channel: u8) {
                 let _ = tui_tx.send(TuiMessage::MidiLog(log_msg));
                 let _ = tui_tx.send(TuiMessage::MidiChannelNotesOff(channel));
                 let _ = tui_tx.send(TuiMessage::TuiAllNotesOff);
+            } else {
+                let value = message[2];
+                let _ = tui_tx.send(TuiMessage::MidiControlChange(controller, value, channel));
             }
         }
         _ => {}
@@ -449,12 +452,18 @@ pub fn play_midi_file(
                                         Instant::now(),
                                     ));
                                 }
-                                MidlyMidiMessage::Controller { controller, .. } => {
+                                MidlyMidiMessage::Controller { controller, value } => {
                                     // CC #123 is "All Notes Off"
                                     if controller.as_int() == 123 {
                                         let _ = tui_tx
                                             .send(TuiMessage::MidiChannelNotesOff(channel_num));
                                         let _ = tui_tx.send(TuiMessage::TuiAllNotesOff);
+                                    } else {
+                                        let _ = tui_tx.send(TuiMessage::MidiControlChange(
+                                            controller.as_int(),
+                                            value.as_int(),
+                                            channel_num,
+                                        ));
                                     }
                                     // TODO: Handle Sustain command (CC #64)
                                 }
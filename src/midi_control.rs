@@ -6,8 +6,10 @@ use std::io::{BufReader, BufWriter};
 use std::path::PathBuf;
 
 use crate::config::MidiEventSpec;
+use crate::organ::Organ;
+use crate::virtual_stops::{key_virtual_stops_by_id, resolve_virtual_stop_ids};
 
-// Defines how a control (Stop channel or Tremulant) is toggled
+// Defines how a control (Stop channel, Tremulant or Coupler) is toggled
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
 pub struct StopChannelControl {
     pub enable_event: Option<MidiEventSpec>,
@@ -26,9 +28,23 @@ pub enum ControlAction {
         id: String,
         active: bool,
     },
+    SetCoupler {
+        id: String,
+        active: bool,
+    },
     LoadPreset {
         slot_index: usize,
     },
+    SetEnclosure {
+        id: String,
+        value: u8,
+    },
+    TransposeStep {
+        delta: i8,
+    },
+    SetMasterTuning {
+        value: u8,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -36,21 +52,48 @@ pub struct MidiControlMap {
     // Map<StopIndex, Map<InternalChannel, Control>>
     pub stops: HashMap<usize, HashMap<u8, StopChannelControl>>,
 
+    // Map<VirtualStopID, Map<InternalChannel, Control>>
+    // Virtual stops are saved by ID. Once loaded, this only holds the controls of
+    // virtual stops the organ doesn't have.
+    #[serde(default)]
+    pub virtual_stops: HashMap<String, HashMap<u8, StopChannelControl>>,
+
     // Map<TremulantID, Control>
     #[serde(default)]
     pub tremulants: HashMap<String, StopChannelControl>,
 
+    // Map<CouplerID, Control>
+    #[serde(default)]
+    pub couplers: HashMap<String, StopChannelControl>,
+
+    // Map<EnclosureID, Controller>
+    #[serde(default)]
+    pub enclosures: HashMap<String, MidiEventSpec>,
+
     // Map<PresetSlotIndex, Trigger> (0-11)
     #[serde(default)]
     pub presets: HashMap<usize, Option<MidiEventSpec>>,
+
+    // Transpose up (enable) and down (disable) triggers
+    #[serde(default)]
+    pub transpose: StopChannelControl,
+
+    // Controller for the master tuning, centered at 64
+    #[serde(default)]
+    pub master_tuning: Option<MidiEventSpec>,
 }
 
 impl MidiControlMap {
     pub fn new() -> Self {
         Self {
             stops: HashMap::new(),
+            virtual_stops: HashMap::new(),
             tremulants: HashMap::new(),
+            couplers: HashMap::new(),
+            enclosures: HashMap::new(),
             presets: HashMap::new(),
+            transpose: StopChannelControl::default(),
+            master_tuning: None,
         }
     }
 
@@ -65,12 +108,13 @@ impl MidiControlMap {
         parent.join(format!("{}.midi_map.json", safe_name))
     }
 
-    pub fn load(organ_name: &str) -> Self {
-        let path = Self::get_file_path(organ_name);
+    pub fn load(organ: &Organ) -> Self {
+        let path = Self::get_file_path(&organ.name);
         if path.exists() {
             if let Ok(file) = File::open(&path) {
                 let reader = BufReader::new(file);
-                if let Ok(map) = serde_json::from_reader(reader) {
+                if let Ok::<Self, _>(mut map) = serde_json::from_reader(reader) {
+                    resolve_virtual_stop_ids(organ, &mut map.virtual_stops, &mut map.stops);
                     return map;
                 }
             }
@@ -78,11 +122,13 @@ impl MidiControlMap {
         Self::new()
     }
 
-    pub fn save(&self, organ_name: &str) -> Result<()> {
-        let path = Self::get_file_path(organ_name);
+    pub fn save(&self, organ: &Organ) -> Result<()> {
+        let path = Self::get_file_path(&organ.name);
+        let mut map = self.clone();
+        key_virtual_stops_by_id(organ, &mut map.stops, &mut map.virtual_stops);
         let file = File::create(path)?;
         let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, self)?;
+        serde_json::to_writer_pretty(writer, &map)?;
         Ok(())
     }
 
@@ -117,10 +163,40 @@ impl MidiControlMap {
         }
     }
 
+    pub fn learn_coupler(
+        &mut self,
+        coupler_id: String,
+        event: MidiEventSpec,
+        is_enable_action: bool,
+    ) {
+        let entry = self.couplers.entry(coupler_id).or_default();
+        if is_enable_action {
+            entry.enable_event = Some(event);
+        } else {
+            entry.disable_event = Some(event);
+        }
+    }
+
+    pub fn learn_enclosure(&mut self, enclosure_id: String, event: MidiEventSpec) {
+        self.enclosures.insert(enclosure_id, event);
+    }
+
     pub fn learn_preset(&mut self, slot_index: usize, event: MidiEventSpec) {
         self.presets.insert(slot_index, Some(event));
     }
 
+    pub fn learn_transpose(&mut self, event: MidiEventSpec, is_up: bool) {
+        if is_up {
+            self.transpose.enable_event = Some(event);
+        } else {
+            self.transpose.disable_event = Some(event);
+        }
+    }
+
+    pub fn learn_master_tuning(&mut self, event: MidiEventSpec) {
+        self.master_tuning = Some(event);
+    }
+
     pub fn clear_stop(&mut self, stop_index: usize, internal_channel: u8) {
         if let Some(stop_entry) = self.stops.get_mut(&stop_index) {
             stop_entry.remove(&internal_channel);
@@ -131,10 +207,26 @@ impl MidiControlMap {
         self.tremulants.remove(trem_id);
     }
 
+    pub fn clear_coupler(&mut self, coupler_id: &str) {
+        self.couplers.remove(coupler_id);
+    }
+
+    pub fn clear_enclosure(&mut self, enclosure_id: &str) {
+        self.enclosures.remove(enclosure_id);
+    }
+
     pub fn clear_preset(&mut self, slot_index: usize) {
         self.presets.remove(&slot_index);
     }
 
+    pub fn clear_transpose(&mut self) {
+        self.transpose = StopChannelControl::default();
+    }
+
+    pub fn clear_master_tuning(&mut self) {
+        self.master_tuning = None;
+    }
+
     /// Checks incoming MIDI against the map and returns a list of actions to take.
     pub fn check_event(&self, incoming: &MidiEventSpec) -> Vec<ControlAction> {
         let mut actions = Vec::new();
@@ -183,6 +275,22 @@ impl MidiControlMap {
             }
         }
 
+        // Check Couplers
+        for (coupler_id, control) in &self.couplers {
+            if control.enable_event.as_ref() == Some(incoming) {
+                actions.push(ControlAction::SetCoupler {
+                    id: coupler_id.clone(),
+                    active: true,
+                });
+            }
+            if control.disable_event.as_ref() == Some(incoming) {
+                actions.push(ControlAction::SetCoupler {
+                    id: coupler_id.clone(),
+                    active: false,
+                });
+            }
+        }
+
         // Check Presets
         for (slot, trigger_opt) in &self.presets {
             if let Some(trigger) = trigger_opt {
@@ -192,6 +300,32 @@ impl MidiControlMap {
             }
         }
 
+        // Check Transpose
+        if self.transpose.enable_event.as_ref() == Some(incoming) {
+            actions.push(ControlAction::TransposeStep { delta: 1 });
+        }
+        if self.transpose.disable_event.as_ref() == Some(incoming) {
+            actions.push(ControlAction::TransposeStep { delta: -1 });
+        }
+
+        actions
+    }
+
+    /// Checks an incoming controller against the enclosure map and the master tuning.
+    /// Unlike switches, these follow the controller value.
+    pub fn check_control_change(&self, incoming: &MidiEventSpec, value: u8) -> Vec<ControlAction> {
+        let mut actions: Vec<ControlAction> = self
+            .enclosures
+            .iter()
+            .filter(|(_, controller)| *controller == incoming)
+            .map(|(enclosure_id, _)| ControlAction::SetEnclosure {
+                id: enclosure_id.clone(),
+                value,
+            })
+            .collect();
+        if self.master_tuning.as_ref() == Some(incoming) {
+            actions.push(ControlAction::SetMasterTuning { value });
+        }
         actions
     }
 }
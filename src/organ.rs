@@ -2,6 +2,8 @@ use anyhow::{Context, Result, anyhow};
 use bytemuck::{cast_slice, cast_slice_mut};
 use rayon::prelude::*;
 use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{BufReader, BufWriter, Read, Write};
@@ -9,37 +11,73 @@ use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, mpsc};
 
+use crate::config::PipeLayerMix;
 use crate::wav_converter;
 use crate::wav_converter::SampleMetadata;
 
 use crate::organ_grandorgue;
 use crate::organ_hauptwerk;
+use crate::release_align::ReleaseAlignment;
+use crate::virtual_stops::VirtualStopSet;
+use crate::voicing::{PipeVoicing, VoicingOverlay};
+
+/// Bump when the layout of the organ structures changes, to invalidate old snapshots.
+const SNAPSHOT_VERSION: u32 = 7;
+/// File name of the organ snapshot inside the organ's cache directory.
+const SNAPSHOT_FILE_NAME: &str = "organ.snapshot";
 
 /// Top-level structure for the entire organ definition.
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Organ {
     pub name: String,
     pub stops: Vec<Stop>,
     pub ranks: HashMap<String, Rank>, // Keyed by rank ID (e.g., "013")
     pub windchest_groups: HashMap<String, WindchestGroup>, // Keyed by group ID (e.g. "001")
     pub tremulants: HashMap<String, Tremulant>, // Keyed by tremulant ID (e.g. "001")
+    pub manuals: HashMap<String, Manual>, // Keyed by manual ID (e.g. "001")
+    pub couplers: HashMap<String, Coupler>, // Keyed by coupler ID (e.g. "001")
+    pub enclosures: HashMap<String, Enclosure>, // Keyed by enclosure ID (e.g. "001")
+    pub panels: Vec<Panel>,           // Graphical console pages, main panel first
     pub base_path: PathBuf,           // The directory containing the .organ file
     pub cache_path: PathBuf,          // The directory for cached converted samples
+    #[serde(skip)]
     pub sample_cache: Option<HashMap<PathBuf, Arc<Vec<f32>>>>, // Cache for loaded samples
+    #[serde(skip)]
     pub metadata_cache: Option<HashMap<PathBuf, Arc<SampleMetadata>>>, // Cache for loop points etc.
 }
 
 /// Represents a single stop (a button on the TUI).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Stop {
     pub name: String,
     pub id_str: String,        // e.g., "013"
     pub rank_ids: Vec<String>, // IDs of ranks it triggers
+    /// Key offset and level per rank, parallel to `rank_ids` (empty = play ranks as is)
+    pub rank_offsets: Vec<StopRankOffset>,
+    /// Defined in the user's virtual stop file rather than by the sample set
+    pub is_virtual: bool,
+}
+
+impl Stop {
+    /// Offset applied to the rank at `rank_index` of `rank_ids`.
+    pub fn rank_offset(&self, rank_index: usize) -> StopRankOffset {
+        self.rank_offsets
+            .get(rank_index)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// How a stop plays one of its ranks, e.g. a 4' borrowed from an 8' rank.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StopRankOffset {
+    pub transpose: i8, // Semitones added to the played key
+    pub gain_db: f32,
 }
 
 /// Represents a rank (a set of pipes).
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Rank {
     pub name: String,
     pub id_str: String,      // e.g., "013"
@@ -56,16 +94,19 @@ pub struct Rank {
 
 /// Represents a Windchest Group (defines shared tremulants/enclosures).
 #[allow(dead_code)]
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct WindchestGroup {
     pub name: String,
     pub id_str: String,
     pub tremulant_ids: Vec<String>, // IDs of tremulants attached to this group
+    pub enclosure_ids: Vec<String>, // IDs of enclosures (swell boxes) around this group
+    /// Stereo position the definition implies (-1 left, 1 right), e.g. for a C# side chest
+    pub pan: Option<f32>,
 }
 
 /// Represents a Tremulant definitions.
 #[allow(dead_code)]
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Tremulant {
     pub name: String,
     pub id_str: String,
@@ -74,25 +115,169 @@ pub struct Tremulant {
     pub stop_rate: f32,
     pub amp_mod_depth: f32,      // Amplitude modulation depth
     pub switch_ids: Vec<String>, // Switches that activate this tremulant
+    /// Wave tremulants switch pipes to tremulant-recorded samples instead of running an LFO
+    pub is_wave: bool,
+}
+
+/// Represents an enclosure (swell box) driven by an expression pedal.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Enclosure {
+    pub name: String,
+    pub id_str: String,
+    pub min_amplitude_level: f32, // Level in % (0-100) when the shutters are fully closed
+    pub default_value: u8,        // Initial pedal position (0 = closed, 127 = open)
+}
+
+/// Represents a keyboard of the console (a manual or the pedalboard).
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manual {
+    pub name: String,
+    pub id_str: String, // e.g., "001" (GrandOrgue uses "000" for the pedal)
+    pub first_midi_note: u8,
+    pub key_count: usize,
+    pub stop_ids: Vec<String>,    // IDs of stops drawn on this manual
+    pub coupler_ids: Vec<String>, // IDs of couplers whose source is this manual
+}
+
+impl Manual {
+    /// Whether this is the pedalboard: GrandOrgue's Manual000, or a keyboard named as one.
+    pub fn is_pedal(&self) -> bool {
+        self.id_str == "000" || self.name.to_lowercase().contains("pedal")
+    }
+}
+
+/// The kind of key action a coupler performs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CouplerKind {
+    #[default]
+    Unison,
+    SubOctave,
+    SuperOctave,
+    /// Only the lowest held key of the source manual is coupled.
+    Bass,
+    /// Only the highest held key of the source manual is coupled.
+    Melody,
+}
+
+impl CouplerKind {
+    /// Classifies a normal (non bass/melody) coupler by its key shift.
+    pub fn from_key_shift(key_shift: i8) -> Self {
+        if key_shift < 0 {
+            CouplerKind::SubOctave
+        } else if key_shift > 0 {
+            CouplerKind::SuperOctave
+        } else {
+            CouplerKind::Unison
+        }
+    }
+}
+
+/// Represents a coupler that plays the stops of one manual from the keys of another.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Coupler {
+    pub name: String,
+    pub id_str: String,
+    pub kind: CouplerKind,
+    pub source_manual_id: String,
+    pub destination_manual_id: String,
+    pub key_shift: i8, // Semitones added to the source key (e.g. -12 for a sub-octave coupler)
+    pub default_active: bool,
+}
+
+/// A page of the graphical console (a GrandOrgue panel or a Hauptwerk display page).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Panel {
+    pub name: String,
+    pub id_str: String,
+    pub width: f32,              // Size of the panel in pixels
+    pub height: f32,             // (0 = fit the images)
+    pub images: Vec<PanelImage>, // Backgrounds and decorations, drawn in order
+    pub elements: Vec<PanelElement>,
+}
+
+/// A static image placed on a panel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelImage {
+    pub path: PathBuf, // Absolute path to the image file
+    pub x: f32,
+    pub y: f32,
+}
+
+/// The organ control a panel element operates.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PanelControl {
+    Stop(String),      // Stop ID
+    Coupler(String),   // Coupler ID
+    Tremulant(String), // Tremulant ID
+}
+
+/// A drawknob, tab or switch placed on a panel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelElement {
+    pub control: PanelControl,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,  // Clickable area in pixels
+    pub height: f32, // (0 = size of the image)
+    pub image_on: Option<PathBuf>,
+    pub image_off: Option<PathBuf>,
 }
 
 /// Represents a single pipe with its attack and release samples.
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Pipe {
-    pub attack_sample_path: PathBuf,
+    /// Attack samples. The first one is the main attack, the others are alternates.
+    pub attacks: Vec<AttackSample>,
     pub gain_db: f32,
     pub pitch_tuning_cents: f32,
     pub releases: Vec<ReleaseSample>,
+    /// User voicing from the overlay, already included in `gain_db`.
+    /// The tuning is applied at playback.
+    pub voicing: PipeVoicing,
+}
+
+/// Represents an attack sample and its selection criteria.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackSample {
+    pub path: PathBuf,
+    /// Key velocity range (inclusive) this attack responds to.
+    pub min_velocity: u8,
+    pub max_velocity: u8,
+    /// Max time since the pipe was last released in ms. -1 means "any".
+    pub max_time_since_release_ms: i64,
+    /// Some(true): only with the tremulant on, Some(false): only with it off.
+    pub is_tremulant: Option<bool>,
+    #[serde(skip)]
     pub preloaded_bytes: Option<Arc<Vec<f32>>>,
 }
 
+impl AttackSample {
+    /// An attack without selection criteria.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            min_velocity: 0,
+            max_velocity: 127,
+            max_time_since_release_ms: -1,
+            is_tremulant: None,
+            preloaded_bytes: None,
+        }
+    }
+}
+
 /// Represents a release sample and its trigger condition.
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReleaseSample {
     pub path: PathBuf,
     /// Max key press time in ms. -1 means "default".
     pub max_key_press_time_ms: i64,
+    /// Start positions matching the phase of the attack, computed after conversion
+    pub alignment: Option<ReleaseAlignment>,
+    #[serde(skip)]
     pub preloaded_bytes: Option<Arc<Vec<f32>>>,
 }
 
@@ -103,6 +288,8 @@ pub struct ConversionTask {
     // We store cents as an integer (x100) to allow hashing/equality checks
     pub tuning_cents_int: i32,
     pub to_16bit: bool,
+    /// Release samples get their alignment table stored with the converted file
+    pub is_release: bool,
 }
 
 impl Organ {
@@ -110,6 +297,8 @@ impl Organ {
     /// This function dispatches to the correct parser based on the file extension.
     ///
     /// `max_preload_ram_mb`: The maximum amount of RAM (in MB) to dedicate to preloading attack transients.
+    /// `pipe_layers`: The Hauptwerk pipe layers (perspectives) to load; empty loads the first layer.
+    #[allow(clippy::too_many_arguments)]
     pub fn load(
         path: &Path,
         convert_to_16_bit: bool,
@@ -118,40 +307,51 @@ impl Organ {
         target_sample_rate: u32,
         progress_tx: Option<mpsc::Sender<(f32, String)>>,
         max_preload_ram_mb: usize,
+        pipe_layers: &[PipeLayerMix],
     ) -> Result<Self> {
-        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
-        let loader_tx = progress_tx.clone();
-
-        // Dispatch to specific loader modules
-        let mut organ = if extension == "organ" {
-            organ_grandorgue::load_grandorgue_dir(
-                path,
-                convert_to_16_bit,
-                original_tuning,
-                target_sample_rate,
-                &loader_tx,
-            )?
-        } else if extension == "orgue" {
-            organ_grandorgue::load_grandorgue_zip(
+        // Skip parsing when a snapshot of the assembled organ exists for these settings
+        let snapshot = Self::get_snapshot_path(path).and_then(|snapshot_path| {
+            let key = Self::snapshot_key(
                 path,
                 convert_to_16_bit,
                 original_tuning,
                 target_sample_rate,
-                &loader_tx,
-            )?
-        } else if extension == "Organ_Hauptwerk_xml" || extension == "xml" {
-            organ_hauptwerk::load_hauptwerk(
-                path,
-                convert_to_16_bit,
-                false,
-                original_tuning,
-                target_sample_rate,
-                &loader_tx,
-            )?
-        } else {
-            return Err(anyhow!("Unsupported organ file format: {:?}", path));
+                pipe_layers,
+            )?;
+            Ok((snapshot_path, key))
+        });
+        let cached_organ = match &snapshot {
+            Ok((snapshot_path, key)) => Self::load_snapshot(snapshot_path, key, &progress_tx),
+            Err(e) => {
+                log::warn!("[Snapshot] Could not compute the organ snapshot key: {}", e);
+                None
+            }
+        };
+
+        let mut organ = match cached_organ {
+            Some(organ) => organ,
+            None => {
+                let organ = Self::load_definition(
+                    path,
+                    convert_to_16_bit,
+                    original_tuning,
+                    target_sample_rate,
+                    pipe_layers,
+                    &progress_tx,
+                )?;
+                if let Ok((snapshot_path, key)) = &snapshot
+                    && let Err(e) = organ.save_snapshot(snapshot_path, key)
+                {
+                    log::warn!("[Snapshot] Failed to write organ snapshot: {}", e);
+                }
+                organ
+            }
         };
 
+        // Virtual stops and voicing are kept out of the snapshot so editing them doesn't invalidate it
+        VirtualStopSet::load(&organ.name).merge_into(&mut organ);
+        VoicingOverlay::load(&organ.name).merge_into(&mut organ);
+
         if pre_cache {
             log::info!("[Organ] Pre-caching mode enabled. This may take a moment...");
 
@@ -174,6 +374,71 @@ impl Organ {
         Ok(organ)
     }
 
+    /// Parses the organ definition with the loader matching its file extension.
+    fn load_definition(
+        path: &Path,
+        convert_to_16_bit: bool,
+        original_tuning: bool,
+        target_sample_rate: u32,
+        pipe_layers: &[PipeLayerMix],
+        progress_tx: &Option<mpsc::Sender<(f32, String)>>,
+    ) -> Result<Self> {
+        let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+
+        // Dispatch to specific loader modules
+        if extension == "organ" {
+            organ_grandorgue::load_grandorgue_dir(
+                path,
+                convert_to_16_bit,
+                original_tuning,
+                target_sample_rate,
+                progress_tx,
+            )
+        } else if extension == "orgue" {
+            organ_grandorgue::load_grandorgue_zip(
+                path,
+                convert_to_16_bit,
+                original_tuning,
+                target_sample_rate,
+                progress_tx,
+            )
+        } else if extension == "Organ_Hauptwerk_xml" || extension == "xml" {
+            organ_hauptwerk::load_hauptwerk(
+                path,
+                convert_to_16_bit,
+                false,
+                original_tuning,
+                target_sample_rate,
+                pipe_layers,
+                progress_tx,
+            )
+        } else {
+            Err(anyhow!("Unsupported organ file format: {:?}", path))
+        }
+    }
+
+    /// Returns the indices (into `stops`) of the stops drawn on the given manual.
+    pub fn stop_indices_for_manual(&self, manual_id: &str) -> Vec<usize> {
+        let Some(manual) = self.manuals.get(manual_id) else {
+            return Vec::new();
+        };
+        self.stops
+            .iter()
+            .enumerate()
+            .filter(|(_, stop)| manual.stop_ids.contains(&stop.id_str))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    /// Returns the ID of the manual the stop at `stop_index` is drawn on, if known.
+    pub fn manual_id_for_stop(&self, stop_index: usize) -> Option<&str> {
+        let stop = self.stops.get(stop_index)?;
+        self.manuals
+            .values()
+            .find(|m| m.stop_ids.contains(&stop.id_str))
+            .map(|m| m.id_str.as_str())
+    }
+
     /// Normalizes a path to an absolute path without resolving symlinks.
     pub fn normalize_path_preserve_symlinks(path: &Path) -> Result<PathBuf> {
         if path.is_absolute() {
@@ -223,7 +488,8 @@ impl Organ {
         }
     }
 
-    /// Helper to execute a set of unique audio conversion tasks in parallel
+    /// Helper to execute a set of unique audio conversion tasks in parallel.
+    /// Returns the source files of the tasks.
     pub fn process_tasks_parallel(
         base_path: &Path,
         cache_path: &Path,
@@ -231,7 +497,29 @@ impl Organ {
         target_sample_rate: u32,
         progress_tx: &Option<mpsc::Sender<(f32, String)>>,
     ) -> Result<()> {
-        let task_list: Vec<ConversionTask> = tasks.into_iter().collect();
+        // A file used as attack and release is converted once, as a release
+        let releases: HashSet<(PathBuf, i32, bool)> = tasks
+            .iter()
+            .filter(|task| task.is_release)
+            .map(|task| {
+                (
+                    task.relative_path.clone(),
+                    task.tuning_cents_int,
+                    task.to_16bit,
+                )
+            })
+            .collect();
+        let task_list: Vec<ConversionTask> = tasks
+            .into_iter()
+            .filter(|task| {
+                task.is_release
+                    || !releases.contains(&(
+                        task.relative_path.clone(),
+                        task.tuning_cents_int,
+                        task.to_16bit,
+                    ))
+            })
+            .collect();
         let total = task_list.len();
         if total == 0 {
             return Ok(());
@@ -243,7 +531,12 @@ impl Organ {
         task_list.par_iter().for_each(|task| {
             let cents = task.tuning_cents_int as f32 / 100.0;
 
-            match wav_converter::process_sample_file(
+            let process = if task.is_release {
+                wav_converter::process_release_file
+            } else {
+                wav_converter::process_sample_file
+            };
+            match process(
                 &task.relative_path,
                 base_path,
                 cache_path,
@@ -505,6 +798,187 @@ impl Organ {
         Ok(())
     }
 
+    /// Path of the organ snapshot, stored in the organ's sample cache directory.
+    fn get_snapshot_path(organ_path: &Path) -> Result<PathBuf> {
+        let organ_name = organ_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .replace(".Organ_Hauptwerk_xml", "");
+        Ok(Self::get_organ_cache_dir(&organ_name)?.join(SNAPSHOT_FILE_NAME))
+    }
+
+    /// Discards the snapshot of the organ a sample belongs to, so the next load
+    /// re-parses the definition and converts missing samples again.
+    /// Called when a sample fails to open; looks for the snapshot in the sample's cache folders.
+    pub fn invalidate_snapshot_for_sample(sample_path: &Path) {
+        let Some(snapshot_path) = sample_path
+            .ancestors()
+            .skip(1)
+            .map(|dir| dir.join(SNAPSHOT_FILE_NAME))
+            .find(|path| path.is_file())
+        else {
+            return;
+        };
+        match fs::remove_file(&snapshot_path) {
+            Ok(()) => log::warn!(
+                "[Snapshot] Sample {:?} could not be opened. The organ will be rebuilt on the next load.",
+                sample_path
+            ),
+            Err(e) => log::warn!("[Snapshot] Failed to remove {:?}: {}", snapshot_path, e),
+        }
+    }
+
+    /// Hashes everything the assembled organ depends on: the definition file,
+    /// its location and the load options.
+    fn snapshot_key(
+        organ_path: &Path,
+        convert_to_16_bit: bool,
+        original_tuning: bool,
+        target_sample_rate: u32,
+        pipe_layers: &[PipeLayerMix],
+    ) -> Result<[u8; 32]> {
+        let logical_path = Self::normalize_path_preserve_symlinks(organ_path)?;
+        let mut hasher = Sha256::new();
+        hasher.update(SNAPSHOT_VERSION.to_le_bytes());
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update(logical_path.to_string_lossy().as_bytes());
+
+        let is_archive = organ_path.extension().is_some_and(|ext| ext == "orgue");
+        if is_archive {
+            // Hashing a multi-gigabyte archive would take longer than parsing it
+            let file_meta = fs::metadata(organ_path)?;
+            let modified = file_meta
+                .modified()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            hasher.update(file_meta.len().to_le_bytes());
+            hasher.update(modified.as_nanos().to_le_bytes());
+        } else {
+            let mut file = BufReader::new(fs::File::open(organ_path)?);
+            std::io::copy(&mut file, &mut hasher)?;
+        }
+
+        hasher.update([convert_to_16_bit as u8, original_tuning as u8]);
+        hasher.update(target_sample_rate.to_le_bytes());
+        for layer in pipe_layers {
+            hasher.update(layer.layer.to_le_bytes());
+            hasher.update(layer.level.to_le_bytes());
+        }
+        Ok(hasher.finalize().into())
+    }
+
+    /// Loads the assembled organ from its snapshot.
+    /// Returns None if there is no snapshot, it was made for another definition or
+    /// settings, it fails its checksum, or the cache directory changed after it was
+    /// written. Individual samples are not checked here: a sample that fails to open
+    /// later discards the snapshot (see `invalidate_snapshot_for_sample`).
+    fn load_snapshot(
+        path: &Path,
+        expected_key: &[u8; 32],
+        progress_tx: &Option<mpsc::Sender<(f32, String)>>,
+    ) -> Option<Self> {
+        let file = fs::File::open(path).ok()?;
+        let snapshot_modified = file.metadata().and_then(|m| m.modified()).ok()?;
+        let cache_modified = path
+            .parent()
+            .and_then(|dir| fs::metadata(dir).and_then(|m| m.modified()).ok())?;
+        if cache_modified > snapshot_modified {
+            log::info!("[Snapshot] Sample cache changed since the snapshot. Invalidating.");
+            return None;
+        }
+        let mut reader = BufReader::with_capacity(1_024 * 1_024, file);
+
+        // Validate Magic Header and Version
+        let mut magic = [0u8; 4];
+        if reader.read_exact(&mut magic).is_err() || &magic != b"ORGS" {
+            log::warn!("[Snapshot] Snapshot file corrupted or invalid format.");
+            return None;
+        }
+        let mut version_buf = [0u8; 4];
+        reader.read_exact(&mut version_buf).ok()?;
+        if u32::from_le_bytes(version_buf) != SNAPSHOT_VERSION {
+            log::info!("[Snapshot] Snapshot format changed. Invalidating.");
+            return None;
+        }
+
+        // Validate Key (definition file and load settings)
+        let mut key = [0u8; 32];
+        reader.read_exact(&mut key).ok()?;
+        if &key != expected_key {
+            log::info!("[Snapshot] Organ definition or load settings changed. Invalidating.");
+            return None;
+        }
+
+        if let Some(tx) = progress_tx {
+            let _ = tx.send((0.0, t!("gui.progress_snapshot_read").to_string()));
+        }
+
+        // Read and verify the payload
+        let mut checksum = [0u8; 32];
+        reader.read_exact(&mut checksum).ok()?;
+        let mut len_buf = [0u8; 8];
+        reader.read_exact(&mut len_buf).ok()?;
+        let mut payload = Vec::new();
+        reader.read_to_end(&mut payload).ok()?;
+        if payload.len() as u64 != u64::from_le_bytes(len_buf)
+            || <[u8; 32]>::from(Sha256::digest(&payload)) != checksum
+        {
+            log::warn!("[Snapshot] Snapshot checksum mismatch. Rebuilding.");
+            return None;
+        }
+
+        match bincode::deserialize::<Organ>(&payload) {
+            Ok(organ) => {
+                log::info!(
+                    "[Snapshot] Loaded organ '{}' from snapshot ({} stops, {} ranks).",
+                    organ.name,
+                    organ.stops.len(),
+                    organ.ranks.len()
+                );
+                Some(organ)
+            }
+            Err(e) => {
+                log::warn!("[Snapshot] Could not decode snapshot: {}. Rebuilding.", e);
+                None
+            }
+        }
+    }
+
+    /// Writes the assembled organ to a snapshot file in the organ's cache directory.
+    fn save_snapshot(&self, path: &Path, key: &[u8; 32]) -> Result<()> {
+        let payload = bincode::serialize(self)?;
+        let checksum: [u8; 32] = Sha256::digest(&payload).into();
+
+        // Write to a temporary file first so an interrupted write never leaves a
+        // half-written snapshot behind
+        let tmp_path = path.with_extension("snapshot.tmp");
+        {
+            let mut writer = BufWriter::new(fs::File::create(&tmp_path)?);
+            writer.write_all(b"ORGS")?;
+            writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+            writer.write_all(key)?;
+            writer.write_all(&checksum)?;
+            writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+            writer.write_all(&payload)?;
+            writer.flush()?;
+        }
+        fs::rename(&tmp_path, path)?;
+        // Date the snapshot after the rename touched the directory, so the cache
+        // directory only looks newer once something else changes in it
+        fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(std::time::SystemTime::now())?;
+
+        log::info!(
+            "[Snapshot] Wrote organ snapshot ({} bytes) to {:?}",
+            payload.len(),
+            path
+        );
+        Ok(())
+    }
+
     fn preload_attack_samples(
         &mut self,
         target_sample_rate: u32,
@@ -522,7 +996,9 @@ impl Organ {
         let mut paths = HashSet::new();
         for rank in self.ranks.values() {
             for pipe in rank.pipes.values() {
-                paths.insert(pipe.attack_sample_path.clone());
+                for a in &pipe.attacks {
+                    paths.insert(a.path.clone());
+                }
                 for r in &pipe.releases {
                     paths.insert(r.path.clone());
                 }
@@ -652,8 +1128,10 @@ impl Organ {
         // Assign the loaded chunks back to the pipes
         for rank in self.ranks.values_mut() {
             for pipe in rank.pipes.values_mut() {
-                if let Some(data) = chunks_map.get(&pipe.attack_sample_path) {
-                    pipe.preloaded_bytes = Some(data.clone());
+                for attack in &mut pipe.attacks {
+                    if let Some(data) = chunks_map.get(&attack.path) {
+                        attack.preloaded_bytes = Some(data.clone());
+                    }
                 }
                 for release in &mut pipe.releases {
                     if let Some(data) = chunks_map.get(&release.path) {
@@ -674,7 +1152,9 @@ impl Organ {
         let mut paths = HashSet::new();
         for rank in self.ranks.values() {
             for pipe in rank.pipes.values() {
-                paths.insert(pipe.attack_sample_path.clone());
+                for attack in &pipe.attacks {
+                    paths.insert(attack.path.clone());
+                }
                 for release in &pipe.releases {
                     paths.insert(release.path.clone());
                 }
@@ -705,6 +1185,7 @@ impl Organ {
                 // This closure runs on a different thread
                 let (samples, metadata) =
                     wav_converter::load_sample_as_f32(path, target_sample_rate)
+                        .inspect_err(|_| Self::invalidate_snapshot_for_sample(path))
                         .with_context(|| format!("Failed to load sample {:?}", path))?;
 
                 // Report progress atomically
@@ -0,0 +1,336 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek};
+use std::path::{Path, PathBuf};
+
+use crate::organ_grandorgue;
+use crate::organ_hauptwerk;
+use crate::wav::{IsWavPackError, parse_smpl_chunk, parse_wav_metadata};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// The kind of problem found in an organ definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// The definition itself could not be read
+    Definition,
+    MissingSample,
+    UnreadableSample,
+    UnresolvedReference,
+    BadLoop,
+    FormatMismatch,
+    EmptyRank,
+    EmptyStop,
+    DuplicateId,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckIssue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    pub object: String, // The ODF object the problem was found in, e.g. "Rank003 Pipe012"
+    pub message: String,
+}
+
+/// Format of a checked sample, used to find samples that differ from the rest.
+#[derive(Debug, Clone)]
+struct SampleFormat {
+    name: String,
+    object: String,
+    sample_rate: u32,
+    channels: u16,
+}
+
+/// Structured result of checking an organ definition (`--check`).
+#[derive(Debug, Default, Serialize)]
+pub struct CheckReport {
+    pub organ: PathBuf,
+    pub samples_checked: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<CheckIssue>,
+    #[serde(skip)]
+    checked_samples: HashSet<String>,
+    #[serde(skip)]
+    sample_formats: Vec<SampleFormat>,
+}
+
+/// Checks an organ definition and all samples it references without loading the organ.
+pub fn check_organ(path: &Path) -> CheckReport {
+    let mut report = CheckReport {
+        organ: path.to_path_buf(),
+        ..Default::default()
+    };
+    let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+    let result = match extension {
+        "organ" | "orgue" => organ_grandorgue::check_grandorgue(path, &mut report),
+        "Organ_Hauptwerk_xml" | "xml" => organ_hauptwerk::check_hauptwerk(path, &mut report),
+        _ => Err(anyhow::anyhow!("Unsupported organ file format: {:?}", path)),
+    };
+    if let Err(e) = result {
+        report.error(IssueKind::Definition, "", format!("{:#}", e));
+    }
+    report.finish();
+    report
+}
+
+impl CheckReport {
+    pub fn error(&mut self, kind: IssueKind, object: &str, message: String) {
+        self.push(Severity::Error, kind, object, message);
+    }
+
+    pub fn warning(&mut self, kind: IssueKind, object: &str, message: String) {
+        self.push(Severity::Warning, kind, object, message);
+    }
+
+    fn push(&mut self, severity: Severity, kind: IssueKind, object: &str, message: String) {
+        self.issues.push(CheckIssue {
+            severity,
+            kind,
+            object: object.to_string(),
+            message,
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors > 0
+    }
+
+    /// Checks a sample file on disk. Each file is only checked once.
+    pub fn check_sample_file(&mut self, object: &str, path: &Path) {
+        let name = path.to_string_lossy().to_string();
+        if !self.checked_samples.insert(name.clone()) {
+            return;
+        }
+        match File::open(path) {
+            Ok(file) => self.check_sample_data(object, &name, BufReader::new(file)),
+            Err(e) => {
+                self.samples_checked += 1;
+                let kind = if path.exists() {
+                    IssueKind::UnreadableSample
+                } else {
+                    IssueKind::MissingSample
+                };
+                self.error(kind, object, format!("{}: {}", name, e));
+            }
+        }
+    }
+
+    /// Checks a sample that is not a plain file (e.g. inside an archive).
+    /// `data` is None if the sample does not exist.
+    pub fn check_sample_entry<R: Read + Seek>(
+        &mut self,
+        object: &str,
+        name: &str,
+        data: Option<R>,
+    ) {
+        if !self.checked_samples.insert(name.to_string()) {
+            return;
+        }
+        match data {
+            Some(reader) => self.check_sample_data(object, name, reader),
+            None => {
+                self.samples_checked += 1;
+                self.error(
+                    IssueKind::MissingSample,
+                    object,
+                    format!("{}: sample not found", name),
+                );
+            }
+        }
+    }
+
+    fn check_sample_data<R: Read + Seek>(&mut self, object: &str, name: &str, mut reader: R) {
+        self.samples_checked += 1;
+        let (fmt, chunks, _data_offset, data_size) =
+            match parse_wav_metadata(&mut reader, Path::new(name)) {
+                Ok(metadata) => metadata,
+                // WavPack files are decoded on load, only their presence is checked here
+                Err(e) if e.is::<IsWavPackError>() => return,
+                Err(e) => {
+                    self.error(
+                        IssueKind::UnreadableSample,
+                        object,
+                        format!("{}: {}", name, e),
+                    );
+                    return;
+                }
+            };
+
+        self.sample_formats.push(SampleFormat {
+            name: name.to_string(),
+            object: object.to_string(),
+            sample_rate: fmt.sample_rate,
+            channels: fmt.num_channels,
+        });
+
+        let bytes_per_frame = (fmt.bits_per_sample as u32 / 8) * fmt.num_channels as u32;
+        if bytes_per_frame == 0 {
+            self.error(
+                IssueKind::UnreadableSample,
+                object,
+                format!("{}: invalid format ({:?})", name, fmt),
+            );
+            return;
+        }
+        let frame_count = data_size / bytes_per_frame;
+
+        for chunk in chunks.iter().filter(|c| &c.id == b"smpl") {
+            let declared_loops = chunk
+                .data
+                .get(28..32)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .unwrap_or(0);
+            match parse_smpl_chunk(&chunk.data) {
+                Some(loops) => {
+                    for (start, end) in loops {
+                        if start >= end {
+                            self.error(
+                                IssueKind::BadLoop,
+                                object,
+                                format!(
+                                    "{}: loop start {} is not before loop end {}",
+                                    name, start, end
+                                ),
+                            );
+                        } else if end > frame_count {
+                            self.error(
+                                IssueKind::BadLoop,
+                                object,
+                                format!(
+                                    "{}: loop {}..{} is outside of the sample ({} frames)",
+                                    name, start, end, frame_count
+                                ),
+                            );
+                        }
+                    }
+                }
+                None if declared_loops > 0 || chunk.data.len() < 36 => self.error(
+                    IssueKind::BadLoop,
+                    object,
+                    format!(
+                        "{}: malformed 'smpl' chunk ({} bytes)",
+                        name,
+                        chunk.data.len()
+                    ),
+                ),
+                None => {}
+            }
+        }
+    }
+
+    /// Reports samples whose sample rate or channel count differs from most of the organ,
+    /// and counts the issues.
+    fn finish(&mut self) {
+        let most_common = |values: Vec<u32>| -> Option<u32> {
+            let mut counts: HashMap<u32, usize> = HashMap::new();
+            for value in values {
+                *counts.entry(value).or_default() += 1;
+            }
+            counts
+                .into_iter()
+                .max_by_key(|&(value, count)| (count, value))
+                .map(|(value, _)| value)
+        };
+        let formats = std::mem::take(&mut self.sample_formats);
+        let common_rate = most_common(formats.iter().map(|f| f.sample_rate).collect());
+        let common_channels = most_common(formats.iter().map(|f| f.channels as u32).collect());
+
+        for format in &formats {
+            if let Some(rate) = common_rate
+                && format.sample_rate != rate
+            {
+                self.warning(
+                    IssueKind::FormatMismatch,
+                    &format.object,
+                    format!(
+                        "{}: sample rate is {} Hz, most samples use {} Hz",
+                        format.name, format.sample_rate, rate
+                    ),
+                );
+            }
+            if let Some(channels) = common_channels
+                && format.channels as u32 != channels
+            {
+                self.warning(
+                    IssueKind::FormatMismatch,
+                    &format.object,
+                    format!(
+                        "{}: has {} channel(s), most samples have {}",
+                        format.name, format.channels, channels
+                    ),
+                );
+            }
+        }
+
+        self.errors = self
+            .issues
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .count();
+        self.warnings = self.issues.len() - self.errors;
+    }
+
+    /// Formats the report for the terminal, errors first.
+    pub fn to_text(&self) -> String {
+        let mut issues: Vec<&CheckIssue> = self.issues.iter().collect();
+        issues.sort_by_key(|i| std::cmp::Reverse(i.severity));
+
+        let mut text = String::new();
+        let _ = writeln!(text, "Checking {}", self.organ.display());
+        for issue in issues {
+            let severity = match issue.severity {
+                Severity::Error => "ERROR",
+                Severity::Warning => "WARNING",
+            };
+            let kind = serde_json::to_value(issue.kind)
+                .ok()
+                .and_then(|v| v.as_str().map(str::to_string))
+                .unwrap_or_default();
+            if issue.object.is_empty() {
+                let _ = writeln!(text, "{:<8} {:<20} {}", severity, kind, issue.message);
+            } else {
+                let _ = writeln!(
+                    text,
+                    "{:<8} {:<20} {}: {}",
+                    severity, kind, issue.object, issue.message
+                );
+            }
+        }
+        let _ = writeln!(
+            text,
+            "Checked {} samples: {} error(s), {} warning(s).",
+            self.samples_checked, self.errors, self.warnings
+        );
+        text
+    }
+}
+
+/// Reports IDs that occur more than once in a list of objects.
+pub fn check_duplicate_ids<'a>(
+    report: &mut CheckReport,
+    object_type: &str,
+    ids: impl Iterator<Item = &'a str>,
+) {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for id in ids {
+        *counts.entry(id).or_default() += 1;
+    }
+    let mut duplicates: Vec<(&str, usize)> = counts.into_iter().filter(|&(_, n)| n > 1).collect();
+    duplicates.sort();
+    for (id, count) in duplicates {
+        report.error(
+            IssueKind::DuplicateId,
+            &format!("{} {}", object_type, id),
+            format!("ID is defined {} times", count),
+        );
+    }
+}
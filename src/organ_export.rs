@@ -0,0 +1,500 @@
+use anyhow::{Context, Result};
+use rust_i18n::t;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::app_state::AppState;
+use crate::organ::{AttackSample, CouplerKind, Organ, Pipe, Rank, Stop};
+
+/// Returns the default export folder for an organ (next to the sample cache).
+pub fn default_export_dir(organ_name: &str) -> Result<PathBuf> {
+    let settings_path = confy::get_configuration_file_path("rusty-pipes", "settings")?;
+    let config_dir = settings_path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Could not get export directory"))?;
+    Ok(config_dir.join("export").join(safe_file_name(organ_name)))
+}
+
+/// Exports the loaded organ on a worker thread and reports the outcome in the MIDI log.
+pub fn export_in_background(app_state: &Arc<Mutex<AppState>>, output_dir: PathBuf) {
+    let organ = {
+        let mut state = app_state.lock().unwrap();
+        let message = t!("organ_manager.export_started", name = state.organ.name).to_string();
+        state.add_midi_log(message);
+        state.organ.clone()
+    };
+    let app_state = Arc::clone(app_state);
+    thread::spawn(move || {
+        let message = match export_grandorgue(&organ, &output_dir) {
+            Ok(odf_path) => t!("organ_manager.export_done", path = odf_path.display()).to_string(),
+            Err(e) => {
+                log::error!("[Export] Failed to export '{}': {:#}", organ.name, e);
+                t!("organ_manager.export_failed", err = e).to_string()
+            }
+        };
+        app_state.lock().unwrap().add_midi_log(message);
+    });
+}
+
+/// Writes a loaded organ as a GrandOrgue sample set into `output_dir`.
+///
+/// The ODF is written as `<organ name>.organ` and every sample the organ plays is placed
+/// below `<organ name>/Rank<NNN>/`, so the folder can be copied to another machine as is.
+/// Samples are taken as loaded, i.e. already resampled and tuned, so the exported pipes
+/// keep only the tuning and gain that was not baked into the audio.
+/// Returns the path of the written ODF.
+pub fn export_grandorgue(organ: &Organ, output_dir: &Path) -> Result<PathBuf> {
+    let organ_name = safe_file_name(&organ.name);
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create export folder {:?}", output_dir))?;
+
+    // GrandOrgue objects are numbered 001, 002, ... in their section names
+    let rank_ids = renumber(organ.ranks.keys());
+    let windchest_group_ids = renumber(organ.windchest_groups.keys());
+    let tremulant_ids = renumber(organ.tremulants.keys());
+    let enclosure_ids = renumber(organ.enclosures.keys());
+    // Virtual stops are the user's own overlay, not part of the sample set
+    let stops: Vec<&Stop> = organ.stops.iter().filter(|s| !s.is_virtual).collect();
+    let stop_ids: HashMap<String, String> = stops
+        .iter()
+        .enumerate()
+        .map(|(i, stop)| (stop.id_str.clone(), format!("{:03}", i + 1)))
+        .collect();
+
+    // GrandOrgue numbers the pedal Manual000 and the manuals from 001
+    let mut manuals: Vec<_> = organ.manuals.values().cloned().collect();
+    manuals.sort_by_key(|m| sort_key(&m.id_str));
+    let pedal_index = manuals.iter().position(|m| m.is_pedal());
+    if let Some(index) = pedal_index {
+        let pedal = manuals.remove(index);
+        manuals.insert(0, pedal);
+    }
+    let has_pedals = pedal_index.is_some();
+    let first_manual_number = if has_pedals { 0 } else { 1 };
+
+    // Stops that are not drawn on any manual are placed on the first one,
+    // otherwise GrandOrgue would not offer them.
+    let orphan_stops: Vec<String> = stops
+        .iter()
+        .filter(|stop| !manuals.iter().any(|m| m.stop_ids.contains(&stop.id_str)))
+        .map(|stop| stop.id_str.clone())
+        .collect();
+    if !orphan_stops.is_empty() {
+        if manuals.is_empty() {
+            manuals.push(crate::organ::Manual {
+                name: "Manual".to_string(),
+                id_str: "001".to_string(),
+                first_midi_note: 36,
+                key_count: 61,
+                ..Default::default()
+            });
+        }
+        let target = if manuals.len() > 1 && has_pedals {
+            1
+        } else {
+            0
+        };
+        log::info!(
+            "[Export] Placing {} stops without a manual on '{}'.",
+            orphan_stops.len(),
+            manuals[target].name
+        );
+        manuals[target].stop_ids.extend(orphan_stops);
+    }
+
+    // Coupler destinations refer to the manuals as they are numbered below.
+    // Couplers into a manual that is not exported are left out.
+    let manual_ids: HashMap<&str, String> = manuals
+        .iter()
+        .enumerate()
+        .map(|(i, m)| (m.id_str.as_str(), format!("{:03}", i + first_manual_number)))
+        .collect();
+    let coupler_ids = renumber(
+        organ
+            .couplers
+            .iter()
+            .filter(|(_, c)| manual_ids.contains_key(c.destination_manual_id.as_str()))
+            .map(|(id, _)| id),
+    );
+
+    let odf_path = output_dir.join(format!("{}.organ", organ_name));
+    let file =
+        File::create(&odf_path).with_context(|| format!("Failed to create ODF {:?}", odf_path))?;
+    let mut out = BufWriter::new(file);
+
+    writeln!(out, "[Organ]")?;
+    writeln!(out, "ChurchName={}", organ.name)?;
+    writeln!(out, "ChurchAddress=")?;
+    writeln!(out, "OrganBuilder=")?;
+    writeln!(out, "RecordingDetails=Exported by Rusty Pipes")?;
+    writeln!(out, "HasPedals={}", yes_no(has_pedals))?;
+    writeln!(
+        out,
+        "NumberOfManuals={}",
+        manuals.len() - has_pedals as usize
+    )?;
+    writeln!(out, "NumberOfEnclosures={}", enclosure_ids.len())?;
+    writeln!(out, "NumberOfTremulants={}", tremulant_ids.len())?;
+    writeln!(out, "NumberOfWindchestGroups={}", windchest_group_ids.len())?;
+    writeln!(out, "NumberOfRanks={}", rank_ids.len())?;
+    writeln!(out, "NumberOfSwitches=0")?;
+    writeln!(out, "NumberOfReversiblePistons=0")?;
+    writeln!(out, "NumberOfGenerals=0")?;
+    writeln!(out, "NumberOfDivisionalCouplers=0")?;
+    writeln!(out, "NumberOfPanels=0")?;
+    writeln!(out)?;
+
+    for (index, manual) in manuals.iter().enumerate() {
+        let stops: Vec<&String> = manual
+            .stop_ids
+            .iter()
+            .filter_map(|id| stop_ids.get(id))
+            .collect();
+        let couplers: Vec<&String> = manual
+            .coupler_ids
+            .iter()
+            .filter_map(|id| coupler_ids.get(id))
+            .collect();
+        let key_count = if manual.key_count > 0 {
+            manual.key_count
+        } else {
+            61
+        };
+
+        writeln!(out, "[Manual{:03}]", index + first_manual_number)?;
+        writeln!(out, "Name={}", manual.name)?;
+        writeln!(out, "NumberOfLogicalKeys={}", key_count)?;
+        writeln!(out, "FirstAccessibleKeyLogicalKeyNumber=1")?;
+        writeln!(
+            out,
+            "FirstAccessibleKeyMIDINoteNumber={}",
+            manual.first_midi_note
+        )?;
+        writeln!(out, "NumberOfAccessibleKeys={}", key_count)?;
+        writeln!(out, "MIDIInputNumber={}", index + 1)?;
+        writeln!(out, "Displayed=N")?;
+        writeln!(out, "NumberOfStops={}", stops.len())?;
+        for (i, id) in stops.iter().enumerate() {
+            writeln!(out, "Stop{:03}={}", i + 1, id)?;
+        }
+        writeln!(out, "NumberOfCouplers={}", couplers.len())?;
+        for (i, id) in couplers.iter().enumerate() {
+            writeln!(out, "Coupler{:03}={}", i + 1, id)?;
+        }
+        writeln!(out, "NumberOfDivisionals=0")?;
+        writeln!(out)?;
+    }
+
+    for (id, coupler) in sorted(&organ.couplers) {
+        let (Some(number), Some(destination)) = (
+            coupler_ids.get(id),
+            manual_ids.get(coupler.destination_manual_id.as_str()),
+        ) else {
+            continue;
+        };
+        let coupler_type = match coupler.kind {
+            CouplerKind::Bass => "Bass",
+            CouplerKind::Melody => "Melody",
+            _ => "Normal",
+        };
+        writeln!(out, "[Coupler{}]", number)?;
+        writeln!(out, "Name={}", coupler.name)?;
+        writeln!(out, "UnisonOff=N")?;
+        writeln!(out, "CouplerType={}", coupler_type)?;
+        writeln!(out, "DestinationManual={}", destination)?;
+        writeln!(out, "DestinationKeyshift={}", coupler.key_shift)?;
+        writeln!(out, "DefaultToEngaged={}", yes_no(coupler.default_active))?;
+        writeln!(out, "Displayed=N")?;
+        writeln!(out)?;
+    }
+
+    for (id, enclosure) in sorted(&organ.enclosures) {
+        writeln!(out, "[Enclosure{}]", enclosure_ids[id])?;
+        writeln!(out, "Name={}", enclosure.name)?;
+        writeln!(out, "AmpMinimumLevel={}", enclosure.min_amplitude_level)?;
+        writeln!(out, "Value={}", enclosure.default_value)?;
+        writeln!(out, "Displayed=N")?;
+        writeln!(out)?;
+    }
+
+    for (id, tremulant) in sorted(&organ.tremulants) {
+        writeln!(out, "[Tremulant{}]", tremulant_ids[id])?;
+        writeln!(out, "Name={}", tremulant.name)?;
+        if tremulant.is_wave {
+            writeln!(out, "TremulantType=Wave")?;
+        } else {
+            writeln!(out, "TremulantType=Synth")?;
+            writeln!(out, "Period={}", tremulant.period)?;
+            writeln!(out, "StartRate={}", tremulant.start_rate)?;
+            writeln!(out, "StopRate={}", tremulant.stop_rate)?;
+            writeln!(out, "AmpModDepth={}", tremulant.amp_mod_depth)?;
+        }
+        writeln!(out, "Displayed=N")?;
+        writeln!(out)?;
+    }
+
+    for (id, group) in sorted(&organ.windchest_groups) {
+        let tremulants: Vec<&String> = group
+            .tremulant_ids
+            .iter()
+            .filter_map(|t| tremulant_ids.get(t))
+            .collect();
+        let enclosures: Vec<&String> = group
+            .enclosure_ids
+            .iter()
+            .filter_map(|e| enclosure_ids.get(e))
+            .collect();
+        writeln!(out, "[WindchestGroup{}]", windchest_group_ids[id])?;
+        writeln!(out, "Name={}", group.name)?;
+        writeln!(out, "NumberOfEnclosures={}", enclosures.len())?;
+        for (i, enclosure) in enclosures.iter().enumerate() {
+            writeln!(out, "Enclosure{:03}={}", i + 1, enclosure)?;
+        }
+        writeln!(out, "NumberOfTremulants={}", tremulants.len())?;
+        for (i, tremulant) in tremulants.iter().enumerate() {
+            writeln!(out, "Tremulant{:03}={}", i + 1, tremulant)?;
+        }
+        writeln!(out)?;
+    }
+
+    let pipe_ranges: HashMap<&String, (u8, usize)> = organ
+        .ranks
+        .iter()
+        .map(|(id, rank)| (id, pipe_range(rank)))
+        .collect();
+
+    for (i, stop) in stops.iter().enumerate() {
+        let (manual_first_note, key_count) = manuals
+            .iter()
+            .find(|m| m.stop_ids.contains(&stop.id_str))
+            .map(|m| (m.first_midi_note, m.key_count.max(1)))
+            .unwrap_or((36, 61));
+        let ranks: Vec<&String> = stop
+            .rank_ids
+            .iter()
+            .filter(|r| rank_ids.contains_key(*r))
+            .collect();
+        writeln!(out, "[Stop{:03}]", i + 1)?;
+        writeln!(out, "Name={}", stop.name)?;
+        writeln!(out, "FirstAccessiblePipeLogicalKeyNumber=1")?;
+        writeln!(out, "NumberOfAccessiblePipes={}", key_count)?;
+        writeln!(out, "DefaultToEngaged=N")?;
+        writeln!(out, "Displayed=N")?;
+        writeln!(out, "NumberOfRanks={}", ranks.len())?;
+        for (r, rank) in ranks.iter().enumerate() {
+            // Align the rank's pipes with the keys that play the same MIDI notes
+            let (first_note, pipe_count) = pipe_ranges[rank];
+            let (first_pipe, first_key) = if first_note >= manual_first_note {
+                (1, (first_note - manual_first_note) as usize + 1)
+            } else {
+                ((manual_first_note - first_note) as usize + 1, 1)
+            };
+            let rank_key = format!("Rank{:03}", r + 1);
+            writeln!(out, "{}={}", rank_key, rank_ids[*rank])?;
+            writeln!(out, "{}FirstPipeNumber={}", rank_key, first_pipe)?;
+            writeln!(
+                out,
+                "{}PipeCount={}",
+                rank_key,
+                pipe_count.saturating_sub(first_pipe - 1)
+            )?;
+            writeln!(out, "{}FirstAccessibleKeyNumber={}", rank_key, first_key)?;
+        }
+        writeln!(out)?;
+    }
+
+    // Samples shared by several pipes (references, perspectives) are copied once
+    let mut exported_samples: HashMap<PathBuf, String> = HashMap::new();
+    let mut sample_count = 0;
+    for (id, rank) in sorted(&organ.ranks) {
+        let new_id = &rank_ids[id];
+        let rank_dir = format!("{}/Rank{}", organ_name, new_id);
+        let mut place = |source: &Path, file_stem: String| -> Result<String> {
+            if let Some(relative) = exported_samples.get(source) {
+                return Ok(relative.clone());
+            }
+            let extension = source.extension().and_then(|e| e.to_str()).unwrap_or("wav");
+            let relative = format!("{}/{}.{}", rank_dir, file_stem, extension);
+            place_sample(source, &output_dir.join(&relative))?;
+            exported_samples.insert(source.to_path_buf(), relative.clone());
+            sample_count += 1;
+            Ok(relative)
+        };
+
+        let windchest_group = rank
+            .windchest_group_id
+            .as_ref()
+            .and_then(|g| windchest_group_ids.get(g));
+
+        let (first_note, pipe_count) = pipe_ranges[id];
+        writeln!(out, "[Rank{}]", new_id)?;
+        writeln!(out, "Name={}", rank.name)?;
+        writeln!(out, "FirstMidiNoteNumber={}", first_note)?;
+        writeln!(out, "NumberOfLogicalPipes={}", pipe_count)?;
+        writeln!(
+            out,
+            "AmplitudeLevel={:.2}",
+            100.0 * 10f32.powf(rank.gain_db / 20.0)
+        )?;
+        if let Some(group) = windchest_group {
+            writeln!(out, "WindchestGroup={}", group)?;
+        }
+        writeln!(out, "Percussive={}", yes_no(rank.is_percussive))?;
+        writeln!(out, "TrackerDelay={}", rank.tracker_delay_ms)?;
+
+        for i in 0..pipe_count {
+            let note = first_note + i as u8;
+            let key = format!("Pipe{:03}", i + 1);
+            let Some(pipe) = rank.pipes.get(&note) else {
+                writeln!(out, "{}=DUMMY", key)?;
+                continue;
+            };
+            let Some(main_attack) = pipe.attacks.first() else {
+                writeln!(out, "{}=DUMMY", key)?;
+                continue;
+            };
+
+            let path = place(&main_attack.path, format!("{:03}-attack", note))?;
+            writeln!(out, "{}={}", key, path)?;
+            write_pipe_properties(&mut out, &key, pipe)?;
+            write_attack_criteria(&mut out, &key, main_attack)?;
+
+            let alternates = &pipe.attacks[1..];
+            if !alternates.is_empty() {
+                writeln!(out, "{}AttackCount={}", key, alternates.len())?;
+            }
+            for (a, attack) in alternates.iter().enumerate() {
+                let attack_key = format!("{}Attack{:03}", key, a + 1);
+                let path = place(&attack.path, format!("{:03}-attack{}", note, a + 1))?;
+                writeln!(out, "{}={}", attack_key, path)?;
+                write_attack_criteria(&mut out, &attack_key, attack)?;
+            }
+
+            if !pipe.releases.is_empty() {
+                // The releases are listed explicitly; don't load the ones embedded in the attack again
+                writeln!(out, "{}LoadRelease=N", key)?;
+                writeln!(out, "{}ReleaseCount={}", key, pipe.releases.len())?;
+            }
+            for (r, release) in pipe.releases.iter().enumerate() {
+                let release_key = format!("{}Release{:03}", key, r + 1);
+                let path = place(&release.path, format!("{:03}-release{}", note, r + 1))?;
+                writeln!(out, "{}={}", release_key, path)?;
+                writeln!(
+                    out,
+                    "{}MaxKeyPressTime={}",
+                    release_key, release.max_key_press_time_ms
+                )?;
+            }
+        }
+        writeln!(out)?;
+    }
+
+    out.flush()?;
+    log::info!(
+        "[Export] Wrote {:?} with {} ranks and {} samples.",
+        odf_path,
+        rank_ids.len(),
+        sample_count
+    );
+    Ok(odf_path)
+}
+
+/// Returns the first MIDI note and the number of pipes to write for a rank.
+fn pipe_range(rank: &Rank) -> (u8, usize) {
+    let lowest = rank
+        .pipes
+        .keys()
+        .min()
+        .copied()
+        .unwrap_or(rank.first_midi_note);
+    let highest = rank.pipes.keys().max().copied().unwrap_or(lowest);
+    let first_note = lowest.min(rank.first_midi_note);
+    if highest < first_note {
+        return (first_note, 0);
+    }
+    (first_note, (highest - first_note) as usize + 1)
+}
+
+fn write_pipe_properties(out: &mut impl Write, key: &str, pipe: &Pipe) -> Result<()> {
+    if pipe.gain_db != 0.0 {
+        writeln!(out, "{}Gain={}", key, pipe.gain_db)?;
+    }
+    // The pipe's own tuning is baked into the exported samples, voicing tuning is not
+    let tuning = pipe.voicing.tuning_cents;
+    if tuning != 0.0 {
+        writeln!(out, "{}PitchTuning={}", key, tuning)?;
+    }
+    Ok(())
+}
+
+fn write_attack_criteria(out: &mut impl Write, key: &str, attack: &AttackSample) -> Result<()> {
+    if attack.min_velocity > 0 {
+        writeln!(out, "{}AttackVelocity={}", key, attack.min_velocity)?;
+    }
+    if attack.max_time_since_release_ms >= 0 {
+        writeln!(
+            out,
+            "{}MaxTimeSinceLastRelease={}",
+            key, attack.max_time_since_release_ms
+        )?;
+    }
+    if let Some(is_tremulant) = attack.is_tremulant {
+        writeln!(out, "{}IsTremulant={}", key, is_tremulant as u8)?;
+    }
+    Ok(())
+}
+
+/// Links (or copies, across file systems) a sample into the export folder.
+fn place_sample(source: &Path, destination: &Path) -> Result<()> {
+    if destination.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::hard_link(source, destination).is_err() {
+        fs::copy(source, destination)
+            .with_context(|| format!("Failed to copy sample {:?}", source))?;
+    }
+    Ok(())
+}
+
+/// Assigns GrandOrgue section numbers to object IDs, keeping their numeric order.
+fn renumber<'a>(ids: impl Iterator<Item = &'a String>) -> HashMap<String, String> {
+    let mut ids: Vec<&String> = ids.collect();
+    ids.sort_by_key(|id| sort_key(id));
+    ids.into_iter()
+        .enumerate()
+        .map(|(i, id)| (id.clone(), format!("{:03}", i + 1)))
+        .collect()
+}
+
+fn sorted<T>(objects: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut objects: Vec<_> = objects.iter().collect();
+    objects.sort_by_key(|(id, _)| sort_key(id));
+    objects
+}
+
+fn sort_key(id: &str) -> (u32, String) {
+    (id.parse().unwrap_or(u32::MAX), id.to_string())
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "Y" } else { "N" }
+}
+
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
@@ -9,8 +9,12 @@ use std::path::{Path, PathBuf};
 use std::sync::{Mutex, mpsc};
 
 use crate::organ::{
-    ConversionTask, Organ, Pipe, Rank, ReleaseSample, Stop, Tremulant, WindchestGroup,
+    AttackSample, ConversionTask, Coupler, CouplerKind, Enclosure, Manual, Organ, Panel,
+    PanelControl, PanelElement, PanelImage, Pipe, Rank, ReleaseSample, Stop, Tremulant,
+    WindchestGroup,
 };
+use crate::organ_check::{CheckReport, IssueKind, check_duplicate_ids};
+use crate::stereo::pan_from_name;
 use crate::wav_converter;
 
 trait NonEmpty: Sized {
@@ -356,7 +360,7 @@ pub fn load_grandorgue_zip(
     };
 
     // Call common loader
-    load_grandorgue_common(
+    let organ = load_grandorgue_common(
         &organ_name,
         &definition_content,
         extracted_source_path,
@@ -366,7 +370,306 @@ pub fn load_grandorgue_zip(
         target_sample_rate,
         progress_tx,
         zip_provisioner,
-    )
+    )?;
+
+    extract_panel_images(zip_path, &organ)?;
+    Ok(organ)
+}
+
+/// Extracts the panel images of a zipped organ next to its extracted samples.
+fn extract_panel_images(zip_path: &Path, organ: &Organ) -> Result<()> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(zip_path)?)?;
+
+    for panel in &organ.panels {
+        let element_images = panel
+            .elements
+            .iter()
+            .flat_map(|e| e.image_on.iter().chain(e.image_off.iter()));
+        for path in panel.images.iter().map(|i| &i.path).chain(element_images) {
+            if path.exists() {
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&organ.base_path) else {
+                continue;
+            };
+            let entry_name = relative.to_string_lossy().replace('\\', "/");
+            let Ok(mut file) = archive.by_name(&entry_name) else {
+                log::warn!("Panel image not found in zip: {}", entry_name);
+                continue;
+            };
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut out = fs::File::create(path)?;
+            std::io::copy(&mut file, &mut out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Where `check_grandorgue` finds the samples: the definition's folder or the archive.
+enum CheckSource {
+    Dir(PathBuf),
+    // Archive plus its entry names in lowercase, for case-insensitive lookup
+    Zip(zip::ZipArchive<fs::File>, HashMap<String, usize>),
+}
+
+impl CheckSource {
+    fn check_sample(&mut self, report: &mut CheckReport, object: &str, relative_path: &str) {
+        let relative_path = relative_path.replace('\\', "/");
+        match self {
+            CheckSource::Dir(base_path) => {
+                report.check_sample_file(object, &base_path.join(&relative_path))
+            }
+            CheckSource::Zip(archive, entries) => {
+                let data = entries
+                    .get(&relative_path.to_lowercase())
+                    .and_then(|&index| {
+                        let mut file = archive.by_index(index).ok()?;
+                        let mut buffer = Vec::new();
+                        file.read_to_end(&mut buffer).ok()?;
+                        Some(Cursor::new(buffer))
+                    });
+                report.check_sample_entry(object, &relative_path, data);
+            }
+        }
+    }
+}
+
+/// Checks a GrandOrgue definition (`.organ` or `.orgue`) and the samples it references
+/// without converting anything. Problems are added to `report`; an error is only
+/// returned if the definition itself cannot be read.
+pub fn check_grandorgue(path: &Path, report: &mut CheckReport) -> Result<()> {
+    let is_zip = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("orgue"));
+
+    let (file_content, mut source) = if is_zip {
+        let mut archive = zip::ZipArchive::new(fs::File::open(path)?)?;
+        let mut entries = HashMap::new();
+        let mut definition_filename = None;
+        for i in 0..archive.len() {
+            let name = archive.by_index(i)?.name().to_string();
+            if definition_filename.is_none() && name.to_lowercase().ends_with(".organ") {
+                definition_filename = Some(name.clone());
+            }
+            entries.insert(name.to_lowercase(), i);
+        }
+        let definition_filename = definition_filename
+            .ok_or_else(|| anyhow!("No .organ definition file found inside {:?}", path))?;
+        let mut buffer = Vec::new();
+        archive
+            .by_name(&definition_filename)?
+            .read_to_end(&mut buffer)?;
+        (
+            resolve_definition_content(buffer)?,
+            CheckSource::Zip(archive, entries),
+        )
+    } else {
+        let base_path = path.parent().unwrap_or(Path::new(".")).to_path_buf();
+        (
+            resolve_definition_content(fs::read(path)?)?,
+            CheckSource::Dir(base_path),
+        )
+    };
+
+    // The INI parser silently merges repeated sections, so look for them in the raw text
+    let section_names = file_content.lines().filter_map(|line| {
+        let line = line.trim();
+        let name = line.strip_prefix('[')?.strip_suffix(']')?;
+        let split = name
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(name.len());
+        let (kind, number) = name.split_at(split);
+        Some(format!(
+            "{}{}",
+            kind.to_lowercase(),
+            normalize_object_id(number)
+        ))
+    });
+    let section_names: Vec<String> = section_names.collect();
+    check_duplicate_ids(report, "Section", section_names.iter().map(|s| s.as_str()));
+
+    let safe_content = file_content.replace('#', "__HASH__");
+    let conf = inistr!(&safe_content);
+    let has_section =
+        |name: &str| conf.contains_key(name) || conf.contains_key(&name.to_lowercase());
+    let count_prop = |section: &str, key: &str| -> usize {
+        get_section_prop(&conf, section, key)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+    };
+
+    let mut sorted_sections: Vec<&String> = conf.keys().collect();
+    sorted_sections.sort();
+
+    // Skeleton ranks (without pipes) to resolve REF: targets against
+    let mut ranks_map: HashMap<String, Rank> = HashMap::new();
+    let mut rank_sections = Vec::new();
+    for section_name in &sorted_sections {
+        let section_lower = section_name.to_lowercase();
+        let is_explicit_rank = section_lower.starts_with("rank");
+        let is_stop_as_rank = section_lower.starts_with("stop")
+            && get_section_prop(&conf, section_name, "Pipe001").is_some();
+        if !is_explicit_rank && !is_stop_as_rank {
+            continue;
+        }
+        let id_str = section_name
+            .trim_start_matches(if is_explicit_rank { "rank" } else { "stop" })
+            .trim_start_matches(if is_explicit_rank { "Rank" } else { "Stop" })
+            .to_string();
+        let first_midi_note =
+            get_section_prop(&conf, section_name, "FirstAccessiblePipeLogicalKeyNumber")
+                .and_then(|v| v.parse::<u8>().ok())
+                .unwrap_or(1)
+                .max(1)
+                - 1
+                + 36;
+        ranks_map.insert(
+            id_str.clone(),
+            Rank {
+                name: get_section_prop(&conf, section_name, "Name").unwrap_or_default(),
+                id_str: id_str.clone(),
+                division_id: String::new(),
+                first_midi_note,
+                pipe_count: count_prop(section_name, "NumberOfLogicalPipes"),
+                gain_db: 0.0,
+                tracker_delay_ms: 0,
+                windchest_group_id: None,
+                pipes: HashMap::new(),
+                is_percussive: false,
+            },
+        );
+        rank_sections.push((section_name.to_string(), id_str));
+    }
+
+    for (section_name, id_str) in &rank_sections {
+        let pipe_count = ranks_map[id_str].pipe_count;
+        let mut defined_pipes = 0;
+
+        for i in 1..=pipe_count {
+            let pipe_key = format!("Pipe{:03}", i);
+            let object = format!("{} {}", section_name, pipe_key);
+            let Some(attack) = get_section_prop(&conf, section_name, &pipe_key) else {
+                continue;
+            };
+            defined_pipes += 1;
+
+            if attack.starts_with("REF:") {
+                let target = locate_pipe_reference(&conf, &ranks_map, &attack).and_then(
+                    |(target_rank, target_note)| {
+                        let rank = &ranks_map[&target_rank];
+                        let pipe_index = (target_note - rank.first_midi_note) as usize + 1;
+                        let target_section = rank_sections
+                            .iter()
+                            .find(|(_, id)| *id == target_rank)
+                            .map(|(section, _)| section.as_str())
+                            .unwrap_or_default();
+                        match get_section_prop(
+                            &conf,
+                            target_section,
+                            &format!("Pipe{:03}", pipe_index),
+                        ) {
+                            Some(_) => Ok(()),
+                            None => {
+                                Err(format!("rank {} has no Pipe{:03}", target_rank, pipe_index))
+                            }
+                        }
+                    },
+                );
+                if let Err(e) = target {
+                    report.error(
+                        IssueKind::UnresolvedReference,
+                        &object,
+                        format!("'{}': {}", attack, e),
+                    );
+                }
+                continue;
+            }
+            if attack.eq_ignore_ascii_case("DUMMY") {
+                continue;
+            }
+            if !attack.contains("BlankLoop") {
+                source.check_sample(report, &object, &attack);
+            }
+
+            for (kind, count_key) in [("Attack", "AttackCount"), ("Release", "ReleaseCount")] {
+                let count = count_prop(section_name, &format!("{}{}", pipe_key, count_key));
+                for j in 1..=count {
+                    let key = format!("{}{}{:03}", pipe_key, kind, j);
+                    if let Some(sample) = get_section_prop(&conf, section_name, &key)
+                        && !sample.starts_with("REF:")
+                    {
+                        source.check_sample(report, &format!("{} {}", section_name, key), &sample);
+                    }
+                }
+            }
+        }
+
+        if defined_pipes == 0 {
+            report.warning(
+                IssueKind::EmptyRank,
+                section_name,
+                "rank has no pipes".to_string(),
+            );
+        }
+    }
+
+    for section_name in &sorted_sections {
+        let section_lower = section_name.to_lowercase();
+        if section_lower.starts_with("stop") {
+            let rank_count = count_prop(section_name, "NumberOfRanks");
+            let mut rank_ids = Vec::new();
+            for i in 1..=rank_count {
+                let key = format!("Rank{:03}", i);
+                if let Some(rank_id) = get_section_prop(&conf, section_name, &key) {
+                    let rank_id = normalize_object_id(&rank_id);
+                    if !has_section(&format!("Rank{}", rank_id)) {
+                        report.error(
+                            IssueKind::UnresolvedReference,
+                            &format!("{} {}", section_name, key),
+                            format!("Rank{} does not exist", rank_id),
+                        );
+                    }
+                    rank_ids.push(rank_id);
+                }
+            }
+            if rank_ids.is_empty() && get_section_prop(&conf, section_name, "Pipe001").is_none() {
+                report.warning(
+                    IssueKind::EmptyStop,
+                    section_name,
+                    "stop has no ranks or pipes".to_string(),
+                );
+            }
+        } else if section_lower.starts_with("manual") {
+            for i in 1..=count_prop(section_name, "NumberOfStops") {
+                let key = format!("Stop{:03}", i);
+                match get_section_prop(&conf, section_name, &key) {
+                    Some(stop_id) => {
+                        let stop_id = normalize_object_id(&stop_id);
+                        if !has_section(&format!("Stop{}", stop_id)) {
+                            report.error(
+                                IssueKind::UnresolvedReference,
+                                &format!("{} {}", section_name, key),
+                                format!("Stop{} does not exist", stop_id),
+                            );
+                        }
+                    }
+                    None => report.error(
+                        IssueKind::UnresolvedReference,
+                        section_name,
+                        format!(
+                            "{} is missing (NumberOfStops={})",
+                            key,
+                            count_prop(section_name, "NumberOfStops")
+                        ),
+                    ),
+                }
+            }
+        }
+    }
+
+    Ok(())
 }
 
 /// The internal logic that parses INI, calls the provisioner, and builds the struct.
@@ -446,7 +749,11 @@ where
             if let Some(attack_path_str) =
                 get_prop(&pipe_key_prefix_upper, &pipe_key_prefix_lower, "").non_empty_or(None)
             {
-                if attack_path_str.starts_with("REF:") {
+                if attack_path_str.starts_with("REF:")
+                    || attack_path_str.eq_ignore_ascii_case("DUMMY")
+                {
+                    // Referenced pipes borrow the samples of their target rank,
+                    // which are collected when that rank is scanned.
                     continue;
                 }
 
@@ -468,9 +775,33 @@ where
                         relative_path: PathBuf::from(&attack_path_str),
                         tuning_cents_int: (pitch_tuning_cents * 100.0) as i32,
                         to_16bit: convert_to_16_bit,
+                        is_release: false,
                     });
                 }
 
+                let attack_count: usize = get_prop(
+                    &format!("{}AttackCount", pipe_key_prefix_upper),
+                    &format!("{}attackcount", pipe_key_prefix_lower),
+                    "0",
+                )
+                .parse()
+                .unwrap_or(0);
+                for a_idx in 1..=attack_count {
+                    let att_key_upper = format!("{}Attack{:03}", pipe_key_prefix_upper, a_idx);
+                    let att_key_lower = format!("{}attack{:03}", pipe_key_prefix_lower, a_idx);
+                    if let Some(att_path_str) =
+                        get_prop(&att_key_upper, &att_key_lower, "").non_empty_or(None)
+                        && !att_path_str.starts_with("REF:")
+                    {
+                        conversion_tasks.insert(ConversionTask {
+                            relative_path: PathBuf::from(att_path_str.replace('\\', "/")),
+                            tuning_cents_int: (pitch_tuning_cents * 100.0) as i32,
+                            to_16bit: convert_to_16_bit,
+                            is_release: false,
+                        });
+                    }
+                }
+
                 let release_count: usize = get_prop(
                     &format!("{}ReleaseCount", pipe_key_prefix_upper),
                     &format!("{}releasecount", pipe_key_prefix_lower),
@@ -492,6 +823,7 @@ where
                             relative_path: PathBuf::from(rel_path_str.replace('\\', "/")),
                             tuning_cents_int: (pitch_tuning_cents * 100.0) as i32,
                             to_16bit: convert_to_16_bit,
+                            is_release: true,
                         });
                     }
                 }
@@ -521,6 +853,7 @@ where
     let mut ranks_map: HashMap<String, Rank> = HashMap::new();
     let mut windchest_groups_map: HashMap<String, WindchestGroup> = HashMap::new();
     let mut tremulants_map: HashMap<String, Tremulant> = HashMap::new();
+    let mut enclosures_map: HashMap<String, Enclosure> = HashMap::new();
 
     // Build Tremulants
     for (section_name, props) in conf.iter() {
@@ -551,6 +884,8 @@ where
             let amp_mod_depth: f32 = get_prop("AmpModDepth", "ampmoddepth", "0")
                 .parse()
                 .unwrap_or(0.0);
+            let is_wave =
+                get_prop("TremulantType", "tremulanttype", "Synth").eq_ignore_ascii_case("Wave");
 
             let switch_count: usize = get_prop("SwitchCount", "switchcount", "0")
                 .parse()
@@ -581,6 +916,57 @@ where
                     stop_rate,
                     amp_mod_depth,
                     switch_ids,
+                    is_wave,
+                },
+            );
+        }
+    }
+
+    // Build Enclosures
+    for (section_name, props) in conf.iter() {
+        let section_lower = section_name.to_lowercase();
+        if section_lower.starts_with("enclosure") {
+            let get_prop = |key_upper: &str, key_lower: &str, default: &str| {
+                props
+                    .get(key_upper)
+                    .or_else(|| props.get(key_lower))
+                    .and_then(|opt| opt.as_deref())
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| default.to_string())
+                    .trim()
+                    .replace("__HASH__", "#")
+                    .to_string()
+            };
+
+            let id_str = section_name
+                .trim_start_matches("enclosure")
+                .trim_start_matches("Enclosure")
+                .to_string();
+            let name = get_prop("Name", "name", "");
+            let min_amplitude_level: f32 = get_prop("AmpMinimumLevel", "ampminimumlevel", "0")
+                .parse::<f32>()
+                .unwrap_or(0.0)
+                .clamp(0.0, 100.0);
+            // Swell boxes start open unless the ODF says otherwise
+            let default_value: u8 = get_prop("Value", "value", "127")
+                .parse::<u8>()
+                .unwrap_or(127)
+                .min(127);
+
+            log::info!(
+                "Loaded Enclosure '{}' (ID: {}) with minimum level {}%.",
+                name,
+                id_str,
+                min_amplitude_level
+            );
+
+            enclosures_map.insert(
+                id_str.clone(),
+                Enclosure {
+                    id_str,
+                    name,
+                    min_amplitude_level,
+                    default_value,
                 },
             );
         }
@@ -624,25 +1010,47 @@ where
                 }
             }
 
+            let enclosure_count: usize = get_prop("NumberOfEnclosures", "numberofenclosures", "0")
+                .parse()
+                .unwrap_or(0);
+            let mut enclosure_ids = Vec::new();
+            for i in 1..=enclosure_count {
+                if let Some(enc_id) = get_prop(
+                    &format!("Enclosure{:03}", i),
+                    &format!("enclosure{:03}", i),
+                    "",
+                )
+                .non_empty_or(None)
+                {
+                    enclosure_ids.push(normalize_object_id(&enc_id));
+                }
+            }
+
             log::info!(
-                "Loaded Windchest Group '{}' (ID: {}) with {} tremulants.",
+                "Loaded Windchest Group '{}' (ID: {}) with {} tremulants and {} enclosures.",
                 name,
                 id_str,
-                tremulant_ids.len()
+                tremulant_ids.len(),
+                enclosure_ids.len()
             );
 
             windchest_groups_map.insert(
                 id_str.clone(),
                 WindchestGroup {
                     id_str,
+                    pan: pan_from_name(&name),
                     name,
                     tremulant_ids,
+                    enclosure_ids,
                 },
             );
         }
     }
 
     // Build Ranks
+    // REF: pipes are resolved once all ranks exist: (rank id, MIDI note, reference)
+    let mut pending_refs: Vec<(String, u8, String)> = Vec::new();
+
     for (section_name, props) in conf.iter() {
         let section_lower = section_name.to_lowercase();
 
@@ -707,7 +1115,9 @@ where
         let windchest_group_id =
             get_prop("WindchestGroup", "windchestgroup", "").non_empty_or(None);
 
-        let tracker_delay_ms: u32 = 0;
+        let tracker_delay_ms: u32 = get_prop("TrackerDelay", "trackerdelay", "0")
+            .parse()
+            .unwrap_or(0);
         let mut pipes = HashMap::new();
 
         for i in 1..=pipe_count {
@@ -719,6 +1129,11 @@ where
                 get_prop(&pipe_key_prefix_upper, &pipe_key_prefix_lower, "").non_empty_or(None)
             {
                 if attack_path_str.starts_with("REF:") {
+                    pending_refs.push((id_str.clone(), midi_note, attack_path_str));
+                    continue;
+                }
+                if attack_path_str.eq_ignore_ascii_case("DUMMY") {
+                    // Placeholder for a pipe without samples
                     continue;
                 }
 
@@ -754,6 +1169,90 @@ where
                     }
                 };
 
+                // Selection criteria share the same key suffixes on the main attack
+                // (PipeXXX...) and on the alternates (PipeXXXAttackYYY...)
+                let with_criteria = |path: PathBuf, key_upper: &str, key_lower: &str| {
+                    let mut attack = AttackSample::new(path);
+                    attack.min_velocity = get_prop(
+                        &format!("{}AttackVelocity", key_upper),
+                        &format!("{}attackvelocity", key_lower),
+                        "0",
+                    )
+                    .parse::<u8>()
+                    .unwrap_or(0)
+                    .min(127);
+                    attack.max_time_since_release_ms = get_prop(
+                        &format!("{}MaxTimeSinceLastRelease", key_upper),
+                        &format!("{}maxtimesincelastrelease", key_lower),
+                        "-1",
+                    )
+                    .parse()
+                    .unwrap_or(-1);
+                    attack.is_tremulant = match get_prop(
+                        &format!("{}IsTremulant", key_upper),
+                        &format!("{}istremulant", key_lower),
+                        "-1",
+                    )
+                    .as_str()
+                    {
+                        "0" => Some(false),
+                        "1" => Some(true),
+                        _ => None,
+                    };
+                    attack
+                };
+
+                let mut attacks = vec![with_criteria(
+                    final_attack_path,
+                    &pipe_key_prefix_upper,
+                    &pipe_key_prefix_lower,
+                )];
+                let attack_count: usize = get_prop(
+                    &format!("{}AttackCount", pipe_key_prefix_upper),
+                    &format!("{}attackcount", pipe_key_prefix_lower),
+                    "0",
+                )
+                .parse()
+                .unwrap_or(0);
+                for a_idx in 1..=attack_count {
+                    let att_key_upper = format!("{}Attack{:03}", pipe_key_prefix_upper, a_idx);
+                    let att_key_lower = format!("{}attack{:03}", pipe_key_prefix_lower, a_idx);
+
+                    let Some(att_path_str) =
+                        get_prop(&att_key_upper, &att_key_lower, "").non_empty_or(None)
+                    else {
+                        continue;
+                    };
+                    if att_path_str.starts_with("REF:") {
+                        continue;
+                    }
+
+                    let att_path_buf = PathBuf::from(att_path_str.replace('\\', "/"));
+                    match wav_converter::process_sample_file(
+                        &att_path_buf,
+                        &organ.base_path,
+                        &organ.cache_path,
+                        pitch_tuning_cents,
+                        convert_to_16_bit,
+                        target_sample_rate,
+                    ) {
+                        Ok(final_att_path) => {
+                            attacks.push(with_criteria(
+                                final_att_path,
+                                &att_key_upper,
+                                &att_key_lower,
+                            ));
+                        }
+                        Err(e) => {
+                            log::warn!(
+                                "GrandOrgue: Skipping attack sample {:?} due to error: {}",
+                                att_path_buf,
+                                e
+                            );
+                        }
+                    }
+                }
+
                 let release_count: usize = get_prop(
                     &format!("{}ReleaseCount", pipe_key_prefix_upper),
                     &format!("{}releasecount", pipe_key_prefix_lower),
@@ -796,14 +1295,21 @@ where
                                 )
                                 .parse()
                                 .unwrap_or(-1);
+                                let alignment = wav_converter::release_alignment(
+                                    &extracted_path,
+                                    &organ.base_path,
+                                    &organ.cache_path,
+                                    target_sample_rate,
+                                );
                                 releases.push(ReleaseSample {
                                     path: extracted_path,
                                     max_key_press_time_ms: max_time,
+                                    alignment,
                                     preloaded_bytes: None,
                                 });
                             }
                         } else {
-                            match wav_converter::process_sample_file(
+                            match wav_converter::process_release_file(
                                 &rel_path_buf,
                                 &organ.base_path,
                                 &organ.cache_path,
@@ -819,9 +1325,16 @@ where
                                     )
                                     .parse()
                                     .unwrap_or(-1);
+                                    let alignment = wav_converter::release_alignment(
+                                        &final_rel_path,
+                                        &organ.base_path,
+                                        &organ.cache_path,
+                                        target_sample_rate,
+                                    );
                                     releases.push(ReleaseSample {
                                         path: final_rel_path,
                                         max_key_press_time_ms: max_time,
+                                        alignment,
                                         preloaded_bytes: None,
                                     });
                                 }
@@ -862,9 +1375,16 @@ where
                             "Found embedded release sample for Pipe MIDI Note {}",
                             midi_note
                         );
+                        let alignment = wav_converter::release_alignment(
+                            &extracted_path,
+                            &organ.base_path,
+                            &organ.cache_path,
+                            target_sample_rate,
+                        );
                         releases.push(ReleaseSample {
                             path: extracted_path,
                             max_key_press_time_ms: -1,
+                            alignment,
                             preloaded_bytes: None,
                         });
                     }
@@ -873,11 +1393,11 @@ where
                 pipes.insert(
                     midi_note,
                     Pipe {
-                        attack_sample_path: final_attack_path,
+                        attacks,
                         gain_db: 0.0,
                         pitch_tuning_cents: 0.0,
                         releases,
-                        preloaded_bytes: None,
+                        ..Default::default()
                     },
                 );
             }
@@ -900,6 +1420,8 @@ where
         );
     }
 
+    resolve_pipe_references(&conf, &mut ranks_map, pending_refs);
+
     log::info!("Scanning for Key Action noise pairs to merge...");
 
     let mut noise_pairs: HashMap<String, (Option<String>, Option<String>)> = HashMap::new();
@@ -1029,18 +1551,781 @@ where
                         name,
                         id_str,
                         rank_ids,
+                        ..Default::default()
                     },
                 );
             }
         }
     }
 
+    // Build Manuals
+    let mut manuals_map: HashMap<String, Manual> = HashMap::new();
+    for (section_name, props) in conf.iter() {
+        let section_lower = section_name.to_lowercase();
+        if !section_lower.starts_with("manual") {
+            continue;
+        }
+
+        let get_prop = |key_upper: &str, key_lower: &str, default: &str| {
+            props
+                .get(key_upper)
+                .or_else(|| props.get(key_lower))
+                .and_then(|opt| opt.as_deref())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| default.to_string())
+                .trim()
+                .replace("__HASH__", "#")
+                .to_string()
+        };
+
+        let id_str = section_name
+            .trim_start_matches("manual")
+            .trim_start_matches("Manual")
+            .to_string();
+        let name = get_prop("Name", "name", "");
+        let first_midi_note: u8 = get_prop(
+            "FirstAccessibleKeyMIDINoteNumber",
+            "firstaccessiblekeymidinotenumber",
+            "36",
+        )
+        .parse()
+        .unwrap_or(36);
+        let key_count: usize = get_prop("NumberOfLogicalKeys", "numberoflogicalkeys", "0")
+            .parse()
+            .unwrap_or(0);
+
+        let stop_count: usize = get_prop("NumberOfStops", "numberofstops", "0")
+            .parse()
+            .unwrap_or(0);
+        let mut stop_ids = Vec::new();
+        for i in 1..=stop_count {
+            if let Some(stop_id) =
+                get_prop(&format!("Stop{:03}", i), &format!("stop{:03}", i), "").non_empty_or(None)
+            {
+                stop_ids.push(normalize_object_id(&stop_id));
+            }
+        }
+
+        let coupler_count: usize = get_prop("NumberOfCouplers", "numberofcouplers", "0")
+            .parse()
+            .unwrap_or(0);
+        let mut coupler_ids = Vec::new();
+        for i in 1..=coupler_count {
+            if let Some(coupler_id) =
+                get_prop(&format!("Coupler{:03}", i), &format!("coupler{:03}", i), "")
+                    .non_empty_or(None)
+            {
+                coupler_ids.push(normalize_object_id(&coupler_id));
+            }
+        }
+
+        log::info!(
+            "Loaded Manual '{}' (ID: {}) with {} stops and {} couplers.",
+            name,
+            id_str,
+            stop_ids.len(),
+            coupler_ids.len()
+        );
+
+        manuals_map.insert(
+            id_str.clone(),
+            Manual {
+                name,
+                id_str,
+                first_midi_note,
+                key_count,
+                stop_ids,
+                coupler_ids,
+            },
+        );
+    }
+
+    // Build Couplers
+    // The source of a coupler is the manual that lists it in its CouplerNNN entries.
+    let mut couplers_map: HashMap<String, Coupler> = HashMap::new();
+    for (section_name, props) in conf.iter() {
+        let section_lower = section_name.to_lowercase();
+        if !section_lower.starts_with("coupler") {
+            continue;
+        }
+
+        let get_prop = |key_upper: &str, key_lower: &str, default: &str| {
+            props
+                .get(key_upper)
+                .or_else(|| props.get(key_lower))
+                .and_then(|opt| opt.as_deref())
+                .map(|s| s.to_string())
+                .unwrap_or_else(|| default.to_string())
+                .trim()
+                .replace("__HASH__", "#")
+                .to_string()
+        };
+
+        let id_str = section_name
+            .trim_start_matches("coupler")
+            .trim_start_matches("Coupler")
+            .to_string();
+        let name = get_prop("Name", "name", "");
+
+        if get_prop("UnisonOff", "unisonoff", "N").eq_ignore_ascii_case("Y") {
+            log::info!("Skipping Unison Off coupler '{}' (ID: {}).", name, id_str);
+            continue;
+        }
+
+        let Some(source_manual_id) = manuals_map
+            .values()
+            .find(|m| m.coupler_ids.contains(&id_str))
+            .map(|m| m.id_str.clone())
+        else {
+            log::warn!(
+                "GrandOrgue: Coupler '{}' (ID: {}) is not assigned to any manual.",
+                name,
+                id_str
+            );
+            continue;
+        };
+
+        let destination_manual_id =
+            normalize_object_id(&get_prop("DestinationManual", "destinationmanual", ""));
+        if !manuals_map.contains_key(&destination_manual_id) {
+            log::warn!(
+                "GrandOrgue: Coupler '{}' (ID: {}) references unknown destination manual '{}'.",
+                name,
+                id_str,
+                destination_manual_id
+            );
+            continue;
+        }
+
+        let key_shift: i8 = get_prop("DestinationKeyshift", "destinationkeyshift", "0")
+            .parse()
+            .unwrap_or(0);
+        let coupler_type = get_prop("CouplerType", "couplertype", "Normal").to_lowercase();
+        let kind = match coupler_type.as_str() {
+            "bass" => CouplerKind::Bass,
+            "melody" => CouplerKind::Melody,
+            _ => CouplerKind::from_key_shift(key_shift),
+        };
+        let default_active =
+            get_prop("DefaultToEngaged", "defaulttoengaged", "N").eq_ignore_ascii_case("Y");
+
+        log::info!(
+            "Loaded Coupler '{}' (ID: {}): Manual {} -> {} ({:?}, shift {}).",
+            name,
+            id_str,
+            source_manual_id,
+            destination_manual_id,
+            kind,
+            key_shift
+        );
+
+        couplers_map.insert(
+            id_str.clone(),
+            Coupler {
+                name,
+                id_str,
+                kind,
+                source_manual_id,
+                destination_manual_id,
+                key_shift,
+                default_active,
+            },
+        );
+    }
+
+    // Build Panels
+    // Panel000 is the main console, any further panels are additional pages.
+    // Elements without an explicit position are placed on GrandOrgue's default layout.
+    let mut panels: Vec<Panel> = Vec::new();
+    for section_name in conf.keys() {
+        let Some(panel_num) = section_name
+            .to_lowercase()
+            .strip_prefix("panel")
+            .map(String::from)
+        else {
+            continue;
+        };
+        if panel_num.len() != 3 || !panel_num.chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        let panel_section = format!("Panel{}", panel_num);
+        let prop = |section: &str, key: &str| get_section_prop(&conf, section, key);
+        let coord = |section: &str, key: &str| -> Option<f32> { prop(section, key)?.parse().ok() };
+        let image_path = |file: String| base_path.join(file.replace('\\', "/"));
+
+        let name = prop(&panel_section, "Name").unwrap_or_else(|| organ_name.to_string());
+        let width =
+            prop(&panel_section, "DispScreenSizeHoriz").map_or(0.0, |v| parse_panel_size(&v, true));
+        let height =
+            prop(&panel_section, "DispScreenSizeVert").map_or(0.0, |v| parse_panel_size(&v, false));
+
+        let image_count: usize = prop(&panel_section, "NumberOfImages")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let mut images = Vec::new();
+        for i in 1..=image_count {
+            let section = format!("{}Image{:03}", panel_section, i);
+            if let Some(file) = prop(&section, "Image") {
+                images.push(PanelImage {
+                    path: image_path(file),
+                    x: coord(&section, "PositionX").unwrap_or(0.0),
+                    y: coord(&section, "PositionY").unwrap_or(0.0),
+                });
+            }
+        }
+
+        let element_count: usize = prop(&panel_section, "NumberOfGUIElements")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0);
+        let element_sections: Vec<String> = (1..=element_count)
+            .map(|i| format!("{}Element{:03}", panel_section, i))
+            .collect();
+        let layout = PanelLayout::new(&conf, &panel_section, width, height, &element_sections);
+        let mut elements = Vec::new();
+        for section in &element_sections {
+            let section = section.as_str();
+            let element_type = prop(section, "Type").unwrap_or_default().to_lowercase();
+            let control = match element_type.as_str() {
+                "stop" => resolve_manual_object(&conf, section, "Stop").map(PanelControl::Stop),
+                "coupler" => {
+                    resolve_manual_object(&conf, section, "Coupler").map(PanelControl::Coupler)
+                }
+                "tremulant" => prop(section, "Tremulant")
+                    .map(|id| PanelControl::Tremulant(normalize_object_id(&id))),
+                _ => None,
+            };
+            let Some(control) = control else {
+                continue;
+            };
+            let (default_x, default_y) = layout.element_position(&conf, section);
+            let x = coord(section, "PositionX").unwrap_or(default_x);
+            let y = coord(section, "PositionY").unwrap_or(default_y);
+            elements.push(PanelElement {
+                control,
+                x,
+                y,
+                width: coord(section, "Width").unwrap_or(0.0),
+                height: coord(section, "Height").unwrap_or(0.0),
+                image_on: prop(section, "ImageOn").map(image_path),
+                image_off: prop(section, "ImageOff").map(image_path),
+            });
+        }
+
+        log::info!(
+            "Loaded Panel '{}' (ID: {}) with {} images and {} controls.",
+            name,
+            panel_num,
+            images.len(),
+            elements.len()
+        );
+        panels.push(Panel {
+            name,
+            id_str: panel_num,
+            width,
+            height,
+            images,
+            elements,
+        });
+    }
+    panels.sort_by(|a, b| a.id_str.cmp(&b.id_str));
+
     let mut stops: Vec<Stop> = stops_map.into_values().collect();
     stops.sort_by(|a, b| a.id_str.cmp(&b.id_str));
     organ.stops = stops;
     organ.ranks = ranks_map;
     organ.windchest_groups = windchest_groups_map;
     organ.tremulants = tremulants_map;
+    organ.enclosures = enclosures_map;
+    organ.manuals = manuals_map;
+    organ.couplers = couplers_map;
+    organ.panels = panels;
 
     Ok(organ)
 }
+
+/// Parses a GrandOrgue panel dimension, given either in pixels or as a named screen size.
+fn parse_panel_size(value: &str, horizontal: bool) -> f32 {
+    let (width, height) = match value.trim().to_uppercase().as_str() {
+        "SMALL" => (800.0, 500.0),
+        "MEDIUM" => (1007.0, 663.0),
+        "MEDIUM LARGE" => (1263.0, 855.0),
+        "LARGE" => (1579.0, 1095.0),
+        other => {
+            let pixels = other.parse().unwrap_or(0.0);
+            (pixels, pixels)
+        }
+    };
+    if horizontal { width } else { height }
+}
+
+/// GrandOrgue's automatic layout of a panel, which places elements without an
+/// explicit position on a grid of drawstop columns around the keyboards.
+struct PanelLayout {
+    drawstop_cols: usize,
+    drawstops_offset: bool,
+    outer_col_offset_up: bool,
+    pair_drawstop_cols: bool,
+    button_cols: usize,
+    extra_button_rows: usize,
+    extra_drawstops_above_buttons: bool,
+    center_x: f32,
+    center_width: f32,
+    /// Size of the extra drawstop rows above the keyboards
+    jamb_top_width: f32,
+    jamb_top_height: f32,
+    /// Top of the extra drawstop and piston rows above the keyboards
+    top_rows_y: f32,
+    jamb_left_x: f32,
+    jamb_right_x: f32,
+    jamb_y: f32,
+    /// Bottom of the keyboards, above the pedal
+    keyboards_bottom: f32,
+}
+
+const DRAWSTOP_SPACING_X: f32 = 78.0;
+const DRAWSTOP_SPACING_Y: f32 = 69.0;
+const BUTTON_SPACING_X: f32 = 44.0;
+const BUTTON_SPACING_Y: f32 = 40.0;
+/// Height of a manual with its piston row
+const MANUAL_HEIGHT: f32 = 72.0;
+const PEDAL_HEIGHT: f32 = 40.0;
+const NATURAL_KEY_WIDTH: f32 = 12.0;
+
+impl PanelLayout {
+    /// Reads the display metrics of `panel_section` and measures the keyboards it shows.
+    fn new(
+        conf: &HashMap<String, HashMap<String, Option<String>>>,
+        panel_section: &str,
+        width: f32,
+        height: f32,
+        element_sections: &[String],
+    ) -> Self {
+        let count = |key: &str, default: usize| -> usize {
+            get_section_prop(conf, panel_section, key)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+        let flag = |key: &str| {
+            get_section_prop(conf, panel_section, key).is_some_and(|v| v.eq_ignore_ascii_case("Y"))
+        };
+        let (screen_width, screen_height) = if width > 0.0 && height > 0.0 {
+            (width, height)
+        } else {
+            (
+                parse_panel_size("SMALL", true),
+                parse_panel_size("SMALL", false),
+            )
+        };
+
+        let drawstop_rows = count("DispDrawstopRows", 1);
+        let drawstop_cols = count("DispDrawstopCols", 2);
+        let extra_drawstop_rows = count("DispExtraDrawstopRows", 0);
+        let extra_drawstop_cols = count("DispExtraDrawstopCols", 0);
+        let button_cols = count("DispButtonCols", 1);
+        let extra_button_rows = count("DispExtraButtonRows", 0);
+        let pair_drawstop_cols = flag("DispPairDrawstopCols");
+
+        // The keyboards shown on the panel set the width of the centre
+        let mut manual_count = 0;
+        let mut has_pedal = flag("DispExtraPedalButtonRow");
+        let mut keyboard_width: f32 = 0.0;
+        for section in element_sections {
+            if !get_section_prop(conf, section, "Type")
+                .is_some_and(|t| t.eq_ignore_ascii_case("Manual"))
+            {
+                continue;
+            }
+            let Some(manual) =
+                get_section_prop(conf, section, "Manual").and_then(|v| v.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let manual_section = format!("Manual{:03}", manual);
+            let first_key: u32 =
+                get_section_prop(conf, &manual_section, "FirstAccessibleKeyMIDINoteNumber")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(36);
+            let key_count: u32 = get_section_prop(conf, &manual_section, "NumberOfLogicalKeys")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+            let naturals = (first_key..first_key + key_count)
+                .filter(|note| ![1, 3, 6, 8, 10].contains(&(note % 12)))
+                .count();
+            keyboard_width = keyboard_width.max(naturals as f32 * NATURAL_KEY_WIDTH);
+            if manual == 0 {
+                has_pedal = true;
+            } else {
+                manual_count += 1;
+            }
+        }
+
+        let jamb_top_width = extra_drawstop_cols as f32 * DRAWSTOP_SPACING_X;
+        let jamb_top_height = extra_drawstop_rows as f32 * DRAWSTOP_SPACING_Y;
+        let piston_top_width = button_cols as f32 * BUTTON_SPACING_X;
+        let piston_top_height = extra_button_rows as f32 * BUTTON_SPACING_Y;
+        let center_width = jamb_top_width.max(piston_top_width).max(keyboard_width);
+        let center_x = ((screen_width - center_width) / 2.0).max(0.0);
+
+        let keyboards_bottom = screen_height - if has_pedal { PEDAL_HEIGHT } else { 0.0 };
+        let top_rows_y = (keyboards_bottom
+            - manual_count as f32 * MANUAL_HEIGHT
+            - jamb_top_height
+            - piston_top_height)
+            .max(0.0);
+
+        let mut jamb_width = (drawstop_cols / 2) as f32 * DRAWSTOP_SPACING_X;
+        if pair_drawstop_cols {
+            jamb_width += (drawstop_cols / 4) as f32 * 18.0 - 8.0;
+        }
+        let jamb_height = (drawstop_rows + 1) as f32 * DRAWSTOP_SPACING_Y;
+        let jamb_left_x = ((center_x - jamb_width) / 2.0).max(0.0);
+
+        Self {
+            drawstop_cols,
+            drawstops_offset: flag("DispDrawstopColsOffset"),
+            outer_col_offset_up: flag("DispDrawstopOuterColOffsetUp"),
+            pair_drawstop_cols,
+            button_cols,
+            extra_button_rows,
+            extra_drawstops_above_buttons: flag("DispExtraDrawstopRowsAboveExtraButtonRows"),
+            center_x,
+            center_width,
+            jamb_top_width,
+            jamb_top_height,
+            top_rows_y,
+            jamb_left_x,
+            jamb_right_x: jamb_left_x + center_x + center_width,
+            jamb_y: ((screen_height - jamb_height) / 2.0).max(0.0),
+            keyboards_bottom,
+        }
+    }
+
+    /// Default position of a panel element: on the drawstop grid, or on the piston
+    /// rows when it is shown as a piston.
+    fn element_position(
+        &self,
+        conf: &HashMap<String, HashMap<String, Option<String>>>,
+        section: &str,
+    ) -> (f32, f32) {
+        let number = |key: &str| -> usize {
+            get_section_prop(conf, section, key)
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1)
+        };
+        let as_piston = get_section_prop(conf, section, "DisplayAsPiston")
+            .is_some_and(|v| v.eq_ignore_ascii_case("Y"));
+        if as_piston {
+            self.button_position(number("DispButtonRow"), number("DispButtonCol"))
+        } else {
+            self.drawstop_position(number("DispDrawstopRow"), number("DispDrawstopCol"))
+        }
+    }
+
+    /// Drawstop rows 1-99 are on the jambs, half of the columns on either side of
+    /// the keyboards. Rows from 100 are the extra rows above the keyboards.
+    fn drawstop_position(&self, row: usize, col: usize) -> (f32, f32) {
+        let col = col.max(1);
+        if row > 99 {
+            let x = self.center_x
+                + (self.center_width - self.jamb_top_width) / 2.0
+                + (col - 1) as f32 * DRAWSTOP_SPACING_X
+                + 6.0;
+            let mut y = self.top_rows_y + (row - 100) as f32 * DRAWSTOP_SPACING_Y + 2.0;
+            if !self.extra_drawstops_above_buttons {
+                y += self.extra_button_rows as f32 * BUTTON_SPACING_Y;
+            }
+            return (x, y);
+        }
+
+        let row = row.max(1);
+        let half = (self.drawstop_cols / 2).max(1);
+        let (jamb_x, jamb_col) = if col <= half {
+            (self.jamb_left_x, col - 1)
+        } else {
+            (self.jamb_right_x, col - 1 - half)
+        };
+        let mut x = jamb_x + jamb_col as f32 * DRAWSTOP_SPACING_X + 6.0;
+        let mut y = self.jamb_y + (row - 1) as f32 * DRAWSTOP_SPACING_Y + 32.0;
+        if self.pair_drawstop_cols {
+            x += (jamb_col / 2) as f32 * 18.0;
+        }
+        // Alternate columns are staggered, counted from the outside of each jamb
+        let from_outside = if col <= half {
+            col
+        } else {
+            self.drawstop_cols.saturating_sub(col) + 1
+        };
+        if self.drawstops_offset && ((from_outside % 2 == 1) != self.outer_col_offset_up) {
+            y += 35.0;
+        }
+        (x, y)
+    }
+
+    /// Button rows 1-98 are the piston rows of the manuals, row 0 (or 99) is the
+    /// pedal's, and rows from 100 are the extra rows above the keyboards.
+    fn button_position(&self, row: usize, col: usize) -> (f32, f32) {
+        let x = self.center_x
+            + (self.center_width - self.button_cols as f32 * BUTTON_SPACING_X) / 2.0
+            + (col.max(1) - 1) as f32 * BUTTON_SPACING_X
+            + 6.0;
+        let y = match row {
+            100.. => {
+                let mut y = self.top_rows_y + (row - 100) as f32 * BUTTON_SPACING_Y + 5.0;
+                if self.extra_drawstops_above_buttons {
+                    y += self.jamb_top_height;
+                }
+                y
+            }
+            0 | 99 => self.keyboards_bottom - BUTTON_SPACING_Y + 5.0,
+            manual => self.keyboards_bottom - manual as f32 * MANUAL_HEIGHT + 5.0,
+        };
+        (x, y.max(0.0))
+    }
+}
+
+/// Resolves the `Manual` and `<kind>` keys of a panel element, which index into the
+/// manual's `<kind>NNN` list, to the global object ID.
+fn resolve_manual_object(
+    conf: &HashMap<String, HashMap<String, Option<String>>>,
+    section: &str,
+    kind: &str,
+) -> Option<String> {
+    let manual: u32 = get_section_prop(conf, section, "Manual")?.parse().ok()?;
+    let index: u32 = get_section_prop(conf, section, kind)?.parse().ok()?;
+    get_section_prop(
+        conf,
+        &format!("Manual{:03}", manual),
+        &format!("{}{:03}", kind, index),
+    )
+    .map(|id| normalize_object_id(&id))
+}
+
+/// Normalizes a numeric object reference (e.g. `Stop001=12`) to the zero-padded
+/// form used for section IDs ("012"). Non-numeric values are returned unchanged.
+fn normalize_object_id(value: &str) -> String {
+    match value.trim().parse::<u32>() {
+        Ok(num) => format!("{:03}", num),
+        Err(_) => value.trim().to_string(),
+    }
+}
+
+/// Parses a GrandOrgue pipe reference of the form `REF:<manual>:<stop>:<pipe>`.
+fn parse_pipe_reference(value: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = value.strip_prefix("REF:")?.split(':');
+    let manual = parts.next()?.trim().parse().ok()?;
+    let stop = parts.next()?.trim().parse().ok()?;
+    let pipe = parts.next()?.trim().parse().ok()?;
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((manual, stop, pipe))
+}
+
+/// Looks up a property in a parsed INI section, accepting both key casings.
+fn get_section_prop(
+    conf: &HashMap<String, HashMap<String, Option<String>>>,
+    section: &str,
+    key: &str,
+) -> Option<String> {
+    let props = conf
+        .get(section)
+        .or_else(|| conf.get(&section.to_lowercase()))?;
+    props
+        .get(key)
+        .or_else(|| props.get(&key.to_lowercase()))
+        .and_then(|opt| opt.as_deref())
+        .map(|s| s.trim().replace("__HASH__", "#"))
+        .and_then(|s| s.non_empty_or(None))
+}
+
+/// Whether a `[StopNNN]` section defines its own pipes instead of referencing ranks.
+fn is_stop_as_rank(conf: &HashMap<String, HashMap<String, Option<String>>>, section: &str) -> bool {
+    (get_section_prop(conf, section, "Pipe001").is_some()
+        || get_section_prop(conf, section, "NumberOfLogicalPipes").is_some())
+        && get_section_prop(conf, section, "NumberOfRanks").is_none()
+}
+
+/// Finds the rank and MIDI note targeted by a `REF:<manual>:<stop>:<pipe>` reference.
+/// The stop number is the index into the manual's `StopNNN` list, and the pipe number
+/// is the 1-based pipe index within the rank that stop plays.
+fn locate_pipe_reference(
+    conf: &HashMap<String, HashMap<String, Option<String>>>,
+    ranks_map: &HashMap<String, Rank>,
+    reference: &str,
+) -> Result<(String, u8), String> {
+    let (manual, stop, pipe) =
+        parse_pipe_reference(reference).ok_or_else(|| "malformed reference".to_string())?;
+
+    let manual_section = format!("Manual{:03}", manual);
+    let stop_num: u32 = get_section_prop(conf, &manual_section, &format!("Stop{:03}", stop))
+        .ok_or_else(|| format!("{} has no Stop{:03}", manual_section, stop))?
+        .parse()
+        .map_err(|_| format!("{} has an invalid Stop{:03}", manual_section, stop))?;
+    let stop_id = format!("{:03}", stop_num);
+
+    // Old-style ODFs define pipes directly on the stop, which then doubles as the rank.
+    // Stops and ranks are numbered independently, so a rank sharing the stop's number
+    // says nothing about which one is meant.
+    let rank_id = if is_stop_as_rank(conf, &format!("Stop{}", stop_id)) {
+        stop_id.clone()
+    } else {
+        let rank_num: u32 = get_section_prop(conf, &format!("Stop{}", stop_id), "Rank001")
+            .ok_or_else(|| format!("Stop{} has no ranks", stop_id))?
+            .parse()
+            .map_err(|_| format!("Stop{} has an invalid Rank001", stop_id))?;
+        format!("{:03}", rank_num)
+    };
+
+    let rank = ranks_map
+        .get(&rank_id)
+        .ok_or_else(|| format!("rank {} does not exist", rank_id))?;
+    if pipe == 0 || pipe as usize > rank.pipe_count {
+        return Err(format!(
+            "pipe {} is out of range for rank {} ({} pipes)",
+            pipe, rank_id, rank.pipe_count
+        ));
+    }
+
+    let midi_note = rank.first_midi_note as u32 + pipe - 1;
+    let midi_note = u8::try_from(midi_note).map_err(|_| "MIDI note out of range".to_string())?;
+    Ok((rank_id, midi_note))
+}
+
+/// Copies the pipes targeted by `REF:` entries into the ranks that reference them.
+/// References can point at other references, so resolution repeats until no more
+/// progress is made. Anything left over is reported as a load warning.
+fn resolve_pipe_references(
+    conf: &HashMap<String, HashMap<String, Option<String>>>,
+    ranks_map: &mut HashMap<String, Rank>,
+    pending_refs: Vec<(String, u8, String)>,
+) {
+    if pending_refs.is_empty() {
+        return;
+    }
+    log::info!(
+        "GrandOrgue: Resolving {} referenced pipes...",
+        pending_refs.len()
+    );
+
+    let mut unresolved = Vec::new();
+    for (rank_id, midi_note, reference) in pending_refs {
+        match locate_pipe_reference(conf, ranks_map, &reference) {
+            Ok(target) => unresolved.push((rank_id, midi_note, reference, target)),
+            Err(e) => log::warn!(
+                "GrandOrgue: Unresolved pipe reference '{}' in rank {} (note {}): {}",
+                reference,
+                rank_id,
+                midi_note,
+                e
+            ),
+        }
+    }
+
+    let mut resolved_count = 0;
+    loop {
+        let before = unresolved.len();
+        let mut remaining = Vec::new();
+
+        for (rank_id, midi_note, reference, (target_rank, target_note)) in unresolved {
+            let target_pipe = ranks_map
+                .get(&target_rank)
+                .and_then(|rank| rank.pipes.get(&target_note))
+                .cloned();
+
+            match (target_pipe, ranks_map.get_mut(&rank_id)) {
+                (Some(pipe), Some(rank)) => {
+                    rank.pipes.insert(midi_note, pipe);
+                    resolved_count += 1;
+                }
+                _ => remaining.push((rank_id, midi_note, reference, (target_rank, target_note))),
+            }
+        }
+
+        unresolved = remaining;
+        if unresolved.is_empty() || unresolved.len() == before {
+            break;
+        }
+    }
+
+    for (rank_id, midi_note, reference, (target_rank, target_note)) in &unresolved {
+        log::warn!(
+            "GrandOrgue: Unresolved pipe reference '{}' in rank {} (note {}): rank {} has no pipe for note {}",
+            reference,
+            rank_id,
+            midi_note,
+            target_rank,
+            target_note
+        );
+    }
+
+    log::info!(
+        "GrandOrgue: Resolved {} referenced pipes ({} unresolved).",
+        resolved_count,
+        unresolved.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn section(props: &[(&str, &str)]) -> HashMap<String, Option<String>> {
+        props
+            .iter()
+            .map(|(k, v)| (k.to_string(), Some(v.to_string())))
+            .collect()
+    }
+
+    fn rank(id: &str, first_midi_note: u8, pipe_count: usize) -> Rank {
+        Rank {
+            name: String::new(),
+            id_str: id.to_string(),
+            division_id: String::new(),
+            first_midi_note,
+            pipe_count,
+            gain_db: 0.0,
+            tracker_delay_ms: 0,
+            windchest_group_id: None,
+            pipes: HashMap::new(),
+            is_percussive: false,
+        }
+    }
+
+    #[test]
+    fn reference_uses_stop_rank_when_numbers_overlap() {
+        let conf: HashMap<String, HashMap<String, Option<String>>> = HashMap::from([
+            ("Manual001".to_string(), section(&[("Stop003", "3")])),
+            (
+                "Stop003".to_string(),
+                section(&[("NumberOfRanks", "1"), ("Rank001", "7")]),
+            ),
+        ]);
+        let ranks_map = HashMap::from([
+            ("003".to_string(), rank("003", 36, 61)),
+            ("007".to_string(), rank("007", 48, 61)),
+        ]);
+
+        assert_eq!(
+            locate_pipe_reference(&conf, &ranks_map, "REF:1:3:5"),
+            Ok(("007".to_string(), 52))
+        );
+    }
+
+    #[test]
+    fn reference_uses_stop_defining_its_own_pipes() {
+        let conf: HashMap<String, HashMap<String, Option<String>>> = HashMap::from([
+            ("Manual001".to_string(), section(&[("Stop001", "3")])),
+            (
+                "Stop003".to_string(),
+                section(&[("NumberOfLogicalPipes", "61"), ("Pipe001", "p/36.wav")]),
+            ),
+        ]);
+        let ranks_map = HashMap::from([("003".to_string(), rank("003", 36, 61))]);
+
+        assert_eq!(
+            locate_pipe_reference(&conf, &ranks_map, "REF:1:1:5"),
+            Ok(("003".to_string(), 40))
+        );
+    }
+}
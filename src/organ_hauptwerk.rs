@@ -10,7 +10,14 @@ use std::io::BufReader;
 use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 
-use crate::organ::{ConversionTask, Organ, Pipe, Rank, ReleaseSample, Stop};
+use crate::config::{PipeLayerInfo, PipeLayerMix};
+use crate::organ::{
+    AttackSample, ConversionTask, Coupler, CouplerKind, Enclosure, Manual, Organ, Panel,
+    PanelControl, PanelElement, PanelImage, Pipe, Rank, ReleaseSample, Stop, Tremulant,
+    WindchestGroup,
+};
+use crate::organ_check::{CheckReport, IssueKind, check_duplicate_ids};
+use crate::stereo::pan_from_name;
 use crate::wav_converter;
 
 // XML Helper Definitions
@@ -24,6 +31,15 @@ fn default_i64() -> i64 {
 fn default_u8() -> u8 {
     0
 }
+fn default_u32_one() -> u32 {
+    1
+}
+fn default_engaged_index() -> u32 {
+    2
+}
+fn default_max_velocity() -> u8 {
+    127
+}
 
 #[derive(Debug, Deserialize, PartialEq)]
 struct XmlV7Object {
@@ -62,6 +78,8 @@ struct XmlStop {
     name: String,
     #[serde(rename = "DivisionID", default = "default_string")]
     division_id: String,
+    #[serde(rename = "ControllingSwitchID", default = "default_string")]
+    controlling_switch_id: String,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -80,6 +98,9 @@ struct XmlRank {
     name: String,
     #[serde(rename = "DivisionID", default = "default_string")]
     division_id: String,
+    /// Delay of the key action between key press and pipe speech (ms)
+    #[serde(rename = "TrackerDelayMs", default)]
+    tracker_delay_ms: u32,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -90,6 +111,11 @@ struct XmlPipe {
     rank_id: String,
     #[serde(rename = "NormalMIDINoteNumber", default = "default_u8")]
     midi_note: u8,
+    #[serde(
+        rename = "WindSupply_SourceWindCompartmentID",
+        default = "default_string"
+    )]
+    wind_compartment_id: String,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -98,6 +124,9 @@ struct XmlLayer {
     id: String,
     #[serde(rename = "PipeID")]
     pipe_id: String,
+    /// Perspective of the recording (e.g. direct, diffuse, rear)
+    #[serde(rename = "PipeLayerNumber")]
+    layer_number: Option<u32>,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -118,6 +147,16 @@ struct XmlAttackSample {
     layer_id: String,
     #[serde(rename = "SampleID")]
     sample_id: String,
+    #[serde(
+        rename = "AttackSelCriteria_HighestVelocity",
+        default = "default_max_velocity"
+    )]
+    highest_velocity: u8,
+    #[serde(
+        rename = "AttackSelCriteria_MaxTimeSincePrevPipeCloseMs",
+        default = "default_i64"
+    )]
+    max_time_since_release_ms: i64,
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
@@ -130,6 +169,264 @@ struct XmlReleaseSample {
     sample_id: String,
 }
 
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlKeyboard {
+    #[serde(rename = "KeyboardID")]
+    id: String,
+    #[serde(rename = "Name", default = "default_string")]
+    name: String,
+    #[serde(rename = "KeyGen_MIDINoteNumberOfFirstKey")]
+    first_midi_note: Option<u8>,
+    #[serde(rename = "KeyGen_NumberOfKeys")]
+    key_count: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlKeyAction {
+    #[serde(rename = "KeyActionID", default = "default_string")]
+    id: String,
+    #[serde(rename = "Name", default = "default_string")]
+    name: String,
+    #[serde(rename = "ConditionSwitchID", default = "default_string")]
+    condition_switch_id: String,
+    #[serde(rename = "SourceKeyboardID", default = "default_string")]
+    source_keyboard_id: String,
+    #[serde(rename = "DestKeyboardID", default = "default_string")]
+    dest_keyboard_id: String,
+    #[serde(rename = "DestDivisionID", default = "default_string")]
+    dest_division_id: String,
+    #[serde(rename = "MIDINoteNumIncrementFromSourceToDest")]
+    key_shift: Option<i8>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlEnclosure {
+    #[serde(rename = "EnclosureID")]
+    id: String,
+    #[serde(rename = "Name", default = "default_string")]
+    name: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlEnclosurePipe {
+    #[serde(rename = "EnclosureID", default = "default_string")]
+    enclosure_id: String,
+    #[serde(rename = "PipeID", default = "default_string")]
+    pipe_id: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlWindCompartment {
+    #[serde(rename = "WindCompartmentID")]
+    id: String,
+    #[serde(rename = "Name", default = "default_string")]
+    name: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlTremulant {
+    #[serde(rename = "TremulantID")]
+    id: String,
+    #[serde(rename = "Name", default = "default_string")]
+    name: String,
+    #[serde(rename = "ControllingSwitchID", default = "default_string")]
+    controlling_switch_id: String,
+    #[serde(
+        rename = "WindSupply_AffectedWindCompartmentID",
+        default = "default_string"
+    )]
+    wind_compartment_id: String,
+    #[serde(rename = "DivisionID", default = "default_string")]
+    division_id: String,
+    /// Modulation period (ms)
+    #[serde(rename = "PeriodMs", default)]
+    period_ms: Option<f32>,
+    /// Time for the modulation to build up after switching on (ms)
+    #[serde(rename = "StartupTimeMs", default)]
+    startup_time_ms: Option<f32>,
+    /// Time for the modulation to die away after switching off (ms)
+    #[serde(rename = "ShutdownTimeMs", default)]
+    shutdown_time_ms: Option<f32>,
+    /// Amplitude modulation depth (%)
+    #[serde(rename = "AmpModDepthPercent", default)]
+    amp_mod_depth_percent: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlDisplayPage {
+    #[serde(rename = "PageID")]
+    id: String,
+    #[serde(rename = "Name", default = "default_string")]
+    name: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlImageSet {
+    #[serde(rename = "ImageSetID")]
+    id: String,
+    #[serde(rename = "InstallationPackageID", default = "default_string")]
+    installation_package_id: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlImageSetElement {
+    #[serde(rename = "ImageSetID", default = "default_string")]
+    image_set_id: String,
+    #[serde(rename = "ElementID", default = "default_u32_one")]
+    index: u32,
+    #[serde(rename = "BitmapFilename", default = "default_string")]
+    path: String,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlImageSetInstance {
+    #[serde(rename = "ImageSetInstanceID")]
+    id: String,
+    #[serde(rename = "ImageSetID", default = "default_string")]
+    image_set_id: String,
+    #[serde(rename = "DisplayPageID", default = "default_string")]
+    page_id: String,
+    #[serde(rename = "LeftXPosPixels", default)]
+    x: f32,
+    #[serde(rename = "TopYPosPixels", default)]
+    y: f32,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct XmlSwitch {
+    #[serde(rename = "SwitchID")]
+    id: String,
+    #[serde(rename = "Disp_ImageSetInstanceID", default = "default_string")]
+    image_set_instance_id: String,
+    #[serde(
+        rename = "Disp_ImageSetIndexEngaged",
+        default = "default_engaged_index"
+    )]
+    engaged_index: u32,
+    #[serde(rename = "Disp_ImageSetIndexDisengaged", default = "default_u32_one")]
+    disengaged_index: u32,
+}
+
+/// Hauptwerk does not store a closed-box level, so we use a typical swell attenuation.
+const DEFAULT_ENCLOSURE_MIN_LEVEL: f32 = 20.0;
+/// Fallbacks for tremulant parameters a definition leaves out (typical organ tremulant).
+const DEFAULT_TREMULANT_PERIOD_MS: f32 = 160.0;
+const DEFAULT_TREMULANT_AMP_MOD_DEPTH: f32 = 15.0;
+const DEFAULT_TREMULANT_RAMP_MS: f32 = 200.0;
+
+/// Converts a tremulant ramp time (ms) into the LFO's level change per second.
+fn ramp_rate(time_ms: Option<f32>) -> f32 {
+    1000.0
+        / time_ms
+            .filter(|t| *t > 0.0)
+            .unwrap_or(DEFAULT_TREMULANT_RAMP_MS)
+}
+
+/// Assigns every rank to a windchest group and returns the groups.
+/// A rank is fed by the wind compartment most of its pipes draw from. Ranks sharing
+/// the same compartment, tremulants and enclosures share one windchest group.
+fn build_windchest_groups(
+    ranks_map: &mut HashMap<String, Rank>,
+    xml_pipes: &[XmlPipe],
+    xml_wind_compartments: &[XmlWindCompartment],
+    xml_tremulants: &[XmlTremulant],
+    rank_enclosures: &HashMap<&str, Vec<String>>,
+    enclosures_map: &HashMap<String, Enclosure>,
+) -> HashMap<String, WindchestGroup> {
+    let mut rank_compartment_votes: HashMap<&str, HashMap<&str, usize>> = HashMap::new();
+    for xp in xml_pipes {
+        if !xp.wind_compartment_id.is_empty() {
+            *rank_compartment_votes
+                .entry(xp.rank_id.as_str())
+                .or_default()
+                .entry(xp.wind_compartment_id.as_str())
+                .or_default() += 1;
+        }
+    }
+    let compartment_names: HashMap<&str, &str> = xml_wind_compartments
+        .iter()
+        .map(|wc| (wc.id.as_str(), wc.name.as_str()))
+        .collect();
+
+    let mut windchest_groups_map: HashMap<String, WindchestGroup> = HashMap::new();
+    let mut group_ids: HashMap<(String, Vec<String>, Vec<String>), String> = HashMap::new();
+    for rank in ranks_map.values_mut() {
+        let compartment_id = rank_compartment_votes
+            .get(rank.id_str.as_str())
+            .and_then(|votes| votes.iter().max_by_key(|(_, count)| **count))
+            .map(|(id, _)| id.to_string())
+            .unwrap_or_default();
+
+        // Tremulants act on their wind compartment, else on their division,
+        // else on the whole organ.
+        let mut tremulant_ids: Vec<String> = xml_tremulants
+            .iter()
+            .filter(|xt| {
+                if !xt.wind_compartment_id.is_empty() {
+                    xt.wind_compartment_id == compartment_id
+                } else if !xt.division_id.is_empty() {
+                    xt.division_id == rank.division_id
+                } else {
+                    true
+                }
+            })
+            .map(|xt| xt.id.clone())
+            .collect();
+        tremulant_ids.sort();
+        let mut enclosure_ids = rank_enclosures
+            .get(rank.id_str.as_str())
+            .cloned()
+            .unwrap_or_default();
+        enclosure_ids.sort();
+
+        if compartment_id.is_empty() && tremulant_ids.is_empty() && enclosure_ids.is_empty() {
+            continue;
+        }
+
+        let key = (compartment_id, tremulant_ids, enclosure_ids);
+        let group_id = if let Some(id) = group_ids.get(&key) {
+            id.clone()
+        } else {
+            let (compartment_id, tremulant_ids, enclosure_ids) = &key;
+            let mut id = if compartment_id.is_empty() {
+                format!("group{}", windchest_groups_map.len() + 1)
+            } else {
+                compartment_id.clone()
+            };
+            if windchest_groups_map.contains_key(&id) {
+                id = format!("{}-{}", id, windchest_groups_map.len() + 1);
+            }
+            let name = compartment_names
+                .get(compartment_id.as_str())
+                .map(|n| n.to_string())
+                .or_else(|| {
+                    enclosure_ids
+                        .first()
+                        .and_then(|e| enclosures_map.get(e))
+                        .map(|e| e.name.clone())
+                })
+                .unwrap_or_else(|| id.clone());
+            let pan = compartment_names
+                .get(compartment_id.as_str())
+                .and_then(|n| pan_from_name(n));
+            windchest_groups_map.insert(
+                id.clone(),
+                WindchestGroup {
+                    name,
+                    id_str: id.clone(),
+                    tremulant_ids: tremulant_ids.clone(),
+                    enclosure_ids: enclosure_ids.clone(),
+                    pan,
+                },
+            );
+            group_ids.insert(key, id.clone());
+            id
+        };
+        rank.windchest_group_id = Some(group_id);
+    }
+    windchest_groups_map
+}
+
 /// Determine the organ root directory by checking for the existence
 /// of the 'OrganInstallationPackages' sibling directory.
 fn detect_hauptwerk_organ_root(xml_path: &Path) -> Result<PathBuf> {
@@ -183,50 +480,234 @@ fn detect_hauptwerk_organ_root(xml_path: &Path) -> Result<PathBuf> {
     ))
 }
 
-/// Loads and parses a Hauptwerk (.Organ_Hauptwerk_xml) file.
-pub fn load_hauptwerk(
-    path: &Path,
-    convert_to_16_bit: bool,
-    pre_cache: bool,
-    _original_tuning: bool,
-    target_sample_rate: u32,
-    progress_tx: &Option<mpsc::Sender<(f32, String)>>,
-) -> Result<Organ> {
-    log::info!("Loading Hauptwerk organ from: {:?}", path);
-    let organ_root_path = detect_hauptwerk_organ_root(path)?;
+fn parse_snippet<'a, T: Deserialize<'a>>(xml: &'a str) -> Result<T> {
+    let mut reader = quick_xml::reader::NsReader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    reader.config_mut().expand_empty_elements = true;
+    let mut deserializer = Deserializer::borrowing(reader);
+    T::deserialize(&mut deserializer).map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+fn read_element_raw(
+    reader: &mut Reader<BufReader<File>>,
+    start_event: &BytesStart,
+    tag_name: &[u8],
+) -> Result<String> {
+    let mut depth = 1;
+    let mut xml = String::from("<");
+    let name_str = String::from_utf8_lossy(tag_name);
+    xml.push_str(&name_str);
+
+    for attr in start_event.attributes() {
+        let attr = attr?;
+        xml.push(' ');
+        xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
+        xml.push_str("=\"");
+        xml.push_str(&String::from_utf8_lossy(&attr.value));
+        xml.push_str("\"");
+    }
+    xml.push('>');
+
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                depth += 1;
+                xml.push('<');
+                xml.push_str(&String::from_utf8_lossy(e.name().as_ref()));
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    xml.push(' ');
+                    xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
+                    xml.push_str("=\"");
+                    xml.push_str(&String::from_utf8_lossy(&attr.value));
+                    xml.push_str("\"");
+                }
+                xml.push('>');
+            }
+            Event::End(e) => {
+                depth -= 1;
+                xml.push_str("</");
+                xml.push_str(&String::from_utf8_lossy(e.name().as_ref()));
+                xml.push('>');
+                if depth == 0 {
+                    return Ok(xml);
+                }
+            }
+            Event::Empty(e) => {
+                xml.push('<');
+                xml.push_str(&String::from_utf8_lossy(e.name().as_ref()));
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    xml.push(' ');
+                    xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
+                    xml.push_str("=\"");
+                    xml.push_str(&String::from_utf8_lossy(&attr.value));
+                    xml.push_str("\"");
+                }
+                xml.push_str("/>");
+            }
+            Event::Text(e) => xml.push_str(&String::from_utf8_lossy(&e)),
+            Event::CData(e) => {
+                xml.push_str("<![CDATA[");
+                xml.push_str(&String::from_utf8_lossy(&e));
+                xml.push_str("]]>");
+            }
+            Event::Eof => return Err(anyhow!("Unexpected EOF while reading {}", name_str)),
+            _ => {}
+        }
+        buf.clear();
+    }
+}
+
+fn deserialize_empty_item<T: for<'de> Deserialize<'de>>(
+    empty_event: &BytesStart,
+    tag_name: &[u8],
+) -> Result<T> {
+    let mut xml = String::from("<");
+    let name_str = String::from_utf8_lossy(tag_name);
+    xml.push_str(&name_str);
+    for attr in empty_event.attributes() {
+        let attr = attr?;
+        xml.push(' ');
+        xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
+        xml.push_str("=\"");
+        xml.push_str(&String::from_utf8_lossy(&attr.value));
+        xml.push_str("\"");
+    }
+    xml.push_str("/>");
+    parse_snippet(&xml).map_err(|e| anyhow::anyhow!("DeError: {} | XML: {}", e, xml))
+}
 
-    let file = File::open(&path).with_context(|| format!("Failed to open {:?}", path))?;
+/// Returns the layer number of each layer: its PipeLayerNumber if present,
+/// otherwise its position among the layers of the same pipe (starting at 1).
+fn layer_numbers(xml_layers: &[XmlLayer]) -> Vec<u32> {
+    let mut layers_per_pipe: HashMap<&str, u32> = HashMap::new();
+    xml_layers
+        .iter()
+        .map(|layer| {
+            let position = layers_per_pipe.entry(layer.pipe_id.as_str()).or_insert(0);
+            *position += 1;
+            layer.layer_number.unwrap_or(*position)
+        })
+        .collect()
+}
+
+/// Lists the pipe layers (perspectives) of a Hauptwerk organ without loading any samples.
+pub fn scan_pipe_layers(path: &Path) -> Result<Vec<PipeLayerInfo>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
     let mut reader = Reader::from_reader(BufReader::new(file));
     reader.config_mut().trim_text(false);
     reader.config_mut().expand_empty_elements = false;
 
-    if let Some(tx) = progress_tx {
-        let _ = tx.send((0.0, t!("gui.progress_parse_xml").to_string()));
+    let mut xml_layers: Vec<XmlLayer> = Vec::new();
+    let mut buf = Vec::new();
+    let mut current_object_type = String::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(ref e) => {
+                let name = e.name();
+                let local = name.local_name();
+                let tag_name = local.as_ref();
+
+                match tag_name {
+                    b"ObjectList" => {
+                        if let Some(Ok(attr)) = e.attributes().find(|a| {
+                            a.as_ref()
+                                .is_ok_and(|a| a.key.local_name().as_ref() == b"ObjectType")
+                        }) {
+                            current_object_type = String::from_utf8_lossy(&attr.value).to_string();
+                        }
+                    }
+                    b"Pipe_SoundEngine01_Layer" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(l) = parse_snippet(&raw)
+                        {
+                            xml_layers.push(l);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Empty(ref e) => {
+                let name = e.name();
+                let local = name.local_name();
+                let tag_name = local.as_ref();
+
+                match tag_name {
+                    b"Pipe_SoundEngine01_Layer" => {
+                        if let Ok(l) = deserialize_empty_item::<XmlLayer>(e, tag_name) {
+                            xml_layers.push(l);
+                        }
+                    }
+                    b"o" if current_object_type == "Pipe_SoundEngine01_Layer" => {
+                        if let Ok(obj) = deserialize_empty_item::<XmlV7Object>(e, tag_name) {
+                            xml_layers.push(XmlLayer {
+                                id: obj.a.unwrap_or_default(),
+                                pipe_id: obj.b.unwrap_or_default(),
+                                layer_number: None,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
     }
 
-    let organ_name = path
-        .file_stem()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .replace(".Organ_Hauptwerk_xml", "");
-    let cache_path = Organ::get_organ_cache_dir(&organ_name)?;
+    let mut pipes_per_layer: HashMap<u32, HashSet<&str>> = HashMap::new();
+    for (layer, number) in xml_layers.iter().zip(layer_numbers(&xml_layers)) {
+        pipes_per_layer
+            .entry(number)
+            .or_default()
+            .insert(layer.pipe_id.as_str());
+    }
+    let mut layers: Vec<PipeLayerInfo> = pipes_per_layer
+        .into_iter()
+        .map(|(number, pipes)| PipeLayerInfo {
+            number,
+            pipe_count: pipes.len(),
+        })
+        .collect();
+    layers.sort_by_key(|l| l.number);
+    Ok(layers)
+}
 
-    let mut organ = Organ {
-        base_path: organ_root_path.to_path_buf(),
-        cache_path: cache_path.clone(),
-        name: organ_name.clone(),
-        sample_cache: if pre_cache {
-            Some(HashMap::new())
-        } else {
-            None
-        },
-        metadata_cache: if pre_cache {
-            Some(HashMap::new())
-        } else {
-            None
-        },
-        ..Default::default()
-    };
+/// The objects of a Hauptwerk organ definition, as read from the XML.
+struct XmlOrgan {
+    name: String,
+    stops: Vec<XmlStop>,
+    ranks: Vec<XmlRank>,
+    stop_ranks: Vec<XmlStopRank>,
+    pipes: Vec<XmlPipe>,
+    layers: Vec<XmlLayer>,
+    attack_samples: Vec<XmlAttackSample>,
+    release_samples: Vec<XmlReleaseSample>,
+    samples: Vec<XmlSample>,
+    divisions: Vec<XmlDivision>,
+    keyboards: Vec<XmlKeyboard>,
+    key_actions: Vec<XmlKeyAction>,
+    enclosures: Vec<XmlEnclosure>,
+    enclosure_pipes: Vec<XmlEnclosurePipe>,
+    wind_compartments: Vec<XmlWindCompartment>,
+    tremulants: Vec<XmlTremulant>,
+    display_pages: Vec<XmlDisplayPage>,
+    image_sets: Vec<XmlImageSet>,
+    image_set_elements: Vec<XmlImageSetElement>,
+    image_set_instances: Vec<XmlImageSetInstance>,
+    switches: Vec<XmlSwitch>,
+}
+
+/// Reads all objects of a Hauptwerk organ definition that the loader understands.
+fn parse_hauptwerk_xml(path: &Path) -> Result<XmlOrgan> {
+    let file = File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut reader = Reader::from_reader(BufReader::new(file));
+    reader.config_mut().trim_text(false);
+    reader.config_mut().expand_empty_elements = false;
 
     let mut xml_stops = Vec::new();
     let mut xml_ranks = Vec::new();
@@ -237,110 +718,22 @@ pub fn load_hauptwerk(
     let mut xml_release_samples = Vec::new();
     let mut xml_samples = Vec::new();
     let mut xml_divisions = Vec::new();
+    let mut xml_keyboards = Vec::new();
+    let mut xml_key_actions = Vec::new();
+    let mut xml_enclosures = Vec::new();
+    let mut xml_enclosure_pipes = Vec::new();
+    let mut xml_wind_compartments = Vec::new();
+    let mut xml_tremulants = Vec::new();
+    let mut xml_display_pages = Vec::new();
+    let mut xml_image_sets = Vec::new();
+    let mut xml_image_set_elements = Vec::new();
+    let mut xml_image_set_instances = Vec::new();
+    let mut xml_switches = Vec::new();
     let mut organ_defined_name = String::new();
 
     let mut buf = Vec::new();
     let mut current_object_type = String::new();
 
-    fn parse_snippet<'a, T: Deserialize<'a>>(xml: &'a str) -> Result<T> {
-        let mut reader = quick_xml::reader::NsReader::from_str(xml);
-        reader.config_mut().trim_text(true);
-        reader.config_mut().expand_empty_elements = true;
-        let mut deserializer = Deserializer::borrowing(reader);
-        T::deserialize(&mut deserializer).map_err(|e| anyhow::anyhow!("{}", e))
-    }
-
-    fn read_element_raw(
-        reader: &mut Reader<BufReader<File>>,
-        start_event: &BytesStart,
-        tag_name: &[u8],
-    ) -> Result<String> {
-        let mut depth = 1;
-        let mut xml = String::from("<");
-        let name_str = String::from_utf8_lossy(tag_name);
-        xml.push_str(&name_str);
-
-        for attr in start_event.attributes() {
-            let attr = attr?;
-            xml.push(' ');
-            xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
-            xml.push_str("=\"");
-            xml.push_str(&String::from_utf8_lossy(&attr.value));
-            xml.push_str("\"");
-        }
-        xml.push('>');
-
-        let mut buf = Vec::new();
-        loop {
-            match reader.read_event_into(&mut buf)? {
-                Event::Start(e) => {
-                    depth += 1;
-                    xml.push('<');
-                    xml.push_str(&String::from_utf8_lossy(e.name().as_ref()));
-                    for attr in e.attributes() {
-                        let attr = attr?;
-                        xml.push(' ');
-                        xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
-                        xml.push_str("=\"");
-                        xml.push_str(&String::from_utf8_lossy(&attr.value));
-                        xml.push_str("\"");
-                    }
-                    xml.push('>');
-                }
-                Event::End(e) => {
-                    depth -= 1;
-                    xml.push_str("</");
-                    xml.push_str(&String::from_utf8_lossy(e.name().as_ref()));
-                    xml.push('>');
-                    if depth == 0 {
-                        return Ok(xml);
-                    }
-                }
-                Event::Empty(e) => {
-                    xml.push('<');
-                    xml.push_str(&String::from_utf8_lossy(e.name().as_ref()));
-                    for attr in e.attributes() {
-                        let attr = attr?;
-                        xml.push(' ');
-                        xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
-                        xml.push_str("=\"");
-                        xml.push_str(&String::from_utf8_lossy(&attr.value));
-                        xml.push_str("\"");
-                    }
-                    xml.push_str("/>");
-                }
-                Event::Text(e) => xml.push_str(&String::from_utf8_lossy(&e)),
-                Event::CData(e) => {
-                    xml.push_str("<![CDATA[");
-                    xml.push_str(&String::from_utf8_lossy(&e));
-                    xml.push_str("]]>");
-                }
-                Event::Eof => return Err(anyhow!("Unexpected EOF while reading {}", name_str)),
-                _ => {}
-            }
-            buf.clear();
-        }
-    }
-
-    fn deserialize_empty_item<T: for<'de> Deserialize<'de>>(
-        empty_event: &BytesStart,
-        tag_name: &[u8],
-    ) -> Result<T> {
-        let mut xml = String::from("<");
-        let name_str = String::from_utf8_lossy(tag_name);
-        xml.push_str(&name_str);
-        for attr in empty_event.attributes() {
-            let attr = attr?;
-            xml.push(' ');
-            xml.push_str(&String::from_utf8_lossy(attr.key.as_ref()));
-            xml.push_str("=\"");
-            xml.push_str(&String::from_utf8_lossy(&attr.value));
-            xml.push_str("\"");
-        }
-        xml.push_str("/>");
-        parse_snippet(&xml).map_err(|e| anyhow::anyhow!("DeError: {} | XML: {}", e, xml))
-    }
-
     loop {
         match reader.read_event_into(&mut buf) {
             Ok(Event::Start(ref e)) => {
@@ -421,6 +814,83 @@ pub fn load_hauptwerk(
                             }
                         }
                     }
+                    b"Keyboard" if current_object_type == "Keyboard" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(k) = parse_snippet(&raw)
+                        {
+                            xml_keyboards.push(k);
+                        }
+                    }
+                    b"KeyAction" if current_object_type == "KeyAction" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(ka) = parse_snippet(&raw)
+                        {
+                            xml_key_actions.push(ka);
+                        }
+                    }
+                    b"Enclosure" if current_object_type == "Enclosure" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(enc) = parse_snippet(&raw)
+                        {
+                            xml_enclosures.push(enc);
+                        }
+                    }
+                    b"EnclosurePipe" if current_object_type == "EnclosurePipe" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(ep) = parse_snippet(&raw)
+                        {
+                            xml_enclosure_pipes.push(ep);
+                        }
+                    }
+                    b"WindCompartment" if current_object_type == "WindCompartment" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(wc) = parse_snippet(&raw)
+                        {
+                            xml_wind_compartments.push(wc);
+                        }
+                    }
+                    b"Tremulant" if current_object_type == "Tremulant" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(tr) = parse_snippet(&raw)
+                        {
+                            xml_tremulants.push(tr);
+                        }
+                    }
+                    b"DisplayPage" if current_object_type == "DisplayPage" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(dp) = parse_snippet(&raw)
+                        {
+                            xml_display_pages.push(dp);
+                        }
+                    }
+                    b"ImageSet" if current_object_type == "ImageSet" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(is) = parse_snippet(&raw)
+                        {
+                            xml_image_sets.push(is);
+                        }
+                    }
+                    b"ImageSetElement" if current_object_type == "ImageSetElement" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(ise) = parse_snippet(&raw)
+                        {
+                            xml_image_set_elements.push(ise);
+                        }
+                    }
+                    b"ImageSetInstance" if current_object_type == "ImageSetInstance" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(isi) = parse_snippet(&raw)
+                        {
+                            xml_image_set_instances.push(isi);
+                        }
+                    }
+                    b"Switch" if current_object_type == "Switch" => {
+                        if let Ok(raw) = read_element_raw(&mut reader, e, tag_name)
+                            && let Ok(sw) = parse_snippet(&raw)
+                        {
+                            xml_switches.push(sw);
+                        }
+                    }
                     b"General" | b"_General" => {
                         if let Ok(raw) = read_element_raw(&mut reader, e, tag_name) {
                             if let Ok(g) = parse_snippet::<XmlGeneral>(&raw) {
@@ -438,11 +908,13 @@ pub fn load_hauptwerk(
                                         id: obj.a.unwrap_or_default(),
                                         name: obj.b.unwrap_or_default(),
                                         division_id: obj.c.unwrap_or_default(),
+                                        controlling_switch_id: String::new(),
                                     }),
                                     "Rank" => xml_ranks.push(XmlRank {
                                         id: obj.a.unwrap_or_default(),
                                         name: obj.b.unwrap_or_default(),
                                         division_id: "".to_string(),
+                                        tracker_delay_ms: 0,
                                     }),
                                     "StopRank" => {
                                         if let (Some(sid), Some(rid)) = (obj.a, obj.d) {
@@ -461,15 +933,19 @@ pub fn load_hauptwerk(
                                             .unwrap_or("0")
                                             .parse()
                                             .unwrap_or(0),
+                                        wind_compartment_id: obj.e.unwrap_or_default(),
                                     }),
                                     "Pipe_SoundEngine01_Layer" => xml_layers.push(XmlLayer {
                                         id: obj.a.unwrap_or_default(),
                                         pipe_id: obj.b.unwrap_or_default(),
+                                        layer_number: None,
                                     }),
                                     "Pipe_SoundEngine01_AttackSample" => {
                                         xml_attack_samples.push(XmlAttackSample {
                                             layer_id: obj.b.unwrap_or_default(),
                                             sample_id: obj.c.unwrap_or_default(),
+                                            highest_velocity: 127,
+                                            max_time_since_release_ms: -1,
                                         })
                                     }
                                     "Pipe_SoundEngine01_ReleaseSample" => {
@@ -486,6 +962,44 @@ pub fn load_hauptwerk(
                                         pitch_exact_sample_pitch: None,
                                         pitch_normal_midi_note_number: None,
                                     }),
+                                    "Keyboard" => xml_keyboards.push(XmlKeyboard {
+                                        id: obj.a.unwrap_or_default(),
+                                        name: obj.b.unwrap_or_default(),
+                                        first_midi_note: obj
+                                            .c
+                                            .as_deref()
+                                            .and_then(|v| v.parse().ok()),
+                                        key_count: obj.d.as_deref().and_then(|v| v.parse().ok()),
+                                    }),
+                                    "KeyAction" => xml_key_actions.push(XmlKeyAction {
+                                        id: obj.a.unwrap_or_default(),
+                                        name: obj.b.unwrap_or_default(),
+                                        condition_switch_id: obj.f.unwrap_or_default(),
+                                        source_keyboard_id: obj.c.unwrap_or_default(),
+                                        dest_keyboard_id: obj.d.unwrap_or_default(),
+                                        dest_division_id: String::new(),
+                                        key_shift: obj.e.as_deref().and_then(|v| v.parse().ok()),
+                                    }),
+                                    "WindCompartment" => {
+                                        xml_wind_compartments.push(XmlWindCompartment {
+                                            id: obj.a.unwrap_or_default(),
+                                            name: obj.b.unwrap_or_default(),
+                                        })
+                                    }
+                                    "Tremulant" => xml_tremulants.push(XmlTremulant {
+                                        id: obj.a.unwrap_or_default(),
+                                        name: obj.b.unwrap_or_default(),
+                                        controlling_switch_id: obj.c.unwrap_or_default(),
+                                        wind_compartment_id: obj.d.unwrap_or_default(),
+                                        division_id: String::new(),
+                                        period_ms: obj.e.as_deref().and_then(|v| v.parse().ok()),
+                                        startup_time_ms: None,
+                                        shutdown_time_ms: None,
+                                        amp_mod_depth_percent: obj
+                                            .f
+                                            .as_deref()
+                                            .and_then(|v| v.parse().ok()),
+                                    }),
                                     "Division" => xml_divisions.push(XmlDivision {
                                         id: obj.a.unwrap_or_default(),
                                         name: obj.b.unwrap_or_default(),
@@ -549,6 +1063,62 @@ pub fn load_hauptwerk(
                             xml_divisions.push(d);
                         }
                     }
+                    b"Keyboard" if current_object_type == "Keyboard" => {
+                        if let Ok(k) = deserialize_empty_item::<XmlKeyboard>(e, tag_name) {
+                            xml_keyboards.push(k);
+                        }
+                    }
+                    b"KeyAction" if current_object_type == "KeyAction" => {
+                        if let Ok(ka) = deserialize_empty_item::<XmlKeyAction>(e, tag_name) {
+                            xml_key_actions.push(ka);
+                        }
+                    }
+                    b"Enclosure" if current_object_type == "Enclosure" => {
+                        if let Ok(enc) = deserialize_empty_item::<XmlEnclosure>(e, tag_name) {
+                            xml_enclosures.push(enc);
+                        }
+                    }
+                    b"EnclosurePipe" if current_object_type == "EnclosurePipe" => {
+                        if let Ok(ep) = deserialize_empty_item::<XmlEnclosurePipe>(e, tag_name) {
+                            xml_enclosure_pipes.push(ep);
+                        }
+                    }
+                    b"WindCompartment" if current_object_type == "WindCompartment" => {
+                        if let Ok(wc) = deserialize_empty_item::<XmlWindCompartment>(e, tag_name) {
+                            xml_wind_compartments.push(wc);
+                        }
+                    }
+                    b"Tremulant" if current_object_type == "Tremulant" => {
+                        if let Ok(tr) = deserialize_empty_item::<XmlTremulant>(e, tag_name) {
+                            xml_tremulants.push(tr);
+                        }
+                    }
+                    b"DisplayPage" if current_object_type == "DisplayPage" => {
+                        if let Ok(dp) = deserialize_empty_item::<XmlDisplayPage>(e, tag_name) {
+                            xml_display_pages.push(dp);
+                        }
+                    }
+                    b"ImageSet" if current_object_type == "ImageSet" => {
+                        if let Ok(is) = deserialize_empty_item::<XmlImageSet>(e, tag_name) {
+                            xml_image_sets.push(is);
+                        }
+                    }
+                    b"ImageSetElement" if current_object_type == "ImageSetElement" => {
+                        if let Ok(ise) = deserialize_empty_item::<XmlImageSetElement>(e, tag_name) {
+                            xml_image_set_elements.push(ise);
+                        }
+                    }
+                    b"ImageSetInstance" if current_object_type == "ImageSetInstance" => {
+                        if let Ok(isi) = deserialize_empty_item::<XmlImageSetInstance>(e, tag_name)
+                        {
+                            xml_image_set_instances.push(isi);
+                        }
+                    }
+                    b"Switch" if current_object_type == "Switch" => {
+                        if let Ok(sw) = deserialize_empty_item::<XmlSwitch>(e, tag_name) {
+                            xml_switches.push(sw);
+                        }
+                    }
                     b"o" => {
                         if let Ok(obj) = deserialize_empty_item::<XmlV7Object>(e, tag_name) {
                             match current_object_type.as_str() {
@@ -556,11 +1126,13 @@ pub fn load_hauptwerk(
                                     id: obj.a.unwrap_or_default(),
                                     name: obj.b.unwrap_or_default(),
                                     division_id: obj.c.unwrap_or_default(),
+                                    controlling_switch_id: String::new(),
                                 }),
                                 "Rank" => xml_ranks.push(XmlRank {
                                     id: obj.a.unwrap_or_default(),
                                     name: obj.b.unwrap_or_default(),
                                     division_id: "".to_string(),
+                                    tracker_delay_ms: 0,
                                 }),
                                 "StopRank" => {
                                     if let (Some(sid), Some(rid)) = (obj.a, obj.d) {
@@ -574,15 +1146,19 @@ pub fn load_hauptwerk(
                                     id: obj.a.unwrap_or_default(),
                                     rank_id: obj.b.unwrap_or_default(),
                                     midi_note: obj.d.as_deref().unwrap_or("0").parse().unwrap_or(0),
+                                    wind_compartment_id: obj.e.unwrap_or_default(),
                                 }),
                                 "Pipe_SoundEngine01_Layer" => xml_layers.push(XmlLayer {
                                     id: obj.a.unwrap_or_default(),
                                     pipe_id: obj.b.unwrap_or_default(),
+                                    layer_number: None,
                                 }),
                                 "Pipe_SoundEngine01_AttackSample" => {
                                     xml_attack_samples.push(XmlAttackSample {
                                         layer_id: obj.b.unwrap_or_default(),
                                         sample_id: obj.c.unwrap_or_default(),
+                                        highest_velocity: 127,
+                                        max_time_since_release_ms: -1,
                                     })
                                 }
                                 "Pipe_SoundEngine01_ReleaseSample" => {
@@ -599,6 +1175,41 @@ pub fn load_hauptwerk(
                                     pitch_exact_sample_pitch: None,
                                     pitch_normal_midi_note_number: None,
                                 }),
+                                "Keyboard" => xml_keyboards.push(XmlKeyboard {
+                                    id: obj.a.unwrap_or_default(),
+                                    name: obj.b.unwrap_or_default(),
+                                    first_midi_note: obj.c.as_deref().and_then(|v| v.parse().ok()),
+                                    key_count: obj.d.as_deref().and_then(|v| v.parse().ok()),
+                                }),
+                                "KeyAction" => xml_key_actions.push(XmlKeyAction {
+                                    id: obj.a.unwrap_or_default(),
+                                    name: obj.b.unwrap_or_default(),
+                                    condition_switch_id: obj.f.unwrap_or_default(),
+                                    source_keyboard_id: obj.c.unwrap_or_default(),
+                                    dest_keyboard_id: obj.d.unwrap_or_default(),
+                                    dest_division_id: String::new(),
+                                    key_shift: obj.e.as_deref().and_then(|v| v.parse().ok()),
+                                }),
+                                "WindCompartment" => {
+                                    xml_wind_compartments.push(XmlWindCompartment {
+                                        id: obj.a.unwrap_or_default(),
+                                        name: obj.b.unwrap_or_default(),
+                                    })
+                                }
+                                "Tremulant" => xml_tremulants.push(XmlTremulant {
+                                    id: obj.a.unwrap_or_default(),
+                                    name: obj.b.unwrap_or_default(),
+                                    controlling_switch_id: obj.c.unwrap_or_default(),
+                                    wind_compartment_id: obj.d.unwrap_or_default(),
+                                    division_id: String::new(),
+                                    period_ms: obj.e.as_deref().and_then(|v| v.parse().ok()),
+                                    startup_time_ms: None,
+                                    shutdown_time_ms: None,
+                                    amp_mod_depth_percent: obj
+                                        .f
+                                        .as_deref()
+                                        .and_then(|v| v.parse().ok()),
+                                }),
                                 "Division" => xml_divisions.push(XmlDivision {
                                     id: obj.a.unwrap_or_default(),
                                     name: obj.b.unwrap_or_default(),
@@ -617,6 +1228,226 @@ pub fn load_hauptwerk(
         buf.clear();
     }
 
+    Ok(XmlOrgan {
+        name: organ_defined_name,
+        stops: xml_stops,
+        ranks: xml_ranks,
+        stop_ranks: xml_stop_ranks,
+        pipes: xml_pipes,
+        layers: xml_layers,
+        attack_samples: xml_attack_samples,
+        release_samples: xml_release_samples,
+        samples: xml_samples,
+        divisions: xml_divisions,
+        keyboards: xml_keyboards,
+        key_actions: xml_key_actions,
+        enclosures: xml_enclosures,
+        enclosure_pipes: xml_enclosure_pipes,
+        wind_compartments: xml_wind_compartments,
+        tremulants: xml_tremulants,
+        display_pages: xml_display_pages,
+        image_sets: xml_image_sets,
+        image_set_elements: xml_image_set_elements,
+        image_set_instances: xml_image_set_instances,
+        switches: xml_switches,
+    })
+}
+
+/// Checks a Hauptwerk organ definition and the samples it references without loading it.
+/// Problems are added to `report`; an error is only returned if the XML cannot be read.
+pub fn check_hauptwerk(path: &Path, report: &mut CheckReport) -> Result<()> {
+    let organ_root_path = detect_hauptwerk_organ_root(path)?;
+    let xml = parse_hauptwerk_xml(path)?;
+
+    check_duplicate_ids(report, "Stop", xml.stops.iter().map(|s| s.id.as_str()));
+    check_duplicate_ids(report, "Rank", xml.ranks.iter().map(|r| r.id.as_str()));
+    check_duplicate_ids(report, "Pipe", xml.pipes.iter().map(|p| p.id.as_str()));
+    check_duplicate_ids(report, "Layer", xml.layers.iter().map(|l| l.id.as_str()));
+    check_duplicate_ids(report, "Sample", xml.samples.iter().map(|s| s.id.as_str()));
+    check_duplicate_ids(
+        report,
+        "Keyboard",
+        xml.keyboards.iter().map(|k| k.id.as_str()),
+    );
+    check_duplicate_ids(report, "Switch", xml.switches.iter().map(|s| s.id.as_str()));
+
+    let stop_ids: HashSet<&str> = xml.stops.iter().map(|s| s.id.as_str()).collect();
+    let rank_ids: HashSet<&str> = xml.ranks.iter().map(|r| r.id.as_str()).collect();
+    let pipe_ids: HashSet<&str> = xml.pipes.iter().map(|p| p.id.as_str()).collect();
+    let sample_map: HashMap<&str, &XmlSample> =
+        xml.samples.iter().map(|s| (s.id.as_str(), s)).collect();
+
+    for stop_rank in &xml.stop_ranks {
+        let object = format!("StopRank {}/{}", stop_rank.stop_id, stop_rank.rank_id);
+        if !stop_ids.contains(stop_rank.stop_id.as_str()) {
+            report.error(
+                IssueKind::UnresolvedReference,
+                &object,
+                format!("stop {} does not exist", stop_rank.stop_id),
+            );
+        }
+        if !rank_ids.contains(stop_rank.rank_id.as_str()) {
+            report.error(
+                IssueKind::UnresolvedReference,
+                &object,
+                format!("rank {} does not exist", stop_rank.rank_id),
+            );
+        }
+    }
+
+    for pipe in &xml.pipes {
+        if !rank_ids.contains(pipe.rank_id.as_str()) {
+            report.error(
+                IssueKind::UnresolvedReference,
+                &format!("Pipe {}", pipe.id),
+                format!("rank {} does not exist", pipe.rank_id),
+            );
+        }
+    }
+
+    let layer_pipes: HashMap<&str, &str> = xml
+        .layers
+        .iter()
+        .map(|l| (l.id.as_str(), l.pipe_id.as_str()))
+        .collect();
+    for layer in &xml.layers {
+        if !pipe_ids.contains(layer.pipe_id.as_str()) {
+            report.error(
+                IssueKind::UnresolvedReference,
+                &format!("Layer {}", layer.id),
+                format!("pipe {} does not exist", layer.pipe_id),
+            );
+        }
+    }
+
+    let sample_links = xml
+        .attack_samples
+        .iter()
+        .map(|a| ("AttackSample", &a.layer_id, &a.sample_id))
+        .chain(
+            xml.release_samples
+                .iter()
+                .map(|r| ("ReleaseSample", &r.layer_id, &r.sample_id)),
+        );
+    for (kind, layer_id, sample_id) in sample_links {
+        let object = format!("{} {}/{}", kind, layer_id, sample_id);
+        if !layer_pipes.contains_key(layer_id.as_str()) {
+            report.error(
+                IssueKind::UnresolvedReference,
+                &object,
+                format!("layer {} does not exist", layer_id),
+            );
+        }
+        let Some(sample) = sample_map.get(sample_id.as_str()) else {
+            report.error(
+                IssueKind::UnresolvedReference,
+                &object,
+                format!("sample {} does not exist", sample_id),
+            );
+            continue;
+        };
+        let sample_path = organ_root_path.join(format!(
+            "OrganInstallationPackages/{:0>6}/{}",
+            sample.installation_package_id,
+            sample.path.replace('\\', "/")
+        ));
+        report.check_sample_file(&format!("Sample {}", sample.id), &sample_path);
+    }
+
+    let ranks_with_pipes: HashSet<&str> = xml.pipes.iter().map(|p| p.rank_id.as_str()).collect();
+    for rank in &xml.ranks {
+        if !ranks_with_pipes.contains(rank.id.as_str()) {
+            report.warning(
+                IssueKind::EmptyRank,
+                &format!("Rank {} ({})", rank.id, rank.name),
+                "rank has no pipes".to_string(),
+            );
+        }
+    }
+
+    let stops_with_ranks: HashSet<&str> = xml
+        .stop_ranks
+        .iter()
+        .map(|sr| sr.stop_id.as_str())
+        .collect();
+    for stop in &xml.stops {
+        if !stops_with_ranks.contains(stop.id.as_str()) {
+            report.warning(
+                IssueKind::EmptyStop,
+                &format!("Stop {} ({})", stop.id, stop.name),
+                "stop has no ranks".to_string(),
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads and parses a Hauptwerk (.Organ_Hauptwerk_xml) file.
+pub fn load_hauptwerk(
+    path: &Path,
+    convert_to_16_bit: bool,
+    pre_cache: bool,
+    _original_tuning: bool,
+    target_sample_rate: u32,
+    pipe_layers: &[PipeLayerMix],
+    progress_tx: &Option<mpsc::Sender<(f32, String)>>,
+) -> Result<Organ> {
+    log::info!("Loading Hauptwerk organ from: {:?}", path);
+    let organ_root_path = detect_hauptwerk_organ_root(path)?;
+
+    if let Some(tx) = progress_tx {
+        let _ = tx.send((0.0, t!("gui.progress_parse_xml").to_string()));
+    }
+
+    let organ_name = path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace(".Organ_Hauptwerk_xml", "");
+    let cache_path = Organ::get_organ_cache_dir(&organ_name)?;
+
+    let mut organ = Organ {
+        base_path: organ_root_path.to_path_buf(),
+        cache_path: cache_path.clone(),
+        name: organ_name.clone(),
+        sample_cache: if pre_cache {
+            Some(HashMap::new())
+        } else {
+            None
+        },
+        metadata_cache: if pre_cache {
+            Some(HashMap::new())
+        } else {
+            None
+        },
+        ..Default::default()
+    };
+
+    let XmlOrgan {
+        name: organ_defined_name,
+        stops: xml_stops,
+        ranks: xml_ranks,
+        stop_ranks: xml_stop_ranks,
+        pipes: xml_pipes,
+        layers: xml_layers,
+        attack_samples: xml_attack_samples,
+        release_samples: xml_release_samples,
+        samples: xml_samples,
+        divisions: xml_divisions,
+        keyboards: xml_keyboards,
+        key_actions: xml_key_actions,
+        enclosures: xml_enclosures,
+        enclosure_pipes: xml_enclosure_pipes,
+        wind_compartments: xml_wind_compartments,
+        tremulants: xml_tremulants,
+        display_pages: xml_display_pages,
+        image_sets: xml_image_sets,
+        image_set_elements: xml_image_set_elements,
+        image_set_instances: xml_image_set_instances,
+        switches: xml_switches,
+    } = parse_hauptwerk_xml(path)?;
+
     println!(
         "Loaded: {} Stops, {} Ranks, {} StopRanks, {} Pipes",
         xml_stops.len(),
@@ -631,8 +1462,8 @@ pub fn load_hauptwerk(
 
     // Build Division Map (ID -> Name)
     let mut division_name_map: HashMap<String, String> = HashMap::new();
-    for div in xml_divisions {
-        division_name_map.insert(div.id, div.name);
+    for div in &xml_divisions {
+        division_name_map.insert(div.id.clone(), div.name.clone());
     }
 
     let get_division_prefix = |div_id: &str| -> String {
@@ -684,7 +1515,7 @@ pub fn load_hauptwerk(
                 pipes: HashMap::new(),
                 first_midi_note: 0,
                 gain_db: 0.0,
-                tracker_delay_ms: 0,
+                tracker_delay_ms: xr.tracker_delay_ms,
                 windchest_group_id: None,
                 is_percussive: false,
             },
@@ -697,10 +1528,14 @@ pub fn load_hauptwerk(
         .filter(|s| !s.path.is_empty())
         .map(|s| (s.id.clone(), s))
         .collect();
-    let attack_map: HashMap<String, &XmlAttackSample> = xml_attack_samples
-        .iter()
-        .map(|a| (a.layer_id.clone(), a))
-        .collect();
+    // The first attack of a layer is its main attack, any others are alternates
+    let mut attack_map: HashMap<String, Vec<&XmlAttackSample>> = HashMap::new();
+    for att in &xml_attack_samples {
+        attack_map
+            .entry(att.layer_id.clone())
+            .or_default()
+            .push(att);
+    }
     let mut release_map: HashMap<String, Vec<&XmlReleaseSample>> = HashMap::new();
     for rel in &xml_release_samples {
         release_map
@@ -709,25 +1544,54 @@ pub fn load_hauptwerk(
             .push(rel);
     }
 
+    // Without a layer selection only the first layer of each pipe is loaded. Otherwise the
+    // lowest selected layer fills the rank itself and every further selected layer is
+    // collected into a companion rank, each at its own mix level.
+    let layer_numbers = layer_numbers(&xml_layers);
+    let available_layers: HashSet<u32> = layer_numbers.iter().copied().collect();
+    let selected_layers: Vec<&PipeLayerMix> = pipe_layers
+        .iter()
+        .filter(|mix| available_layers.contains(&mix.layer) && mix.level > 0.0)
+        .collect();
+    if selected_layers.len() < pipe_layers.len() {
+        log::warn!(
+            "Ignoring {} pipe layer(s) that are muted or missing from this organ.",
+            pipe_layers.len() - selected_layers.len()
+        );
+    }
+    let main_layer = selected_layers.iter().map(|mix| mix.layer).min();
+    // Returns None if the layer is not loaded, else (companion rank layer, gain in dB)
+    let layer_role = |number: u32| -> Option<(Option<u32>, f32)> {
+        if selected_layers.is_empty() {
+            return Some((None, 0.0));
+        }
+        let mix = selected_layers.iter().find(|mix| mix.layer == number)?;
+        let extra_layer = (Some(number) != main_layer).then_some(number);
+        Some((extra_layer, 20.0 * mix.level.log10()))
+    };
+
     let mut conversion_tasks: HashSet<ConversionTask> = HashSet::new();
-    let mut seen_pipes: HashSet<(String, u8)> = HashSet::new();
+    let mut seen_pipes: HashSet<(String, u8, Option<u32>)> = HashSet::new();
 
-    for layer in &xml_layers {
+    for (layer, &layer_number) in xml_layers.iter().zip(&layer_numbers) {
         let Some(pipe_info) = pipe_map.get(&layer.pipe_id) else {
             continue;
         };
         if !ranks_map.contains_key(&pipe_info.rank_id) {
             continue;
         }
+        let Some((extra_layer, _)) = layer_role(layer_number) else {
+            continue;
+        };
 
-        if seen_pipes.contains(&(pipe_info.rank_id.clone(), pipe_info.midi_note)) {
+        if !seen_pipes.insert((pipe_info.rank_id.clone(), pipe_info.midi_note, extra_layer)) {
             continue;
         }
-        seen_pipes.insert((pipe_info.rank_id.clone(), pipe_info.midi_note));
 
-        let Some(attack_link) = attack_map.get(&layer.id) else {
+        let Some(attack_links) = attack_map.get(&layer.id) else {
             continue;
         };
+        let attack_link = attack_links[0];
         let Some(attack_sample_info) = sample_map.get(&attack_link.sample_id) else {
             continue;
         };
@@ -757,8 +1621,25 @@ pub fn load_hauptwerk(
             relative_path: PathBuf::from(path_str),
             tuning_cents_int: (tuning * 100.0) as i32,
             to_16bit: convert_to_16_bit,
+            is_release: false,
         });
 
+        for alt_link in &attack_links[1..] {
+            if let Some(alt) = sample_map.get(&alt_link.sample_id) {
+                let path_str = format!(
+                    "OrganInstallationPackages/{:0>6}/{}",
+                    alt.installation_package_id,
+                    alt.path.replace('\\', "/")
+                );
+                conversion_tasks.insert(ConversionTask {
+                    relative_path: PathBuf::from(path_str),
+                    tuning_cents_int: (tuning * 100.0) as i32,
+                    to_16bit: convert_to_16_bit,
+                    is_release: false,
+                });
+            }
+        }
+
         if let Some(xml_release_links) = release_map.get(&layer.id) {
             for release_link in xml_release_links {
                 if let Some(rs) = sample_map.get(&release_link.sample_id) {
@@ -771,6 +1652,7 @@ pub fn load_hauptwerk(
                         relative_path: PathBuf::from(path_str),
                         tuning_cents_int: (tuning * 100.0) as i32,
                         to_16bit: convert_to_16_bit,
+                        is_release: true,
                     });
                 }
             }
@@ -789,7 +1671,12 @@ pub fn load_hauptwerk(
         let _ = tx.send((1.0, t!("gui.progress_assemble_organ").to_string()));
     }
 
-    for layer in &xml_layers {
+    let mut extra_layer_pipes: HashMap<(String, u32), HashMap<u8, Pipe>> = HashMap::new();
+
+    for (layer, &layer_number) in xml_layers.iter().zip(&layer_numbers) {
+        let Some((extra_layer, layer_gain_db)) = layer_role(layer_number) else {
+            continue;
+        };
         let Some(pipe_info) = pipe_map.get(&layer.pipe_id) else {
             log::warn!(
                 "Layer {} references non-existent PipeID {}",
@@ -808,14 +1695,21 @@ pub fn load_hauptwerk(
             continue;
         };
 
-        if rank.pipes.contains_key(&pipe_info.midi_note) {
+        let already_loaded = match extra_layer {
+            None => rank.pipes.contains_key(&pipe_info.midi_note),
+            Some(number) => extra_layer_pipes
+                .get(&(pipe_info.rank_id.clone(), number))
+                .is_some_and(|pipes| pipes.contains_key(&pipe_info.midi_note)),
+        };
+        if already_loaded {
             continue;
         }
 
-        let Some(attack_link) = attack_map.get(&layer.id) else {
+        let Some(attack_links) = attack_map.get(&layer.id) else {
             log::warn!("Layer {} has no attack sample link.", layer.id);
             continue;
         };
+        let attack_link = attack_links[0];
 
         let Some(attack_sample_info) = sample_map.get(&attack_link.sample_id) else {
             log::warn!(
@@ -876,6 +1770,45 @@ pub fn load_hauptwerk(
             }
         };
 
+        let mut attacks = Vec::with_capacity(attack_links.len());
+        for (idx, link) in attack_links.iter().enumerate() {
+            let path = if idx == 0 {
+                final_attack_path.clone()
+            } else {
+                let Some(alt) = sample_map.get(&link.sample_id) else {
+                    continue;
+                };
+                let alt_path_buf = PathBuf::from(format!(
+                    "OrganInstallationPackages/{:0>6}/{}",
+                    alt.installation_package_id,
+                    alt.path.replace('\\', "/")
+                ));
+                match wav_converter::process_sample_file(
+                    &alt_path_buf,
+                    &organ.base_path,
+                    &organ.cache_path,
+                    final_pitch_tuning_cents,
+                    convert_to_16_bit,
+                    target_sample_rate,
+                ) {
+                    Ok(path) => path,
+                    Err(e) => {
+                        log::warn!(
+                            "Skipping attack sample for LayerID {} due to error: {:?} - {}",
+                            layer.id,
+                            alt_path_buf,
+                            e
+                        );
+                        continue;
+                    }
+                }
+            };
+            let mut attack = AttackSample::new(path);
+            attack.max_velocity = link.highest_velocity.min(127);
+            attack.max_time_since_release_ms = link.max_time_since_release_ms;
+            attacks.push(attack);
+        }
+
         let mut releases = Vec::new();
         if let Some(xml_release_links) = release_map.get(&layer.id) {
             for release_link in xml_release_links {
@@ -900,14 +1833,21 @@ pub fn load_hauptwerk(
                             convert_to_16_bit,
                             target_sample_rate,
                         ) {
+                            let alignment = wav_converter::release_alignment(
+                                &extracted_path,
+                                &organ.base_path,
+                                &organ.cache_path,
+                                target_sample_rate,
+                            );
                             releases.push(ReleaseSample {
                                 path: extracted_path,
                                 max_key_press_time_ms: release_link.max_key_press_time_ms,
+                                alignment,
                                 preloaded_bytes: None,
                             });
                         }
                     } else {
-                        match wav_converter::process_sample_file(
+                        match wav_converter::process_release_file(
                             &rel_path_buf,
                             &organ.base_path,
                             &organ.cache_path,
@@ -916,9 +1856,16 @@ pub fn load_hauptwerk(
                             target_sample_rate,
                         ) {
                             Ok(final_rel_path) => {
+                                let alignment = wav_converter::release_alignment(
+                                    &final_rel_path,
+                                    &organ.base_path,
+                                    &organ.cache_path,
+                                    target_sample_rate,
+                                );
                                 releases.push(ReleaseSample {
                                     path: final_rel_path,
                                     max_key_press_time_ms: release_link.max_key_press_time_ms,
+                                    alignment,
                                     preloaded_bytes: None,
                                 });
                             }
@@ -952,24 +1899,39 @@ pub fn load_hauptwerk(
                 convert_to_16_bit,
                 target_sample_rate,
             ) {
+                let alignment = wav_converter::release_alignment(
+                    &extracted_path,
+                    &organ.base_path,
+                    &organ.cache_path,
+                    target_sample_rate,
+                );
                 releases.push(ReleaseSample {
                     path: extracted_path,
                     max_key_press_time_ms: -1,
+                    alignment,
                     preloaded_bytes: None,
                 });
             }
         }
 
-        rank.pipes.insert(
-            pipe_info.midi_note,
-            Pipe {
-                attack_sample_path: final_attack_path,
-                gain_db: 0.0,
-                pitch_tuning_cents: 0.0,
-                releases,
-                preloaded_bytes: None,
-            },
-        );
+        let pipe = Pipe {
+            attacks,
+            gain_db: layer_gain_db,
+            pitch_tuning_cents: 0.0,
+            releases,
+            ..Default::default()
+        };
+        match extra_layer {
+            None => {
+                rank.pipes.insert(pipe_info.midi_note, pipe);
+            }
+            Some(number) => {
+                extra_layer_pipes
+                    .entry((pipe_info.rank_id.clone(), number))
+                    .or_default()
+                    .insert(pipe_info.midi_note, pipe);
+            }
+        }
     }
 
     for rank in ranks_map.values_mut() {
@@ -1009,6 +1971,17 @@ pub fn load_hauptwerk(
             .unwrap_or(999999)
     };
 
+    let xml_stop_divisions: Vec<(String, String)> = xml_stops
+        .iter()
+        .map(|xs| (xs.id.clone(), xs.division_id.clone()))
+        .collect();
+
+    let stop_switches: Vec<(String, String)> = xml_stops
+        .iter()
+        .filter(|xs| !xs.controlling_switch_id.is_empty())
+        .map(|xs| (xs.controlling_switch_id.clone(), xs.id.clone()))
+        .collect();
+
     for xs in xml_stops {
         if xs.name.contains("Key action") || xs.name.contains("noise") || xs.name.is_empty() {
             stops_filtered += 1;
@@ -1185,16 +2158,321 @@ pub fn load_hauptwerk(
                 name: final_name,
                 id_str: xs.id,
                 rank_ids,
+                ..Default::default()
             },
         );
     }
     log::info!("--- Filtered {} stops ---", stops_filtered);
 
+    // Build Manuals and Couplers
+    // Key actions that target a division wire a keyboard to that division's stops.
+    // Key actions that target another keyboard are couplers.
+    let mut division_keyboard_map: HashMap<String, String> = HashMap::new();
+    for ka in &xml_key_actions {
+        if ka.dest_division_id.is_empty() || ka.source_keyboard_id.is_empty() {
+            continue;
+        }
+        // Prefer the permanent (unswitched) wiring if a division has several inputs
+        if ka.condition_switch_id.is_empty()
+            || !division_keyboard_map.contains_key(&ka.dest_division_id)
+        {
+            division_keyboard_map
+                .insert(ka.dest_division_id.clone(), ka.source_keyboard_id.clone());
+        }
+    }
+
+    let mut manuals_map: HashMap<String, Manual> = HashMap::new();
+    for kb in &xml_keyboards {
+        let mut stop_ids: Vec<String> = xml_stop_divisions
+            .iter()
+            .filter(|(stop_id, div_id)| {
+                stops_map.contains_key(stop_id) && division_keyboard_map.get(div_id) == Some(&kb.id)
+            })
+            .map(|(stop_id, _)| stop_id.clone())
+            .collect();
+        stop_ids.sort_by_key(|id| id.parse::<u32>().unwrap_or(0));
+
+        manuals_map.insert(
+            kb.id.clone(),
+            Manual {
+                name: kb.name.clone(),
+                id_str: kb.id.clone(),
+                first_midi_note: kb.first_midi_note.unwrap_or(36),
+                key_count: kb.key_count.unwrap_or(0),
+                stop_ids,
+                coupler_ids: Vec::new(),
+            },
+        );
+    }
+
+    let mut couplers_map: HashMap<String, Coupler> = HashMap::new();
+    for ka in &xml_key_actions {
+        if ka.dest_keyboard_id.is_empty() || ka.source_keyboard_id.is_empty() {
+            continue;
+        }
+        if !manuals_map.contains_key(&ka.dest_keyboard_id) {
+            log::warn!(
+                "Key action '{}' (ID: {}) references unknown keyboard {}",
+                ka.name,
+                ka.id,
+                ka.dest_keyboard_id
+            );
+            continue;
+        }
+        let Some(source_manual) = manuals_map.get_mut(&ka.source_keyboard_id) else {
+            log::warn!(
+                "Key action '{}' (ID: {}) references unknown keyboard {}",
+                ka.name,
+                ka.id,
+                ka.source_keyboard_id
+            );
+            continue;
+        };
+
+        let key_shift = ka.key_shift.unwrap_or(0);
+        let kind = CouplerKind::from_key_shift(key_shift);
+
+        source_manual.coupler_ids.push(ka.id.clone());
+        couplers_map.insert(
+            ka.id.clone(),
+            Coupler {
+                name: ka.name.clone(),
+                id_str: ka.id.clone(),
+                kind,
+                source_manual_id: ka.source_keyboard_id.clone(),
+                destination_manual_id: ka.dest_keyboard_id.clone(),
+                key_shift,
+                // Key actions without a switch are permanently engaged
+                default_active: ka.condition_switch_id.is_empty(),
+            },
+        );
+    }
+
+    log::info!(
+        "Loaded {} manuals and {} couplers.",
+        manuals_map.len(),
+        couplers_map.len()
+    );
+
+    // Enclosures act on individual pipes, so each rank collects the enclosures its pipes sit in
+    let pipe_rank_map: HashMap<&str, &str> = xml_pipes
+        .iter()
+        .map(|p| (p.id.as_str(), p.rank_id.as_str()))
+        .collect();
+    let mut rank_enclosures: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut enclosures_map: HashMap<String, Enclosure> = HashMap::new();
+    for xe in &xml_enclosures {
+        let enclosed_ranks: HashSet<&str> = xml_enclosure_pipes
+            .iter()
+            .filter(|ep| ep.enclosure_id == xe.id)
+            .filter_map(|ep| pipe_rank_map.get(ep.pipe_id.as_str()).copied())
+            .collect();
+        for rank_id in &enclosed_ranks {
+            rank_enclosures
+                .entry(*rank_id)
+                .or_default()
+                .push(xe.id.clone());
+        }
+        log::info!(
+            "Loaded Enclosure '{}' (ID: {}) covering {} ranks.",
+            xe.name,
+            xe.id,
+            enclosed_ranks.len()
+        );
+
+        enclosures_map.insert(
+            xe.id.clone(),
+            Enclosure {
+                name: xe.name.clone(),
+                id_str: xe.id.clone(),
+                min_amplitude_level: DEFAULT_ENCLOSURE_MIN_LEVEL,
+                default_value: 127,
+            },
+        );
+    }
+
+    // Build Tremulants
+    // These are synthetic tremulants. Tremulant sample layers aren't parsed, so sets
+    // with tremulant-recorded samples play their normal samples with the LFO.
+    let mut tremulants_map: HashMap<String, Tremulant> = HashMap::new();
+    for xt in &xml_tremulants {
+        log::info!("Loaded Tremulant '{}' (ID: {}).", xt.name, xt.id);
+        let mut switch_ids = Vec::new();
+        if !xt.controlling_switch_id.is_empty() {
+            switch_ids.push(xt.controlling_switch_id.clone());
+        }
+        tremulants_map.insert(
+            xt.id.clone(),
+            Tremulant {
+                name: xt.name.clone(),
+                id_str: xt.id.clone(),
+                period: xt
+                    .period_ms
+                    .filter(|p| *p > 0.0)
+                    .unwrap_or(DEFAULT_TREMULANT_PERIOD_MS),
+                start_rate: ramp_rate(xt.startup_time_ms),
+                stop_rate: ramp_rate(xt.shutdown_time_ms),
+                amp_mod_depth: xt
+                    .amp_mod_depth_percent
+                    .map(|d| d.clamp(0.0, 100.0))
+                    .unwrap_or(DEFAULT_TREMULANT_AMP_MOD_DEPTH),
+                switch_ids,
+                is_wave: false,
+            },
+        );
+    }
+
+    // Build Windchest Groups
+    let windchest_groups_map = build_windchest_groups(
+        &mut ranks_map,
+        &xml_pipes,
+        &xml_wind_compartments,
+        &xml_tremulants,
+        &rank_enclosures,
+        &enclosures_map,
+    );
+    log::info!(
+        "Loaded {} tremulants and {} windchest groups.",
+        tremulants_map.len(),
+        windchest_groups_map.len()
+    );
+
+    // Companion ranks of extra pipe layers share the wind and stops of their rank
+    for ((rank_id, layer_number), pipes) in extra_layer_pipes {
+        let Some(base) = ranks_map.get(&rank_id) else {
+            continue;
+        };
+        let layer_rank_id = format!("{}-L{}", rank_id, layer_number);
+        let layer_rank = Rank {
+            name: format!("{} (Layer {})", base.name, layer_number),
+            id_str: layer_rank_id.clone(),
+            division_id: base.division_id.clone(),
+            first_midi_note: pipes.keys().min().copied().unwrap_or(0),
+            pipe_count: pipes.len(),
+            gain_db: base.gain_db,
+            tracker_delay_ms: base.tracker_delay_ms,
+            windchest_group_id: base.windchest_group_id.clone(),
+            pipes,
+            is_percussive: base.is_percussive,
+        };
+        for stop in stops_map.values_mut() {
+            if stop.rank_ids.contains(&rank_id) {
+                stop.rank_ids.push(layer_rank_id.clone());
+            }
+        }
+        ranks_map.insert(layer_rank_id, layer_rank);
+    }
+
+    // Build Panels
+    // Every display page becomes a panel. Image set instances shown by a switch that
+    // drives a stop, coupler or tremulant are its controls; all others are static images.
+    let image_set_packages: HashMap<&str, &str> = xml_image_sets
+        .iter()
+        .map(|is| (is.id.as_str(), is.installation_package_id.as_str()))
+        .collect();
+    let image_set_files: HashMap<(&str, u32), PathBuf> = xml_image_set_elements
+        .iter()
+        .filter(|ise| !ise.path.is_empty())
+        .map(|ise| {
+            let package = image_set_packages
+                .get(ise.image_set_id.as_str())
+                .copied()
+                .unwrap_or_default();
+            let path = organ.base_path.join(format!(
+                "OrganInstallationPackages/{:0>6}/{}",
+                package,
+                ise.path.replace('\\', "/")
+            ));
+            ((ise.image_set_id.as_str(), ise.index), path)
+        })
+        .collect();
+
+    let mut switch_controls: HashMap<&str, PanelControl> = HashMap::new();
+    for (switch_id, stop_id) in &stop_switches {
+        if stops_map.contains_key(stop_id) {
+            switch_controls.insert(switch_id, PanelControl::Stop(stop_id.clone()));
+        }
+    }
+    for ka in &xml_key_actions {
+        if !ka.condition_switch_id.is_empty() && couplers_map.contains_key(&ka.id) {
+            switch_controls.insert(
+                &ka.condition_switch_id,
+                PanelControl::Coupler(ka.id.clone()),
+            );
+        }
+    }
+    for xt in &xml_tremulants {
+        if !xt.controlling_switch_id.is_empty() {
+            switch_controls.insert(
+                &xt.controlling_switch_id,
+                PanelControl::Tremulant(xt.id.clone()),
+            );
+        }
+    }
+    let instance_switches: HashMap<&str, &XmlSwitch> = xml_switches
+        .iter()
+        .filter(|sw| !sw.image_set_instance_id.is_empty())
+        .map(|sw| (sw.image_set_instance_id.as_str(), sw))
+        .collect();
+
+    let mut panels: Vec<Panel> = xml_display_pages
+        .iter()
+        .map(|page| Panel {
+            name: page.name.clone(),
+            id_str: page.id.clone(),
+            ..Default::default()
+        })
+        .collect();
+    for instance in &xml_image_set_instances {
+        let Some(panel) = panels.iter_mut().find(|p| p.id_str == instance.page_id) else {
+            continue;
+        };
+        let image = |index: u32| {
+            image_set_files
+                .get(&(instance.image_set_id.as_str(), index))
+                .cloned()
+        };
+        match instance_switches.get(instance.id.as_str()) {
+            Some(switch) => {
+                let Some(control) = switch_controls.get(switch.id.as_str()) else {
+                    continue;
+                };
+                panel.elements.push(PanelElement {
+                    control: control.clone(),
+                    x: instance.x,
+                    y: instance.y,
+                    width: 0.0,
+                    height: 0.0,
+                    image_on: image(switch.engaged_index),
+                    image_off: image(switch.disengaged_index),
+                });
+            }
+            None => {
+                if let Some(path) = image(1) {
+                    panel.images.push(PanelImage {
+                        path,
+                        x: instance.x,
+                        y: instance.y,
+                    });
+                }
+            }
+        }
+    }
+    panels.retain(|p| !p.images.is_empty() || !p.elements.is_empty());
+    panels.sort_by_key(|p| p.id_str.parse::<u32>().unwrap_or(0));
+    log::info!("Loaded {} display pages.", panels.len());
+
     let mut stops: Vec<Stop> = stops_map.into_values().collect();
     stops.sort_by_key(|s| s.id_str.parse::<u32>().unwrap_or(0));
 
     organ.stops = stops;
     organ.ranks = ranks_map;
+    organ.manuals = manuals_map;
+    organ.couplers = couplers_map;
+    organ.enclosures = enclosures_map;
+    organ.tremulants = tremulants_map;
+    organ.windchest_groups = windchest_groups_map;
+    organ.panels = panels;
 
     log::debug!(
         "Final maps: {} stops, {} ranks.",
@@ -1203,3 +2481,62 @@ pub fn load_hauptwerk(
     );
     Ok(organ)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMPACT_ORGAN: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<Hauptwerk FileFormat="Organ" FileFormatVersion="7.00">
+<ObjectList ObjectType="Rank"><o><a>1</a><b>Principal 8</b></o></ObjectList>
+<ObjectList ObjectType="Pipe_SoundEngine01"><o><a>10</a><b>1</b><d>36</d><e>3</e></o><o><a>11</a><b>1</b><d>37</d><e>3</e></o></ObjectList>
+<ObjectList ObjectType="WindCompartment"><o><a>3</a><b>Great wind</b></o></ObjectList>
+<ObjectList ObjectType="Tremulant"><o><a>1</a><b>Tremulant</b><c>20</c><d>3</d></o></ObjectList>
+</Hauptwerk>
+"#;
+
+    #[test]
+    fn compact_tremulant_reaches_rank_windchest_group() {
+        let path = std::env::temp_dir().join(format!(
+            "rusty-pipes-compact-{}.Organ_Hauptwerk_xml",
+            std::process::id()
+        ));
+        std::fs::write(&path, COMPACT_ORGAN).unwrap();
+        let xml = parse_hauptwerk_xml(&path);
+        std::fs::remove_file(&path).unwrap();
+        let xml = xml.unwrap();
+
+        let mut ranks_map: HashMap<String, Rank> = xml
+            .ranks
+            .iter()
+            .map(|xr| {
+                let rank = Rank {
+                    name: xr.name.clone(),
+                    id_str: xr.id.clone(),
+                    division_id: xr.division_id.clone(),
+                    first_midi_note: 36,
+                    pipe_count: 2,
+                    gain_db: 0.0,
+                    tracker_delay_ms: 0,
+                    windchest_group_id: None,
+                    pipes: HashMap::new(),
+                    is_percussive: false,
+                };
+                (xr.id.clone(), rank)
+            })
+            .collect();
+        let groups = build_windchest_groups(
+            &mut ranks_map,
+            &xml.pipes,
+            &xml.wind_compartments,
+            &xml.tremulants,
+            &HashMap::new(),
+            &HashMap::new(),
+        );
+
+        let group_id = ranks_map["1"].windchest_group_id.as_ref().unwrap();
+        assert_eq!(group_id, "3");
+        assert_eq!(groups[group_id].name, "Great wind");
+        assert_eq!(groups[group_id].tremulant_ids, vec!["1".to_string()]);
+    }
+}
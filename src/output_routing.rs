@@ -0,0 +1,235 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::config::{load_settings, save_settings};
+use crate::organ::{Organ, Rank};
+
+/// Most buses the audio thread mixes. Their buffers are allocated when the stream opens.
+pub const MAX_OUTPUT_BUSES: usize = 16;
+
+/// One stereo output of the audio device, e.g. the speaker pair of a division.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutputBus {
+    pub name: String,
+    /// Device channel of the left signal (0 = first output), the right one follows it
+    pub first_channel: usize,
+    /// Linear gain of the bus (1.0 = unchanged)
+    pub gain: f32,
+    /// Share of the bus sent to the reverb (1.0 = the full reverb mix)
+    pub reverb_send: f32,
+}
+
+impl OutputBus {
+    /// First device channel the bus plays on. Buses past the channels of the device
+    /// play on the first two.
+    pub fn output_channel(&self, device_channels: usize) -> usize {
+        if self.first_channel + 1 < device_channels {
+            self.first_channel
+        } else {
+            0
+        }
+    }
+}
+
+/// The distinct first channels the buses play on, each of which gets its own reverb.
+pub fn reverb_channels(buses: &[OutputBus], device_channels: usize) -> Vec<usize> {
+    let mut channels: Vec<usize> = buses
+        .iter()
+        .map(|bus| bus.output_channel(device_channels))
+        .collect();
+    channels.sort_unstable();
+    channels.dedup();
+    channels
+}
+
+impl Default for OutputBus {
+    fn default() -> Self {
+        Self {
+            name: "Main".to_string(),
+            first_channel: 0,
+            gain: 1.0,
+            reverb_send: 1.0,
+        }
+    }
+}
+
+/// The part of an organ a route applies to.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RouteTarget {
+    Division,
+    WindchestGroup,
+    Rank,
+}
+
+/// Sends a division, windchest group or rank to an output bus.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct OutputRoute {
+    pub target: RouteTarget,
+    pub id: String,
+    /// Index into the output buses
+    pub bus: usize,
+}
+
+/// The output buses of the audio device and the routes of one organ.
+/// Without buses everything plays on the first two channels, as a plain stereo device.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OutputRouting {
+    pub buses: Vec<OutputBus>,
+    pub routes: Vec<OutputRoute>,
+}
+
+impl OutputRouting {
+    /// Reads the buses and the routes of an organ from the saved settings.
+    pub fn load(organ_name: &str) -> Self {
+        let settings = load_settings().unwrap_or_default();
+        Self {
+            routes: settings
+                .output_routes
+                .get(organ_name)
+                .cloned()
+                .unwrap_or_default(),
+            buses: settings.output_buses,
+        }
+    }
+
+    /// Stores the buses and the routes of an organ in the settings, keeping everything else.
+    pub fn save(&self, organ_name: &str) -> Result<()> {
+        let mut settings = load_settings().unwrap_or_default();
+        settings.output_buses = self.buses.clone();
+        if self.routes.is_empty() {
+            settings.output_routes.remove(organ_name);
+        } else {
+            settings
+                .output_routes
+                .insert(organ_name.to_string(), self.routes.clone());
+        }
+        save_settings(&settings)
+    }
+
+    /// The buses to mix into. There is always at least the main bus, and at most
+    /// `MAX_OUTPUT_BUSES`.
+    pub fn effective_buses(&self) -> Vec<OutputBus> {
+        if self.buses.is_empty() {
+            vec![OutputBus::default()]
+        } else {
+            self.buses.iter().take(MAX_OUTPUT_BUSES).cloned().collect()
+        }
+    }
+
+    /// Number of device channels the buses play on.
+    pub fn channels_needed(&self) -> usize {
+        self.buses
+            .iter()
+            .map(|bus| bus.first_channel + 2)
+            .max()
+            .unwrap_or(2)
+    }
+
+    pub fn route(&self, target: RouteTarget, id: &str) -> Option<usize> {
+        self.routes
+            .iter()
+            .find(|r| r.target == target && r.id == id)
+            .map(|r| r.bus)
+    }
+
+    /// Routes a target to a bus, or back to the one above it with `None`.
+    pub fn set_route(&mut self, target: RouteTarget, id: &str, bus: Option<usize>) {
+        self.routes.retain(|r| !(r.target == target && r.id == id));
+        if let Some(bus) = bus {
+            self.routes.push(OutputRoute {
+                target,
+                id: id.to_string(),
+                bus,
+            });
+        }
+    }
+
+    /// Removes a bus. Its routes are dropped and later buses move down by one.
+    pub fn remove_bus(&mut self, index: usize) {
+        if index >= self.buses.len() {
+            return;
+        }
+        self.buses.remove(index);
+        self.routes.retain(|r| r.bus != index);
+        for route in &mut self.routes {
+            if route.bus > index {
+                route.bus -= 1;
+            }
+        }
+    }
+
+    /// Bus of a rank: its own route, else its windchest group's, else its division's.
+    /// Unrouted ranks and routes to buses that no longer exist play on the main bus.
+    pub fn rank_bus(&self, organ: &Organ, rank: &Rank) -> usize {
+        let bus = self
+            .route(RouteTarget::Rank, &rank.id_str)
+            .or_else(|| {
+                let group_id = rank.windchest_group_id.as_deref()?;
+                let unpadded = group_id.trim_start_matches('0');
+                self.routes
+                    .iter()
+                    .find(|r| {
+                        r.target == RouteTarget::WindchestGroup
+                            && (r.id == group_id || r.id.trim_start_matches('0') == unpadded)
+                    })
+                    .map(|r| r.bus)
+            })
+            .or_else(|| self.route(RouteTarget::Division, &rank_division(organ, rank)?))
+            .unwrap_or(0);
+        if bus < self.buses.len().min(MAX_OUTPUT_BUSES) {
+            bus
+        } else {
+            0
+        }
+    }
+
+    /// Bus of every rank of the organ, for the audio thread.
+    pub fn rank_buses(&self, organ: &Organ) -> HashMap<String, usize> {
+        organ
+            .ranks
+            .values()
+            .map(|rank| (rank.id_str.clone(), self.rank_bus(organ, rank)))
+            .filter(|(_, bus)| *bus != 0)
+            .collect()
+    }
+}
+
+/// Division a rank belongs to. Hauptwerk names it on the rank; otherwise it is the
+/// manual of the first stop that plays the rank.
+pub fn rank_division(organ: &Organ, rank: &Rank) -> Option<String> {
+    if !rank.division_id.is_empty() {
+        return Some(rank.division_id.clone());
+    }
+    let mut manuals: Vec<_> = organ.manuals.values().collect();
+    manuals.sort_by(|a, b| a.id_str.cmp(&b.id_str));
+    manuals
+        .into_iter()
+        .find(|manual| {
+            organ
+                .stops
+                .iter()
+                .filter(|stop| manual.stop_ids.contains(&stop.id_str))
+                .any(|stop| stop.rank_ids.contains(&rank.id_str))
+        })
+        .map(|manual| manual.id_str.clone())
+}
+
+/// The divisions of an organ as (ID, display name), sorted by ID.
+pub fn divisions(organ: &Organ) -> Vec<(String, String)> {
+    let mut divisions: Vec<(String, String)> = organ
+        .ranks
+        .values()
+        .filter_map(|rank| rank_division(organ, rank))
+        .map(|id| {
+            let name = organ
+                .manuals
+                .get(&id)
+                .map_or_else(|| id.clone(), |manual| manual.name.clone());
+            (id, name)
+        })
+        .collect();
+    divisions.sort();
+    divisions.dedup();
+    divisions
+}
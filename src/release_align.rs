@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+use crate::voice::CHANNEL_COUNT;
+
+/// Length of the start of a release sample searched for a matching phase, in seconds.
+/// Long enough to hold a full period of the lowest pipes.
+pub const RELEASE_ALIGN_TIME: f32 = 0.1;
+
+const AMPLITUDE_STEPS: usize = 32;
+const DIRECTION_STEPS: usize = 2;
+
+/// Where a release sample can start so that it continues the waveform of the attack
+/// it takes over from, like GrandOrgue's release alignment tables.
+/// Positions are indexed by the attack's current amplitude and whether it is rising or falling.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseAlignment {
+    /// Largest absolute amplitude in the searched part of the release
+    pub max_amplitude: f32,
+    /// Mean square level of the searched part, per channel
+    pub level: f32,
+    /// First frame reaching each [direction][amplitude step], if any
+    pub positions: Vec<Option<u32>>,
+}
+
+impl ReleaseAlignment {
+    /// Builds the table from the start of a release sample (interleaved stereo).
+    pub fn compute(samples: &[f32], sample_rate: u32) -> Option<Self> {
+        let window =
+            ((sample_rate as f32 * RELEASE_ALIGN_TIME) as usize).min(samples.len() / CHANNEL_COUNT);
+        if window < 2 {
+            return None;
+        }
+        let samples = &samples[..window * CHANNEL_COUNT];
+        let mono: Vec<f32> = samples
+            .chunks_exact(CHANNEL_COUNT)
+            .map(|frame| (frame[0] + frame[1]) * 0.5)
+            .collect();
+        let max_amplitude = mono.iter().fold(0.0f32, |max, s| max.max(s.abs()));
+        if max_amplitude <= f32::EPSILON {
+            return None;
+        }
+        let level = samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32;
+
+        let mut positions = vec![None; DIRECTION_STEPS * AMPLITUDE_STEPS];
+        for (frame, pair) in mono.windows(2).enumerate() {
+            let cell = cell(pair[0] / max_amplitude, pair[1] - pair[0]);
+            positions[cell].get_or_insert(frame as u32);
+        }
+
+        Some(Self {
+            max_amplitude,
+            level,
+            positions,
+        })
+    }
+
+    /// Frame to start the release at for an attack currently at `value` and moving by
+    /// `derivative` per frame (both in the release's scale). Only frames up to `max_frame`
+    /// are considered; the closest amplitude going the same way is used when the exact one
+    /// is never reached.
+    pub fn start_frame(&self, value: f32, derivative: f32, max_frame: usize) -> usize {
+        let cell = cell(value / self.max_amplitude, derivative);
+        let row_start = cell - cell % AMPLITUDE_STEPS;
+        let step = cell % AMPLITUDE_STEPS;
+        let usable = |step: usize| {
+            if step >= AMPLITUDE_STEPS {
+                return None;
+            }
+            self.positions[row_start + step].filter(|&frame| frame as usize <= max_frame)
+        };
+        (0..AMPLITUDE_STEPS)
+            .find_map(|distance| {
+                step.checked_sub(distance)
+                    .and_then(usable)
+                    .or_else(|| usable(step + distance))
+            })
+            .map_or(0, |frame| frame as usize)
+    }
+}
+
+fn cell(normalized_value: f32, derivative: f32) -> usize {
+    let step = ((normalized_value.clamp(-1.0, 1.0) + 1.0) * 0.5 * (AMPLITUDE_STEPS - 1) as f32)
+        .round() as usize;
+    let direction = usize::from(derivative >= 0.0);
+    direction * AMPLITUDE_STEPS + step
+}
@@ -0,0 +1,199 @@
+use clap::ValueEnum;
+use rust_i18n::t;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use crate::voice::CHANNEL_COUNT;
+
+/// Half the length of the windowed-sinc kernel, in frames on each side of the cursor.
+const SINC_HALF_TAPS: usize = 8;
+const SINC_TAPS: usize = SINC_HALF_TAPS * 2;
+/// Fractional positions the sinc kernel is tabulated at. Positions between them are interpolated.
+const SINC_PHASES: usize = 256;
+/// Cutoff of the sinc kernel relative to the Nyquist frequency, leaving room for the window's transition band.
+const SINC_CUTOFF: f32 = 0.9;
+/// Kernel sets per octave of playback speed above the recorded pitch. Faster playback
+/// uses a set with a lower cutoff, so the frequencies it shifts past Nyquist are filtered.
+const SINC_BANDS_PER_OCTAVE: f32 = 4.0;
+/// Kernel sets, covering speeds up to two octaves up. Faster voices use the last one.
+const SINC_BANDS: usize = 9;
+
+/// Frames a voice keeps before its playback position, so that interpolators can look back.
+pub const RESAMPLE_HISTORY_FRAMES: usize = SINC_HALF_TAPS;
+
+/// How voices are interpolated when they play at another pitch than recorded,
+/// e.g. under a tremulant or when transposed.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+#[value(rename_all = "lower")]
+pub enum ResamplingQuality {
+    /// Straight line between neighbouring frames. Cheapest, but dulls the treble and aliases.
+    #[default]
+    Linear,
+    /// Cubic Hermite (Catmull-Rom) curve through four frames.
+    Cubic,
+    /// Windowed-sinc polyphase filter over sixteen frames.
+    Sinc,
+}
+
+impl ResamplingQuality {
+    pub const ALL: [ResamplingQuality; 3] = [
+        ResamplingQuality::Linear,
+        ResamplingQuality::Cubic,
+        ResamplingQuality::Sinc,
+    ];
+
+    pub fn display_name(&self) -> String {
+        match self {
+            ResamplingQuality::Linear => t!("config.resampling_linear"),
+            ResamplingQuality::Cubic => t!("config.resampling_cubic"),
+            ResamplingQuality::Sinc => t!("config.resampling_sinc"),
+        }
+        .to_string()
+    }
+
+    /// Estimated CPU cost of a modulated voice relative to linear interpolation,
+    /// from the number of frames each output frame reads. Not measured.
+    pub fn relative_cost(&self) -> u32 {
+        match self {
+            ResamplingQuality::Linear => 1,
+            ResamplingQuality::Cubic => 2,
+            ResamplingQuality::Sinc => 10,
+        }
+    }
+
+    /// Frames after the cursor's frame the interpolator reads.
+    pub fn lookahead_frames(&self) -> usize {
+        match self {
+            ResamplingQuality::Linear => 1,
+            ResamplingQuality::Cubic => 2,
+            ResamplingQuality::Sinc => SINC_HALF_TAPS,
+        }
+    }
+}
+
+/// Interpolates interleaved stereo input at fractional positions.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    pub quality: ResamplingQuality,
+    /// Kernel coefficients per band, `SINC_TAPS` per phase, with one extra phase for
+    /// interpolating the last one
+    sinc_table: Vec<f32>,
+}
+
+impl Default for Resampler {
+    fn default() -> Self {
+        Self::new(ResamplingQuality::default())
+    }
+}
+
+impl Resampler {
+    pub fn new(quality: ResamplingQuality) -> Self {
+        Self {
+            quality,
+            sinc_table: build_sinc_table(),
+        }
+    }
+
+    /// Kernel set for voices advancing `ratio` input frames per output frame,
+    /// to pass to `interpolate`.
+    pub fn band(ratio: f32) -> usize {
+        if ratio <= 1.0 {
+            return 0;
+        }
+        ((ratio.log2() * SINC_BANDS_PER_OCTAVE).ceil() as usize).min(SINC_BANDS - 1)
+    }
+
+    /// The stereo frame at `frame + frac` of `input`, filtered for kernel set `band`.
+    /// `frame` must have `RESAMPLE_HISTORY_FRAMES` frames before it and the quality's
+    /// lookahead after it.
+    #[inline]
+    pub fn interpolate(
+        &self,
+        input: &[f32],
+        frame: usize,
+        frac: f32,
+        band: usize,
+    ) -> [f32; CHANNEL_COUNT] {
+        match self.quality {
+            ResamplingQuality::Linear => {
+                let i = frame * CHANNEL_COUNT;
+                let s = &input[i..i + 2 * CHANNEL_COUNT];
+                [s[0] + (s[2] - s[0]) * frac, s[1] + (s[3] - s[1]) * frac]
+            }
+            ResamplingQuality::Cubic => {
+                let i = (frame - 1) * CHANNEL_COUNT;
+                let s = &input[i..i + 4 * CHANNEL_COUNT];
+                [
+                    hermite(s[0], s[2], s[4], s[6], frac),
+                    hermite(s[1], s[3], s[5], s[7], frac),
+                ]
+            }
+            ResamplingQuality::Sinc => {
+                let position = frac * SINC_PHASES as f32;
+                let phase = (position as usize).min(SINC_PHASES - 1);
+                let blend = position - phase as f32;
+                let first = (band * (SINC_PHASES + 1) + phase) * SINC_TAPS;
+                let row = &self.sinc_table[first..first + 2 * SINC_TAPS];
+                let (current, next) = row.split_at(SINC_TAPS);
+
+                let i = (frame + 1 - SINC_HALF_TAPS) * CHANNEL_COUNT;
+                let s = &input[i..i + SINC_TAPS * CHANNEL_COUNT];
+                let mut out = [0.0f32; CHANNEL_COUNT];
+                for ((frame, &a), &b) in s.chunks_exact(CHANNEL_COUNT).zip(current).zip(next) {
+                    let coeff = a + (b - a) * blend;
+                    out[0] += frame[0] * coeff;
+                    out[1] += frame[1] * coeff;
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Catmull-Rom spline between `x0` and `x1` at `t`.
+#[inline]
+fn hermite(xm1: f32, x0: f32, x1: f32, x2: f32, t: f32) -> f32 {
+    let c1 = 0.5 * (x1 - xm1);
+    let c2 = xm1 - 2.5 * x0 + 2.0 * x1 - 0.5 * x2;
+    let c3 = 0.5 * (x2 - xm1) + 1.5 * (x0 - x1);
+    ((c3 * t + c2) * t + c1) * t + x0
+}
+
+/// Blackman-windowed sinc kernels for every band and phase. The cutoff of each band is
+/// lowered by the speed it covers. Each kernel is normalized to unity gain so that
+/// interpolation doesn't change the level.
+fn build_sinc_table() -> Vec<f32> {
+    let mut table = Vec::with_capacity(SINC_BANDS * (SINC_PHASES + 1) * SINC_TAPS);
+    for band in 0..SINC_BANDS {
+        let cutoff = SINC_CUTOFF / 2f32.powf(band as f32 / SINC_BANDS_PER_OCTAVE);
+        push_sinc_kernels(&mut table, cutoff);
+    }
+    table
+}
+
+fn push_sinc_kernels(table: &mut Vec<f32>, cutoff: f32) {
+    for phase in 0..=SINC_PHASES {
+        let frac = phase as f32 / SINC_PHASES as f32;
+        let kernel: Vec<f32> = (0..SINC_TAPS)
+            .map(|tap| {
+                // Distance of the tap from the interpolated position
+                let x = tap as f32 + 1.0 - SINC_HALF_TAPS as f32 - frac;
+                let sinc = if x.abs() < 1e-6 {
+                    cutoff
+                } else {
+                    (PI * cutoff * x).sin() / (PI * x)
+                };
+                let w = (x / SINC_HALF_TAPS as f32 + 1.0) * 0.5;
+                let window = if (0.0..=1.0).contains(&w) {
+                    0.42 - 0.5 * (2.0 * PI * w).cos() + 0.08 * (4.0 * PI * w).cos()
+                } else {
+                    0.0
+                };
+                sinc * window
+            })
+            .collect();
+        let sum: f32 = kernel.iter().sum();
+        table.extend(kernel.iter().map(|c| c / sum));
+    }
+}
@@ -0,0 +1,268 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use crate::organ::{Organ, Rank};
+
+/// How the pipes of a rank are spread around its pan position.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PanLayout {
+    /// All pipes at the pan position
+    #[default]
+    Center,
+    /// C side to the left, C# side to the right
+    Alternate,
+    /// Pipe front with the largest pipes in the middle and the treble alternating outwards
+    Pyramid,
+    /// Pipe front with the largest pipes alternating at the sides and the treble in the middle
+    InvertedPyramid,
+    /// Bass to the left, treble to the right
+    Chromatic,
+}
+
+impl PanLayout {
+    pub const ALL: [PanLayout; 5] = [
+        PanLayout::Center,
+        PanLayout::Alternate,
+        PanLayout::Pyramid,
+        PanLayout::InvertedPyramid,
+        PanLayout::Chromatic,
+    ];
+
+    /// Offset of a pipe from the rank's pan position, from -1 to 1.
+    /// `position` is the pipe's place in the compass, 0 for the lowest and 1 for the highest.
+    fn offset(&self, note: u8, position: f32) -> f32 {
+        // Whole-tone sides: C, D, E, F#, G#, A# on one, C#, D#, F, G, A, B on the other
+        let side = if note.is_multiple_of(2) { -1.0 } else { 1.0 };
+        match self {
+            PanLayout::Center => 0.0,
+            PanLayout::Alternate => side,
+            PanLayout::Pyramid => side * position,
+            PanLayout::InvertedPyramid => side * (1.0 - position),
+            PanLayout::Chromatic => position * 2.0 - 1.0,
+        }
+    }
+}
+
+/// Stereo position of a windchest group or rank.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct PanPosition {
+    /// -1 is hard left, 1 hard right
+    #[serde(default)]
+    pub pan: f32,
+    #[serde(default)]
+    pub layout: PanLayout,
+    /// How far the layout spreads the pipes from the pan position
+    #[serde(default = "default_width")]
+    pub width: f32,
+}
+
+fn default_width() -> f32 {
+    0.5
+}
+
+impl Default for PanPosition {
+    fn default() -> Self {
+        Self {
+            pan: 0.0,
+            layout: PanLayout::Center,
+            width: default_width(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PipePan {
+    pub rank_id: String,
+    pub note: u8,
+    pub pan: f32,
+}
+
+/// The stereo placement of one organ, stored next to its MIDI map.
+/// A rank's own position replaces the one of its windchest group,
+/// and single pipes can be placed on top of that.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct StereoLayout {
+    // Map<WindchestGroupID, Position>
+    #[serde(default)]
+    pub windchest_groups: HashMap<String, PanPosition>,
+    // Map<RankID, Position>
+    #[serde(default)]
+    pub ranks: HashMap<String, PanPosition>,
+    #[serde(default)]
+    pub pipes: Vec<PipePan>,
+}
+
+impl StereoLayout {
+    pub fn get_file_path(organ_name: &str) -> PathBuf {
+        let config_path = confy::get_configuration_file_path("rusty-pipes", "settings")
+            .expect("Could not get configuration file path");
+        let parent = config_path.parent().expect("Could not get config parent");
+        let safe_name: String = organ_name
+            .chars()
+            .map(|x| if x.is_alphanumeric() { x } else { '_' })
+            .collect();
+        parent.join(format!("{}.stereo.json", safe_name))
+    }
+
+    pub fn load(organ_name: &str) -> Self {
+        let path = Self::get_file_path(organ_name);
+        if path.exists() {
+            match File::open(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|file| Ok(serde_json::from_reader(BufReader::new(file))?))
+            {
+                Ok(layout) => return layout,
+                Err(e) => log::warn!("Could not read stereo layout from {:?}: {}", path, e),
+            }
+        }
+        Self::default()
+    }
+
+    pub fn save(&self, organ_name: &str) -> Result<()> {
+        let path = Self::get_file_path(organ_name);
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windchest_groups.is_empty() && self.ranks.is_empty() && self.pipes.is_empty()
+    }
+
+    /// Position of a rank: its own, else its windchest group's, else the one the organ
+    /// definition gives its windchest group. Group IDs may be unpadded ("1" vs "001").
+    pub fn rank_position(&self, organ: &Organ, rank: &Rank) -> Option<PanPosition> {
+        if let Some(position) = self.ranks.get(&rank.id_str) {
+            return Some(*position);
+        }
+        let group_id = rank.windchest_group_id.as_deref()?;
+        let unpadded = group_id.trim_start_matches('0');
+        let by_id = |id: &String| id.trim_start_matches('0') == unpadded;
+        if let Some(position) = self.windchest_groups.get(group_id).or_else(|| {
+            self.windchest_groups
+                .iter()
+                .find(|(id, _)| by_id(id))
+                .map(|(_, position)| position)
+        }) {
+            return Some(*position);
+        }
+        let group = organ.windchest_groups.get(group_id).or_else(|| {
+            organ
+                .windchest_groups
+                .values()
+                .find(|group| by_id(&group.id_str))
+        })?;
+        Some(PanPosition {
+            pan: group.pan?,
+            ..PanPosition::default()
+        })
+    }
+}
+
+impl PanPosition {
+    /// Pan of the pipe sounding `note` in a rank spanning `lowest` to `highest`,
+    /// from -1 (left) to 1 (right).
+    fn pipe_pan(&self, note: u8, lowest: u8, highest: u8) -> f32 {
+        let compass_position = if highest > lowest {
+            (note.clamp(lowest, highest) - lowest) as f32 / (highest - lowest) as f32
+        } else {
+            0.0
+        };
+        let offset = self.layout.offset(note, compass_position);
+        (self.pan + offset * self.width).clamp(-1.0, 1.0)
+    }
+}
+
+/// How a voice's left and right input are mixed into each output channel:
+/// `[left output, right output]`, each as `[from left, from right]`.
+pub type PanMatrix = [[f32; 2]; 2];
+
+/// Leaves a voice at its recorded stereo image.
+pub const UNPANNED: PanMatrix = [[1.0, 0.0], [0.0, 1.0]];
+
+/// The pan of every pipe a stereo layout or the organ definition places,
+/// worked out once so that playing a note only looks it up.
+#[derive(Debug, Default)]
+pub struct PanTable {
+    // Map<RankID, Map<MIDI note, Pan>>
+    ranks: HashMap<String, HashMap<u8, PanMatrix>>,
+}
+
+impl PanTable {
+    pub fn new(layout: &StereoLayout, organ: &Organ) -> Self {
+        let pipe_pans: HashMap<(&str, u8), f32> = layout
+            .pipes
+            .iter()
+            .map(|pipe| ((pipe.rank_id.as_str(), pipe.note), pipe.pan))
+            .collect();
+        let mut ranks = HashMap::new();
+        for rank in organ.ranks.values() {
+            let (Some(&lowest), Some(&highest)) =
+                (rank.pipes.keys().min(), rank.pipes.keys().max())
+            else {
+                continue;
+            };
+            let position = layout.rank_position(organ, rank);
+            let pans: HashMap<u8, PanMatrix> = rank
+                .pipes
+                .keys()
+                .filter_map(|&note| {
+                    let pan = match pipe_pans.get(&(rank.id_str.as_str(), note)) {
+                        Some(pan) => pan.clamp(-1.0, 1.0),
+                        None => position?.pipe_pan(note, lowest, highest),
+                    };
+                    Some((note, pan_matrix(pan)))
+                })
+                .collect();
+            if !pans.is_empty() {
+                ranks.insert(rank.id_str.clone(), pans);
+            }
+        }
+        Self { ranks }
+    }
+
+    /// Pan of one pipe.
+    pub fn get(&self, rank_id: &str, note: u8) -> PanMatrix {
+        self.ranks
+            .get(rank_id)
+            .and_then(|pans| pans.get(&note))
+            .copied()
+            .unwrap_or(UNPANNED)
+    }
+}
+
+/// Pans a voice without dropping either channel of a stereo sample: both channels
+/// turn towards the pan position, and the width of the image narrows to nothing
+/// at the sides. A mono sample, the same on both channels, follows the
+/// constant-power pan law, scaled so that it keeps its level in the center.
+pub fn pan_matrix(pan: f32) -> PanMatrix {
+    let pan = pan.clamp(-1.0, 1.0);
+    let angle = (pan + 1.0) * FRAC_PI_4;
+    let spread = (1.0 - pan.abs()) * FRAC_PI_4;
+    let scale = 1.0 / (SQRT_2 * spread.cos());
+    let (left, right) = (angle - spread, angle + spread);
+    [
+        [left.cos() * scale, right.cos() * scale],
+        [left.sin() * scale, right.sin() * scale],
+    ]
+}
+
+/// Pan of a windchest named after the side of the organ it stands on: "left" or
+/// "right", or the C or C# side of a chest split into whole tones.
+pub fn pan_from_name(name: &str) -> Option<f32> {
+    const SIDE_PAN: f32 = 0.5;
+    name.to_lowercase()
+        .split(|c: char| c.is_whitespace() || matches!(c, '-' | '_' | '(' | ')' | '/'))
+        .find_map(|word| match word {
+            "left" | "links" | "gauche" | "sinistra" | "c" => Some(-SIDE_PAN),
+            "right" | "rechts" | "droite" | "destra" | "c#" | "cs" | "cis" => Some(SIDE_PAN),
+            _ => None,
+        })
+}
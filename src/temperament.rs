@@ -0,0 +1,250 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::config::get_temperament_directory;
+
+pub const EQUAL_TEMPERAMENT: &str = "Equal";
+
+/// Pitch class names, starting at C.
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "G#", "A", "Bb", "B",
+];
+
+/// Pitch class of A, which keeps its pitch in every temperament.
+const REFERENCE_PITCH_CLASS: usize = 9;
+
+/// A tuning of the twelve pitch classes, applied when the samples are played.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Temperament {
+    pub name: String,
+    /// Deviation from equal temperament in cents of each pitch class, starting at C
+    pub offsets_cents: [f32; 12],
+}
+
+impl Temperament {
+    fn new(name: &str, offsets_cents: [f32; 12]) -> Self {
+        Self {
+            name: name.to_string(),
+            offsets_cents,
+        }
+    }
+
+    pub fn equal() -> Self {
+        Self::new(EQUAL_TEMPERAMENT, [0.0; 12])
+    }
+
+    /// The temperaments that ship with the application.
+    pub fn built_in() -> Vec<Self> {
+        vec![
+            Self::equal(),
+            Self::new(
+                "Meantone (1/4 comma)",
+                [
+                    10.3, -13.7, 3.4, 20.5, -3.4, 13.7, -10.3, 6.8, -17.1, 0.0, 17.1, -6.8,
+                ],
+            ),
+            Self::new(
+                "Werckmeister III",
+                [
+                    0.0, -9.8, -7.8, -5.9, -9.8, -2.0, -11.7, -3.9, -7.8, -11.7, -3.9, -7.8,
+                ],
+            ),
+            Self::new(
+                "Kirnberger III",
+                [
+                    0.0, -9.8, -6.8, -5.9, -13.7, -2.0, -9.8, -3.4, -7.8, -10.3, -3.9, -11.7,
+                ],
+            ),
+            Self::new(
+                "Vallotti",
+                [
+                    5.9, 0.0, 2.0, 3.9, -2.0, 7.8, -2.0, 3.9, 2.0, 0.0, 5.9, -3.9,
+                ],
+            ),
+        ]
+    }
+
+    /// Reads a user-defined temperament from a JSON file.
+    pub fn load_file(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let temperament: Self = serde_json::from_reader(BufReader::new(file))?;
+        if let Some(offset) = temperament
+            .offsets_cents
+            .iter()
+            .find(|c| !c.is_finite() || c.abs() > 100.0)
+        {
+            return Err(anyhow!(
+                "'{}' has an offset of {} cents, expected -100 to 100",
+                temperament.name,
+                offset
+            ));
+        }
+        Ok(temperament)
+    }
+
+    /// Looks up a temperament by name, or loads it if `spec` is the path of a file.
+    pub fn resolve(spec: &str) -> Result<Self> {
+        let path = Path::new(spec);
+        if path.is_file() {
+            return Self::load_file(path);
+        }
+        get_available_temperaments()
+            .into_iter()
+            .find(|t| t.name.eq_ignore_ascii_case(spec.trim()))
+            .ok_or_else(|| anyhow!("Unknown temperament '{}'", spec))
+    }
+
+    /// Cent offsets of each pitch class (C = 0) with the temperament built on
+    /// `base_note` instead of C. A is left at its pitch.
+    pub fn cents_for_base(&self, base_note: u8) -> [f32; 12] {
+        let base = base_note as usize % 12;
+        let rotated: [f32; 12] =
+            std::array::from_fn(|pc| self.offsets_cents[(pc + 12 - base) % 12]);
+        let reference = rotated[REFERENCE_PITCH_CLASS];
+        rotated.map(|cents| cents - reference)
+    }
+}
+
+/// Built-in temperaments followed by the user's temperament files.
+pub fn get_available_temperaments() -> Vec<Temperament> {
+    let mut temperaments = Temperament::built_in();
+    let mut user_temperaments = Vec::new();
+
+    if let Ok(dir) = get_temperament_directory()
+        && let Ok(entries) = fs::read_dir(dir)
+    {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match Temperament::load_file(&path) {
+                Ok(t) => user_temperaments.push(t),
+                Err(e) => log::warn!("Could not read temperament {:?}: {}", path, e),
+            }
+        }
+    }
+
+    user_temperaments.sort_by_key(|t| t.name.to_lowercase());
+    temperaments.extend(user_temperaments);
+    temperaments
+}
+
+/// Parses a pitch class given as a note name ("D", "Eb", "F#") or a number 0-11.
+pub fn parse_base_note(s: &str) -> Result<u8, String> {
+    let s = s.trim();
+    if let Ok(n) = s.parse::<u8>()
+        && n < 12
+    {
+        return Ok(n);
+    }
+    let mut chars = s.chars();
+    let letter = chars.next().map(|c| c.to_ascii_uppercase());
+    let natural = match letter {
+        Some('C') => 0,
+        Some('D') => 2,
+        Some('E') => 4,
+        Some('F') => 5,
+        Some('G') => 7,
+        Some('A') => 9,
+        Some('B') => 11,
+        _ => return Err(format!("'{}' is not a note name", s)),
+    };
+    let accidental = match chars.as_str() {
+        "" => 0,
+        "#" => 1,
+        "b" => 11,
+        _ => return Err(format!("'{}' is not a note name", s)),
+    };
+    Ok((natural + accidental) % 12)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PURE_FIFTH: f32 = 701.955;
+    const PYTHAGOREAN_COMMA: f32 = 23.460;
+    const SYNTONIC_COMMA: f32 = 21.506;
+
+    /// Deviation from equal temperament of each pitch class when the eleven fifths
+    /// upwards from `start` are narrowed from pure by `narrowing` cents each.
+    /// The last fifth takes what is left to close the circle.
+    fn from_fifths(start: usize, narrowing: [f32; 11]) -> [f32; 12] {
+        let mut cents = [0.0; 12];
+        let mut deviation = 0.0;
+        for (i, narrowed) in narrowing.iter().enumerate() {
+            deviation += PURE_FIFTH - narrowed - 700.0;
+            cents[(start + 7 * (i + 1)) % 12] = deviation;
+        }
+        cents
+    }
+
+    fn assert_matches(name: &str, expected: [f32; 12]) {
+        let temperament = Temperament::built_in()
+            .into_iter()
+            .find(|t| t.name == name)
+            .unwrap();
+        // Tables are compared relative to A, as they are played
+        let reference = expected[REFERENCE_PITCH_CLASS];
+        let offsets = temperament.cents_for_base(0);
+        for (pc, cents) in offsets.iter().enumerate() {
+            let expected = expected[pc] - reference;
+            assert!(
+                (cents - expected).abs() < 0.1,
+                "{} {}: {} cents, expected {:.1}",
+                name,
+                NOTE_NAMES[pc],
+                cents,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn meantone_narrows_every_fifth_by_a_quarter_comma() {
+        // Eb-Bb, Bb-F ... C#-G#, leaving the wolf fifth G#-Eb
+        let narrowing = [SYNTONIC_COMMA / 4.0; 11];
+        assert_matches("Meantone (1/4 comma)", from_fifths(3, narrowing));
+    }
+
+    #[test]
+    fn werckmeister_iii_narrows_four_fifths_by_a_quarter_comma() {
+        let q = PYTHAGOREAN_COMMA / 4.0;
+        // C-G G-D D-A A-E E-B B-F# F#-C# C#-G# G#-Eb Eb-Bb Bb-F
+        let narrowing = [q, q, q, 0.0, 0.0, q, 0.0, 0.0, 0.0, 0.0, 0.0];
+        assert_matches("Werckmeister III", from_fifths(0, narrowing));
+    }
+
+    #[test]
+    fn kirnberger_iii_narrows_the_fifths_from_c_to_e() {
+        let q = SYNTONIC_COMMA / 4.0;
+        let schisma = PYTHAGOREAN_COMMA - SYNTONIC_COMMA;
+        let narrowing = [q, q, q, q, 0.0, 0.0, schisma, 0.0, 0.0, 0.0, 0.0];
+        assert_matches("Kirnberger III", from_fifths(0, narrowing));
+    }
+
+    #[test]
+    fn vallotti_narrows_the_fifths_from_f_to_b() {
+        let s = PYTHAGOREAN_COMMA / 6.0;
+        // F-C is the last fifth and closes the circle
+        let narrowing = [s, s, s, s, s, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        assert_matches("Vallotti", from_fifths(0, narrowing));
+    }
+
+    #[test]
+    fn base_note_rotates_the_temperament_and_keeps_a() {
+        let temperament = Temperament::resolve("Werckmeister III").unwrap();
+        let on_d = temperament.cents_for_base(2);
+        let on_c = temperament.cents_for_base(0);
+        assert_eq!(on_d[REFERENCE_PITCH_CLASS], 0.0);
+        for pc in 0..12 {
+            let interval = on_d[(pc + 2) % 12] - on_d[2];
+            let expected = on_c[pc] - on_c[0];
+            assert!((interval - expected).abs() < 1e-4);
+        }
+    }
+}
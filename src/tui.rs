@@ -30,6 +30,7 @@ use crate::config::{MidiEventSpec, load_organ_library};
 use crate::input::MusicCommand;
 use crate::tui_midi_learn::{MidiLearnTuiState, draw_midi_learn_modal};
 use crate::tui_organ_manager::{OrganManagerTuiState, draw_organ_manager};
+use crate::tui_virtual_stops::{VirtualStopsTuiState, draw_virtual_stops};
 
 const NUM_COLUMNS: usize = 3; // Number of columns for the stop list
 
@@ -39,6 +40,7 @@ enum AppMode {
     PresetSaveName(usize, String), // Holds (slot_index, current_name_buffer)
     MidiLearn,
     OrganManager,
+    VirtualStops,
 }
 
 #[derive(Clone, PartialEq, Eq, Default)]
@@ -46,6 +48,8 @@ enum MainViewMode {
     #[default]
     Stops,
     Tremulants,
+    Couplers,
+    Enclosures,
     Presets,
 }
 
@@ -53,7 +57,9 @@ impl MainViewMode {
     fn next(&self) -> Self {
         match self {
             MainViewMode::Stops => MainViewMode::Tremulants,
-            MainViewMode::Tremulants => MainViewMode::Presets,
+            MainViewMode::Tremulants => MainViewMode::Couplers,
+            MainViewMode::Couplers => MainViewMode::Enclosures,
+            MainViewMode::Enclosures => MainViewMode::Presets,
             MainViewMode::Presets => MainViewMode::Stops,
         }
     }
@@ -64,18 +70,21 @@ struct TuiState {
     mode: AppMode,
     app_state: Arc<Mutex<AppState>>,
 
-    // Main View Mode (Stops, Tremulants, Presets)
+    // Main View Mode (Stops, Tremulants, Couplers, Enclosures, Presets)
     main_view_mode: MainViewMode,
 
     // List States for each view
     stop_list_state: ListState, // Renamed from list_state
     tremulant_list_state: ListState,
+    coupler_list_state: ListState,
+    enclosure_list_state: ListState,
     preset_list_state: ListState,
 
     items_per_column: usize,
     stops_count: usize,
     midi_learn_state: MidiLearnTuiState,
     organ_manager_state: OrganManagerTuiState,
+    virtual_stops_state: VirtualStopsTuiState,
 }
 
 impl TuiState {
@@ -95,10 +104,24 @@ impl TuiState {
             tremulant_list_state.select(Some(0));
         }
 
+        // Coupler list state
+        let mut coupler_list_state = ListState::default();
+        if !app_state_locked.organ.couplers.is_empty() {
+            coupler_list_state.select(Some(0));
+        }
+
+        // Enclosure list state
+        let mut enclosure_list_state = ListState::default();
+        if !app_state_locked.organ.enclosures.is_empty() {
+            enclosure_list_state.select(Some(0));
+        }
+
         // Preset list state
         let mut preset_list_state = ListState::default();
         preset_list_state.select(Some(0)); // Presets are always 12 slots
 
+        let virtual_stops_state = VirtualStopsTuiState::new(&app_state_locked.organ.name);
+
         drop(app_state_locked); // Explicitly drop the lock
 
         Ok(Self {
@@ -107,11 +130,14 @@ impl TuiState {
             app_state,
             stop_list_state,
             tremulant_list_state,
+            coupler_list_state,
+            enclosure_list_state,
             preset_list_state,
             items_per_column,
             stops_count, // Keeping this cached for Stops view
             midi_learn_state: MidiLearnTuiState::default(),
             organ_manager_state: OrganManagerTuiState::new(),
+            virtual_stops_state,
         })
     }
 
@@ -140,6 +166,28 @@ impl TuiState {
                 };
                 self.tremulant_list_state.select(Some(i));
             }
+            MainViewMode::Couplers => {
+                let count = self.app_state.lock().unwrap().organ.couplers.len();
+                if count == 0 {
+                    return;
+                }
+                let i = match self.coupler_list_state.selected() {
+                    Some(i) => (i + 1) % count,
+                    None => 0,
+                };
+                self.coupler_list_state.select(Some(i));
+            }
+            MainViewMode::Enclosures => {
+                let count = self.app_state.lock().unwrap().organ.enclosures.len();
+                if count == 0 {
+                    return;
+                }
+                let i = match self.enclosure_list_state.selected() {
+                    Some(i) => (i + 1) % count,
+                    None => 0,
+                };
+                self.enclosure_list_state.select(Some(i));
+            }
             MainViewMode::Presets => {
                 let count = 12; // Fixed
                 let i = match self.preset_list_state.selected() {
@@ -186,6 +234,40 @@ impl TuiState {
                 };
                 self.tremulant_list_state.select(Some(i));
             }
+            MainViewMode::Couplers => {
+                let count = self.app_state.lock().unwrap().organ.couplers.len();
+                if count == 0 {
+                    return;
+                }
+                let i = match self.coupler_list_state.selected() {
+                    Some(i) => {
+                        if i == 0 {
+                            count - 1
+                        } else {
+                            i - 1
+                        }
+                    }
+                    None => 0,
+                };
+                self.coupler_list_state.select(Some(i));
+            }
+            MainViewMode::Enclosures => {
+                let count = self.app_state.lock().unwrap().organ.enclosures.len();
+                if count == 0 {
+                    return;
+                }
+                let i = match self.enclosure_list_state.selected() {
+                    Some(i) => {
+                        if i == 0 {
+                            count - 1
+                        } else {
+                            i - 1
+                        }
+                    }
+                    None => 0,
+                };
+                self.enclosure_list_state.select(Some(i));
+            }
             MainViewMode::Presets => {
                 let count = 12;
                 let i = match self.preset_list_state.selected() {
@@ -226,6 +308,26 @@ impl TuiState {
         }
     }
 
+    /// Returns the (ID, name) of the selected enclosure, in display order.
+    fn selected_enclosure(&self) -> Option<(String, String)> {
+        let idx = self.enclosure_list_state.selected()?;
+        let state = self.app_state.lock().unwrap();
+        let mut enclosures: Vec<_> = state.organ.enclosures.values().collect();
+        enclosures.sort_by_key(|e| &e.name);
+        enclosures
+            .get(idx)
+            .map(|e| (e.id_str.clone(), e.name.clone()))
+    }
+
+    fn nudge_enclosure(&mut self, delta: i16, audio_tx: &Sender<AppMessage>) {
+        if let Some((id, _)) = self.selected_enclosure() {
+            let mut state = self.app_state.lock().unwrap();
+            let current = state.enclosure_values.get(&id).copied().unwrap_or(127);
+            let value = (current as i16 + delta).clamp(0, 127) as u8;
+            state.set_enclosure_value(id, value, audio_tx);
+        }
+    }
+
     fn toggle_stop_channel(&mut self, channel: u8, audio_tx: &Sender<AppMessage>) -> Result<()> {
         if let MainViewMode::Stops = self.main_view_mode {
             if let Some(selected_index) = self.stop_list_state.selected() {
@@ -352,7 +454,10 @@ pub fn run_tui_loop(
 
             if let Event::Key(key) = event {
                 // 1. Process Music Input via the shared helper
-                let command = {
+                // The virtual stop editor takes text input, so it gets all keys
+                let command = if tui_state.mode == AppMode::VirtualStops {
+                    MusicCommand::None
+                } else {
                     let state = tui_state.app_state.lock().unwrap();
                     state.keyboard_layout.map_crossterm(key.code)
                 };
@@ -392,6 +497,11 @@ pub fn run_tui_loop(
                                         {
                                             tui_state.mode = AppMode::OrganManager;
                                         }
+                                        KeyCode::Char('U')
+                                            if key.modifiers.contains(KeyModifiers::SHIFT) =>
+                                        {
+                                            tui_state.mode = AppMode::VirtualStops;
+                                        }
                                         KeyCode::Char('i') => {
                                             match tui_state.main_view_mode {
                                                 MainViewMode::Stops => {
@@ -437,6 +547,41 @@ pub fn run_tui_loop(
                                                         }
                                                     }
                                                 }
+                                                MainViewMode::Couplers => {
+                                                    if let Some(idx) =
+                                                        tui_state.coupler_list_state.selected()
+                                                    {
+                                                        let coupler = {
+                                                            let state =
+                                                                tui_state.app_state.lock().unwrap();
+                                                            let mut couplers: Vec<_> = state
+                                                                .organ
+                                                                .couplers
+                                                                .values()
+                                                                .collect();
+                                                            couplers.sort_by_key(|c| &c.name);
+                                                            couplers.get(idx).map(|c| {
+                                                                (c.id_str.clone(), c.name.clone())
+                                                            })
+                                                        };
+                                                        if let Some((id, name)) = coupler {
+                                                            tui_state
+                                                                .midi_learn_state
+                                                                .reset_coupler(id, name);
+                                                            tui_state.mode = AppMode::MidiLearn;
+                                                        }
+                                                    }
+                                                }
+                                                MainViewMode::Enclosures => {
+                                                    if let Some((id, name)) =
+                                                        tui_state.selected_enclosure()
+                                                    {
+                                                        tui_state
+                                                            .midi_learn_state
+                                                            .reset_enclosure(id, name);
+                                                        tui_state.mode = AppMode::MidiLearn;
+                                                    }
+                                                }
                                                 MainViewMode::Presets => {
                                                     if let Some(slot) =
                                                         tui_state.preset_list_state.selected()
@@ -449,7 +594,7 @@ pub fn run_tui_loop(
                                                 }
                                             }
                                         }
-                                        // Space toggle for Tremulants
+                                        // Space toggle for Tremulants and Couplers
                                         KeyCode::Char(' ') | KeyCode::Enter => {
                                             #[allow(clippy::single_match)]
                                             match tui_state.main_view_mode {
@@ -481,6 +626,35 @@ pub fn run_tui_loop(
                                                         }
                                                     }
                                                 }
+                                                MainViewMode::Couplers => {
+                                                    if let Some(idx) =
+                                                        tui_state.coupler_list_state.selected()
+                                                    {
+                                                        let id = {
+                                                            let state =
+                                                                tui_state.app_state.lock().unwrap();
+                                                            let mut couplers: Vec<_> = state
+                                                                .organ
+                                                                .couplers
+                                                                .values()
+                                                                .collect();
+                                                            couplers.sort_by_key(|c| &c.name);
+                                                            couplers
+                                                                .get(idx)
+                                                                .map(|c| c.id_str.clone())
+                                                        };
+
+                                                        if let Some(id) = id {
+                                                            let mut state =
+                                                                tui_state.app_state.lock().unwrap();
+                                                            let active =
+                                                                state.active_couplers.contains(&id);
+                                                            state.set_coupler_active(
+                                                                id, !active, &audio_tx,
+                                                            )?;
+                                                        }
+                                                    }
+                                                }
                                                 MainViewMode::Presets => {
                                                     if let Some(slot) =
                                                         tui_state.preset_list_state.selected()
@@ -517,6 +691,24 @@ pub fn run_tui_loop(
                                             tui_state.toggle_stop_channel(9, &audio_tx)?;
                                         }
 
+                                        // Swell pedal moves for Enclosures
+                                        KeyCode::Right
+                                            if matches!(
+                                                tui_state.main_view_mode,
+                                                MainViewMode::Enclosures
+                                            ) =>
+                                        {
+                                            tui_state.nudge_enclosure(8, &audio_tx);
+                                        }
+                                        KeyCode::Left
+                                            if matches!(
+                                                tui_state.main_view_mode,
+                                                MainViewMode::Enclosures
+                                            ) =>
+                                        {
+                                            tui_state.nudge_enclosure(-8, &audio_tx);
+                                        }
+
                                         // Passthrough to other keys
                                         _ => {
                                             match key.code {
@@ -531,7 +723,11 @@ pub fn run_tui_loop(
                                                 KeyCode::Right => tui_state.next_col(),
                                                 KeyCode::Left => tui_state.prev_col(),
                                                 KeyCode::Char('p') => {
-                                                    audio_tx.send(AppMessage::AllNotesOff)?;
+                                                    tui_state
+                                                        .app_state
+                                                        .lock()
+                                                        .unwrap()
+                                                        .all_notes_off(&audio_tx)?;
                                                 }
                                                 KeyCode::Char('m')
                                                     if key
@@ -660,6 +856,47 @@ pub fn run_tui_loop(
                                                         .unwrap()
                                                         .modify_polyphony(-16, &audio_tx);
                                                 }
+                                                // Transposition and master tuning
+                                                KeyCode::Char('>') | KeyCode::Char('<') => {
+                                                    let delta = if key.code == KeyCode::Char('>') {
+                                                        1
+                                                    } else {
+                                                        -1
+                                                    };
+                                                    let mut state =
+                                                        tui_state.app_state.lock().unwrap();
+                                                    let transpose = state.transpose + delta;
+                                                    state.set_transpose(transpose, &audio_tx);
+                                                }
+                                                KeyCode::Char('}') | KeyCode::Char('{') => {
+                                                    let delta = if key.code == KeyCode::Char('}') {
+                                                        1.0
+                                                    } else {
+                                                        -1.0
+                                                    };
+                                                    let mut state =
+                                                        tui_state.app_state.lock().unwrap();
+                                                    let cents = state.master_tuning_cents + delta;
+                                                    state.set_master_tuning(cents, &audio_tx);
+                                                }
+                                                KeyCode::Char('T')
+                                                    if key
+                                                        .modifiers
+                                                        .contains(KeyModifiers::SHIFT) =>
+                                                {
+                                                    tui_state.midi_learn_state.reset_transpose();
+                                                    tui_state.mode = AppMode::MidiLearn;
+                                                }
+                                                KeyCode::Char('F')
+                                                    if key
+                                                        .modifiers
+                                                        .contains(KeyModifiers::SHIFT) =>
+                                                {
+                                                    tui_state
+                                                        .midi_learn_state
+                                                        .reset_master_tuning();
+                                                    tui_state.mode = AppMode::MidiLearn;
+                                                }
                                                 _ => {}
                                             }
                                         } // end passthrough
@@ -716,6 +953,21 @@ pub fn run_tui_loop(
                                         tui_state.mode = AppMode::MainApp;
                                     }
                                 }
+                                AppMode::VirtualStops => {
+                                    if tui_state.virtual_stops_state.handle_input(
+                                        key.code,
+                                        &exit_action,
+                                        &tui_state.app_state,
+                                    ) {
+                                        // Applying the stops reloads the organ
+                                        if let MainLoopAction::Continue =
+                                            *exit_action.lock().unwrap()
+                                        {
+                                            break;
+                                        }
+                                        tui_state.mode = AppMode::MainApp;
+                                    }
+                                }
                             }
                         }
                     } // non-note keyboard commmands
@@ -743,18 +995,28 @@ fn draw_main_app_ui(frame: &mut Frame, app_state: &mut AppState, tui_state: &mut
         .split(frame.area());
 
     // --- Tabs ---
-    let titles = vec!["Stops", "Tremulants", "Presets"];
+    let titles = vec!["Stops", "Tremulants", "Couplers", "Swell", "Presets"];
     let selected_tab = match tui_state.main_view_mode {
         MainViewMode::Stops => 0,
         MainViewMode::Tremulants => 1,
-        MainViewMode::Presets => 2,
+        MainViewMode::Couplers => 2,
+        MainViewMode::Enclosures => 3,
+        MainViewMode::Presets => 4,
     };
 
+    let tuning_status = t!(
+        "tui.tuning_status_fmt",
+        transpose = format!("{:+}", app_state.transpose),
+        cents = format!("{:+.1}", app_state.master_tuning_cents)
+    )
+    .to_string();
+
     let tabs = ratatui::widgets::Tabs::new(titles)
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(app_state.organ.name.as_str()),
+                .title(app_state.organ.name.as_str())
+                .title_top(Line::from(tuning_status).right_aligned()),
         )
         .select(selected_tab)
         .highlight_style(
@@ -780,6 +1042,18 @@ fn draw_main_app_ui(frame: &mut Frame, app_state: &mut AppState, tui_state: &mut
             app_state,
             &mut tui_state.tremulant_list_state,
         ),
+        MainViewMode::Couplers => draw_couplers_view(
+            frame,
+            content_area,
+            app_state,
+            &mut tui_state.coupler_list_state,
+        ),
+        MainViewMode::Enclosures => draw_enclosures_view(
+            frame,
+            content_area,
+            app_state,
+            &mut tui_state.enclosure_list_state,
+        ),
         MainViewMode::Presets => draw_presets_view(
             frame,
             content_area,
@@ -896,6 +1170,10 @@ fn draw_main_app_ui(frame: &mut Frame, app_state: &mut AppState, tui_state: &mut
         }
     };
 
+    let is_pool_exhausted = app_state
+        .last_voice_pool_exhausted
+        .is_some_and(|last| last.elapsed() < Duration::from_secs(2));
+
     // --- Footer Help Text / Error ---
 
     let rec_status = if app_state.is_recording_midi && app_state.is_recording_audio {
@@ -919,6 +1197,15 @@ fn draw_main_app_ui(frame: &mut Frame, app_state: &mut AppState, tui_state: &mut
                     .bg(Color::Red)
                     .add_modifier(Modifier::BOLD),
             )
+    } else if is_pool_exhausted {
+        Paragraph::new(t!("tui.err_voice_pool", count = app_state.skipped_voice_count).to_string())
+            .alignment(Alignment::Center)
+            .style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
     } else {
         let status = t!(
             "tui.status_bar_fmt",
@@ -1065,6 +1352,105 @@ fn draw_tremulants_view(
     frame.render_stateful_widget(list, area, list_state);
 }
 
+fn draw_couplers_view(
+    frame: &mut Frame,
+    area: Rect,
+    app_state: &AppState,
+    list_state: &mut ListState,
+) {
+    // Sort couplers to match navigation order
+    let mut couplers: Vec<_> = app_state.organ.couplers.values().collect();
+    couplers.sort_by_key(|c| &c.name);
+
+    if couplers.is_empty() {
+        let msg = Paragraph::new("No Couplers").alignment(Alignment::Center);
+        frame.render_widget(msg, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = couplers
+        .iter()
+        .map(|coupler| {
+            let active = app_state.active_couplers.contains(&coupler.id_str);
+            let status = if active { "[ON] " } else { "[   ] " };
+            let content = format!("{}{}", status, coupler.name);
+
+            // Check if midi learned
+            let learned = app_state
+                .midi_control_map
+                .couplers
+                .contains_key(&coupler.id_str);
+            let learned_mark = if learned { " (M)" } else { "" };
+
+            let style = if active {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default()
+            };
+
+            ListItem::new(format!("{}{}", content, learned_mark)).style(style)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Couplers"))
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+
+    frame.render_stateful_widget(list, area, list_state);
+}
+
+fn draw_enclosures_view(
+    frame: &mut Frame,
+    area: Rect,
+    app_state: &AppState,
+    list_state: &mut ListState,
+) {
+    // Sort enclosures to match navigation order
+    let mut enclosures: Vec<_> = app_state.organ.enclosures.values().collect();
+    enclosures.sort_by_key(|e| &e.name);
+
+    if enclosures.is_empty() {
+        let msg = Paragraph::new("No Swell Boxes").alignment(Alignment::Center);
+        frame.render_widget(msg, area);
+        return;
+    }
+
+    let items: Vec<ListItem> = enclosures
+        .iter()
+        .map(|enclosure| {
+            let value = app_state
+                .enclosure_values
+                .get(&enclosure.id_str)
+                .copied()
+                .unwrap_or(enclosure.default_value);
+            let filled = (value as usize * 16) / 127;
+            let bar = format!("[{}{}]", "#".repeat(filled), "-".repeat(16 - filled));
+
+            // Check if midi learned
+            let learned = app_state
+                .midi_control_map
+                .enclosures
+                .contains_key(&enclosure.id_str);
+            let learned_mark = if learned { " (M)" } else { "" };
+
+            ListItem::new(format!(
+                "{} {:>3} {}{}",
+                bar, value, enclosure.name, learned_mark
+            ))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Swell Boxes (Left/Right to move)"),
+        )
+        .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan));
+
+    frame.render_stateful_widget(list, area, list_state);
+}
+
 fn draw_presets_view(
     frame: &mut Frame,
     area: Rect,
@@ -1116,6 +1502,11 @@ fn ui(frame: &mut Frame, state: &mut TuiState) {
                 &app_state_locked.organ.name,
             );
         }
+        AppMode::VirtualStops => {
+            draw_main_app_ui(frame, &mut app_state_locked, state);
+            let organ = app_state_locked.organ.clone();
+            draw_virtual_stops(frame, &mut state.virtual_stops_state, &organ);
+        }
     }
 }
 
@@ -6,12 +6,18 @@ use ratatui::{
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
 };
 use rust_i18n::t;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::Duration;
 
 use crate::app::LOGO;
 use crate::audio::get_supported_sample_rates;
-use crate::config::{AppSettings, ConfigState, RuntimeConfig};
+use crate::config::{AppSettings, ConfigState, PipeLayerInfo, RuntimeConfig};
+use crate::organ_hauptwerk;
+use crate::resample::ResamplingQuality;
+use crate::temperament::NOTE_NAMES;
 use crate::tui::{cleanup_terminal, setup_terminal};
 use crate::tui_filepicker;
 use crate::tui_lcd;
@@ -27,6 +33,7 @@ enum ConfigMode {
     MidiDeviceList,           // List of detected devices
     MidiMapping(usize),       // Editing device at specific index in settings.midi_devices
     LcdConfig,                // New LCD Config Mode
+    PipeLayerSelection,       // Perspectives of a Hauptwerk organ
 }
 
 struct TuiConfigState {
@@ -35,31 +42,75 @@ struct TuiConfigState {
     audio_list_state: ListState,
     sample_rate_list_state: ListState,
     ir_list_state: ListState,
+    pipe_layer_list_state: ListState,
     midi_dev_list_state: ListState,
     midi_mapping_state: tui_midi::TuiMidiState,
     lcd_state: tui_lcd::TuiLcdState,
     mode: ConfigMode,
+    pipe_layers_scanned_for: Option<PathBuf>,
+    pipe_layers_tx: Sender<(PathBuf, Vec<PipeLayerInfo>)>,
+    pipe_layers_rx: Receiver<(PathBuf, Vec<PipeLayerInfo>)>,
+}
+
+impl TuiConfigState {
+    /// Picks up finished pipe layer scans and starts a new one on a worker
+    /// thread when the selected organ changed.
+    fn refresh_pipe_layers(&mut self) {
+        while let Ok((path, layers)) = self.pipe_layers_rx.try_recv() {
+            if self.config_state.settings.organ_file.as_ref() == Some(&path) {
+                self.config_state.available_pipe_layers = layers;
+                self.pipe_layer_list_state.select(Some(0));
+            }
+        }
+
+        if self.pipe_layers_scanned_for == self.config_state.settings.organ_file {
+            return;
+        }
+        self.pipe_layers_scanned_for = self.config_state.settings.organ_file.clone();
+        self.config_state.available_pipe_layers.clear();
+        let Some(path) = self.config_state.settings.organ_file.clone() else {
+            return;
+        };
+        if !matches!(
+            path.extension().and_then(|e| e.to_str()),
+            Some("Organ_Hauptwerk_xml" | "xml")
+        ) {
+            return;
+        }
+        let tx = self.pipe_layers_tx.clone();
+        thread::spawn(move || {
+            let layers = organ_hauptwerk::scan_pipe_layers(&path).unwrap_or_else(|e| {
+                log::warn!("Failed to scan pipe layers of {:?}: {}", path, e);
+                Vec::new()
+            });
+            let _ = tx.send((path, layers));
+        });
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
 enum SettingRow {
     OrganFile = 0,
-    MidiDevices = 1,
-    MidiFile = 2,
-    AudioDevice = 3,
-    SampleRate = 4,
-    ReverbIRFile = 5,
-    ReverbMix = 6,
-    Gain = 7,
-    Polyphony = 8,
-    AudioBuffer = 9,
-    MaxRAMGB = 10,
-    Precache = 11,
-    ConvertTo16Bit = 12,
-    OriginalTuning = 13,
-    LcdConfiguration = 14,
-    Start = 15,
-    Quit = 16,
+    PipeLayers = 1,
+    MidiDevices = 2,
+    MidiFile = 3,
+    AudioDevice = 4,
+    SampleRate = 5,
+    ReverbIRFile = 6,
+    ReverbMix = 7,
+    Gain = 8,
+    Polyphony = 9,
+    Resampling = 10,
+    AudioBuffer = 11,
+    MaxRAMGB = 12,
+    Precache = 13,
+    ConvertTo16Bit = 14,
+    OriginalTuning = 15,
+    Temperament = 16,
+    TemperamentBaseNote = 17,
+    LcdConfiguration = 18,
+    Start = 19,
+    Quit = 20,
 }
 
 impl SettingRow {
@@ -67,22 +118,26 @@ impl SettingRow {
     pub fn from_index(index: usize) -> Option<Self> {
         match index {
             0 => Some(Self::OrganFile),
-            1 => Some(Self::MidiDevices),
-            2 => Some(Self::MidiFile),
-            3 => Some(Self::AudioDevice),
-            4 => Some(Self::SampleRate),
-            5 => Some(Self::ReverbIRFile),
-            6 => Some(Self::ReverbMix),
-            7 => Some(Self::Gain),
-            8 => Some(Self::Polyphony),
-            9 => Some(Self::AudioBuffer),
-            10 => Some(Self::MaxRAMGB),
-            11 => Some(Self::Precache),
-            12 => Some(Self::ConvertTo16Bit),
-            13 => Some(Self::OriginalTuning),
-            14 => Some(Self::LcdConfiguration),
-            15 => Some(Self::Start),
-            16 => Some(Self::Quit),
+            1 => Some(Self::PipeLayers),
+            2 => Some(Self::MidiDevices),
+            3 => Some(Self::MidiFile),
+            4 => Some(Self::AudioDevice),
+            5 => Some(Self::SampleRate),
+            6 => Some(Self::ReverbIRFile),
+            7 => Some(Self::ReverbMix),
+            8 => Some(Self::Gain),
+            9 => Some(Self::Polyphony),
+            10 => Some(Self::Resampling),
+            11 => Some(Self::AudioBuffer),
+            12 => Some(Self::MaxRAMGB),
+            13 => Some(Self::Precache),
+            14 => Some(Self::ConvertTo16Bit),
+            15 => Some(Self::OriginalTuning),
+            16 => Some(Self::Temperament),
+            17 => Some(Self::TemperamentBaseNote),
+            18 => Some(Self::LcdConfiguration),
+            19 => Some(Self::Start),
+            20 => Some(Self::Quit),
             _ => None,
         }
     }
@@ -98,6 +153,19 @@ fn get_item_display(idx: usize, state: &ConfigState) -> String {
             val = path_to_str(settings.organ_file.as_deref())
         )
         .to_string(),
+        SettingRow::PipeLayers => {
+            let val = if state.available_pipe_layers.is_empty() {
+                t!("config.status_none").to_string()
+            } else {
+                state
+                    .effective_pipe_layers()
+                    .iter()
+                    .map(|mix| format!("{} ({:.2})", mix.layer, mix.level))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            t!("tui_config.fmt_pipe_layers", val = val).to_string()
+        }
         SettingRow::AudioDevice => {
             let val = state
                 .selected_audio_device_name
@@ -129,6 +197,12 @@ fn get_item_display(idx: usize, state: &ConfigState) -> String {
             t!("tui_config.fmt_gain", val = format!("{:.2}", settings.gain)).to_string()
         }
         SettingRow::Polyphony => t!("tui_config.fmt_poly", val = settings.polyphony).to_string(),
+        SettingRow::Resampling => t!(
+            "tui_config.fmt_resampling",
+            val = settings.resampling_quality.display_name(),
+            cost = settings.resampling_quality.relative_cost()
+        )
+        .to_string(),
         SettingRow::AudioBuffer => {
             t!("tui_config.fmt_buffer", val = settings.audio_buffer_frames).to_string()
         }
@@ -148,6 +222,14 @@ fn get_item_display(idx: usize, state: &ConfigState) -> String {
             val = bool_to_str(settings.original_tuning)
         )
         .to_string(),
+        SettingRow::Temperament => {
+            t!("tui_config.fmt_temperament", val = settings.temperament).to_string()
+        }
+        SettingRow::TemperamentBaseNote => t!(
+            "tui_config.fmt_temperament_base",
+            val = NOTE_NAMES[settings.temperament_base_note as usize % 12]
+        )
+        .to_string(),
         SettingRow::LcdConfiguration => t!(
             "tui_config.fmt_lcd_config",
             count = settings.lcd_displays.len()
@@ -212,6 +294,7 @@ pub fn run_config_ui(
     let mut ir_list_state = ListState::default();
     ir_list_state.select(Some(initial_ir_index));
 
+    let (pipe_layers_tx, pipe_layers_rx) = mpsc::channel();
     let mut state = TuiConfigState {
         config_state,
         list_state: ListState::default(),
@@ -220,15 +303,21 @@ pub fn run_config_ui(
         midi_mapping_state: tui_midi::TuiMidiState::new(),
         sample_rate_list_state: ListState::default(),
         ir_list_state,
+        pipe_layer_list_state: ListState::default(),
         lcd_state: tui_lcd::TuiLcdState::new(),
         mode: ConfigMode::Main,
+        pipe_layers_scanned_for: None,
+        pipe_layers_tx,
+        pipe_layers_rx,
     };
     state.list_state.select(Some(0));
     state.midi_dev_list_state.select(Some(0));
+    state.pipe_layer_list_state.select(Some(0));
 
     let mut final_config: Option<RuntimeConfig> = None;
 
     'config_loop: loop {
+        state.refresh_pipe_layers();
         terminal.draw(|f| draw_config_ui(f, &mut state))?;
 
         if !event::poll(Duration::from_millis(50))? {
@@ -248,11 +337,11 @@ pub fn run_config_ui(
                     match key.code {
                         KeyCode::Char('q') | KeyCode::Esc => break 'config_loop,
                         KeyCode::Down | KeyCode::Char('j') => {
-                            let i = state.list_state.selected().map_or(0, |i| (i + 1) % 17);
+                            let i = state.list_state.selected().map_or(0, |i| (i + 1) % 21);
                             state.list_state.select(Some(i));
                         }
                         KeyCode::Up | KeyCode::Char('k') => {
-                            let i = state.list_state.selected().map_or(16, |i| (i + 16) % 17);
+                            let i = state.list_state.selected().map_or(20, |i| (i + 20) % 21);
                             state.list_state.select(Some(i));
                         }
                         KeyCode::Enter => {
@@ -267,6 +356,12 @@ pub fn run_config_ui(
                                         )?;
                                         if let Some(p) = path {
                                             state.config_state.settings.organ_file = Some(p);
+                                            state.refresh_pipe_layers();
+                                        }
+                                    }
+                                    SettingRow::PipeLayers => {
+                                        if state.config_state.available_pipe_layers.len() > 1 {
+                                            state.mode = ConfigMode::PipeLayerSelection;
                                         }
                                     }
                                     SettingRow::AudioDevice => {
@@ -309,6 +404,16 @@ pub fn run_config_ui(
                                             state.config_state.settings.polyphony.to_string();
                                         state.mode = ConfigMode::TextInput(idx, polyphony);
                                     }
+                                    SettingRow::Resampling => {
+                                        // Cycle through the resampling qualities
+                                        let quality =
+                                            &mut state.config_state.settings.resampling_quality;
+                                        let next = ResamplingQuality::ALL
+                                            .iter()
+                                            .position(|q| q == quality)
+                                            .map_or(0, |i| (i + 1) % ResamplingQuality::ALL.len());
+                                        *quality = ResamplingQuality::ALL[next];
+                                    }
                                     SettingRow::AudioBuffer => {
                                         // Audio Buffer
                                         let buffer = state
@@ -338,6 +443,25 @@ pub fn run_config_ui(
                                         state.config_state.settings.original_tuning =
                                             !state.config_state.settings.original_tuning
                                     }
+                                    SettingRow::Temperament => {
+                                        // Cycle through the available temperaments
+                                        let cs = &mut state.config_state;
+                                        let next = cs
+                                            .available_temperaments
+                                            .iter()
+                                            .position(|t| t.name == cs.settings.temperament)
+                                            .map_or(0, |i| {
+                                                (i + 1) % cs.available_temperaments.len()
+                                            });
+                                        if let Some(t) = cs.available_temperaments.get(next) {
+                                            cs.settings.temperament = t.name.clone();
+                                        }
+                                    }
+                                    SettingRow::TemperamentBaseNote => {
+                                        let settings = &mut state.config_state.settings;
+                                        settings.temperament_base_note =
+                                            (settings.temperament_base_note + 1) % 12;
+                                    }
                                     SettingRow::LcdConfiguration => {
                                         state.mode = ConfigMode::LcdConfig;
                                     }
@@ -375,6 +499,16 @@ pub fn run_config_ui(
                                                 active_midi_devices: active_devices,
                                                 gain: s.gain,
                                                 polyphony: s.polyphony,
+                                                tracker_delay_scale: s.tracker_delay_scale,
+                                                temperament: s.temperament.clone(),
+                                                temperament_base_note: s.temperament_base_note,
+                                                master_tuning_cents: s.master_tuning_cents,
+                                                transpose: s.transpose,
+                                                loop_selection: s.loop_selection,
+                                                resampling_quality: s.resampling_quality,
+                                                pipe_layers: s.pipe_layers.clone(),
+                                                output_buses: s.output_buses.clone(),
+                                                output_routes: s.output_routes.clone(),
                                                 audio_device_name: state
                                                     .config_state
                                                     .selected_audio_device_name
@@ -456,6 +590,57 @@ pub fn run_config_ui(
                         _ => {}
                     }
                 }
+                ConfigMode::PipeLayerSelection => {
+                    let count = state.config_state.available_pipe_layers.len();
+                    let selected = state
+                        .pipe_layer_list_state
+                        .selected()
+                        .and_then(|idx| state.config_state.available_pipe_layers.get(idx))
+                        .map(|info| info.number);
+                    let current = selected.and_then(|layer| {
+                        state
+                            .config_state
+                            .effective_pipe_layers()
+                            .into_iter()
+                            .find(|mix| mix.layer == layer)
+                    });
+                    match key.code {
+                        KeyCode::Esc => state.mode = ConfigMode::Main,
+                        KeyCode::Down | KeyCode::Char('j') if count > 0 => {
+                            let i = state
+                                .pipe_layer_list_state
+                                .selected()
+                                .map_or(0, |i| (i + 1) % count);
+                            state.pipe_layer_list_state.select(Some(i));
+                        }
+                        KeyCode::Up | KeyCode::Char('k') if count > 0 => {
+                            let i = state
+                                .pipe_layer_list_state
+                                .selected()
+                                .map_or(count - 1, |i| (i + count - 1) % count);
+                            state.pipe_layer_list_state.select(Some(i));
+                        }
+                        KeyCode::Char(' ') | KeyCode::Enter => {
+                            if let Some(layer) = selected {
+                                let level = if current.is_some() { None } else { Some(1.0) };
+                                state.config_state.set_pipe_layer(layer, level);
+                            }
+                        }
+                        KeyCode::Left | KeyCode::Right => {
+                            if let (Some(layer), Some(mix)) = (selected, current) {
+                                let step = if key.code == KeyCode::Left {
+                                    -0.05
+                                } else {
+                                    0.05
+                                };
+                                state
+                                    .config_state
+                                    .set_pipe_layer(layer, Some(mix.level + step));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
                 ConfigMode::MidiMapping(idx) => {
                     let action = if let Some(device) =
                         state.config_state.settings.midi_devices.get_mut(idx)
@@ -764,6 +949,9 @@ fn draw_config_ui(frame: &mut Frame, state: &mut TuiConfigState) {
         ConfigMode::MidiDeviceList => {
             draw_midi_device_list(frame, state);
         }
+        ConfigMode::PipeLayerSelection => {
+            draw_pipe_layer_list(frame, state);
+        }
         ConfigMode::LcdConfig => {
             tui_lcd::draw(
                 frame,
@@ -867,6 +1055,40 @@ fn draw_midi_device_list(frame: &mut Frame, state: &mut TuiConfigState) {
     frame.render_stateful_widget(list, area, &mut state.midi_dev_list_state);
 }
 
+fn draw_pipe_layer_list(frame: &mut Frame, state: &mut TuiConfigState) {
+    let area = centered_rect(frame.area(), 60, 50);
+
+    let selection = state.config_state.effective_pipe_layers();
+    let items: Vec<ListItem> = state
+        .config_state
+        .available_pipe_layers
+        .iter()
+        .map(|info| {
+            let label = t!(
+                "config.fmt_pipe_layer",
+                layer = info.number,
+                count = info.pipe_count
+            );
+            match selection.iter().find(|mix| mix.layer == info.number) {
+                Some(mix) => ListItem::new(format!("[x] {}  {:.2}", label, mix.level)),
+                None => ListItem::new(format!("[ ] {}", label)),
+            }
+        })
+        .collect();
+
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(t!("tui_config.title_pipe_layers"))
+        .title_bottom(t!("tui_config.footer_pipe_layers"));
+
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().bg(Color::Cyan).fg(Color::Black));
+
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut state.pipe_layer_list_state);
+}
+
 fn draw_modal_list(frame: &mut Frame, title: &str, items: &[String], list_state: &mut ListState) {
     let area = centered_rect(frame.area(), 60, 50);
 
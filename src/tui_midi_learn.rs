@@ -1,4 +1,5 @@
 use crate::app_state::AppState;
+use crate::config::MidiEventSpec;
 use crossterm::event::KeyCode;
 use ratatui::{
     prelude::*,
@@ -13,8 +14,12 @@ use std::time::Instant;
 pub enum LearnTarget {
     Stop(usize),
     Tremulant(String),
+    Coupler(String),
+    Enclosure(String),
     #[allow(dead_code)]
     Preset(usize),
+    Transpose,
+    MasterTuning,
 }
 
 impl Default for LearnTarget {
@@ -63,6 +68,18 @@ impl MidiLearnTuiState {
         self.common_reset();
     }
 
+    pub fn reset_coupler(&mut self, coupler_id: String, coupler_name: String) {
+        self.target = LearnTarget::Coupler(coupler_id);
+        self.target_name = coupler_name;
+        self.common_reset();
+    }
+
+    pub fn reset_enclosure(&mut self, enclosure_id: String, enclosure_name: String) {
+        self.target = LearnTarget::Enclosure(enclosure_id);
+        self.target_name = enclosure_name;
+        self.common_reset();
+    }
+
     #[allow(dead_code)]
     pub fn reset_preset(&mut self, slot: usize) {
         self.target = LearnTarget::Preset(slot);
@@ -70,6 +87,18 @@ impl MidiLearnTuiState {
         self.common_reset();
     }
 
+    pub fn reset_transpose(&mut self) {
+        self.target = LearnTarget::Transpose;
+        self.target_name = t!("gui.transpose_label").to_string();
+        self.common_reset();
+    }
+
+    pub fn reset_master_tuning(&mut self) {
+        self.target = LearnTarget::MasterTuning;
+        self.target_name = t!("gui.master_tuning_label").to_string();
+        self.common_reset();
+    }
+
     fn common_reset(&mut self) {
         self.row_idx = 0;
         self.col_idx = 0;
@@ -105,11 +134,10 @@ impl MidiLearnTuiState {
             KeyCode::Right => self.col_idx = (self.col_idx + 1).min(2),
 
             KeyCode::Enter => {
-                // For Tremulants, we only have one "row" (index 0)
-                let effective_row = if let LearnTarget::Tremulant(_) = self.target {
-                    0
-                } else {
-                    self.row_idx
+                // Only Stops have one row per internal channel, the rest use row 0
+                let effective_row = match self.target {
+                    LearnTarget::Stop(_) => self.row_idx,
+                    _ => 0,
                 };
 
                 match self.col_idx {
@@ -133,11 +161,23 @@ impl MidiLearnTuiState {
                             LearnTarget::Tremulant(id) => {
                                 state.midi_control_map.clear_tremulant(id);
                             }
+                            LearnTarget::Coupler(id) => {
+                                state.midi_control_map.clear_coupler(id);
+                            }
+                            LearnTarget::Enclosure(id) => {
+                                state.midi_control_map.clear_enclosure(id);
+                            }
                             LearnTarget::Preset(slot) => {
                                 state.midi_control_map.clear_preset(*slot);
                             }
+                            LearnTarget::Transpose => {
+                                state.midi_control_map.clear_transpose();
+                            }
+                            LearnTarget::MasterTuning => {
+                                state.midi_control_map.clear_master_tuning();
+                            }
                         }
-                        let _ = state.midi_control_map.save(&state.organ.name);
+                        let _ = state.midi_control_map.save(&state.organ);
                     }
                     _ => {}
                 }
@@ -152,7 +192,12 @@ impl MidiLearnTuiState {
             let mut state = app_state.lock().unwrap();
 
             if let Some((event, time)) = &state.last_midi_event_received {
-                if *time > self.last_interaction {
+                // Enclosures and the master tuning can only follow a continuous controller
+                let is_ignored = matches!(
+                    self.target,
+                    LearnTarget::Enclosure(_) | LearnTarget::MasterTuning
+                ) && !matches!(event, MidiEventSpec::ControlChange { .. });
+                if *time > self.last_interaction && !is_ignored {
                     let event_clone = event.clone();
 
                     match &self.target {
@@ -171,6 +216,20 @@ impl MidiLearnTuiState {
                                 is_enable,
                             );
                         }
+                        LearnTarget::Coupler(id) => {
+                            state.midi_control_map.learn_coupler(
+                                id.clone(),
+                                event_clone.clone(),
+                                is_enable,
+                            );
+                        }
+                        LearnTarget::Enclosure(id) => {
+                            if is_enable {
+                                state
+                                    .midi_control_map
+                                    .learn_enclosure(id.clone(), event_clone.clone());
+                            }
+                        }
                         LearnTarget::Preset(slot) => {
                             if is_enable {
                                 state
@@ -178,9 +237,21 @@ impl MidiLearnTuiState {
                                     .learn_preset(*slot, event_clone.clone());
                             }
                         }
+                        LearnTarget::Transpose => {
+                            state
+                                .midi_control_map
+                                .learn_transpose(event_clone.clone(), is_enable);
+                        }
+                        LearnTarget::MasterTuning => {
+                            if is_enable {
+                                state
+                                    .midi_control_map
+                                    .learn_master_tuning(event_clone.clone());
+                            }
+                        }
                     }
 
-                    let _ = state.midi_control_map.save(&state.organ.name);
+                    let _ = state.midi_control_map.save(&state.organ);
                     self.learning_slot = None;
 
                     let action_text = if is_enable {
@@ -257,6 +328,29 @@ pub fn draw_midi_learn_modal(
                 tui_state,
             )]
         }
+        LearnTarget::Coupler(id) => {
+            let control = app_state
+                .midi_control_map
+                .couplers
+                .get(id)
+                .cloned()
+                .unwrap_or_default();
+            let label = "Coupler".to_string();
+            // Single row, index 0
+            vec![build_row(
+                0,
+                label,
+                control.enable_event.clone(),
+                control.disable_event.clone(),
+                tui_state,
+            )]
+        }
+        LearnTarget::Enclosure(id) => {
+            let controller = app_state.midi_control_map.enclosures.get(id).cloned();
+            let label = "Swell Pedal".to_string();
+            // Single row, Enable column is used for the controller, Disable is N/A
+            vec![build_row(0, label, controller, None, tui_state)]
+        }
         LearnTarget::Preset(slot) => {
             let trigger = app_state
                 .midi_control_map
@@ -268,6 +362,27 @@ pub fn draw_midi_learn_modal(
             // Single row, Enable column is used for Trigger, Disable is N/A
             vec![build_row(0, label, trigger, None, tui_state)]
         }
+        LearnTarget::Transpose => {
+            let control = app_state.midi_control_map.transpose.clone();
+            // Enable steps up, Disable steps down
+            vec![build_row(
+                0,
+                t!("gui.transpose_label").to_string(),
+                control.enable_event,
+                control.disable_event,
+                tui_state,
+            )]
+        }
+        LearnTarget::MasterTuning => {
+            let controller = app_state.midi_control_map.master_tuning.clone();
+            vec![build_row(
+                0,
+                t!("gui.master_tuning_label").to_string(),
+                controller,
+                None,
+                tui_state,
+            )]
+        }
     };
 
     let widths = [
@@ -277,10 +392,22 @@ pub fn draw_midi_learn_modal(
         Constraint::Length(10),
     ];
 
+    let (enable_header, disable_header) = if tui_state.target == LearnTarget::Transpose {
+        (
+            t!("midi_learn.col_transpose_up"),
+            t!("midi_learn.col_transpose_down"),
+        )
+    } else {
+        (
+            t!("midi_learn.col_enable_event"),
+            t!("midi_learn.col_disable_event"),
+        )
+    };
+
     let header_row = Row::new(vec![
         t!("tui_midi_learn.hdr_internal").to_string(),
-        t!("midi_learn.col_enable_event").to_string(),
-        t!("midi_learn.col_disable_event").to_string(),
+        enable_header.to_string(),
+        disable_header.to_string(),
         t!("tui_midi_learn.hdr_action").to_string(),
     ])
     .style(
@@ -1,6 +1,7 @@
 use crate::app::MainLoopAction;
 use crate::app_state::AppState;
 use crate::config::{OrganLibrary, OrganProfile, load_organ_library, save_organ_library};
+use crate::organ_export::{default_export_dir, export_in_background};
 use crate::tui_filepicker::run_file_picker;
 use crossterm::event::KeyCode;
 use ratatui::{
@@ -75,7 +76,7 @@ impl OrganManagerTuiState {
         key: KeyCode,
         terminal: &mut TuiTerminal, // Needed for file picker
         exit_action: &Arc<Mutex<MainLoopAction>>,
-        _app_state: &Arc<Mutex<AppState>>,
+        app_state: &Arc<Mutex<AppState>>,
     ) -> bool {
         // If we are confirming removal
         if let Some(remove_idx) = self.confirm_remove_index {
@@ -169,6 +170,15 @@ impl OrganManagerTuiState {
                 }
             }
 
+            // Export the loaded organ (the TUI has no folder picker, use the default folder)
+            KeyCode::Char('e') => {
+                let organ_name = app_state.lock().unwrap().organ.name.clone();
+                match default_export_dir(&organ_name) {
+                    Ok(output_dir) => export_in_background(app_state, output_dir),
+                    Err(e) => log::error!("Failed to get export folder: {}", e),
+                }
+            }
+
             // Learn Trigger
             KeyCode::Char('i') => {
                 if let Some(idx) = self.list_state.selected() {
@@ -288,9 +298,10 @@ pub fn draw_organ_manager(
     } else {
         // Help
         format!(
-            "Enter: {} | a: {} | d: Remove | i: {} | c: {} | Esc: Close",
+            "Enter: {} | a: {} | d: Remove | e: {} | i: {} | c: {} | Esc: Close",
             t!("organ_manager.load"),
             t!("organ_manager.add_organ"),
+            t!("organ_manager.export_organ"),
             t!("midi_learn.btn_learn"),
             t!("midi_learn.btn_clear")
         )
@@ -0,0 +1,382 @@
+use crate::app::MainLoopAction;
+use crate::app_state::AppState;
+use crate::organ::Organ;
+use crate::virtual_stops::{
+    MAX_VIRTUAL_STOP_TRANSPOSE, VirtualStop, VirtualStopRank, VirtualStopSet,
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
+};
+use rust_i18n::t;
+use std::sync::{Arc, Mutex};
+
+/// Rows of the stop editor: name, manual, one per rank, then the "add rank" row.
+const NAME_ROW: usize = 0;
+const MANUAL_ROW: usize = 1;
+const FIRST_RANK_ROW: usize = 2;
+
+struct EditState {
+    // Index in the set of the stop being edited, None for a new stop
+    index: Option<usize>,
+    draft: VirtualStop,
+    row: usize,
+    // Index into the sorted rank IDs offered on the "add rank" row
+    rank_choice: usize,
+}
+
+pub struct VirtualStopsTuiState {
+    set: VirtualStopSet,
+    organ_name: String,
+    list_state: ListState,
+    editing: Option<EditState>,
+    // If Some, we are confirming removal of the stop at this index
+    confirm_remove_index: Option<usize>,
+    status: Option<String>,
+}
+
+impl VirtualStopsTuiState {
+    pub fn new(organ_name: &str) -> Self {
+        let set = VirtualStopSet::load(organ_name);
+        let mut list_state = ListState::default();
+        if !set.stops.is_empty() {
+            list_state.select(Some(0));
+        }
+        Self {
+            set,
+            organ_name: organ_name.to_string(),
+            list_state,
+            editing: None,
+            confirm_remove_index: None,
+            status: None,
+        }
+    }
+
+    /// Handles input for the virtual stop editor.
+    /// Returns true if the TUI should close the editor. If the organ has to be
+    /// reloaded, `exit_action` is set to `MainLoopAction::Continue`.
+    pub fn handle_input(
+        &mut self,
+        key: KeyCode,
+        exit_action: &Arc<Mutex<MainLoopAction>>,
+        app_state: &Arc<Mutex<AppState>>,
+    ) -> bool {
+        if self.editing.is_some() {
+            let organ = app_state.lock().unwrap().organ.clone();
+            self.handle_edit_input(key, &organ);
+            return false;
+        }
+
+        if let Some(remove_idx) = self.confirm_remove_index {
+            match key {
+                KeyCode::Char('y') | KeyCode::Enter => {
+                    self.set.stops.remove(remove_idx);
+                    self.save();
+                    if self.set.stops.is_empty() {
+                        self.list_state.select(None);
+                    } else if remove_idx >= self.set.stops.len() {
+                        self.list_state.select(Some(self.set.stops.len() - 1));
+                    }
+                    self.confirm_remove_index = None;
+                }
+                KeyCode::Char('n') | KeyCode::Esc => {
+                    self.confirm_remove_index = None;
+                }
+                _ => {}
+            }
+            return false;
+        }
+
+        match key {
+            KeyCode::Esc => return true,
+            KeyCode::Up => {
+                if let Some(i) = self.list_state.selected() {
+                    self.list_state.select(Some(i.saturating_sub(1)));
+                }
+            }
+            KeyCode::Down => {
+                if let Some(i) = self.list_state.selected() {
+                    self.list_state
+                        .select(Some((i + 1).min(self.set.stops.len() - 1)));
+                }
+            }
+            KeyCode::Char('n') => {
+                self.status = None;
+                self.editing = Some(EditState {
+                    index: None,
+                    draft: VirtualStop::default(),
+                    row: NAME_ROW,
+                    rank_choice: 0,
+                });
+            }
+            KeyCode::Enter => {
+                if let Some(idx) = self.list_state.selected() {
+                    self.status = None;
+                    self.editing = Some(EditState {
+                        index: Some(idx),
+                        draft: self.set.stops[idx].clone(),
+                        row: NAME_ROW,
+                        rank_choice: 0,
+                    });
+                }
+            }
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if let Some(idx) = self.list_state.selected() {
+                    self.confirm_remove_index = Some(idx);
+                }
+            }
+            // Reload the same organ so the stops are merged in again
+            KeyCode::Char('a') => {
+                *exit_action.lock().unwrap() = MainLoopAction::Continue;
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn handle_edit_input(&mut self, key: KeyCode, organ: &Organ) {
+        let Some(edit) = self.editing.as_mut() else {
+            return;
+        };
+        let mut rank_ids: Vec<&String> = organ.ranks.keys().collect();
+        rank_ids.sort();
+        let mut manual_ids: Vec<&String> = organ.manuals.keys().collect();
+        manual_ids.sort();
+        let add_row = FIRST_RANK_ROW + edit.draft.ranks.len();
+
+        match (key, edit.row) {
+            (KeyCode::Esc, _) => self.editing = None,
+            (KeyCode::Up, _) => edit.row = edit.row.saturating_sub(1),
+            (KeyCode::Down, _) => edit.row = (edit.row + 1).min(add_row),
+
+            // Name
+            (KeyCode::Char(c), NAME_ROW) => edit.draft.name.push(c),
+            (KeyCode::Backspace, NAME_ROW) => {
+                edit.draft.name.pop();
+            }
+
+            // Manual: cycle through "none" and the organ's manuals
+            (KeyCode::Left | KeyCode::Right, MANUAL_ROW) => {
+                let current = edit
+                    .draft
+                    .manual_id
+                    .as_ref()
+                    .and_then(|id| manual_ids.iter().position(|m| *m == id))
+                    .map_or(0, |i| i + 1);
+                let count = manual_ids.len() + 1;
+                let next = if key == KeyCode::Right {
+                    (current + 1) % count
+                } else {
+                    (current + count - 1) % count
+                };
+                edit.draft.manual_id = next.checked_sub(1).map(|i| manual_ids[i].clone());
+            }
+
+            // Add rank
+            (KeyCode::Left, row) if row == add_row => {
+                edit.rank_choice = edit.rank_choice.saturating_sub(1);
+            }
+            (KeyCode::Right, row) if row == add_row => {
+                edit.rank_choice = (edit.rank_choice + 1).min(rank_ids.len().saturating_sub(1));
+            }
+            (KeyCode::Enter, row) if row == add_row => {
+                if let Some(rank_id) = rank_ids.get(edit.rank_choice) {
+                    edit.draft.ranks.push(VirtualStopRank {
+                        rank_id: (*rank_id).clone(),
+                        ..Default::default()
+                    });
+                    edit.row += 1;
+                }
+            }
+
+            // Rank rows: transposition, gain and removal
+            (KeyCode::Left, row) if row >= FIRST_RANK_ROW => {
+                let rank = &mut edit.draft.ranks[row - FIRST_RANK_ROW];
+                rank.transpose = (rank.transpose - 1).max(-MAX_VIRTUAL_STOP_TRANSPOSE);
+            }
+            (KeyCode::Right, row) if row >= FIRST_RANK_ROW => {
+                let rank = &mut edit.draft.ranks[row - FIRST_RANK_ROW];
+                rank.transpose = (rank.transpose + 1).min(MAX_VIRTUAL_STOP_TRANSPOSE);
+            }
+            (KeyCode::Char('-'), row) if row >= FIRST_RANK_ROW => {
+                let rank = &mut edit.draft.ranks[row - FIRST_RANK_ROW];
+                rank.gain_db = (rank.gain_db - 0.5).max(-24.0);
+            }
+            (KeyCode::Char('+'), row) if row >= FIRST_RANK_ROW => {
+                let rank = &mut edit.draft.ranks[row - FIRST_RANK_ROW];
+                rank.gain_db = (rank.gain_db + 0.5).min(12.0);
+            }
+            (KeyCode::Delete | KeyCode::Backspace, row) if row >= FIRST_RANK_ROW => {
+                edit.draft.ranks.remove(row - FIRST_RANK_ROW);
+            }
+
+            // Save the stop
+            (KeyCode::Enter, _) => {
+                if let Err(e) = self.set.validate(organ, &edit.draft, edit.index) {
+                    self.status = Some(t!("virtual_stops.save_failed", err = e).to_string());
+                    return;
+                }
+                let index = self.set.upsert(edit.draft.clone(), edit.index);
+                self.list_state.select(Some(index));
+                self.editing = None;
+                self.save();
+            }
+            _ => {}
+        }
+    }
+
+    fn save(&mut self) {
+        self.status = Some(match self.set.save(&self.organ_name) {
+            Ok(()) => t!("virtual_stops.saved").to_string(),
+            Err(e) => {
+                log::error!("Failed to save virtual stops: {}", e);
+                t!("virtual_stops.save_failed", err = e).to_string()
+            }
+        });
+    }
+}
+
+pub fn draw_virtual_stops(frame: &mut Frame, state: &mut VirtualStopsTuiState, organ: &Organ) {
+    let area = centered_rect(frame.area(), 80, 80);
+    frame.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" {} ", t!("virtual_stops.title")))
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Cyan));
+
+    frame.render_widget(block.clone(), area);
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints([
+            Constraint::Min(5),    // List or editor
+            Constraint::Length(5), // Info/Help
+        ])
+        .split(area);
+
+    let rank_label = |id: &str| match organ.ranks.get(id) {
+        Some(rank) => format!("{} ({})", rank.name, id),
+        None => id.to_string(),
+    };
+
+    let help = if let Some(edit) = &state.editing {
+        // --- Stop Editor ---
+        let mut rank_ids: Vec<&String> = organ.ranks.keys().collect();
+        rank_ids.sort();
+        let manual = match &edit.draft.manual_id {
+            Some(id) => organ.manuals.get(id).map_or(id.clone(), |m| m.name.clone()),
+            None => t!("virtual_stops.no_manual").to_string(),
+        };
+
+        let mut items = vec![
+            ListItem::new(format!(
+                "{}: {}_",
+                t!("virtual_stops.name"),
+                edit.draft.name
+            )),
+            ListItem::new(format!("{}: < {} >", t!("virtual_stops.manual"), manual)),
+        ];
+        for rank in &edit.draft.ranks {
+            items.push(ListItem::new(format!(
+                "   {}  {}{:+}  {:+.1} dB",
+                rank_label(&rank.rank_id),
+                t!("virtual_stops.transpose"),
+                rank.transpose,
+                rank.gain_db
+            )));
+        }
+        let candidate = rank_ids
+            .get(edit.rank_choice)
+            .map_or("-".to_string(), |id| rank_label(id));
+        items.push(ListItem::new(format!(
+            "{}: < {} >",
+            t!("virtual_stops.add_rank"),
+            candidate
+        )));
+
+        let list = List::new(items)
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+            .highlight_symbol("");
+        let mut list_state = ListState::default();
+        list_state.select(Some(edit.row));
+        frame.render_stateful_widget(list, layout[0], &mut list_state);
+
+        t!("virtual_stops.tui_edit_help").to_string()
+    } else {
+        // --- Virtual Stop List ---
+        let items: Vec<ListItem> = state
+            .set
+            .stops
+            .iter()
+            .map(|stop| {
+                let ranks: Vec<String> = stop
+                    .ranks
+                    .iter()
+                    .map(|r| format!("{} {:+}", rank_label(&r.rank_id), r.transpose))
+                    .collect();
+                ListItem::new(format!("{}\n      {}", stop.name, ranks.join(", ")))
+            })
+            .collect();
+
+        if items.is_empty() {
+            frame.render_widget(Paragraph::new(t!("virtual_stops.empty")), layout[0]);
+        } else {
+            let list = List::new(items)
+                .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+                .highlight_symbol("");
+            frame.render_stateful_widget(list, layout[0], &mut state.list_state);
+        }
+
+        if let Some(idx) = state.confirm_remove_index {
+            t!(
+                "virtual_stops.remove_confirm",
+                name = state.set.stops[idx].name
+            )
+            .to_string()
+        } else {
+            t!("virtual_stops.tui_help").to_string()
+        }
+    };
+
+    // --- Footer / Status ---
+    let footer_text = match &state.status {
+        Some(status) => format!("{}\n{}", status, help),
+        None => help,
+    };
+    let p = Paragraph::new(footer_text)
+        .wrap(Wrap { trim: true })
+        .style(
+            Style::default().fg(if state.confirm_remove_index.is_some() {
+                Color::Yellow
+            } else {
+                Color::White
+            }),
+        )
+        .block(Block::default().borders(Borders::TOP));
+
+    frame.render_widget(p, layout[1]);
+}
+
+fn centered_rect(r: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
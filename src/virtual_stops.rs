@@ -0,0 +1,257 @@
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use utoipa::ToSchema;
+
+use crate::organ::{Organ, Stop, StopRankOffset};
+
+/// Largest transposition of a rank in a virtual stop, in semitones
+pub const MAX_VIRTUAL_STOP_TRANSPOSE: i8 = 24;
+
+/// One rank played by a virtual stop.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, Default, PartialEq)]
+pub struct VirtualStopRank {
+    pub rank_id: String,
+    /// Semitones added to the played key (12 = a 4' from an 8' rank)
+    #[serde(default)]
+    pub transpose: i8,
+    #[serde(default)]
+    pub gain_db: f32,
+}
+
+/// A user-defined stop that combines existing ranks.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, Default, PartialEq)]
+pub struct VirtualStop {
+    /// Stop ID, assigned when the stop is first saved and kept across edits
+    /// and deletions of other stops
+    #[serde(default)]
+    #[schema(read_only)]
+    pub id: String,
+    pub name: String,
+    /// Manual the stop is drawn on, so couplers pick it up
+    #[serde(default)]
+    pub manual_id: Option<String>,
+    pub ranks: Vec<VirtualStopRank>,
+}
+
+/// The virtual stops of one organ, stored next to its MIDI map.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VirtualStopSet {
+    pub stops: Vec<VirtualStop>,
+    /// Number of the next stop ID, so IDs of deleted stops are not handed out again
+    #[serde(default)]
+    pub next_id: u32,
+}
+
+impl VirtualStopSet {
+    pub fn get_file_path(organ_name: &str) -> PathBuf {
+        let config_path = confy::get_configuration_file_path("rusty-pipes", "settings")
+            .expect("Could not get configuration file path");
+        let parent = config_path.parent().expect("Could not get config parent");
+        let safe_name: String = organ_name
+            .chars()
+            .map(|x| if x.is_alphanumeric() { x } else { '_' })
+            .collect();
+        parent.join(format!("{}.virtual_stops.json", safe_name))
+    }
+
+    pub fn load(organ_name: &str) -> Self {
+        let path = Self::get_file_path(organ_name);
+        if path.exists() {
+            match File::open(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|file| Ok(serde_json::from_reader::<_, Self>(BufReader::new(file))?))
+            {
+                Ok(mut set) => {
+                    set.assign_missing_ids();
+                    return set;
+                }
+                Err(e) => log::warn!("Could not read virtual stops from {:?}: {}", path, e),
+            }
+        }
+        Self::default()
+    }
+
+    pub fn save(&self, organ_name: &str) -> Result<()> {
+        let path = Self::get_file_path(organ_name);
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Gives stops saved before IDs were stored the ID their position used to imply.
+    fn assign_missing_ids(&mut self) {
+        for i in 0..self.stops.len() {
+            if self.stops[i].id.is_empty() {
+                let candidate = format!("V{:03}", i + 1);
+                self.stops[i].id = if self.stops.iter().any(|s| s.id == candidate) {
+                    self.allocate_id()
+                } else {
+                    candidate
+                };
+            }
+        }
+    }
+
+    /// An ID no stop of the set uses or has used.
+    fn allocate_id(&mut self) -> String {
+        let highest = self
+            .stops
+            .iter()
+            .filter_map(|s| s.id.strip_prefix('V')?.parse::<u32>().ok())
+            .max()
+            .unwrap_or(0);
+        let number = self.next_id.max(highest + 1);
+        self.next_id = number + 1;
+        format!("V{:03}", number)
+    }
+
+    /// Stores a validated stop, replacing the one at `replace_index` (whose ID it
+    /// keeps) or appending it with a new ID. Returns the stop's index.
+    pub fn upsert(&mut self, mut stop: VirtualStop, replace_index: Option<usize>) -> usize {
+        stop.name = stop.name.trim().to_string();
+        match replace_index {
+            Some(i) => {
+                stop.id = self.stops[i].id.clone();
+                self.stops[i] = stop;
+                i
+            }
+            None => {
+                stop.id = self.allocate_id();
+                self.stops.push(stop);
+                self.stops.len() - 1
+            }
+        }
+    }
+
+    /// Checks a virtual stop before it is added to the set. `replace_index` is the
+    /// stop being edited, whose own name doesn't count as a duplicate.
+    pub fn validate(
+        &self,
+        organ: &Organ,
+        stop: &VirtualStop,
+        replace_index: Option<usize>,
+    ) -> Result<()> {
+        let name = stop.name.trim();
+        if name.is_empty() {
+            return Err(anyhow!("The stop needs a name"));
+        }
+        if stop.ranks.is_empty() {
+            return Err(anyhow!("'{}' has no ranks", name));
+        }
+        let name_taken = organ.stops.iter().any(|s| !s.is_virtual && s.name == name)
+            || self
+                .stops
+                .iter()
+                .enumerate()
+                .any(|(i, s)| Some(i) != replace_index && s.name == name);
+        if name_taken {
+            return Err(anyhow!("A stop named '{}' already exists", name));
+        }
+        if let Some(rank) = stop
+            .ranks
+            .iter()
+            .find(|r| !organ.ranks.contains_key(&r.rank_id))
+        {
+            return Err(anyhow!("Rank '{}' does not exist", rank.rank_id));
+        }
+        if let Some(rank) = stop
+            .ranks
+            .iter()
+            .find(|r| r.transpose.abs() > MAX_VIRTUAL_STOP_TRANSPOSE)
+        {
+            return Err(anyhow!(
+                "Rank '{}' is transposed by {} semitones, the limit is {}",
+                rank.rank_id,
+                rank.transpose,
+                MAX_VIRTUAL_STOP_TRANSPOSE
+            ));
+        }
+        if let Some(manual_id) = &stop.manual_id
+            && !organ.manuals.contains_key(manual_id)
+        {
+            return Err(anyhow!("Manual '{}' does not exist", manual_id));
+        }
+        Ok(())
+    }
+
+    /// Appends the virtual stops to the organ's stops, after the ones of the sample set
+    /// so their indices in presets and MIDI maps stay valid. Virtual stops themselves are
+    /// saved there by ID (see `key_virtual_stops_by_id`).
+    pub fn merge_into(&self, organ: &mut Organ) {
+        let mut added = 0;
+        for (i, virtual_stop) in self.stops.iter().enumerate() {
+            if let Err(e) = self.validate(organ, virtual_stop, Some(i)) {
+                log::warn!("Skipping virtual stop: {}", e);
+                continue;
+            }
+            let id_str = virtual_stop.id.clone();
+            if let Some(manual) = virtual_stop
+                .manual_id
+                .as_ref()
+                .and_then(|id| organ.manuals.get_mut(id))
+            {
+                manual.stop_ids.push(id_str.clone());
+            }
+            organ.stops.push(Stop {
+                name: virtual_stop.name.trim().to_string(),
+                id_str,
+                rank_ids: virtual_stop
+                    .ranks
+                    .iter()
+                    .map(|r| r.rank_id.clone())
+                    .collect(),
+                rank_offsets: virtual_stop
+                    .ranks
+                    .iter()
+                    .map(|r| StopRankOffset {
+                        transpose: r.transpose,
+                        gain_db: r.gain_db,
+                    })
+                    .collect(),
+                is_virtual: true,
+            });
+            added += 1;
+        }
+        if added > 0 {
+            log::info!("Added {} virtual stops.", added);
+        }
+    }
+}
+
+/// Moves saved entries of virtual stops from their stop ID to the stop's index in `organ`.
+/// Entries of virtual stops the organ doesn't have (e.g. skipped as invalid) stay in `by_id`.
+pub fn resolve_virtual_stop_ids<T>(
+    organ: &Organ,
+    by_id: &mut HashMap<String, T>,
+    by_index: &mut HashMap<usize, T>,
+) {
+    for (index, stop) in organ.stops.iter().enumerate() {
+        if stop.is_virtual
+            && let Some(entry) = by_id.remove(&stop.id_str)
+        {
+            by_index.insert(index, entry);
+        }
+    }
+}
+
+/// Moves entries of virtual stops from their index to their stop ID before saving.
+/// Virtual stops are saved by ID because their index changes when another one is
+/// removed or skipped.
+pub fn key_virtual_stops_by_id<T>(
+    organ: &Organ,
+    by_index: &mut HashMap<usize, T>,
+    by_id: &mut HashMap<String, T>,
+) {
+    for (index, stop) in organ.stops.iter().enumerate() {
+        if stop.is_virtual
+            && let Some(entry) = by_index.remove(&index)
+        {
+            by_id.insert(stop.id_str.clone(), entry);
+        }
+    }
+}
@@ -1,21 +1,40 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use decibel::{AmplitudeRatio, DecibelRatio};
-use ringbuf::traits::{Producer, Split};
+use ringbuf::traits::{Consumer, Observer, Producer};
 use ringbuf::{HeapCons, HeapProd, HeapRb};
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, Ordering, fence};
 use std::sync::{Arc, mpsc};
+use std::thread;
 use std::time::Instant;
 
 use crate::organ::Organ;
+use crate::release_align::ReleaseAlignment;
+use crate::resample::RESAMPLE_HISTORY_FRAMES;
+use crate::stereo::{PanMatrix, UNPANNED};
 
 // Common Audio Constants
 pub const CHANNEL_COUNT: usize = 2;
 pub const VOICE_BUFFER_FRAMES: usize = 14400;
 pub const CROSSFADE_TIME: f32 = 0.10;
 pub const VOICE_STEALING_FADE_TIME: f32 = 1.00;
+/// Length of the crossfade at a sustain loop's end into the audio before its start.
+pub const LOOP_CROSSFADE_TIME: f32 = 0.015;
+/// Time constant of a voice's level meter, used to match a release to its attack.
+pub const VOICE_LEVEL_TIME: f32 = 0.05;
+/// Largest gain change (either way) an aligned release gets to match its attack's level.
+pub const RELEASE_ALIGN_MAX_GAIN: f32 = 2.0;
 pub const MAX_NEW_VOICES_PER_BLOCK: usize = 28;
+/// Ring buffers preallocated per voice of polyphony. Releases, crossfades and
+/// fading voices hold buffers on top of the voices counted against the polyphony.
+pub const VOICE_POOL_BUFFERS_PER_VOICE: usize = 3;
+pub const MIN_VOICE_POOL_SIZE: usize = 64;
+pub const MAX_VOICE_POOL_SIZE: usize = 2048;
 pub const TREMULANT_AM_BOOST: f32 = 1.0;
+/// Low-pass cutoff of a fully closed swell box.
+pub const ENCLOSURE_CLOSED_CUTOFF_HZ: f32 = 1500.0;
+/// How fast the swell shutters follow the pedal (full travel per second).
+pub const ENCLOSURE_SLEW_PER_SEC: f32 = 8.0;
 
 pub struct TremulantLfo {
     pub phase: f32,
@@ -27,12 +46,159 @@ pub struct SpawnJob {
     pub organ: Arc<Organ>,
     pub sample_rate: u32,
     pub is_attack_sample: bool,
+    /// Percussive samples play to their end and ignore loop points
+    pub is_percussive: bool,
+    /// Start playback at the loop start instead of the attack (wave tremulant switching).
+    /// Samples without a loop start at the note's position, given in `frames_to_skip`.
+    pub start_in_loop: bool,
+    /// Which of the sample's loops to sustain on, taken modulo the number of loops
+    pub loop_choice: u64,
     pub frames_to_skip: usize,
     pub producer: HeapProd<f32>,
     pub is_finished: Arc<AtomicBool>,
     pub is_cancelled: Arc<AtomicBool>,
 }
 
+/// Voice buffers allocated in the background to grow a `VoicePool`.
+pub struct VoiceBuffers {
+    ring_buffers: Vec<Arc<HeapRb<f32>>>,
+    input_buffers: Vec<Vec<f32>>,
+}
+
+impl VoiceBuffers {
+    fn allocate(count: usize) -> Self {
+        Self {
+            ring_buffers: (0..count)
+                .map(|_| Arc::new(HeapRb::<f32>::new(VOICE_BUFFER_FRAMES * CHANNEL_COUNT)))
+                .collect(),
+            input_buffers: (0..count).map(|_| Vec::with_capacity(4096)).collect(),
+        }
+    }
+}
+
+/// The ring buffers and input buffers of voices, allocated up front and reused so that
+/// starting a note does not allocate its sample buffers on the audio thread. The rest
+/// of a voice (its flags, sample path and IDs) is still allocated per note.
+/// A ring buffer is free again once both its voice and its loader have let go of it.
+pub struct VoicePool {
+    ring_buffers: Vec<Arc<HeapRb<f32>>>,
+    input_buffers: Vec<Vec<f32>>,
+    next_buffer: usize,
+    loader_tx: mpsc::Sender<SpawnJob>,
+    /// Asks the allocator thread for this many more buffers
+    grow_request_tx: mpsc::Sender<usize>,
+    /// Buffers allocated for a raised polyphony arrive here
+    grow_rx: mpsc::Receiver<VoiceBuffers>,
+    /// Buffers requested but not arrived yet
+    pending_buffers: usize,
+    /// Voices that could not start because every buffer was in use, since last taken
+    exhausted_count: usize,
+}
+
+impl VoicePool {
+    /// Allocates the buffers for `polyphony` and starts the thread that allocates more
+    /// when the polyphony is raised. Call it before the audio thread starts playing.
+    pub fn new(polyphony: usize, loader_tx: mpsc::Sender<SpawnJob>) -> Self {
+        let (grow_request_tx, grow_request_rx) = mpsc::channel::<usize>();
+        let (grow_tx, grow_rx) = mpsc::channel();
+        // Ends once the pool, and with it the request sender, is dropped
+        thread::spawn(move || {
+            for count in grow_request_rx {
+                if grow_tx.send(VoiceBuffers::allocate(count)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut pool = Self {
+            // Room for the largest pool, so growing it never reallocates these
+            ring_buffers: Vec::with_capacity(MAX_VOICE_POOL_SIZE),
+            input_buffers: Vec::with_capacity(MAX_VOICE_POOL_SIZE),
+            next_buffer: 0,
+            loader_tx,
+            grow_request_tx,
+            grow_rx,
+            pending_buffers: 0,
+            exhausted_count: 0,
+        };
+        pool.add_buffers(VoiceBuffers::allocate(Self::size_for(polyphony)));
+        pool
+    }
+
+    /// Number of buffers preallocated for a polyphony.
+    pub fn size_for(polyphony: usize) -> usize {
+        (polyphony * VOICE_POOL_BUFFERS_PER_VOICE).clamp(MIN_VOICE_POOL_SIZE, MAX_VOICE_POOL_SIZE)
+    }
+
+    pub fn size(&self) -> usize {
+        self.ring_buffers.len()
+    }
+
+    /// Grows the pool to hold the voices of a (raised) polyphony. It never shrinks.
+    /// The buffers are allocated on the allocator thread and join the pool in
+    /// `receive_buffers` once they are ready.
+    pub fn reserve_for(&mut self, polyphony: usize) {
+        let missing = Self::size_for(polyphony)
+            .saturating_sub(self.ring_buffers.len() + self.pending_buffers);
+        if missing == 0 {
+            return;
+        }
+        if self.grow_request_tx.send(missing).is_ok() {
+            self.pending_buffers += missing;
+        }
+    }
+
+    /// Adds the buffers that were allocated in the background since the last call.
+    pub fn receive_buffers(&mut self) {
+        while let Ok(buffers) = self.grow_rx.try_recv() {
+            self.pending_buffers = self
+                .pending_buffers
+                .saturating_sub(buffers.ring_buffers.len());
+            self.add_buffers(buffers);
+        }
+    }
+
+    fn add_buffers(&mut self, buffers: VoiceBuffers) {
+        self.ring_buffers.extend(buffers.ring_buffers);
+        self.input_buffers.extend(buffers.input_buffers);
+        log::info!("[VoicePool] {} voice buffers", self.ring_buffers.len());
+    }
+
+    /// A cleared producer and consumer of a free ring buffer, if there is one.
+    fn acquire_ring_buffer(&mut self) -> Option<(HeapProd<f32>, HeapCons<f32>)> {
+        let count = self.ring_buffers.len();
+        for offset in 0..count {
+            let index = (self.next_buffer + offset) % count;
+            // Only the pool clones these, so a buffer held by nobody else stays free
+            if Arc::strong_count(&self.ring_buffers[index]) == 1 {
+                // Pairs with the release of the last voice or loader dropping its half
+                fence(Ordering::Acquire);
+                self.next_buffer = (index + 1) % count;
+                let ring_buffer = &self.ring_buffers[index];
+                let producer = HeapProd::new(Arc::clone(ring_buffer));
+                let mut consumer = HeapCons::new(Arc::clone(ring_buffer));
+                consumer.clear();
+                return Some((producer, consumer));
+            }
+        }
+        self.exhausted_count += 1;
+        None
+    }
+
+    /// Returns the input buffer of a voice that is done playing.
+    pub fn recycle(&mut self, voice: &mut Voice) {
+        let mut input_buffer = std::mem::take(&mut voice.input_buffer);
+        if input_buffer.capacity() > 0 && self.input_buffers.len() < self.input_buffers.capacity() {
+            input_buffer.clear();
+            self.input_buffers.push(input_buffer);
+        }
+    }
+
+    /// Voices that failed to start since the last call.
+    pub fn take_exhausted_count(&mut self) -> usize {
+        std::mem::take(&mut self.exhausted_count)
+    }
+}
+
 /// Represents one playing sample, either attack or release.
 pub struct Voice {
     pub gain: f32,
@@ -48,10 +214,31 @@ pub struct Voice {
 
     pub note_on_time: Instant,
     pub is_attack_sample: bool,
+    pub is_percussive: bool,
     pub fade_increment: f32,
 
     pub windchest_group_id: Option<String>,
+    /// One-pole low-pass state (L, R) used when the voice sits in an enclosure
+    pub enclosure_lp_state: [f32; CHANNEL_COUNT],
+    /// Frames of silence before the voice starts sounding (tracker delay)
+    pub start_delay_frames: usize,
+    /// Frames to keep sounding before a pending fade-out begins
+    pub fade_out_delay_frames: usize,
+    /// Playback speed relative to the sample (voicing tuning)
+    pub pitch_ratio: f32,
+    /// Left and right gains of the voice's stereo position (1.0 each in the center)
+    pub pan: PanMatrix,
+    /// Output bus the voice is mixed into (0 = main)
+    pub output_bus: usize,
+    /// Frames the voice may sound before it fades out (capped release length)
+    pub max_length_frames: Option<usize>,
+    /// Mean square level of the recently played input, per channel
+    pub level: f32,
+    /// Start positions of a release sample matching the phase of its attack
+    pub release_alignment: Option<ReleaseAlignment>,
 
+    /// Input read from the consumer. Played frames stay before `buffer_start_idx`
+    /// (at least `RESAMPLE_HISTORY_FRAMES`, silence at the start) for the interpolator.
     pub input_buffer: Vec<f32>,
     pub buffer_start_idx: usize,
     pub cursor_pos: f32,
@@ -66,9 +253,12 @@ impl Voice {
         gain_db: f32,
         start_fading_in: bool,
         is_attack_sample: bool,
+        is_percussive: bool,
+        start_in_loop: bool,
+        loop_choice: u64,
         note_on_time: Instant,
         preloaded_bytes: Option<Arc<Vec<f32>>>,
-        spawner_tx: &mpsc::Sender<SpawnJob>,
+        voice_pool: &mut VoicePool,
         windchest_group_id: Option<String>,
     ) -> Result<Self> {
         let fade_frames = (sample_rate as f32 * CROSSFADE_TIME) as usize;
@@ -78,11 +268,14 @@ impl Voice {
             1.0
         };
 
-        let amplitude_ratio: AmplitudeRatio<f64> = DecibelRatio(gain_db as f64).into();
-        let gain = amplitude_ratio.amplitude_value() as f32;
+        let gain = db_to_amplitude(gain_db);
 
-        let ring_buf = HeapRb::<f32>::new(VOICE_BUFFER_FRAMES * CHANNEL_COUNT);
-        let (mut producer, consumer) = ring_buf.split();
+        let Some((mut producer, consumer)) = voice_pool.acquire_ring_buffer() else {
+            return Err(anyhow!(
+                "Voice pool exhausted ({} buffers in use)",
+                voice_pool.size()
+            ));
+        };
 
         let is_finished = Arc::new(AtomicBool::new(false));
         let is_cancelled = Arc::new(AtomicBool::new(false));
@@ -92,23 +285,39 @@ impl Voice {
             let pushed = producer.push_slice(preloaded);
             preloaded_frames_count = pushed / CHANNEL_COUNT;
         }
+        // A sample without a loop to start in takes over where the note has got to
+        let frames_to_skip = if start_in_loop {
+            let elapsed_frames = note_on_time.elapsed().as_secs_f32() * sample_rate as f32;
+            preloaded_frames_count.max(elapsed_frames as usize)
+        } else {
+            preloaded_frames_count
+        };
 
         let job = SpawnJob {
             path: path.to_path_buf(),
             organ: Arc::clone(&organ),
             sample_rate,
             is_attack_sample,
-            frames_to_skip: preloaded_frames_count,
+            is_percussive,
+            start_in_loop,
+            loop_choice,
+            frames_to_skip,
             producer,
             is_finished: Arc::clone(&is_finished),
             is_cancelled: Arc::clone(&is_cancelled),
         };
 
-        if let Err(e) = spawner_tx.send(job) {
+        if let Err(e) = voice_pool.loader_tx.send(job) {
             log::error!("Failed to queue voice spawn job: {}", e);
             is_finished.store(true, Ordering::Relaxed);
         }
 
+        let mut input_buffer = voice_pool
+            .input_buffers
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(4096));
+        input_buffer.resize(RESAMPLE_HISTORY_FRAMES * CHANNEL_COUNT, 0.0);
+
         Ok(Self {
             gain,
             consumer,
@@ -121,15 +330,75 @@ impl Voice {
             release_voice_id: None,
             note_on_time,
             is_attack_sample,
+            is_percussive,
             fade_increment,
             windchest_group_id,
-            input_buffer: Vec::with_capacity(4096),
-            buffer_start_idx: 0,
+            enclosure_lp_state: [0.0; CHANNEL_COUNT],
+            start_delay_frames: 0,
+            fade_out_delay_frames: 0,
+            pitch_ratio: 1.0,
+            pan: UNPANNED,
+            output_bus: 0,
+            max_length_frames: None,
+            level: 0.0,
+            release_alignment: None,
+            input_buffer,
+            buffer_start_idx: RESAMPLE_HISTORY_FRAMES * CHANNEL_COUNT,
             cursor_pos: 0.0,
         })
     }
 }
 
+impl Voice {
+    /// The next frame the voice plays, as a mono value and its change to the frame after it.
+    pub fn current_phase(&self) -> Option<(f32, f32)> {
+        let idx = self.buffer_start_idx + self.cursor_pos as usize * CHANNEL_COUNT;
+        let frames = self.input_buffer.get(idx..idx + 2 * CHANNEL_COUNT)?;
+        let now = (frames[0] + frames[1]) * 0.5;
+        let next = (frames[2] + frames[3]) * 0.5;
+        Some((now, next - now))
+    }
+
+    /// Lets a release that is about to take over continue the waveform of its attack:
+    /// skips ahead to the start matching the attack's phase and matches the attack's level.
+    /// `reserve_frames` of the buffered audio are kept to play right away.
+    pub fn align_release(
+        &mut self,
+        attack_phase: (f32, f32),
+        attack_level: f32,
+        reserve_frames: usize,
+    ) {
+        let Some(alignment) = &self.release_alignment else {
+            return;
+        };
+        // Only a release that hasn't played anything yet can be aligned
+        if self.input_buffer.len() > self.buffer_start_idx {
+            return;
+        }
+        let gain = if attack_level > 0.0 && alignment.level > 0.0 {
+            (attack_level / alignment.level)
+                .sqrt()
+                .clamp(1.0 / RELEASE_ALIGN_MAX_GAIN, RELEASE_ALIGN_MAX_GAIN)
+        } else {
+            1.0
+        };
+        let (value, derivative) = attack_phase;
+        let available = self.consumer.occupied_len() / CHANNEL_COUNT;
+        let start = alignment.start_frame(
+            value / gain,
+            derivative / gain,
+            available.saturating_sub(reserve_frames),
+        );
+        self.consumer.skip(start * CHANNEL_COUNT);
+        self.gain *= gain;
+    }
+}
+
+pub fn db_to_amplitude(gain_db: f32) -> f32 {
+    let amplitude_ratio: AmplitudeRatio<f64> = DecibelRatio(gain_db as f64).into();
+    amplitude_ratio.amplitude_value() as f32
+}
+
 impl Drop for Voice {
     fn drop(&mut self) {
         self.is_cancelled.store(true, Ordering::SeqCst);
@@ -0,0 +1,125 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+use utoipa::ToSchema;
+
+use crate::organ::Organ;
+
+/// Voicing corrections of one pipe, on top of the sample set's own values.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PipeVoicing {
+    #[serde(default)]
+    pub gain_db: f32,
+    #[serde(default)]
+    pub tuning_cents: f32,
+    /// Release samples are faded out after this many milliseconds
+    #[serde(default)]
+    pub max_release_ms: Option<u32>,
+}
+
+impl PipeVoicing {
+    pub fn is_neutral(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct VoicingEntry {
+    pub rank_id: String,
+    pub note: u8,
+    #[serde(flatten)]
+    pub voicing: PipeVoicing,
+}
+
+/// The voicing overlay of one organ, stored next to its MIDI map.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct VoicingOverlay {
+    pub pipes: Vec<VoicingEntry>,
+}
+
+impl VoicingOverlay {
+    pub fn get_file_path(organ_name: &str) -> PathBuf {
+        let config_path = confy::get_configuration_file_path("rusty-pipes", "settings")
+            .expect("Could not get configuration file path");
+        let parent = config_path.parent().expect("Could not get config parent");
+        let safe_name: String = organ_name
+            .chars()
+            .map(|x| if x.is_alphanumeric() { x } else { '_' })
+            .collect();
+        parent.join(format!("{}.voicing.json", safe_name))
+    }
+
+    pub fn load(organ_name: &str) -> Self {
+        let path = Self::get_file_path(organ_name);
+        if path.exists() {
+            match File::open(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|file| Ok(serde_json::from_reader(BufReader::new(file))?))
+            {
+                Ok(overlay) => return overlay,
+                Err(e) => log::warn!("Could not read voicing from {:?}: {}", path, e),
+            }
+        }
+        Self::default()
+    }
+
+    pub fn save(&self, organ_name: &str) -> Result<()> {
+        let path = Self::get_file_path(organ_name);
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn get(&self, rank_id: &str, note: u8) -> PipeVoicing {
+        self.pipes
+            .iter()
+            .find(|e| e.rank_id == rank_id && e.note == note)
+            .map(|e| e.voicing)
+            .unwrap_or_default()
+    }
+
+    /// Sets the voicing of a pipe. Neutral voicing removes the pipe from the overlay.
+    pub fn set(&mut self, rank_id: &str, note: u8, voicing: PipeVoicing) {
+        self.pipes
+            .retain(|e| !(e.rank_id == rank_id && e.note == note));
+        if !voicing.is_neutral() {
+            self.pipes.push(VoicingEntry {
+                rank_id: rank_id.to_string(),
+                note,
+                voicing,
+            });
+            self.pipes
+                .sort_by(|a, b| (&a.rank_id, a.note).cmp(&(&b.rank_id, b.note)));
+        }
+    }
+
+    /// Applies the overlay to the organ's pipes. The gain is added to the pipe's own gain,
+    /// and the voicing is kept on the pipe so it can be replaced live and its tuning
+    /// applied at playback.
+    pub fn merge_into(&self, organ: &mut Organ) {
+        let mut applied = 0;
+        for entry in &self.pipes {
+            let Some(pipe) = organ
+                .ranks
+                .get_mut(&entry.rank_id)
+                .and_then(|rank| rank.pipes.get_mut(&entry.note))
+            else {
+                log::warn!(
+                    "Skipping voicing of rank '{}' note {}: no such pipe",
+                    entry.rank_id,
+                    entry.note
+                );
+                continue;
+            };
+            pipe.gain_db += entry.voicing.gain_db;
+            pipe.voicing = entry.voicing;
+            applied += 1;
+        }
+        if applied > 0 {
+            log::info!("Applied voicing to {} pipes.", applied);
+        }
+    }
+}
@@ -199,8 +199,8 @@ impl<R: Read + Seek> Iterator for WavSampleReader<R> {
     }
 }
 
-/// Parses a 'smpl' chunk's data. Returns (loop_start, loop_end) in samples.
-pub fn parse_smpl_chunk(data: &[u8]) -> Option<(u32, u32)> {
+/// Parses a 'smpl' chunk's data. Returns every (loop_start, loop_end) in samples, in file order.
+pub fn parse_smpl_chunk(data: &[u8]) -> Option<Vec<(u32, u32)>> {
     // A 'smpl' chunk has a 36-byte header, followed by an array of loops.
     // Each loop entry is 24 bytes.
     if data.len() < 36 {
@@ -234,31 +234,42 @@ pub fn parse_smpl_chunk(data: &[u8]) -> Option<(u32, u32)> {
         return None;
     }
 
-    if data.len() < 36 + 24 {
+    let loops_in_data = (data.len() - 36) / 24;
+    if loops_in_data == 0 {
         log::warn!(
             "[parse_smpl_chunk] 'smpl' data is too short for one loop entry: {} bytes",
             data.len()
         );
         return None;
     }
+    if loops_in_data < num_sample_loops as usize {
+        log::warn!(
+            "[parse_smpl_chunk] 'smpl' chunk declares {} loops but only holds {}",
+            num_sample_loops,
+            loops_in_data
+        );
+    }
 
-    // We only care about the first loop.
-    let _cue_point_id = cursor.read_u32::<LittleEndian>().ok()?;
-    let _loop_type = cursor.read_u32::<LittleEndian>().ok()?; // 0 = forward, 1 = alternating, 2 = backward
-    let loop_start = cursor.read_u32::<LittleEndian>().ok()?;
-    let loop_end = cursor.read_u32::<LittleEndian>().ok()?; // This is the *sample after* the loop
-    let _fraction = cursor.read_u32::<LittleEndian>().ok()?;
-    let _play_count = cursor.read_u32::<LittleEndian>().ok()?; // 0 = infinite
-
-    log::debug!(
-        "[parse_smpl_chunk] Found loop: {} -> {}",
-        loop_start,
-        loop_end
-    );
-
-    // The 'end' sample is exclusive, so `loop_end - 1` is the last sample.
-    // We'll use a check `current_frame >= loop_end`
-    Some((loop_start, loop_end))
+    let mut loops = Vec::new();
+    for _ in 0..loops_in_data.min(num_sample_loops as usize) {
+        let _cue_point_id = cursor.read_u32::<LittleEndian>().ok()?;
+        let _loop_type = cursor.read_u32::<LittleEndian>().ok()?; // 0 = forward, 1 = alternating, 2 = backward
+        let loop_start = cursor.read_u32::<LittleEndian>().ok()?;
+        let loop_end = cursor.read_u32::<LittleEndian>().ok()?; // This is the *sample after* the loop
+        let _fraction = cursor.read_u32::<LittleEndian>().ok()?;
+        let _play_count = cursor.read_u32::<LittleEndian>().ok()?; // 0 = infinite
+
+        log::debug!(
+            "[parse_smpl_chunk] Found loop: {} -> {}",
+            loop_start,
+            loop_end
+        );
+
+        // The 'end' sample is exclusive, so `loop_end - 1` is the last sample.
+        // We'll use a check `current_frame >= loop_end`
+        loops.push((loop_start, loop_end));
+    }
+    Some(loops)
 }
 
 /// Parses a 'cue ' chunk's data and returns a list of sample offsets.
@@ -12,6 +12,7 @@ use symphonia::core::audio::SampleBuffer;
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::probe::Hint;
 
+use crate::release_align::{RELEASE_ALIGN_TIME, ReleaseAlignment};
 use crate::wav::{IsWavPackError, OtherChunk, WavFmt, parse_smpl_chunk};
 
 const I16_MAX_F: f32 = 32768.0; // 2^15
@@ -20,7 +21,8 @@ const I32_MAX_F: f32 = 2147483648.0; // 2^31
 
 #[derive(Debug)]
 pub struct SampleMetadata {
-    pub loop_info: Option<(u32, u32)>,
+    /// Sustain loops as (start, end) frames, empty for samples that play to their end
+    pub loops: Vec<(u32, u32)>,
     pub channel_count: u16,
 }
 
@@ -406,16 +408,16 @@ pub fn load_sample_as_f32(
                 ));
             }
 
-            let mut loop_info = None;
+            let mut loops = Vec::new();
             for chunk in other_chunks {
                 if &chunk.id == b"smpl" {
-                    loop_info = parse_smpl_chunk(&chunk.data);
+                    loops = parse_smpl_chunk(&chunk.data).unwrap_or_default();
                     break;
                 }
             }
 
             let metadata = SampleMetadata {
-                loop_info,
+                loops,
                 channel_count: format.num_channels,
             };
 
@@ -454,7 +456,7 @@ pub fn load_sample_as_f32(
                 // TODO: extracting loop points from WavPack is complex via Symphonia.
                 // For now, we assume 0 loops or rely on ODF override.
                 let metadata = SampleMetadata {
-                    loop_info: None,
+                    loops: Vec::new(),
                     channel_count: channels,
                 };
 
@@ -479,6 +481,16 @@ pub fn load_sample_as_f32(
     }
 }
 
+/// True if the converted file exists and was written after its source last changed.
+fn is_cache_fresh(cache_path: &Path, source_path: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    match (modified(cache_path), modified(source_path)) {
+        (Some(cache_time), Some(source_time)) => cache_time >= source_time,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
 /// Checks and processes audio file. Supports WavPack input, always outputs WAV to cache.
 pub fn process_sample_file(
     relative_path: &Path,
@@ -487,6 +499,47 @@ pub fn process_sample_file(
     pitch_tuning_cents: f32,
     convert_to_16_bit: bool,
     target_sample_rate: u32,
+) -> Result<PathBuf> {
+    convert_sample_file(
+        relative_path,
+        base_dir,
+        cache_dir,
+        pitch_tuning_cents,
+        convert_to_16_bit,
+        target_sample_rate,
+        false,
+    )
+}
+
+/// Like `process_sample_file`, and stores the release alignment table of the
+/// converted sample next to it.
+pub fn process_release_file(
+    relative_path: &Path,
+    base_dir: &Path,
+    cache_dir: &Path,
+    pitch_tuning_cents: f32,
+    convert_to_16_bit: bool,
+    target_sample_rate: u32,
+) -> Result<PathBuf> {
+    convert_sample_file(
+        relative_path,
+        base_dir,
+        cache_dir,
+        pitch_tuning_cents,
+        convert_to_16_bit,
+        target_sample_rate,
+        true,
+    )
+}
+
+fn convert_sample_file(
+    relative_path: &Path,
+    base_dir: &Path,
+    cache_dir: &Path,
+    pitch_tuning_cents: f32,
+    convert_to_16_bit: bool,
+    target_sample_rate: u32,
+    is_release: bool,
 ) -> Result<PathBuf> {
     let full_source_path = base_dir.join(relative_path);
     if !full_source_path.exists() {
@@ -566,7 +619,7 @@ pub fn process_sample_file(
 
     let cache_full_path = parent_in_cache.join(new_file_name);
 
-    if cache_full_path.exists() {
+    if is_cache_fresh(&cache_full_path, &full_source_path) {
         return Ok(cache_full_path);
     }
 
@@ -760,6 +813,10 @@ pub fn process_sample_file(
     writer.write_all(&final_data_chunk)?;
     writer.flush()?;
 
+    if is_release {
+        store_release_alignment(&cache_full_path, &output_waves, target_sample_rate);
+    }
+
     Ok(cache_full_path)
 }
 
@@ -902,6 +959,84 @@ pub fn load_sample_head(
     }
 }
 
+/// Where the alignment table of a release sample is cached: next to the converted
+/// sample, or in the cache directory for samples played from the sample set as they are.
+fn release_alignment_path(
+    path: &Path,
+    base_dir: &Path,
+    cache_dir: &Path,
+    target_sample_rate: u32,
+) -> PathBuf {
+    if path.starts_with(cache_dir) {
+        path.with_extension("align")
+    } else {
+        let relative = path.strip_prefix(base_dir).unwrap_or(path);
+        let relative = relative.strip_prefix("/").unwrap_or(relative);
+        cache_dir
+            .join(relative)
+            .with_extension(format!("{}hz.align", target_sample_rate))
+    }
+}
+
+/// Builds the alignment table from planar samples at the target rate and writes it
+/// next to the converted release at `cache_path`.
+fn store_release_alignment(cache_path: &Path, waves: &[Vec<f32>], target_sample_rate: u32) {
+    let Some(left) = waves.first() else {
+        return;
+    };
+    let right = waves.get(1).unwrap_or(left);
+    let frames = ((target_sample_rate as f32 * RELEASE_ALIGN_TIME).ceil() as usize).min(left.len());
+    let head: Vec<f32> = (0..frames).flat_map(|i| [left[i], right[i]]).collect();
+    let table = ReleaseAlignment::compute(&head, target_sample_rate);
+    if let Err(e) = write_release_alignment(&cache_path.with_extension("align"), &table) {
+        log::warn!(
+            "Failed to store release alignment of {:?}: {}",
+            cache_path,
+            e
+        );
+    }
+}
+
+fn write_release_alignment(path: &Path, table: &Option<ReleaseAlignment>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, bincode::serialize(table)?)?;
+    Ok(())
+}
+
+/// The alignment table of a release sample, as stored when it was converted. Samples
+/// converted before the tables were stored, or played without conversion, get theirs
+/// built from the start of the file once and cached.
+pub fn release_alignment(
+    path: &Path,
+    base_dir: &Path,
+    cache_dir: &Path,
+    target_sample_rate: u32,
+) -> Option<ReleaseAlignment> {
+    let table_path = release_alignment_path(path, base_dir, cache_dir, target_sample_rate);
+    if is_cache_fresh(&table_path, path)
+        && let Ok(table) = fs::read(&table_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| Ok(bincode::deserialize::<Option<ReleaseAlignment>>(&bytes)?))
+    {
+        return table;
+    }
+
+    let frames = (target_sample_rate as f32 * RELEASE_ALIGN_TIME).ceil() as usize;
+    let table = match load_sample_head(path, target_sample_rate, frames) {
+        Ok(head) => ReleaseAlignment::compute(&head, target_sample_rate),
+        Err(e) => {
+            log::warn!("Failed to compute release alignment of {:?}: {}", path, e);
+            return None;
+        }
+    };
+    if let Err(e) = write_release_alignment(&table_path, &table) {
+        log::warn!("Failed to store release alignment of {:?}: {}", path, e);
+    }
+    table
+}
+
 /// Attempts to extract a release tail from the attack sample if a valid CUE marker exists.
 /// Returns Ok(Some(PathBuf)) if a release sample was generated.
 pub fn try_extract_release_sample(
@@ -928,12 +1063,12 @@ pub fn try_extract_release_sample(
             Err(_) => return Ok(None), // Skip if not a valid WAV or is WavPack
         };
 
-    // Find Loop End
+    // Find Loop End (of the last loop, the attack part has to hold all of them)
     let mut loop_end = 0;
     for chunk in &chunks {
         if &chunk.id == b"smpl" {
-            if let Some((_, end)) = crate::wav::parse_smpl_chunk(&chunk.data) {
-                loop_end = end;
+            if let Some(loops) = crate::wav::parse_smpl_chunk(&chunk.data) {
+                loop_end = loops.iter().map(|&(_, end)| end).max().unwrap_or(0);
             }
         }
     }
@@ -986,7 +1121,7 @@ pub fn try_extract_release_sample(
     };
     let cache_full_path = parent_in_cache.join(new_file_name);
 
-    if cache_full_path.exists() {
+    if is_cache_fresh(&cache_full_path, &full_source_path) {
         return Ok(Some(cache_full_path));
     }
 
@@ -1141,5 +1276,7 @@ pub fn try_extract_release_sample(
     writer.write_all(&final_data)?;
     writer.flush()?;
 
+    store_release_alignment(&cache_full_path, &output_waves, target_sample_rate);
+
     Ok(Some(cache_full_path))
 }
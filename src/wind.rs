@@ -0,0 +1,227 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use crate::organ::Organ;
+use crate::voice::{Voice, db_to_amplitude};
+
+/// Loads below this are treated as a silent wind supply.
+const MIN_LOAD: f32 = 0.0001;
+
+/// How one wind supply reacts to the pipes that draw from it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WindParams {
+    /// Number of speaking pipes at which the supply reaches full load
+    pub capacity_pipes: f32,
+    /// Pitch drop at full load
+    pub pitch_droop_cents: f32,
+    /// Volume drop at full load
+    pub amplitude_droop_db: f32,
+    /// Time constant of the regulator following a change of load
+    pub response_ms: f32,
+}
+
+impl Default for WindParams {
+    fn default() -> Self {
+        Self {
+            capacity_pipes: 48.0,
+            pitch_droop_cents: 4.0,
+            amplitude_droop_db: 1.0,
+            response_ms: 120.0,
+        }
+    }
+}
+
+/// The wind model of one organ, stored next to its MIDI map.
+/// Windchest groups without their own parameters use `default`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WindModel {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub default: WindParams,
+    // Map<WindchestGroupID, Params>
+    #[serde(default)]
+    pub groups: HashMap<String, WindParams>,
+}
+
+impl WindModel {
+    pub fn get_file_path(organ_name: &str) -> PathBuf {
+        let config_path = confy::get_configuration_file_path("rusty-pipes", "settings")
+            .expect("Could not get configuration file path");
+        let parent = config_path.parent().expect("Could not get config parent");
+        let safe_name: String = organ_name
+            .chars()
+            .map(|x| if x.is_alphanumeric() { x } else { '_' })
+            .collect();
+        parent.join(format!("{}.wind.json", safe_name))
+    }
+
+    pub fn load(organ_name: &str) -> Self {
+        let path = Self::get_file_path(organ_name);
+        if path.exists() {
+            match File::open(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(|file| Ok(serde_json::from_reader(BufReader::new(file))?))
+            {
+                Ok(model) => return model,
+                Err(e) => log::warn!("Could not read wind model from {:?}: {}", path, e),
+            }
+        }
+        Self::default()
+    }
+
+    pub fn save(&self, organ_name: &str) -> Result<()> {
+        let path = Self::get_file_path(organ_name);
+        let file = File::create(path)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Parameters of a windchest group. Voices may carry the ID unpadded ("1" vs "001").
+    pub fn params(&self, group_id: &str) -> &WindParams {
+        self.groups
+            .get(group_id)
+            .or_else(|| {
+                let unpadded = group_id.trim_start_matches('0');
+                self.groups
+                    .iter()
+                    .find(|(id, _)| id.trim_start_matches('0') == unpadded)
+                    .map(|(_, params)| params)
+            })
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Pitch and gain factors of one windchest group for the current block.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WindDroop {
+    pub pitch: f32,
+    pub gain: f32,
+}
+
+impl Default for WindDroop {
+    fn default() -> Self {
+        Self {
+            pitch: 1.0,
+            gain: 1.0,
+        }
+    }
+}
+
+/// Wind state of one windchest group, kept between blocks.
+#[derive(Default)]
+struct WindGroup {
+    id: String,
+    params: WindParams,
+    speaking: f32,
+    load: f32,
+    prev_droop: WindDroop,
+    droop: WindDroop,
+}
+
+/// Tracks the smoothed load of each windchest group in the audio thread.
+/// The groups are allocated up front so updating them allocates nothing.
+#[derive(Default)]
+pub struct WindSupply {
+    pub model: WindModel,
+    groups: Vec<WindGroup>,
+    // Map<WindchestGroupID, index into groups>, also keyed by the unpadded ID
+    index: HashMap<String, usize>,
+}
+
+impl WindSupply {
+    /// Creates the wind state of every windchest group of the organ.
+    pub fn new(organ: &Organ) -> Self {
+        let mut supply = Self::default();
+        let group_ids = organ.windchest_groups.keys().chain(
+            organ
+                .ranks
+                .values()
+                .filter_map(|r| r.windchest_group_id.as_ref()),
+        );
+        for id in group_ids {
+            if supply.index.contains_key(id) {
+                continue;
+            }
+            let index = supply.groups.len();
+            supply.groups.push(WindGroup {
+                id: id.clone(),
+                ..Default::default()
+            });
+            supply.index.insert(id.clone(), index);
+            let unpadded = id.trim_start_matches('0');
+            let unpadded = if unpadded.is_empty() { "0" } else { unpadded };
+            supply.index.entry(unpadded.to_string()).or_insert(index);
+        }
+        supply
+    }
+
+    pub fn set_model(&mut self, model: WindModel) {
+        for group in &mut self.groups {
+            group.params = *model.params(&group.id);
+            if !model.enabled {
+                group.load = 0.0;
+                group.prev_droop = WindDroop::default();
+                group.droop = WindDroop::default();
+            }
+        }
+        self.model = model;
+    }
+
+    /// Droop of a windchest group at the start and end of the current block.
+    pub fn droop(&self, group_id: &str) -> (WindDroop, WindDroop) {
+        match self.index.get(group_id) {
+            Some(&index) => (self.groups[index].prev_droop, self.groups[index].droop),
+            None => (WindDroop::default(), WindDroop::default()),
+        }
+    }
+
+    /// Advances the load of every windchest group by `dt` seconds. Only sustaining
+    /// attack samples whose pipes are speaking draw wind: voices still waiting out
+    /// their tracker delay or held back for a release crossfade do not.
+    pub fn update(&mut self, voices: &HashMap<u64, Voice>, dt: f32) {
+        if !self.model.enabled {
+            return;
+        }
+
+        for group in &mut self.groups {
+            group.speaking = 0.0;
+        }
+        for voice in voices.values() {
+            let is_sounding = voice.start_delay_frames == 0
+                && (voice.fade_level > 0.0 || voice.is_fading_in || voice.is_fading_out);
+            if voice.is_attack_sample
+                && !voice.is_fading_out
+                && is_sounding
+                && let Some(wc_id) = &voice.windchest_group_id
+                && let Some(&index) = self.index.get(wc_id.as_str())
+            {
+                self.groups[index].speaking += 1.0;
+            }
+        }
+
+        for group in &mut self.groups {
+            let params = &group.params;
+            let target = (group.speaking / params.capacity_pipes.max(1.0)).min(1.0);
+            let alpha = if params.response_ms > 0.0 {
+                1.0 - (-dt * 1000.0 / params.response_ms).exp()
+            } else {
+                1.0
+            };
+            group.load += (target - group.load) * alpha;
+            if group.load < MIN_LOAD && target == 0.0 {
+                group.load = 0.0;
+            }
+            group.prev_droop = group.droop;
+            group.droop = WindDroop {
+                pitch: 2.0f32.powf(-params.pitch_droop_cents * group.load / 1200.0),
+                gain: db_to_amplitude(-params.amplitude_droop_db * group.load),
+            };
+        }
+    }
+}